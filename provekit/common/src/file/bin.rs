@@ -1,7 +1,8 @@
 use {
     super::{BufExt as _, CountingWriter},
-    crate::utils::human,
+    crate::{utils::human, FieldElement},
     anyhow::{ensure, Context as _, Result},
+    ark_serialize::{CanonicalDeserialize, CanonicalSerialize},
     bytes::{Buf, BufMut as _, Bytes, BytesMut},
     serde::{Deserialize, Serialize},
     std::{
@@ -14,8 +15,9 @@ use {
 };
 
 const ZSTD_COMPRESSION: i32 = zstd::DEFAULT_COMPRESSION_LEVEL;
-const HEADER_SIZE: usize = 20;
-const MAGIC_BYTES: &[u8] = b"\xDC\xDFOZkp\x01\x00";
+const HEADER_SIZE: usize = 52;
+const MAGIC_BYTES: &[u8] = b"\xDC\xDFOZkp\x02\x00";
+const DIGEST_SIZE: usize = 32;
 
 /// Write a compressed binary file (fast and small).
 #[instrument(skip(value))]
@@ -24,6 +26,7 @@ pub fn write_bin<T: Serialize>(
     path: &Path,
     format: [u8; 8],
     (major, minor): (u16, u16),
+    digest: FieldElement,
 ) -> Result<()> {
     // Open file
     let mut file = File::create(path).context("while creating output file")?;
@@ -35,6 +38,11 @@ pub fn write_bin<T: Serialize>(
     header.put(&format[..]);
     header.put_u16_le(major);
     header.put_u16_le(minor);
+    let mut digest_bytes = Vec::with_capacity(DIGEST_SIZE);
+    digest
+        .serialize_compressed(&mut digest_bytes)
+        .context("while encoding digest")?;
+    header.put(&digest_bytes[..]);
     file_counter
         .write_all(&header)
         .context("while writing header")?;
@@ -70,16 +78,41 @@ pub fn write_bin<T: Serialize>(
     Ok(())
 }
 
-/// Read a compressed binary file.
+/// Read a compressed binary file and the digest in its header.
 #[instrument(fields(size = path.metadata().map(|m| m.len()).ok()))]
 pub fn read_bin<T: for<'a> Deserialize<'a>>(
     path: &Path,
     format: [u8; 8],
-    (major, minor): (u16, u16),
-) -> Result<T> {
+    version: (u16, u16),
+) -> Result<(FieldElement, T)> {
     let mut file = File::open(path).context("while opening input file")?;
+    let digest = read_header(&mut file, format, version)?;
+
+    // Decompressor
+    let mut decompressor = ZstdDecoder::new(&mut file).context("while creating decompressor")?;
+
+    // Postcard
+    // See <https://github.com/jamesmunns/postcard/pull/212> for the reason for the full uncompressed buffer.
+    let mut uncompressed = Vec::new();
+    decompressor
+        .read_to_end(&mut uncompressed)
+        .context("while reading decompressed data")?;
+    let value = postcard::from_bytes(&uncompressed).context("while decoding from postcard")?;
+    Ok((digest, value))
+}
 
-    // Read header
+/// Read the digest in the header of a compressed binary file.
+#[instrument]
+pub fn read_bin_digest(path: &Path, format: [u8; 8], version: (u16, u16)) -> Result<FieldElement> {
+    let mut file = File::open(path).context("while opening input file")?;
+    read_header(&mut file, format, version)
+}
+
+fn read_header(
+    file: &mut File,
+    format: [u8; 8],
+    (major, minor): (u16, u16),
+) -> Result<FieldElement> {
     let mut buffer = [0; HEADER_SIZE];
     file.read_exact(&mut buffer)
         .context("while reading header")?;
@@ -97,15 +130,6 @@ pub fn read_bin<T: for<'a> Deserialize<'a>>(
         header.get_u16_le() >= minor,
         "Incompatible format minor version"
     );
-
-    // Decompressor
-    let mut decompressor = ZstdDecoder::new(&mut file).context("while creating decompressor")?;
-
-    // Postcard
-    // See <https://github.com/jamesmunns/postcard/pull/212> for the reason for the full uncompressed buffer.
-    let mut uncompressed = Vec::new();
-    decompressor
-        .read_to_end(&mut uncompressed)
-        .context("while reading decompressed data")?;
-    postcard::from_bytes(&uncompressed).context("while decoding from postcard")
+    FieldElement::deserialize_compressed(&header.get_bytes::<DIGEST_SIZE>()[..])
+        .context("while decoding digest")
 }
@@ -5,13 +5,13 @@ mod json;
 
 use {
     self::{
-        bin::{read_bin, write_bin},
+        bin::{read_bin, read_bin_digest, write_bin},
         buf_ext::BufExt,
         counting_writer::CountingWriter,
         json::{read_json, write_json},
     },
-    crate::{NoirProof, NoirProofScheme},
-    anyhow::Result,
+    crate::{FieldElement, NoirProof, NoirProofScheme, ProvingKey, VerificationKey},
+    anyhow::{ensure, Result},
     serde::{Deserialize, Serialize},
     std::{ffi::OsStr, path::Path},
     tracing::instrument,
@@ -22,18 +22,49 @@ pub trait FileFormat: Serialize + for<'a> Deserialize<'a> {
     const FORMAT: [u8; 8];
     const EXTENSION: &'static str;
     const VERSION: (u16, u16);
+
+    /// The digest of the circuit, written in the header of binary files.
+    fn digest(&self) -> Result<FieldElement>;
 }
 
 impl FileFormat for NoirProofScheme {
     const FORMAT: [u8; 8] = *b"NrProScm";
     const EXTENSION: &'static str = "nps";
-    const VERSION: (u16, u16) = (0, 0);
+    const VERSION: (u16, u16) = (0, 6);
+
+    fn digest(&self) -> Result<FieldElement> {
+        self.digest()
+    }
+}
+
+impl FileFormat for ProvingKey {
+    const FORMAT: [u8; 8] = *b"NPSPrvKy";
+    const EXTENSION: &'static str = "npk";
+    const VERSION: (u16, u16) = (0, 5);
+
+    fn digest(&self) -> Result<FieldElement> {
+        Ok(self.digest)
+    }
+}
+
+impl FileFormat for VerificationKey {
+    const FORMAT: [u8; 8] = *b"NPSVerKy";
+    const EXTENSION: &'static str = "nvk";
+    const VERSION: (u16, u16) = (0, 3);
+
+    fn digest(&self) -> Result<FieldElement> {
+        Ok(self.digest)
+    }
 }
 
 impl FileFormat for NoirProof {
     const FORMAT: [u8; 8] = *b"NPSProof";
     const EXTENSION: &'static str = "np";
-    const VERSION: (u16, u16) = (0, 0);
+    const VERSION: (u16, u16) = (0, 4);
+
+    fn digest(&self) -> Result<FieldElement> {
+        Ok(self.digest)
+    }
 }
 
 /// Write a file with format determined from extension.
@@ -41,7 +72,9 @@ impl FileFormat for NoirProof {
 pub fn write<T: FileFormat>(value: &T, path: &Path) -> Result<()> {
     match path.extension().and_then(OsStr::to_str) {
         Some("json") => write_json(value, path),
-        Some(ext) if ext == T::EXTENSION => write_bin(value, path, T::FORMAT, T::VERSION),
+        Some(ext) if ext == T::EXTENSION => {
+            write_bin(value, path, T::FORMAT, T::VERSION, value.digest()?)
+        }
         _ => Err(anyhow::anyhow!(
             "Unsupported file extension, please specify .{} or .json",
             T::EXTENSION
@@ -54,7 +87,27 @@ pub fn write<T: FileFormat>(value: &T, path: &Path) -> Result<()> {
 pub fn read<T: FileFormat>(path: &Path) -> Result<T> {
     match path.extension().and_then(OsStr::to_str) {
         Some("json") => read_json(path),
-        Some(ext) if ext == T::EXTENSION => read_bin(path, T::FORMAT, T::VERSION),
+        Some(ext) if ext == T::EXTENSION => {
+            let (digest, value) = read_bin::<T>(path, T::FORMAT, T::VERSION)?;
+            ensure!(
+                value.digest()? == digest,
+                "Digest in header does not match the contents"
+            );
+            Ok(value)
+        }
+        _ => Err(anyhow::anyhow!(
+            "Unsupported file extension, please specify .{} or .json",
+            T::EXTENSION
+        )),
+    }
+}
+
+/// Read the circuit digest of a file, from the header only for binary files.
+#[instrument()]
+pub fn read_digest<T: FileFormat>(path: &Path) -> Result<FieldElement> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("json") => read_json::<T>(path)?.digest(),
+        Some(ext) if ext == T::EXTENSION => read_bin_digest(path, T::FORMAT, T::VERSION),
         _ => Err(anyhow::anyhow!(
             "Unsupported file extension, please specify .{} or .json",
             T::EXTENSION
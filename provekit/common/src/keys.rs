@@ -0,0 +1,109 @@
+use {
+    crate::{
+        noir_proof_scheme::{
+            decode_public_outputs, encode_public_inputs, read_public_inputs, read_public_outputs,
+        },
+        utils::serde_ark,
+        FieldElement, NoirProofScheme, WhirR1CSScheme, R1CS,
+    },
+    anyhow::Result,
+    noirc_abi::{input_parser::InputValue, Abi, InputMap},
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+/// Everything needed to prove statements about a Noir program.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvingKey {
+    #[serde(with = "serde_ark")]
+    pub digest: FieldElement,
+    pub scheme: NoirProofScheme,
+}
+
+/// The parts of a [`NoirProofScheme`] needed to verify proofs. It does not
+/// contain the program or the witness generation artifacts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationKey {
+    #[serde(with = "serde_ark")]
+    pub digest:           FieldElement,
+    /// ABI restricted to the public parameters, in witness order, and the
+    /// return value.
+    pub public_abi:       Abi,
+    /// The R1CS instance, unless the scheme commits to the matrices.
+    pub r1cs:             Option<R1CS>,
+    pub whir_for_witness: WhirR1CSScheme,
+}
+
+impl ProvingKey {
+    pub fn new(scheme: NoirProofScheme) -> Result<Self> {
+        Ok(Self {
+            digest: scheme.digest()?,
+            scheme,
+        })
+    }
+}
+
+impl VerificationKey {
+    #[must_use]
+    pub fn new(proving_key: &ProvingKey) -> Self {
+        let scheme = &proving_key.scheme;
+        Self {
+            digest:           proving_key.digest,
+            public_abi:       scheme.witness_generator.abi().clone().public_abi(),
+            r1cs:             scheme
+                .whir_for_witness
+                .spark
+                .is_none()
+                .then(|| scheme.r1cs.clone()),
+            whir_for_witness: scheme.whir_for_witness.clone(),
+        }
+    }
+
+    /// The number of constraints and witnesses, if the key holds the R1CS
+    /// instance.
+    #[must_use]
+    pub fn size(&self) -> Option<(usize, usize)> {
+        self.r1cs
+            .as_ref()
+            .map(|r1cs| (r1cs.num_constraints(), r1cs.num_witnesses()))
+    }
+
+    /// Reads the public inputs from a TOML or JSON input file. Private inputs
+    /// may be present but are ignored.
+    pub fn read_public_inputs(&self, path: impl AsRef<Path>) -> Result<Vec<FieldElement>> {
+        read_public_inputs(
+            &self.public_abi,
+            self.whir_for_witness.num_public_inputs,
+            path,
+        )
+    }
+
+    /// Encodes the public inputs from an input map, in the order they are laid
+    /// out in the R1CS witness.
+    pub fn public_inputs(&self, input_map: &InputMap) -> Result<Vec<FieldElement>> {
+        encode_public_inputs(
+            &self.public_abi,
+            self.whir_for_witness.num_public_inputs,
+            input_map,
+        )
+    }
+
+    /// Reads the expected return value from a TOML or JSON input file, if it
+    /// has one.
+    pub fn read_public_outputs(&self, path: impl AsRef<Path>) -> Result<Option<Vec<FieldElement>>> {
+        read_public_outputs(
+            &self.public_abi,
+            self.whir_for_witness.num_public_outputs,
+            path,
+        )
+    }
+
+    /// Decodes the public outputs of a proof into the return value of the
+    /// program.
+    pub fn decode_public_outputs(
+        &self,
+        public_outputs: &[FieldElement],
+    ) -> Result<Option<InputValue>> {
+        decode_public_outputs(&self.public_abi, public_outputs)
+    }
+}
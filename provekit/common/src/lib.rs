@@ -1,8 +1,11 @@
 pub mod file;
 mod interner;
+mod keys;
 mod noir_proof_scheme;
 mod r1cs;
 pub mod skyscraper;
+mod source_map;
+pub mod spark;
 mod sparse_matrix;
 pub mod utils;
 mod whir_r1cs;
@@ -14,8 +17,10 @@ use crate::{
 };
 pub use {
     acir::FieldElement as NoirElement,
+    keys::{ProvingKey, VerificationKey},
     noir_proof_scheme::{NoirProof, NoirProofScheme},
     r1cs::R1CS,
+    source_map::{Origin, SourceLocation, SourceMap},
     whir::crypto::fields::Field256 as FieldElement,
     whir_r1cs::{IOPattern, WhirConfig, WhirR1CSProof, WhirR1CSScheme},
 };
@@ -1,15 +1,21 @@
 use {
     crate::{
+        skyscraper::hash_bytes,
+        utils::{native_to_noir, noir_to_native, serde_ark},
         whir_r1cs::{WhirR1CSProof, WhirR1CSScheme},
-        witness::{NoirWitnessGenerator, WitnessBuilder},
-        NoirElement, R1CS,
+        witness::{NoirWitnessGenerator, SplitWitnessBuilders},
+        FieldElement, NoirElement, SourceMap, R1CS,
     },
-    acir::circuit::Program,
-    anyhow::Result,
+    acir::{
+        circuit::Program,
+        native_types::{Witness, WitnessMap},
+    },
+    anyhow::{ensure, Context as _, Result},
     noir_artifact_cli::fs::inputs::read_inputs_from_file,
-    noirc_abi::InputMap,
+    noirc_abi::{input_parser::InputValue, Abi, InputMap},
     serde::{Deserialize, Serialize},
-    std::path::Path,
+    std::{collections::BTreeMap, path::Path},
+    tracing::instrument,
 };
 
 /// A scheme for proving a Noir program.
@@ -17,13 +23,24 @@ use {
 pub struct NoirProofScheme {
     pub program:           Program<NoirElement>,
     pub r1cs:              R1CS,
-    pub witness_builders:  Vec<WitnessBuilder>,
+    pub witness_builders:  SplitWitnessBuilders,
     pub witness_generator: NoirWitnessGenerator,
     pub whir_for_witness:  WhirR1CSScheme,
+    /// The origins of the constraints and witnesses, for diagnostics. It is
+    /// not part of the digest.
+    pub source_map:        Option<SourceMap>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NoirProof {
+    /// The digest of the circuit the proof is for
+    #[serde(with = "serde_ark")]
+    pub digest:          FieldElement,
+    #[serde(with = "serde_ark")]
+    pub public_inputs:   Vec<FieldElement>,
+    /// The return values of the program
+    #[serde(with = "serde_ark")]
+    pub public_outputs:  Vec<FieldElement>,
     pub whir_r1cs_proof: WhirR1CSProof,
 }
 
@@ -33,10 +50,145 @@ impl NoirProofScheme {
         (self.r1cs.num_constraints(), self.r1cs.num_witnesses())
     }
 
+    /// A digest identifying the circuit. It commits to the R1CS instance, the
+    /// witness builders, the ABI and the WHIR parameters, and is absorbed first
+    /// into the proof transcript.
+    #[instrument(skip_all)]
+    pub fn digest(&self) -> Result<FieldElement> {
+        let encoding = postcard::to_allocvec(&(
+            &self.r1cs,
+            &self.witness_builders,
+            self.witness_generator.abi(),
+            &self.whir_for_witness,
+        ))
+        .context("while encoding circuit for digest")?;
+
+        Ok(hash_bytes(&encoding))
+    }
+
     pub fn read_witness(&self, prover_toml: impl AsRef<Path>) -> Result<InputMap> {
         let (input_map, _expected_return) =
             read_inputs_from_file(prover_toml.as_ref(), self.witness_generator.abi())?;
 
         Ok(input_map)
     }
+
+    /// Reads the public inputs from a TOML or JSON input file. Private inputs
+    /// may be present but are ignored.
+    pub fn read_public_inputs(&self, path: impl AsRef<Path>) -> Result<Vec<FieldElement>> {
+        let public_abi = self.witness_generator.abi().clone().public_abi();
+        read_public_inputs(&public_abi, self.r1cs.num_public_inputs, path)
+    }
+
+    /// Encodes the public inputs from an input map, in the order they are laid
+    /// out in the R1CS witness.
+    pub fn public_inputs(&self, input_map: &InputMap) -> Result<Vec<FieldElement>> {
+        let public_abi = self.witness_generator.abi().clone().public_abi();
+        encode_public_inputs(&public_abi, self.r1cs.num_public_inputs, input_map)
+    }
+
+    /// Reads the expected return value from a TOML or JSON input file, if it
+    /// has one.
+    pub fn read_public_outputs(&self, path: impl AsRef<Path>) -> Result<Option<Vec<FieldElement>>> {
+        read_public_outputs(
+            self.witness_generator.abi(),
+            self.r1cs.num_public_outputs,
+            path,
+        )
+    }
+
+    /// Decodes the public outputs of a proof into the return value of the
+    /// program.
+    pub fn decode_public_outputs(
+        &self,
+        public_outputs: &[FieldElement],
+    ) -> Result<Option<InputValue>> {
+        decode_public_outputs(self.witness_generator.abi(), public_outputs)
+    }
+}
+
+pub(crate) fn read_public_inputs(
+    public_abi: &Abi,
+    num_public_inputs: usize,
+    path: impl AsRef<Path>,
+) -> Result<Vec<FieldElement>> {
+    let (input_map, _expected_return) = read_inputs_from_file(path.as_ref(), public_abi)?;
+
+    encode_public_inputs(public_abi, num_public_inputs, &input_map)
+}
+
+pub(crate) fn encode_public_inputs(
+    public_abi: &Abi,
+    num_public_inputs: usize,
+    input_map: &InputMap,
+) -> Result<Vec<FieldElement>> {
+    let public_input_map = input_map
+        .iter()
+        .filter(|(name, _)| public_abi.parameter_names().contains(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    // The ABI assigns witnesses to parameters in order, so the public
+    // parameters are encoded in ascending witness order.
+    let public_inputs: Vec<FieldElement> = public_abi
+        .encode(&public_input_map, None)?
+        .into_iter()
+        .map(|(_, value)| noir_to_native(value))
+        .collect();
+    ensure!(
+        public_inputs.len() == num_public_inputs,
+        "Unexpected number of public inputs"
+    );
+
+    Ok(public_inputs)
+}
+
+/// The ABI restricted to the return value. Return witnesses follow the
+/// parameters, so without parameters they start at zero.
+fn return_abi(abi: &Abi) -> Abi {
+    Abi {
+        parameters:  Vec::new(),
+        return_type: abi.return_type.clone(),
+        error_types: abi.error_types.clone(),
+    }
+}
+
+pub(crate) fn read_public_outputs(
+    abi: &Abi,
+    num_public_outputs: usize,
+    path: impl AsRef<Path>,
+) -> Result<Option<Vec<FieldElement>>> {
+    let return_abi = return_abi(abi);
+    let (_input_map, expected_return) = read_inputs_from_file(path.as_ref(), &return_abi)?;
+    let Some(expected_return) = expected_return else {
+        return Ok(None);
+    };
+
+    let public_outputs: Vec<FieldElement> = return_abi
+        .encode(&InputMap::new(), Some(expected_return))?
+        .into_iter()
+        .map(|(_, value)| noir_to_native(value))
+        .collect();
+    ensure!(
+        public_outputs.len() == num_public_outputs,
+        "Unexpected number of public outputs"
+    );
+
+    Ok(Some(public_outputs))
+}
+
+pub(crate) fn decode_public_outputs(
+    abi: &Abi,
+    public_outputs: &[FieldElement],
+) -> Result<Option<InputValue>> {
+    let witness_map = WitnessMap::from(
+        public_outputs
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (Witness(i as u32), native_to_noir(*value)))
+            .collect::<BTreeMap<_, _>>(),
+    );
+    let (_input_map, return_value) = return_abi(abi).decode(&witness_map)?;
+
+    Ok(return_value)
 }
@@ -6,11 +6,13 @@ use {
 /// Represents a R1CS constraint system.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct R1CS {
-    pub num_public_inputs: usize,
-    pub interner:          Interner,
-    pub a:                 SparseMatrix,
-    pub b:                 SparseMatrix,
-    pub c:                 SparseMatrix,
+    pub num_public_inputs:  usize,
+    /// Number of return values, laid out after the public inputs
+    pub num_public_outputs: usize,
+    pub interner:           Interner,
+    pub a:                  SparseMatrix,
+    pub b:                  SparseMatrix,
+    pub c:                  SparseMatrix,
 }
 
 impl Default for R1CS {
@@ -23,14 +25,22 @@ impl R1CS {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            num_public_inputs: 0,
-            interner:          Interner::new(),
-            a:                 SparseMatrix::new(0, 0),
-            b:                 SparseMatrix::new(0, 0),
-            c:                 SparseMatrix::new(0, 0),
+            num_public_inputs:  0,
+            num_public_outputs: 0,
+            interner:           Interner::new(),
+            a:                  SparseMatrix::new(0, 0),
+            b:                  SparseMatrix::new(0, 0),
+            c:                  SparseMatrix::new(0, 0),
         }
     }
 
+    /// Number of public inputs and outputs, which follow the constant one
+    /// witness.
+    #[must_use]
+    pub const fn num_public_values(&self) -> usize {
+        self.num_public_inputs + self.num_public_outputs
+    }
+
     #[must_use]
     pub const fn a(&self) -> HydratedSparseMatrix<'_> {
         self.a.hydrate(&self.interner)
@@ -69,6 +79,14 @@ impl R1CS {
         self.grow_matrices(self.num_constraints(), self.num_witnesses() + count);
     }
 
+    /// Renumber the witnesses of the R1CS instance. The mapping must be a
+    /// permutation of the witness indices.
+    pub fn remap_witnesses(&mut self, remap: impl Fn(usize) -> usize + Copy) {
+        self.a.remap_columns(remap);
+        self.b.remap_columns(remap);
+        self.c.remap_columns(remap);
+    }
+
     /// Add an R1CS constraint.
     pub fn add_constraint(
         &mut self,
@@ -2,4 +2,8 @@ mod pow;
 mod sponge;
 mod whir;
 
-pub use self::{pow::SkyscraperPoW, sponge::SkyscraperSponge, whir::SkyscraperMerkleConfig};
+pub use self::{
+    pow::SkyscraperPoW,
+    sponge::{hash_bytes, SkyscraperSponge},
+    whir::SkyscraperMerkleConfig,
+};
@@ -2,7 +2,7 @@ use {
     crate::FieldElement,
     ark_bn254::Fr,
     ark_ff::{BigInt, PrimeField},
-    spongefish::duplex_sponge::{DuplexSponge, Permutation},
+    spongefish::duplex_sponge::{DuplexSponge, DuplexSpongeInterface, Permutation},
     zeroize::Zeroize,
 };
 
@@ -58,3 +58,20 @@ impl Permutation for Skyscraper {
 }
 
 pub type SkyscraperSponge = DuplexSponge<Skyscraper>;
+
+/// Hashes a byte string to a single field element. The bytes are absorbed as
+/// little-endian 31 byte chunks, prefixed with their length.
+pub fn hash_bytes(bytes: &[u8]) -> FieldElement {
+    let mut iv = [0_u8; 32];
+    iv[..15].copy_from_slice(b"provekit-digest");
+
+    let elements = std::iter::once(FieldElement::from(bytes.len() as u64))
+        .chain(bytes.chunks(31).map(FieldElement::from_le_bytes_mod_order))
+        .collect::<Vec<_>>();
+
+    let mut output = [FieldElement::from(0)];
+    SkyscraperSponge::new(iv)
+        .absorb_unchecked(&elements)
+        .squeeze_unchecked(&mut output);
+    output[0]
+}
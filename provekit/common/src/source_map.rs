@@ -0,0 +1,166 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fmt::{self, Display, Formatter},
+    },
+};
+
+/// A location in the Noir source of a program.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub path:   String,
+    pub line:   usize,
+    pub column: usize,
+    /// The source line, without its line terminator
+    pub text:   String,
+}
+
+/// The gadget that added some constraints and witnesses, and the ACIR opcode
+/// it compiles.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Origin {
+    /// The ACIR function being compiled
+    pub function: u32,
+    /// The index of the opcode in its function, unless the gadget constrains
+    /// the operations of a whole function, e.g. its range checks
+    pub opcode:   Option<usize>,
+    /// A description of the gadget, e.g. "range check 8-bit via lookup"
+    pub gadget:   String,
+    /// The Noir source location of the opcode, once resolved from the debug
+    /// symbols of the program
+    pub source:   Option<SourceLocation>,
+}
+
+/// Maps the constraints and witnesses of an R1CS instance to their origins.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// The names of the ACIR functions, if known
+    pub functions:   Vec<String>,
+    pub origins:     Vec<Origin>,
+    /// The index of the origin of each constraint
+    pub constraints: Vec<usize>,
+    /// The index of the origin of each witness, including the witnesses that
+    /// were eliminated from the R1CS instance
+    pub witnesses:   Vec<usize>,
+}
+
+impl SourceMap {
+    /// The origin of a constraint.
+    #[must_use]
+    pub fn constraint_origin(&self, row: usize) -> Option<&Origin> {
+        self.constraints.get(row).map(|&i| &self.origins[i])
+    }
+
+    /// The origin of a witness.
+    #[must_use]
+    pub fn witness_origin(&self, witness: usize) -> Option<&Origin> {
+        self.witnesses.get(witness).map(|&i| &self.origins[i])
+    }
+
+    /// Renumber the witnesses, given the new index of each.
+    pub fn remap_witnesses(&mut self, remap: impl Fn(usize) -> usize) {
+        let mut witnesses = vec![0; self.witnesses.len()];
+        for (old, &origin) in self.witnesses.iter().enumerate() {
+            witnesses[remap(old)] = origin;
+        }
+        self.witnesses = witnesses;
+    }
+
+    /// Keep the constraints for which `keep` holds, in order.
+    pub fn retain_constraints(&mut self, keep: impl Fn(usize) -> bool) {
+        self.constraints = self
+            .constraints
+            .iter()
+            .enumerate()
+            .filter(|&(row, _)| keep(row))
+            .map(|(_, &origin)| origin)
+            .collect();
+    }
+
+    /// The name of an ACIR function, or its index if unknown.
+    #[must_use]
+    pub fn function_name(&self, function: u32) -> String {
+        self.functions
+            .get(function as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("function {function}"))
+    }
+
+    /// The numbers of constraints and witnesses of the origins with the same
+    /// key, e.g. the same gadget or function.
+    pub fn profile<K: Ord>(&self, key: impl Fn(&Origin) -> K) -> BTreeMap<K, (usize, usize)> {
+        let mut profile = BTreeMap::<K, (usize, usize)>::new();
+        for &i in &self.constraints {
+            profile.entry(key(&self.origins[i])).or_default().0 += 1;
+        }
+        for &i in &self.witnesses {
+            profile.entry(key(&self.origins[i])).or_default().1 += 1;
+        }
+        profile
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.gadget)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " (ACIR opcode {opcode} of function {})", self.function)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " at {source}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Origin, SourceMap};
+
+    #[test]
+    fn test_profile() {
+        let origin = |function, gadget: &str| Origin {
+            function,
+            opcode: None,
+            gadget: gadget.to_owned(),
+            source: None,
+        };
+        let source_map = SourceMap {
+            functions:   vec!["main".to_owned()],
+            origins:     vec![
+                origin(0, "assert zero"),
+                origin(1, "assert zero"),
+                origin(1, "range check"),
+            ],
+            constraints: vec![0, 1, 1, 2],
+            witnesses:   vec![0, 2, 2],
+        };
+        assert_eq!(
+            source_map
+                .profile(|origin| origin.gadget.clone())
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![
+                ("assert zero".to_owned(), (3, 1)),
+                ("range check".to_owned(), (1, 2))
+            ]
+        );
+        assert_eq!(
+            source_map
+                .profile(|origin| source_map.function_name(origin.function))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![
+                ("function 1".to_owned(), (3, 2)),
+                ("main".to_owned(), (1, 1))
+            ]
+        );
+    }
+}
@@ -0,0 +1,456 @@
+//! Spark-style commitment to the sparse R1CS matrices.
+//!
+//! Each matrix is described by its non-zero entries `(row, col, val)`, padded
+//! to a power of two with zero entries at `(0, 0)`. To show `M(x, y) = v` the
+//! prover commits to `eq(x, row)` and `eq(y, col)` for every entry, proves with
+//! a sumcheck that `Σ val · eq(x, row) · eq(y, col) = v`, and proves with LogUp
+//! that the committed values are lookups into the tables `eq(x, ·)` and
+//! `eq(y, ·)`. The rows, columns, values and lookup counts only depend on the
+//! matrices, so they are committed to once when preparing the scheme.
+
+use {
+    crate::{
+        utils::{next_power_of_two, serde_ark, sumcheck::SumcheckIOPattern},
+        FieldElement, HydratedSparseMatrix, IOPattern, WhirConfig, R1CS,
+    },
+    ark_std::{One, Zero},
+    serde::{Deserialize, Serialize},
+    spongefish::codecs::arkworks_algebra::FieldDomainSeparator,
+    whir::whir::domainsep::WhirDomainSeparator,
+};
+
+/// Number of variables selecting a slot of the matrix commitment: rows,
+/// columns and values of each matrix, followed by the row and column counts.
+pub const LOG_MATRIX_SLOTS: usize = 4;
+
+/// A term `coefficient · Π polynomials[i]` of a sumcheck over products of
+/// multilinear polynomials.
+pub type SumcheckTerm = (FieldElement, Vec<usize>);
+
+/// Evaluation claims of the committed polynomials, as points and values.
+pub type Openings = Vec<(Vec<FieldElement>, FieldElement)>;
+
+/// The challenges of the Spark protocol.
+#[derive(Clone, Copy, Debug)]
+pub struct SparkChallenges {
+    /// LogUp challenge for the index
+    pub gamma: FieldElement,
+    /// LogUp challenge for the looked up value
+    pub delta: FieldElement,
+    /// Randomness combining the claims of a sumcheck
+    pub theta: FieldElement,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SparkScheme {
+    /// Number of variables indexing the non-zero entries of a matrix
+    pub log_num_entries: usize,
+    /// Number of variables indexing the row and column lookup tables
+    pub log_table_size:  usize,
+    /// Column at which the witnesses committed after the challenges start
+    pub column_offset_2: Option<usize>,
+    /// Merkle root of the commitment to the matrices
+    #[serde(with = "serde_ark")]
+    pub matrix_root:     FieldElement,
+    /// Commitment to the rows, columns, values and lookup counts
+    pub whir_matrices:   WhirConfig,
+    /// Commitment to the values looked up by each entry
+    pub whir_lookups:    WhirConfig,
+    /// Commitment to the LogUp inverses of the entries and the tables
+    pub whir_inverses:   WhirConfig,
+}
+
+/// The non-zero entries of a matrix, padded to the number of entries of the
+/// scheme.
+pub struct SparkEntries {
+    pub rows:   Vec<usize>,
+    pub cols:   Vec<usize>,
+    pub values: Vec<FieldElement>,
+}
+
+impl SparkScheme {
+    /// Number of lookups per matrix: the row, then the column for each witness
+    /// commitment.
+    pub const fn num_lookups(&self) -> usize {
+        if self.column_offset_2.is_some() {
+            3
+        } else {
+            2
+        }
+    }
+
+    pub fn log_slot_size(&self) -> usize {
+        self.log_num_entries.max(self.log_table_size)
+    }
+
+    /// Number of variables selecting a slot of the lookup commitment.
+    pub const fn log_lookup_slots(&self) -> usize {
+        next_power_of_two(3 * self.num_lookups())
+    }
+
+    /// Number of variables selecting a slot of the inverse commitment. The
+    /// entry inverses come first, in the same order as the lookups, followed by
+    /// the table inverses.
+    pub const fn log_inverse_slots(&self) -> usize {
+        next_power_of_two(4 * self.num_lookups())
+    }
+
+    /// Slot of the lookup commitment holding a lookup of a matrix. The entry
+    /// inverses of the lookup use the same slot of the inverse commitment.
+    pub const fn lookup_slot(&self, matrix: usize, lookup: usize) -> usize {
+        matrix * self.num_lookups() + lookup
+    }
+
+    /// Slot of the inverse commitment holding the inverses of a lookup table.
+    pub const fn table_slot(&self, lookup: usize) -> usize {
+        3 * self.num_lookups() + lookup
+    }
+
+    /// Position of a witness column in the column lookup table.
+    pub fn column_index(&self, w1_size: usize, col: usize) -> usize {
+        match self.column_offset_2 {
+            Some(offset) if col >= w1_size => offset + col - w1_size,
+            _ => col,
+        }
+    }
+
+    /// The points defining the lookup tables `eq(point, ·)`: the row table,
+    /// then a column table for each witness commitment. Each table only covers
+    /// the part of the column index space belonging to its commitment.
+    pub fn table_points(
+        &self,
+        row_point: &[FieldElement],
+        column_points: &[&[FieldElement]],
+    ) -> Vec<Vec<FieldElement>> {
+        assert_eq!(column_points.len() + 1, self.num_lookups());
+        let mut points = vec![
+            embed_point(row_point, 0, self.log_table_size),
+            embed_point(column_points[0], 0, self.log_table_size),
+        ];
+        if let Some(offset) = self.column_offset_2 {
+            points.push(embed_point(column_points[1], offset, self.log_table_size));
+        }
+        points
+    }
+
+    /// The padded entries of a matrix, with columns mapped to the column table.
+    pub fn entries(&self, matrix: HydratedSparseMatrix<'_>, w1_size: usize) -> SparkEntries {
+        let size = 1 << self.log_num_entries;
+        assert!(matrix.matrix.num_entries() <= size);
+        let mut entries = SparkEntries {
+            rows:   vec![0; size],
+            cols:   vec![0; size],
+            values: vec![FieldElement::zero(); size],
+        };
+        for (k, ((row, col), value)) in matrix.iter().enumerate() {
+            entries.rows[k] = row;
+            entries.cols[k] = self.column_index(w1_size, col);
+            entries.values[k] = value;
+        }
+        entries
+    }
+
+    /// Evaluations of the committed matrix polynomial.
+    pub fn matrix_polynomial(&self, r1cs: &R1CS, w1_size: usize) -> Vec<FieldElement> {
+        let slot_size = 1 << self.log_slot_size();
+        let table_size = 1 << self.log_table_size;
+        let mut evaluations = vec![FieldElement::zero(); slot_size << LOG_MATRIX_SLOTS];
+        let mut slots = evaluations.chunks_exact_mut(slot_size).collect::<Vec<_>>();
+
+        for (matrix, hydrated) in [r1cs.a(), r1cs.b(), r1cs.c()].into_iter().enumerate() {
+            let entries = self.entries(hydrated, w1_size);
+            let mut row_counts = vec![0_u64; table_size];
+            let mut col_counts = vec![0_u64; table_size];
+            for k in 0..entries.values.len() {
+                slots[matrix_slot(matrix, 0)][k] = FieldElement::from(entries.rows[k] as u64);
+                slots[matrix_slot(matrix, 1)][k] = FieldElement::from(entries.cols[k] as u64);
+                slots[matrix_slot(matrix, 2)][k] = entries.values[k];
+                row_counts[entries.rows[k]] += 1;
+                col_counts[entries.cols[k]] += 1;
+            }
+            for (i, (rows, cols)) in row_counts.into_iter().zip(col_counts).enumerate() {
+                slots[count_slot(matrix, 0)][i] = FieldElement::from(rows);
+                slots[count_slot(matrix, 1)][i] = FieldElement::from(cols);
+            }
+        }
+
+        evaluations
+    }
+
+    /// Terms of the sumcheck over the entries. The polynomials are `eq(τ, ·)`,
+    /// the rows, columns and values of each matrix, the lookups and the entry
+    /// inverses. Together they show the matrix evaluations and that the
+    /// inverses are well formed.
+    pub fn entry_terms(&self, challenges: SparkChallenges) -> Vec<SumcheckTerm> {
+        let num_lookups = self.num_lookups();
+        let num_proofs = num_lookups - 1;
+        let coefficients = powers(challenges.theta, 3 * num_proofs + 6 * num_lookups);
+        let lookup = |slot: usize| 10 + slot;
+        let inverse = |slot: usize| 10 + 3 * num_lookups + slot;
+
+        let mut terms = Vec::new();
+        for matrix in 0..3 {
+            for proof in 0..num_proofs {
+                terms.push((coefficients[matrix * num_proofs + proof], vec![
+                    1 + matrix_slot(matrix, 2),
+                    lookup(self.lookup_slot(matrix, 0)),
+                    lookup(self.lookup_slot(matrix, proof + 1)),
+                ]));
+            }
+        }
+        for matrix in 0..3 {
+            for d in 0..num_lookups {
+                let slot = self.lookup_slot(matrix, d);
+                let index = 1 + matrix_slot(matrix, d.min(1));
+                let sum = coefficients[3 * num_proofs + 2 * slot];
+                let well_formed = coefficients[3 * num_proofs + 2 * slot + 1];
+                terms.extend([
+                    (sum, vec![inverse(slot)]),
+                    (well_formed * challenges.gamma, vec![0, inverse(slot)]),
+                    (-well_formed, vec![0, inverse(slot), index]),
+                    (-well_formed * challenges.delta, vec![
+                        0,
+                        inverse(slot),
+                        lookup(slot),
+                    ]),
+                    (-well_formed, vec![0]),
+                ]);
+            }
+        }
+        terms
+    }
+
+    /// The sum of the entry terms, given the evaluations of each matrix claimed
+    /// by each witness opening and the sums of the entry inverses.
+    pub fn entry_claim(
+        &self,
+        challenges: SparkChallenges,
+        matrix_claims: &[[FieldElement; 3]],
+        sums: &[FieldElement],
+    ) -> FieldElement {
+        let num_lookups = self.num_lookups();
+        let num_proofs = num_lookups - 1;
+        assert_eq!(matrix_claims.len(), num_proofs);
+        assert_eq!(sums.len(), 3 * num_lookups);
+        let coefficients = powers(challenges.theta, 3 * num_proofs + 6 * num_lookups);
+
+        let mut claim = FieldElement::zero();
+        for matrix in 0..3 {
+            for (proof, claims) in matrix_claims.iter().enumerate() {
+                claim += coefficients[matrix * num_proofs + proof] * claims[matrix];
+            }
+        }
+        for (slot, sum) in sums.iter().enumerate() {
+            claim += coefficients[3 * num_proofs + 2 * slot] * sum;
+        }
+        claim
+    }
+
+    /// Terms of the sumcheck over the tables. The polynomials are `eq(τ, ·)`,
+    /// the index, the tables, the row and column counts of each matrix and the
+    /// table inverses. Together they complete the LogUp arguments.
+    pub fn table_terms(&self, challenges: SparkChallenges) -> Vec<SumcheckTerm> {
+        let num_lookups = self.num_lookups();
+        let coefficients = powers(challenges.theta, 4 * num_lookups);
+        let table = |d: usize| 2 + d;
+        let count = |matrix: usize, dimension: usize| 2 + num_lookups + 2 * matrix + dimension;
+        let inverse = |d: usize| 8 + num_lookups + d;
+
+        let mut terms = Vec::new();
+        for matrix in 0..3 {
+            for d in 0..num_lookups {
+                terms.push((coefficients[self.lookup_slot(matrix, d)], vec![
+                    count(matrix, d.min(1)),
+                    inverse(d),
+                ]));
+            }
+        }
+        for d in 0..num_lookups {
+            let well_formed = coefficients[3 * num_lookups + d];
+            terms.extend([
+                (well_formed * challenges.gamma, vec![0, inverse(d)]),
+                (-well_formed, vec![0, inverse(d), 1]),
+                (-well_formed * challenges.delta, vec![0, inverse(d), table(d)]),
+                (-well_formed, vec![0]),
+            ]);
+        }
+        terms
+    }
+
+    /// The sum of the table terms, given the sums of the entry inverses.
+    pub fn table_claim(&self, challenges: SparkChallenges, sums: &[FieldElement]) -> FieldElement {
+        assert_eq!(sums.len(), 3 * self.num_lookups());
+        let coefficients = powers(challenges.theta, sums.len());
+        coefficients.iter().zip(sums).map(|(c, s)| *c * s).sum()
+    }
+
+    /// The evaluations of the matrix, lookup and inverse commitments implied by
+    /// the final evaluations of the entry and table sumchecks. The evaluations
+    /// are those sent in the transcript, so exclude `eq(τ, ·)`, the index and
+    /// the tables.
+    pub fn openings(
+        &self,
+        entry_point: &[FieldElement],
+        entry_evaluations: &[FieldElement],
+        table_point: &[FieldElement],
+        table_evaluations: &[FieldElement],
+    ) -> [Openings; 3] {
+        let num_lookups = 3 * self.num_lookups();
+        assert_eq!(entry_evaluations.len(), 9 + 2 * num_lookups);
+        assert_eq!(table_evaluations.len(), 6 + self.num_lookups());
+        let log_slot_size = self.log_slot_size();
+        let (fields, tail) = entry_evaluations.split_at(9);
+        let (lookups, inverses) = tail.split_at(num_lookups);
+        let (counts, table_inverses) = table_evaluations.split_at(6);
+
+        let matrices = fields
+            .iter()
+            .enumerate()
+            .map(|(slot, value)| {
+                let point = slot_point(slot, LOG_MATRIX_SLOTS, log_slot_size, entry_point);
+                (point, *value)
+            })
+            .chain(counts.iter().enumerate().map(|(i, value)| {
+                let point = slot_point(
+                    count_slot(i / 2, i % 2),
+                    LOG_MATRIX_SLOTS,
+                    log_slot_size,
+                    table_point,
+                );
+                (point, *value)
+            }))
+            .collect();
+        let lookups = lookups
+            .iter()
+            .enumerate()
+            .map(|(slot, value)| {
+                let point = slot_point(
+                    slot,
+                    self.log_lookup_slots(),
+                    self.log_num_entries,
+                    entry_point,
+                );
+                (point, *value)
+            })
+            .collect();
+        let inverses = inverses
+            .iter()
+            .enumerate()
+            .map(|(slot, value)| {
+                let point = slot_point(slot, self.log_inverse_slots(), log_slot_size, entry_point);
+                (point, *value)
+            })
+            .chain(table_inverses.iter().enumerate().map(|(d, value)| {
+                let point = slot_point(
+                    self.table_slot(d),
+                    self.log_inverse_slots(),
+                    log_slot_size,
+                    table_point,
+                );
+                (point, *value)
+            }))
+            .collect();
+
+        [matrices, lookups, inverses]
+    }
+
+    pub fn add_io_pattern(&self, io: IOPattern) -> IOPattern {
+        let num_lookups = 3 * self.num_lookups();
+        io.commit_statement(&self.whir_matrices)
+            .commit_statement(&self.whir_lookups)
+            .challenge_scalars(2, "spark:logup_challenges")
+            .commit_statement(&self.whir_inverses)
+            .add_scalars(num_lookups, "spark:logup_sums")
+            .challenge_scalars(1, "spark:combination_randomness")
+            .add_rand(self.log_num_entries + self.log_table_size)
+            .add_sumcheck_polynomials(self.log_num_entries)
+            .add_scalars(9 + 2 * num_lookups, "spark:entry_evaluations")
+            .add_sumcheck_polynomials(self.log_table_size)
+            .add_scalars(6 + self.num_lookups(), "spark:table_evaluations")
+            .add_whir_proof(&self.whir_matrices)
+            .add_whir_proof(&self.whir_lookups)
+            .add_whir_proof(&self.whir_inverses)
+    }
+}
+
+/// Slot of the matrix commitment holding the rows (0), columns (1) or values
+/// (2) of a matrix.
+pub const fn matrix_slot(matrix: usize, field: usize) -> usize {
+    3 * matrix + field
+}
+
+/// Slot of the matrix commitment holding the row (0) or column (1) lookup
+/// counts of a matrix.
+pub const fn count_slot(matrix: usize, dimension: usize) -> usize {
+    9 + 2 * matrix + dimension
+}
+
+/// Embeds `point` into a hypercube of `num_variables` variables, such that
+/// `eq(embedded, i) = eq(point, i - offset)` on the sub-cube starting at
+/// `offset` and zero elsewhere. The offset must be aligned to the size of the
+/// sub-cube.
+pub fn embed_point(
+    point: &[FieldElement],
+    offset: usize,
+    num_variables: usize,
+) -> Vec<FieldElement> {
+    assert!(point.len() <= num_variables);
+    assert_eq!(offset % (1 << point.len()), 0);
+    let high = offset >> point.len();
+    let num_high = num_variables - point.len();
+    assert_eq!(high >> num_high, 0, "Offset out of range");
+
+    (0..num_high)
+        .map(|i| {
+            if (high >> (num_high - 1 - i)) & 1 == 1 {
+                FieldElement::one()
+            } else {
+                FieldElement::zero()
+            }
+        })
+        .chain(point.iter().copied())
+        .collect()
+}
+
+/// The point selecting `point` within a slot of a committed polynomial made of
+/// `1 << log_slots` slots of `1 << log_slot_size` evaluations each.
+pub fn slot_point(
+    slot: usize,
+    log_slots: usize,
+    log_slot_size: usize,
+    point: &[FieldElement],
+) -> Vec<FieldElement> {
+    embed_point(point, slot << log_slot_size, log_slots + log_slot_size)
+}
+
+/// Evaluates a sum of products of polynomials given the evaluations of the
+/// polynomials.
+pub fn evaluate_terms(terms: &[SumcheckTerm], evaluations: &[FieldElement]) -> FieldElement {
+    terms
+        .iter()
+        .map(|(coefficient, factors)| {
+            factors
+                .iter()
+                .fold(*coefficient, |acc, &i| acc * evaluations[i])
+        })
+        .sum()
+}
+
+/// The first `count` powers of `base`, starting at one.
+pub fn powers(base: FieldElement, count: usize) -> Vec<FieldElement> {
+    let mut result = Vec::with_capacity(count);
+    let mut power = FieldElement::one();
+    for _ in 0..count {
+        result.push(power);
+        power *= base;
+    }
+    result
+}
+
+/// Evaluates the multilinear extension of the index function `i ↦ i` at
+/// `point`.
+pub fn evaluate_index(point: &[FieldElement]) -> FieldElement {
+    point
+        .iter()
+        .fold(FieldElement::zero(), |acc, x| acc + acc + x)
+}
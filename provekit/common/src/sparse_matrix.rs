@@ -91,6 +91,24 @@ impl SparseMatrix {
         }
     }
 
+    /// Renumber the columns of the matrix, e.g. after reordering the
+    /// witnesses. The mapping must be injective.
+    pub fn remap_columns(&mut self, remap: impl Fn(usize) -> usize) {
+        for row in 0..self.num_rows {
+            let row_range = self.row_range(row);
+            let mut entries = self.col_indices[row_range.clone()]
+                .iter()
+                .zip(&self.values[row_range.clone()])
+                .map(|(&col, &value)| (remap(col as usize) as u32, value))
+                .collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(col, _)| *col);
+            for (i, (col, value)) in row_range.zip(entries) {
+                self.col_indices[i] = col;
+                self.values[i] = value;
+            }
+        }
+    }
+
     /// Iterate over the non-default entries of a row of the matrix.
     pub fn iter_row(
         &self,
@@ -7,7 +7,7 @@ pub mod serde_jsonify;
 pub mod sumcheck;
 pub mod zk_utils;
 
-pub use self::print_abi::PrintAbi;
+pub use self::print_abi::{PrintAbi, PrintInputValue};
 use {
     crate::{FieldElement, NoirElement},
     ark_ff::{BigInt, PrimeField},
@@ -68,6 +68,12 @@ pub fn noir_to_native(n: NoirElement) -> FieldElement {
     FieldElement::from(BigInt(limbs))
 }
 
+/// Convert a native `FieldElement` to a Noir field element
+#[inline(always)]
+pub fn native_to_noir(n: FieldElement) -> NoirElement {
+    NoirElement::from_repr(ark_bn254::Fr::from(n.into_bigint()))
+}
+
 /// Calculates the degree of the next smallest power of two
 pub const fn next_power_of_two(n: usize) -> usize {
     let mut power = 1;
@@ -1,5 +1,5 @@
 use {
-    noirc_abi::{Abi, AbiType, AbiVisibility, Sign},
+    noirc_abi::{input_parser::InputValue, Abi, AbiType, AbiVisibility, Sign},
     std::fmt::{Display, Formatter, Result},
 };
 
@@ -7,6 +7,8 @@ pub struct PrintAbi<'a>(pub &'a Abi);
 
 pub struct PrintType<'a>(pub &'a AbiType);
 
+pub struct PrintInputValue<'a>(pub &'a InputValue);
+
 impl Display for PrintAbi<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(f, "(")?;
@@ -60,3 +62,32 @@ impl Display for PrintType<'_> {
         }
     }
 }
+
+impl Display for PrintInputValue<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            InputValue::Field(value) => write!(f, "{value}"),
+            InputValue::String(value) => write!(f, "{value:?}"),
+            InputValue::Vec(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    write!(f, "{}", PrintInputValue(value))?;
+                    if idx < values.len() - 1 {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            InputValue::Struct(fields) => {
+                write!(f, "{{")?;
+                for (idx, (name, value)) in fields.iter().enumerate() {
+                    write!(f, "{}: {}", name, PrintInputValue(value))?;
+                    if idx < fields.len() - 1 {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -216,3 +216,33 @@ pub fn calculate_external_row_of_r1cs_matrices(
     );
     [a, b, c]
 }
+
+/// Weights of length `size` selecting the public inputs and outputs from the
+/// R1CS witness, combined with the powers of `tau`. Public values are laid out
+/// directly after the constant one witness.
+pub fn calculate_public_input_weights(
+    tau: FieldElement,
+    num_public_values: usize,
+    size: usize,
+) -> Vec<FieldElement> {
+    let mut weights = vec![FieldElement::zero(); size];
+    let mut power = FieldElement::one();
+    for weight in &mut weights[1..=num_public_values] {
+        *weight = power;
+        power *= tau;
+    }
+    weights
+}
+
+/// Expands each value `x` into the monomials `(1, x, x^2, x^3)` of a cubic
+/// sumcheck polynomial.
+pub fn expand_powers(values: &[FieldElement]) -> Vec<FieldElement> {
+    let mut result = Vec::with_capacity(values.len() * 4);
+    for &value in values {
+        result.push(FieldElement::one());
+        result.push(value);
+        result.push(value * value);
+        result.push(value * value * value);
+    }
+    result
+}
@@ -1,11 +1,12 @@
 use {
     crate::{
         skyscraper::{SkyscraperMerkleConfig, SkyscraperPoW, SkyscraperSponge},
+        spark::SparkScheme,
         utils::{serde_hex, sumcheck::SumcheckIOPattern},
         FieldElement,
     },
     serde::{Deserialize, Serialize},
-    spongefish::DomainSeparator,
+    spongefish::{codecs::arkworks_algebra::FieldDomainSeparator, DomainSeparator},
     std::fmt::{Debug, Formatter},
     tracing::instrument,
     whir::whir::{domainsep::WhirDomainSeparator, parameters::WhirConfig as GenericWhirConfig},
@@ -19,24 +20,67 @@ pub struct WhirR1CSScheme {
     pub m: usize,
     pub m_0: usize,
     pub a_num_terms: usize,
+    pub num_public_inputs: usize,
+    pub num_public_outputs: usize,
+    /// Number of witnesses committed before the challenges are derived
+    pub w1_size: usize,
+    /// Number of challenges derived from the first witness commitment
+    pub num_challenges: usize,
     pub whir_witness: WhirConfig,
+    /// Commitment to the witnesses that depend on the challenges, if any
+    pub whir_witness_2: Option<WhirConfig>,
     pub whir_for_hiding_spartan: WhirConfig,
+    /// Commitment to the R1CS matrices, if the verifier should not need them
+    pub spark: Option<SparkScheme>,
 }
 
 impl WhirR1CSScheme {
     #[instrument(skip_all)]
     pub fn create_io_pattern(&self) -> IOPattern {
-        let io = IOPattern::new("🌪️")
-            .commit_statement(&self.whir_witness)
+        let mut io = IOPattern::new("🌪️").add_scalars(1, "circuit_digest");
+        if self.num_public_values() > 0 {
+            io = io.add_scalars(self.num_public_values(), "public_values");
+        }
+
+        io = io.commit_statement(&self.whir_witness);
+        if let Some(whir_witness_2) = &self.whir_witness_2 {
+            io = io
+                .challenge_scalars(self.num_challenges, "wb:challenges")
+                .commit_statement(whir_witness_2);
+        }
+
+        io = io
             .add_rand(self.m_0)
             .commit_statement(&self.whir_for_hiding_spartan)
             .add_zk_sumcheck_polynomials(self.m_0)
-            .add_whir_proof(&self.whir_for_hiding_spartan)
+            .add_whir_proof(&self.whir_for_hiding_spartan);
+
+        // Randomness for combining the public values into a single opening
+        if self.num_public_values() > 0 {
+            io = io.challenge_scalars(1, "public_inputs_rand");
+        }
+
+        io = io
             .hint("claimed_evaluations")
             .add_whir_proof(&self.whir_witness);
+        if let Some(whir_witness_2) = &self.whir_witness_2 {
+            io = io
+                .hint("claimed_evaluations_2")
+                .add_whir_proof(whir_witness_2);
+        }
+
+        // Proof of the matrix evaluations deferred by the witness openings
+        if let Some(spark) = &self.spark {
+            io = spark.add_io_pattern(io);
+        }
 
         io
     }
+
+    /// Number of public inputs and outputs bound to the transcript.
+    pub const fn num_public_values(&self) -> usize {
+        self.num_public_inputs + self.num_public_outputs
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -51,6 +95,11 @@ impl Debug for WhirR1CSScheme {
         f.debug_struct("WhirR1CSScheme")
             .field("m", &self.m)
             .field("m_0", &self.m_0)
+            .field("num_public_inputs", &self.num_public_inputs)
+            .field("num_public_outputs", &self.num_public_outputs)
+            .field("w1_size", &self.w1_size)
+            .field("num_challenges", &self.num_challenges)
+            .field("spark", &self.spark.is_some())
             .finish()
     }
 }
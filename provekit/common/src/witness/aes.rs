@@ -0,0 +1,157 @@
+//! AES-128, on the 32-bit words of the columns of its state, so that the XORs
+//! fit the bin op lookups. The bytes of a column, from its first row, are the
+//! little-endian bytes of its word. The S-box and the multiplications of
+//! MixColumns are byte table lookups.
+
+use {
+    crate::witness::byte_table::ByteTable,
+    serde::{Deserialize, Serialize},
+};
+
+/// Number of rounds of AES-128
+pub const NUM_ROUNDS: usize = 10;
+
+/// Number of bytes of a block, and of a key
+pub const BLOCK_BYTES: usize = 16;
+
+/// Number of witnesses written by a round other than the last: the bytes of
+/// the state (16), their S-box lookups (16), the multiplications of
+/// MixColumns (32), the four words of each column combined by MixColumns (16)
+/// and the XORs of MixColumns and AddRoundKey (16).
+pub const WITNESSES_PER_ROUND: usize = 96;
+
+/// Number of witnesses written by the last round, which has no MixColumns: the
+/// bytes of the state (16), their S-box lookups (16), the words of the columns
+/// (4) and the XORs of AddRoundKey (4).
+pub const WITNESSES_PER_LAST_ROUND: usize = 40;
+
+/// Number of witnesses written by the key schedule: for each round, the bytes
+/// of the last word of the previous round key (4), their S-box lookups (4),
+/// the word they form (1), the XOR of the round constant (1) and the words of
+/// the round key (4).
+pub const KEY_SCHEDULE_WITNESSES: usize = 140;
+
+/// The round constants of the key schedule
+pub const ROUND_CONSTANTS: [u8; NUM_ROUNDS] =
+    [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// The operations of AES on bytes and 32-bit words, implemented both to solve
+/// for the witnesses and to constrain them. Each operation returns the values
+/// it writes, and must write them in the order they are returned.
+pub trait AesOps {
+    type Value: Copy;
+
+    fn xor(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value;
+
+    fn xor_constant(&mut self, word: Self::Value, constant: u32) -> Self::Value;
+
+    fn lookup(&mut self, table: ByteTable, byte: Self::Value) -> Self::Value;
+
+    /// The word with the given little-endian bytes
+    fn pack(&mut self, bytes: [Self::Value; 4]) -> Self::Value;
+
+    /// The little-endian bytes of a word
+    fn unpack(&mut self, word: Self::Value) -> [Self::Value; 4];
+}
+
+/// Allocates the witnesses of the key schedule of the given key, as written by
+/// [`key_schedule`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Aes128KeyScheduleWitnesses {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    /// The witnesses of the words of the key
+    pub key:               [usize; 4],
+}
+
+impl Aes128KeyScheduleWitnesses {
+    /// The number of witnesses written to
+    pub const fn num_witnesses(&self) -> usize {
+        KEY_SCHEDULE_WITNESSES
+    }
+}
+
+/// Allocates the witnesses of a round applied to the given state, as written
+/// by [`aes_round`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Aes128RoundWitnesses {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    /// The index of the round, from 1
+    pub round:             usize,
+    /// The witnesses of the words of the input state
+    pub state:             [usize; 4],
+    /// The witnesses of the words of the round key
+    pub round_key:         [usize; 4],
+}
+
+impl Aes128RoundWitnesses {
+    /// The number of witnesses written to
+    pub const fn num_witnesses(&self) -> usize {
+        if self.round == NUM_ROUNDS {
+            WITNESSES_PER_LAST_ROUND
+        } else {
+            WITNESSES_PER_ROUND
+        }
+    }
+}
+
+/// The round keys of a key, the first of which is the key itself. Writes
+/// [`KEY_SCHEDULE_WITNESSES`] values.
+pub fn key_schedule<O: AesOps>(ops: &mut O, key: [O::Value; 4]) -> [[O::Value; 4]; NUM_ROUNDS + 1] {
+    let mut round_keys = [key; NUM_ROUNDS + 1];
+    for round in 1..=NUM_ROUNDS {
+        let previous = round_keys[round - 1];
+        // RotWord and SubWord
+        let bytes = ops.unpack(previous[3]);
+        let substituted = [1, 2, 3, 0].map(|i| ops.lookup(ByteTable::AesSbox, bytes[i]));
+        let word = ops.pack(substituted);
+        let mut word = ops.xor_constant(word, u32::from(ROUND_CONSTANTS[round - 1]));
+        for (i, previous) in previous.into_iter().enumerate() {
+            word = ops.xor(previous, word);
+            round_keys[round][i] = word;
+        }
+    }
+    round_keys
+}
+
+/// A round of AES-128, from 1 to [`NUM_ROUNDS`], returning the output state.
+/// Writes [`WITNESSES_PER_ROUND`] values, or [`WITNESSES_PER_LAST_ROUND`] for
+/// the last round.
+pub fn aes_round<O: AesOps>(
+    ops: &mut O,
+    state: [O::Value; 4],
+    round_key: [O::Value; 4],
+    round: usize,
+) -> [O::Value; 4] {
+    // SubBytes and ShiftRows, which moves row `r` of column `c + r` to column `c`
+    let bytes = state.map(|word| ops.unpack(word));
+    let substituted = bytes.map(|column| column.map(|byte| ops.lookup(ByteTable::AesSbox, byte)));
+    let shifted: [[O::Value; 4]; 4] =
+        std::array::from_fn(|c| std::array::from_fn(|r| substituted[(c + r) % 4][r]));
+
+    if round == NUM_ROUNDS {
+        let columns = shifted.map(|column| ops.pack(column));
+        return std::array::from_fn(|c| ops.xor(columns[c], round_key[c]));
+    }
+
+    // MixColumns, where row `r` is `2 a_r + 3 a_(r+1) + a_(r+2) + a_(r+3)`, and
+    // AddRoundKey
+    let doubled = shifted.map(|column| column.map(|byte| ops.lookup(ByteTable::AesMul2, byte)));
+    let tripled = shifted.map(|column| column.map(|byte| ops.lookup(ByteTable::AesMul3, byte)));
+    std::array::from_fn(|c| {
+        let rotated = |column: [O::Value; 4], by: usize| -> [O::Value; 4] {
+            std::array::from_fn(|r| column[(r + by) % 4])
+        };
+        let terms = [
+            ops.pack(doubled[c]),
+            ops.pack(rotated(tripled[c], 1)),
+            ops.pack(rotated(shifted[c], 2)),
+            ops.pack(rotated(shifted[c], 3)),
+        ];
+        let mixed = terms[1..]
+            .iter()
+            .fold(terms[0], |word, term| ops.xor(word, *term));
+        ops.xor(mixed, round_key[c])
+    })
+}
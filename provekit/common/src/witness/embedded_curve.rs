@@ -0,0 +1,196 @@
+//! Grumpkin, the curve `y^2 = x^3 - 17` over the BN254 scalar field, used by
+//! the Noir `multi_scalar_mul` and `embedded_curve_add` black boxes. Its group
+//! has prime order (the BN254 base field modulus), so no point other than the
+//! point at infinity has a zero y coordinate. As `-17` is not a square, no
+//! point has a zero x coordinate either, and the point at infinity is
+//! represented as `(0, 0)` with an infinity flag.
+
+use {
+    crate::{utils::uint_to_field, FieldElement},
+    ark_ff::{Field, One, Zero},
+    ruint::uint,
+    serde::{Deserialize, Serialize},
+};
+
+/// The constant term of the curve equation, which is `-B`
+pub const B: u64 = 17;
+
+/// The generator of the group used by Noir
+pub const GENERATOR: [FieldElement; 2] = [
+    uint_to_field(uint!(1_U256)),
+    uint_to_field(uint!(
+        17631683881184975370165255887551781615748388533673675138860_U256
+    )),
+];
+
+/// Number of witnesses written by a complete addition: see [`complete_add`]
+pub const COMPLETE_ADD_WITNESSES: usize = 22;
+
+/// Number of witnesses written by an incomplete addition or a doubling: see
+/// [`chord`] and [`double`]
+pub const INCOMPLETE_ADD_WITNESSES: usize = 4;
+
+/// Allocates the witnesses of a complete addition of two points given as
+/// (x, y, is_infinite) witnesses, as written by [`complete_add`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddedCurveAddWitnesses {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    pub lhs:               [usize; 3],
+    pub rhs:               [usize; 3],
+}
+
+impl EmbeddedCurveAddWitnesses {
+    /// The number of witnesses written to
+    pub const fn num_witnesses(&self) -> usize {
+        COMPLETE_ADD_WITNESSES
+    }
+}
+
+/// The inverse of a value, or zero for zero
+fn inverse_or_zero(value: FieldElement) -> FieldElement {
+    value.inverse().unwrap_or_default()
+}
+
+/// The witnesses of the sum of two points on the curve, each either finite or
+/// the point at infinity, in the order:
+///
+/// - `1 / (x2 - x1)` (or 0), and the flag `x1 = x2`
+/// - `1 / (y1 + y2)` (or 0), and the flag `y1 + y2 = 0`
+/// - the flag that the points are opposite, the product of those two flags
+/// - `x1^2`, `x1 = x2 ? y1 : 0` and `x1 = x2 ? 3 x1^2 - (y2 - y1) : 0`
+/// - the slope `λ` of the chord or tangent, and the third point `(x3, y3)` on
+///   its line, negated
+/// - the flag that both points are finite, and the flag that they are finite
+///   and not opposite, so that their sum is `(x3, y3)`
+/// - the terms of the x coordinate of the sum selected from `x3`, `x1` and
+///   `x2`, and likewise for the y coordinate
+/// - the sum `(x, y, is_infinite)`, which is `(0, 0, 1)` at infinity
+pub fn complete_add(
+    [x1, y1, infinite1]: [FieldElement; 3],
+    [x2, y2, infinite2]: [FieldElement; 3],
+) -> [FieldElement; COMPLETE_ADD_WITNESSES] {
+    let one = FieldElement::one();
+    let inverse_dx = inverse_or_zero(x2 - x1);
+    let equal_x = one - (x2 - x1) * inverse_dx;
+    let inverse_sum_y = inverse_or_zero(y1 + y2);
+    let zero_sum_y = one - (y1 + y2) * inverse_sum_y;
+    let opposite = equal_x * zero_sum_y;
+    let xx = x1 * x1;
+    let tangent_y = equal_x * y1;
+    let tangent_correction = equal_x * (FieldElement::from(3u64) * xx - y2 + y1);
+    let lambda = (y2 - y1 + tangent_correction) * inverse_or_zero(x2 - x1 + tangent_y.double());
+    let x3 = lambda.square() - x1 - x2;
+    let y3 = lambda * (x1 - x3) - y1;
+    let finite = (one - infinite1) * (one - infinite2);
+    let chord = finite * (one - opposite);
+    let [chord_x, lhs_x, rhs_x, chord_y, lhs_y, rhs_y] = [
+        chord * x3,
+        (one - infinite1 - finite) * x1,
+        (one - infinite2 - finite) * x2,
+        chord * y3,
+        (one - infinite1 - finite) * y1,
+        (one - infinite2 - finite) * y2,
+    ];
+    [
+        inverse_dx,
+        equal_x,
+        inverse_sum_y,
+        zero_sum_y,
+        opposite,
+        xx,
+        tangent_y,
+        tangent_correction,
+        lambda,
+        x3,
+        y3,
+        finite,
+        chord,
+        chord_x,
+        lhs_x,
+        rhs_x,
+        chord_y,
+        lhs_y,
+        rhs_y,
+        chord_x + lhs_x + rhs_x,
+        chord_y + lhs_y + rhs_y,
+        infinite1 + infinite2 + finite.double() - one - chord,
+    ]
+}
+
+/// The witnesses of the sum of two finite points with distinct x coordinates:
+/// `1 / (x2 - x1)`, the slope of the chord and the sum.
+pub fn chord(
+    [x1, y1]: [FieldElement; 2],
+    [x2, y2]: [FieldElement; 2],
+) -> [FieldElement; INCOMPLETE_ADD_WITNESSES] {
+    let inverse_dx = inverse_or_zero(x2 - x1);
+    let lambda = (y2 - y1) * inverse_dx;
+    let x3 = lambda.square() - x1 - x2;
+    [inverse_dx, lambda, x3, lambda * (x1 - x3) - y1]
+}
+
+/// The witnesses of the double of a finite point: `x^2`, the slope of the
+/// tangent and the double.
+pub fn double([x, y]: [FieldElement; 2]) -> [FieldElement; INCOMPLETE_ADD_WITNESSES] {
+    let xx = x.square();
+    let lambda = FieldElement::from(3u64) * xx * inverse_or_zero(y.double());
+    let x3 = lambda.square() - x.double();
+    [xx, lambda, x3, lambda * (x - x3) - y]
+}
+
+/// The finite point with the least x coordinate of at least `x`, which has an
+/// unknown discrete logarithm.
+pub fn find_point(mut x: FieldElement) -> [FieldElement; 2] {
+    loop {
+        if let Some(y) = (x.square() * x - FieldElement::from(B)).sqrt() {
+            return [x, y];
+        }
+        x += FieldElement::one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{chord, complete_add, double, find_point, GENERATOR},
+        crate::FieldElement,
+        ark_ff::{One, Zero},
+    };
+
+    fn finite([x, y]: [FieldElement; 2]) -> [FieldElement; 3] {
+        [x, y, FieldElement::zero()]
+    }
+
+    fn sum(lhs: [FieldElement; 3], rhs: [FieldElement; 3]) -> [FieldElement; 3] {
+        let witnesses = complete_add(lhs, rhs);
+        [witnesses[19], witnesses[20], witnesses[21]]
+    }
+
+    #[test]
+    fn test_complete_add() {
+        let infinity = [
+            FieldElement::zero(),
+            FieldElement::zero(),
+            FieldElement::one(),
+        ];
+        let generator = finite(GENERATOR);
+        let [_, _, x2, y2] = double(GENERATOR);
+        let [_, _, x3, y3] = chord(GENERATOR, [x2, y2]);
+        assert_eq!(find_point(FieldElement::one())[0], GENERATOR[0]);
+
+        assert_eq!(sum(generator, finite([x2, y2])), finite([x3, y3]));
+        assert_eq!(sum(generator, generator), finite([x2, y2]));
+        assert_eq!(
+            sum(generator, [
+                GENERATOR[0],
+                -GENERATOR[1],
+                FieldElement::zero()
+            ]),
+            infinity
+        );
+        assert_eq!(sum(infinity, generator), generator);
+        assert_eq!(sum(generator, infinity), generator);
+        assert_eq!(sum(infinity, infinity), infinity);
+    }
+}
@@ -0,0 +1,153 @@
+//! The Keccak-f[1600] permutation, on 64-bit lanes split into a low and a high
+//! 32-bit word, so that its bitwise operations fit the bin op lookups.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of rounds of the permutation
+pub const NUM_ROUNDS: usize = 24;
+
+/// Number of 64-bit lanes in the state, indexed by `x + 5 * y`
+pub const NUM_LANES: usize = 25;
+
+/// Number of witnesses written by a round: the XORs of θ (40), the rotations
+/// and XORs of `D` (40), the XORs of `D` into the state (50), the rotations of
+/// ρ and π (24 * 6), the AND-NOTs and XORs of χ (100) and the XORs of ι (2).
+pub const WITNESSES_PER_ROUND: usize = 376;
+
+/// The round constants of ι
+pub const ROUND_CONSTANTS: [u64; NUM_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The left rotation of each lane in ρ, indexed by `x + 5 * y`
+pub const ROTATION_OFFSETS: [u32; NUM_LANES] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// A 64-bit lane as its (low, high) 32-bit words
+pub type Lane<W> = [W; 2];
+
+/// The operations of a round on 32-bit words, implemented both to solve for
+/// the witnesses of a round and to constrain them. Each operation returns the
+/// words it writes, and must write them in the order they are returned.
+pub trait KeccakOps {
+    type Word: Copy;
+
+    fn xor(&mut self, lhs: Self::Word, rhs: Self::Word) -> Self::Word;
+
+    fn xor_constant(&mut self, word: Self::Word, constant: u32) -> Self::Word;
+
+    /// `!lhs & rhs`
+    fn and_not(&mut self, lhs: Self::Word, rhs: Self::Word) -> Self::Word;
+
+    /// Rotates a lane left by `0 < amount < 32` bits. Each word is split into
+    /// its low `32 - amount` bits and its high `amount` bits, which are
+    /// written (low word first) before the rotated lane.
+    fn rotate_left(&mut self, lane: Lane<Self::Word>, amount: u32) -> Lane<Self::Word>;
+}
+
+/// Allocates the witnesses of a round of Keccak-f[1600] applied to the given
+/// state, as written by [`keccak_round`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeccakRoundWitnesses {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    /// The index of the round
+    pub round:             usize,
+    /// The witnesses of the (low, high) words of the input lanes
+    pub state:             [Lane<usize>; NUM_LANES],
+}
+
+impl KeccakRoundWitnesses {
+    /// The number of witnesses written to
+    pub const fn num_witnesses(&self) -> usize {
+        WITNESSES_PER_ROUND
+    }
+}
+
+/// Rotates a lane left by any amount, swapping its words for amounts of 32 or
+/// more.
+fn rotate_lane<O: KeccakOps>(ops: &mut O, lane: Lane<O::Word>, amount: u32) -> Lane<O::Word> {
+    let (lane, amount) = if amount >= 32 {
+        ([lane[1], lane[0]], amount - 32)
+    } else {
+        (lane, amount)
+    };
+    if amount == 0 {
+        lane
+    } else {
+        ops.rotate_left(lane, amount)
+    }
+}
+
+/// A round of Keccak-f[1600], returning the output state. Writes
+/// [`WITNESSES_PER_ROUND`] words.
+pub fn keccak_round<O: KeccakOps>(
+    ops: &mut O,
+    state: &[Lane<O::Word>; NUM_LANES],
+    round: usize,
+) -> [Lane<O::Word>; NUM_LANES] {
+    // θ
+    let columns: [Lane<O::Word>; 5] = std::array::from_fn(|x| {
+        (1..5).fold(state[x], |column, y| {
+            let lane = state[x + 5 * y];
+            [0, 1].map(|h| ops.xor(column[h], lane[h]))
+        })
+    });
+    let d: [Lane<O::Word>; 5] = std::array::from_fn(|x| {
+        let rotated = rotate_lane(ops, columns[(x + 1) % 5], 1);
+        let previous = columns[(x + 4) % 5];
+        [0, 1].map(|h| ops.xor(previous[h], rotated[h]))
+    });
+    let state: [Lane<O::Word>; NUM_LANES] =
+        std::array::from_fn(|i| [0, 1].map(|h| ops.xor(state[i][h], d[i % 5][h])));
+
+    // ρ and π
+    let mut permuted = [state[0]; NUM_LANES];
+    for x in 0..5 {
+        for y in 0..5 {
+            permuted[y + 5 * ((2 * x + 3 * y) % 5)] =
+                rotate_lane(ops, state[x + 5 * y], ROTATION_OFFSETS[x + 5 * y]);
+        }
+    }
+
+    // χ
+    let mut state: [Lane<O::Word>; NUM_LANES] = std::array::from_fn(|i| {
+        let (x, y) = (i % 5, i / 5);
+        let next = permuted[(x + 1) % 5 + 5 * y];
+        let next_but_one = permuted[(x + 2) % 5 + 5 * y];
+        let and_not = [0, 1].map(|h| ops.and_not(next[h], next_but_one[h]));
+        [0, 1].map(|h| ops.xor(permuted[i][h], and_not[h]))
+    });
+
+    // ι
+    let constant = ROUND_CONSTANTS[round];
+    state[0] = [
+        ops.xor_constant(state[0][0], constant as u32),
+        ops.xor_constant(state[0][1], (constant >> 32) as u32),
+    ];
+    state
+}
@@ -1,5 +1,11 @@
+pub mod aes;
 mod binops;
+pub mod byte_table;
 mod digits;
+pub mod embedded_curve;
+pub mod keccak;
+pub mod non_native;
+pub mod poseidon2;
 mod ram;
 mod witness_builder;
 mod witness_generator;
@@ -10,13 +16,19 @@ use {
     serde::{Deserialize, Serialize},
 };
 pub use {
-    binops::{BINOP_ATOMIC_BITS, BINOP_BITS, NUM_DIGITS},
+    aes::{Aes128KeyScheduleWitnesses, Aes128RoundWitnesses},
+    binops::{BINOP_ATOMIC_BITS, BINOP_BITS},
     digits::DigitalDecompositionWitnesses,
+    embedded_curve::EmbeddedCurveAddWitnesses,
+    keccak::KeccakRoundWitnesses,
+    non_native::NonNativeIdentity,
+    poseidon2::Poseidon2Witnesses,
     ram::{SpiceMemoryOperation, SpiceWitnesses},
     witness_builder::{
-        ConstantTerm, ProductLinearTerm, SumTerm, WitnessBuilder, WitnessCoefficient,
+        ConstantTerm, ProductLinearTerm, SplitWitnessBuilders, SumTerm, WitnessBuilder,
+        WitnessCoefficient,
     },
-    witness_generator::NoirWitnessGenerator,
+    witness_generator::{CallFrame, NoirWitnessGenerator},
 };
 
 /// The index of the constant 1 witness in the R1CS instance
@@ -0,0 +1,144 @@
+//! Arithmetic modulo a prime of up to 256 bits other than the native field
+//! modulus, such as the base and scalar fields of secp256k1 and secp256r1.
+//!
+//! Values are represented by [`NUM_LIMBS`] range checked limbs of
+//! [`LIMB_BITS`] bits, in little-endian order. A [`NonNativeIdentity`] is a
+//! sum of products, multiples and constants that is zero modulo the modulus,
+//! or zero over the integers. It is checked by writing it as a polynomial in
+//! the limbs, minus a multiple of the modulus, and checking that this
+//! polynomial vanishes at `2^LIMB_BITS` with range checked carries.
+
+use {
+    crate::FieldElement,
+    ark_ff::Field,
+    serde::{Deserialize, Serialize},
+};
+
+/// Number of limbs of a value
+pub const NUM_LIMBS: usize = 4;
+
+/// Number of bits of a limb
+pub const LIMB_BITS: u32 = 64;
+
+/// Number of limbs of the quotient by the modulus
+pub const NUM_QUOTIENT_LIMBS: usize = 5;
+
+/// The quotient by the modulus is offset by this multiple of its top limb,
+/// i.e. by `2^263`, so that it is non-negative for identities with negative
+/// terms: those terms are at most a small multiple of `2^512`, and the modulus
+/// has 256 bits.
+pub const QUOTIENT_OFFSET: u64 = 1 << 7;
+
+/// Number of coefficients of the polynomial of an identity: products of
+/// values have degree `2 * (NUM_LIMBS - 1)`, and products of the quotient and
+/// the modulus degree `NUM_QUOTIENT_LIMBS + NUM_LIMBS - 2`.
+pub const NUM_COEFFICIENTS: usize = NUM_QUOTIENT_LIMBS + NUM_LIMBS - 1;
+
+/// Number of bits of a carry between coefficients, which is offset by
+/// `2^(CARRY_BITS - 1)` as it may be negative.
+pub const CARRY_BITS: u32 = 81;
+
+/// The witnesses of the limbs of a value
+pub type Limbs = [usize; NUM_LIMBS];
+
+/// A value in a [`NonNativeIdentity`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NonNativeOperand {
+    Limbs(Limbs),
+    /// The value that the identity is solved for. It must appear linearly.
+    Result,
+}
+
+/// A term of a [`NonNativeIdentity`], with a small signed coefficient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NonNativeTerm {
+    /// (coefficient, lhs, rhs)
+    Product(i64, NonNativeOperand, NonNativeOperand),
+    /// (coefficient, value)
+    Linear(i64, NonNativeOperand),
+    /// (coefficient, limbs of the constant)
+    Constant(i64, [u64; NUM_LIMBS]),
+}
+
+/// Allocates the witnesses of an identity: the limbs of the result (if the
+/// identity has one), then the limbs of the offset quotient (if the identity
+/// is modulo a modulus), then the offset carries between the coefficients of
+/// its polynomial.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NonNativeIdentity {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    /// The limbs of the 256-bit modulus, or `None` for an identity over the
+    /// integers
+    pub modulus:           Option<[u64; NUM_LIMBS]>,
+    /// The terms, which sum to zero
+    pub terms:             Vec<NonNativeTerm>,
+}
+
+impl NonNativeIdentity {
+    /// Whether the identity is solved for a result
+    pub fn has_result(&self) -> bool {
+        self.terms.iter().any(|term| match term {
+            NonNativeTerm::Product(_, lhs, rhs) => {
+                *lhs == NonNativeOperand::Result || *rhs == NonNativeOperand::Result
+            }
+            NonNativeTerm::Linear(_, value) => *value == NonNativeOperand::Result,
+            NonNativeTerm::Constant(..) => false,
+        })
+    }
+
+    /// The index of the first limb of the offset quotient
+    pub fn quotient_start(&self) -> usize {
+        self.first_witness_idx + if self.has_result() { NUM_LIMBS } else { 0 }
+    }
+
+    /// The index of the first offset carry
+    pub fn carries_start(&self) -> usize {
+        self.quotient_start()
+            + if self.modulus.is_some() {
+                NUM_QUOTIENT_LIMBS
+            } else {
+                0
+            }
+    }
+
+    /// The number of witnesses written to
+    pub fn num_witnesses(&self) -> usize {
+        self.carries_start() + NUM_COEFFICIENTS - 1 - self.first_witness_idx
+    }
+
+    /// The limbs of the values of the identity, other than the result
+    pub fn operands(&self) -> impl Iterator<Item = &Limbs> {
+        self.terms
+            .iter()
+            .flat_map(|term| match term {
+                NonNativeTerm::Product(_, lhs, rhs) => vec![lhs, rhs],
+                NonNativeTerm::Linear(_, value) => vec![value],
+                NonNativeTerm::Constant(..) => vec![],
+            })
+            .filter_map(|operand| match operand {
+                NonNativeOperand::Limbs(limbs) => Some(limbs),
+                NonNativeOperand::Result => None,
+            })
+    }
+
+    /// Mutable references to the limbs of [`Self::operands`]
+    pub fn operands_mut(&mut self) -> impl Iterator<Item = &mut Limbs> {
+        self.terms
+            .iter_mut()
+            .flat_map(|term| match term {
+                NonNativeTerm::Product(_, lhs, rhs) => vec![lhs, rhs],
+                NonNativeTerm::Linear(_, value) => vec![value],
+                NonNativeTerm::Constant(..) => vec![],
+            })
+            .filter_map(|operand| match operand {
+                NonNativeOperand::Limbs(limbs) => Some(limbs),
+                NonNativeOperand::Result => None,
+            })
+    }
+}
+
+/// The offset of a carry, `2^(CARRY_BITS - 1)`
+pub fn carry_offset() -> FieldElement {
+    FieldElement::from(2u64).pow([u64::from(CARRY_BITS - 1)])
+}
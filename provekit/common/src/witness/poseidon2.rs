@@ -0,0 +1,447 @@
+//! The Poseidon2 permutation over BN254 with a state of 4 field elements, as
+//! used by the Noir `poseidon2_permutation` black box.
+
+use {
+    crate::{utils::uint_to_field, witness::ConstantOrR1CSWitness, FieldElement},
+    ark_ff::Field,
+    ruint::uint,
+    serde::{Deserialize, Serialize},
+};
+
+/// Number of field elements in the state
+pub const WIDTH: usize = 4;
+
+/// Number of full rounds, half before and half after the partial rounds
+pub const FULL_ROUNDS: usize = 8;
+
+/// Number of partial rounds, applying the S-box to the first lane only
+pub const PARTIAL_ROUNDS: usize = 56;
+
+/// Number of S-boxes applied in a permutation
+pub const NUM_SBOXES: usize = FULL_ROUNDS * WIDTH + PARTIAL_ROUNDS;
+
+/// Number of witnesses for each S-box `x^5`: `x^2`, `x^4` and `x^5`
+pub const WITNESSES_PER_SBOX: usize = 3;
+
+/// The external matrix `circ(2 M4, M4, ..)`, which for a width of 4 is `M4`.
+pub const EXTERNAL_MATRIX: [[u64; WIDTH]; WIDTH] =
+    [[5, 7, 1, 3], [4, 6, 1, 1], [1, 3, 5, 7], [1, 1, 4, 6]];
+
+/// Round constants of the full rounds, the first half before the partial rounds
+/// and the second half after
+pub const FULL_ROUND_CONSTANTS: [[FieldElement; WIDTH]; FULL_ROUNDS] = [
+    [
+        uint_to_field(uint!(
+            11633431549750490989983886834189948010834808234699737327785600195936805266405_U256
+        )),
+        uint_to_field(uint!(
+            17353750182810071758476407404624088842693631054828301270920107619055744005334_U256
+        )),
+        uint_to_field(uint!(
+            11575173631114898451293296430061690731976535592475236587664058405912382527658_U256
+        )),
+        uint_to_field(uint!(
+            9724643380371653925020965751082872123058642683375812487991079305063678725624_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            20936725237749945635418633443468987188819556232926135747685274666391889856770_U256
+        )),
+        uint_to_field(uint!(
+            6427758822462294912934022562310355233516927282963039741999349770315205779230_U256
+        )),
+        uint_to_field(uint!(
+            16782979953202249973699352594809882974187694538612412531558950864304931387798_U256
+        )),
+        uint_to_field(uint!(
+            8979171037234948998646722737761679613767384188475887657669871981433930833742_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            5428827536651017352121626533783677797977876323745420084354839999137145767736_U256
+        )),
+        uint_to_field(uint!(
+            507241738797493565802569310165979445570507129759637903167193063764556368390_U256
+        )),
+        uint_to_field(uint!(
+            6711578168107599474498163409443059675558516582274824463959700553865920673097_U256
+        )),
+        uint_to_field(uint!(
+            2197359304646916921018958991647650011119043556688567376178243393652789311643_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            4634703622846121403803831560584049007806112989824652272428991253572845447400_U256
+        )),
+        uint_to_field(uint!(
+            17008376818199175111793852447685303011746023680921106348278379453039148937791_U256
+        )),
+        uint_to_field(uint!(
+            18430784755956196942937899353653692286521408688385681805132578732731487278753_U256
+        )),
+        uint_to_field(uint!(
+            4573768376486344895797915946239137669624900197544620153250805961657870918727_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            10670120969725161535937685539136065944959698664551200616467222887025111751992_U256
+        )),
+        uint_to_field(uint!(
+            4731853626374224678749618809759140702342195350742653173378450474772131006181_U256
+        )),
+        uint_to_field(uint!(
+            14473527495914528513885847341981310373531349450901830749157165104135412062812_U256
+        )),
+        uint_to_field(uint!(
+            16937191362061486658876740597821783333355021670608822932942683228741190786143_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            5656559696428674390125424316117443507583679061659043998559560535270557939546_U256
+        )),
+        uint_to_field(uint!(
+            8897648276515725841133578021896617755369443750194849587616503841335248902806_U256
+        )),
+        uint_to_field(uint!(
+            14938684446722672719637788054570691068799510611164812175626676768545923371470_U256
+        )),
+        uint_to_field(uint!(
+            15284149043690546115252102390417391226617211133644099356880071475803043461465_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            2623479025068612775740107497276979457946709347831661908218182874823658838107_U256
+        )),
+        uint_to_field(uint!(
+            6809791961761836061129379546794905411734858375517368211894790874813684813988_U256
+        )),
+        uint_to_field(uint!(
+            2417620338751920563196799065781703780495622795713803712576790485412779971775_U256
+        )),
+        uint_to_field(uint!(
+            4445143310792944321746901285176579692343442786777464604312772017806735512661_U256
+        )),
+    ],
+    [
+        uint_to_field(uint!(
+            1429019233589939118995503267516676481141938536269008901607126781291273208629_U256
+        )),
+        uint_to_field(uint!(
+            19874283200702583165110559932895904979843482162236139561356679724680604144459_U256
+        )),
+        uint_to_field(uint!(
+            13426632171723830006915194799390005513190035492503509233177687891041405113055_U256
+        )),
+        uint_to_field(uint!(
+            10582332261829184460912611488470654685922576576939233092337240630493625631748_U256
+        )),
+    ],
+];
+
+/// Round constants of the partial rounds, added to the first lane only
+pub const PARTIAL_ROUND_CONSTANTS: [FieldElement; PARTIAL_ROUNDS] = [
+    uint_to_field(uint!(
+        5624865188680173294191042415227598609140934495743721047183803859030618890703_U256
+    )),
+    uint_to_field(uint!(
+        8228252753786907198149068514193371173033070694924002912950645971088002709521_U256
+    )),
+    uint_to_field(uint!(
+        17586714789554691446538331362711502394998837215506284064347036653995353304693_U256
+    )),
+    uint_to_field(uint!(
+        12985198716830497423350597750558817467658937953000235442251074063454897365701_U256
+    )),
+    uint_to_field(uint!(
+        13480076116139680784838493959937969792577589073830107110893279354229821035984_U256
+    )),
+    uint_to_field(uint!(
+        480609231761423388761863647137314056373740727639536352979673303078459561332_U256
+    )),
+    uint_to_field(uint!(
+        19503345496799249258956440299354839375920540225688429628121751361906635419276_U256
+    )),
+    uint_to_field(uint!(
+        16837818502122887883669221005435922946567532037624537243846974433811447595173_U256
+    )),
+    uint_to_field(uint!(
+        5492108497278641078569490709794391352213168666744080628008171695469579703581_U256
+    )),
+    uint_to_field(uint!(
+        11365311159988448419785032079155356000691294261495515880484003277443744617083_U256
+    )),
+    uint_to_field(uint!(
+        13876891705632851072613751905778242936713392247975808888614530203269491723653_U256
+    )),
+    uint_to_field(uint!(
+        10660388389107698747692475159023710744797290186015856503629656779989214850043_U256
+    )),
+    uint_to_field(uint!(
+        18876318870401623474401728758498150977988613254023317877612912724282285739292_U256
+    )),
+    uint_to_field(uint!(
+        15543349138237018307536452195922365893694804703361435879256942490123776892424_U256
+    )),
+    uint_to_field(uint!(
+        2839988449157209999638903652853828318645773519300826410959678570041742458201_U256
+    )),
+    uint_to_field(uint!(
+        7566039810305694135184226097163626060317478635973510706368412858136696413063_U256
+    )),
+    uint_to_field(uint!(
+        6344830340705033582410486810600848473125256338903726340728639711688240744220_U256
+    )),
+    uint_to_field(uint!(
+        12475357769019880256619207099578191648078162511547701737481203260317463892731_U256
+    )),
+    uint_to_field(uint!(
+        13337401254840718303633782478677852514218549070508887338718446132574012311307_U256
+    )),
+    uint_to_field(uint!(
+        21161869193849404954234950798647336336709035097706159414187214758702055364571_U256
+    )),
+    uint_to_field(uint!(
+        20671052961616073313397254362345395594858011165315285344464242404604146448678_U256
+    )),
+    uint_to_field(uint!(
+        2772189387845778213446441819361180378678387127454165972767013098872140927416_U256
+    )),
+    uint_to_field(uint!(
+        3339032002224218054945450150550795352855387702520990006196627537441898997147_U256
+    )),
+    uint_to_field(uint!(
+        14919705931281848425960108279746818433850049439186607267862213649460469542157_U256
+    )),
+    uint_to_field(uint!(
+        17056699976793486403099510941807022658662936611123286147276760381688934087770_U256
+    )),
+    uint_to_field(uint!(
+        16144580075268719403964467603213740327573316872987042261854346306108421013323_U256
+    )),
+    uint_to_field(uint!(
+        15582343953927413680541644067712456296539774919658221087452235772880573393376_U256
+    )),
+    uint_to_field(uint!(
+        17528510080741946423534916423363640132610906812668323263058626230135522155749_U256
+    )),
+    uint_to_field(uint!(
+        3190600034239022251529646836642735752388641846393941612827022280601486805721_U256
+    )),
+    uint_to_field(uint!(
+        8463814172152682468446984305780323150741498069701538916468821815030498611418_U256
+    )),
+    uint_to_field(uint!(
+        16533435971270903741871235576178437313873873358463959658178441562520661055273_U256
+    )),
+    uint_to_field(uint!(
+        11845696835505436397913764735273748291716405946246049903478361223369666046634_U256
+    )),
+    uint_to_field(uint!(
+        18391057370973634202531308463652130631065370546571735004701144829951670507215_U256
+    )),
+    uint_to_field(uint!(
+        262537877325812689820791215463881982531707709719292538608229687240243203710_U256
+    )),
+    uint_to_field(uint!(
+        2187234489894387585309965540987639130975753519805550941279098789852422770021_U256
+    )),
+    uint_to_field(uint!(
+        19189656350920455659006418422409390013967064310525314160026356916172976152967_U256
+    )),
+    uint_to_field(uint!(
+        15839474183930359560478122372067744245080413846070743460407578046890458719219_U256
+    )),
+    uint_to_field(uint!(
+        1805019124769763805045852541831585930225376844141668951787801647576910524592_U256
+    )),
+    uint_to_field(uint!(
+        323592203814803486950280155834638828455175703393817797003361354810251742052_U256
+    )),
+    uint_to_field(uint!(
+        9780393509796825017346015868945480913627956475147371732521398519483580624282_U256
+    )),
+    uint_to_field(uint!(
+        14009429785059642386335012561867511048847749030947687313594053997432177705759_U256
+    )),
+    uint_to_field(uint!(
+        13749550162460745037234826077137388777330401847577727796245150843898019635981_U256
+    )),
+    uint_to_field(uint!(
+        19497187499283431845443758879472819384797584633472792651343926414232528405311_U256
+    )),
+    uint_to_field(uint!(
+        3708428802547661961864524194762556064568867603968214870300574294082023305587_U256
+    )),
+    uint_to_field(uint!(
+        1339414413482882567499652761996854155383863472782829777976929310155400981782_U256
+    )),
+    uint_to_field(uint!(
+        6396261245879814100794661157306877072718690153118140891315137894471052482309_U256
+    )),
+    uint_to_field(uint!(
+        2069661495404347929962833138824526893650803079024564477269192079629046031674_U256
+    )),
+    uint_to_field(uint!(
+        15793521554502133342917616035884588152451122589545915605459159078589855944361_U256
+    )),
+    uint_to_field(uint!(
+        17053424498357819626596285492499512504457128907932827007302385782133229252374_U256
+    )),
+    uint_to_field(uint!(
+        13658536470391360399708067455536748955260723760813498481671323619545320978896_U256
+    )),
+    uint_to_field(uint!(
+        21546095668130239633971575351786704948662094117932406102037724221634677838565_U256
+    )),
+    uint_to_field(uint!(
+        21411726238386979516934941789127061362496195649331822900487557574597304399109_U256
+    )),
+    uint_to_field(uint!(
+        1944776378988765673004063363506638781964264107780425928778257145151172817981_U256
+    )),
+    uint_to_field(uint!(
+        15590719714223718537172639598316570285163081746016049278954513732528516468773_U256
+    )),
+    uint_to_field(uint!(
+        1351266421179051765004709939353170430290500926943038391678843253157009556309_U256
+    )),
+    uint_to_field(uint!(
+        6772476224477167317130064764757502335545080109882028900432703947986275397548_U256
+    )),
+];
+
+/// Diagonal of the internal matrix, minus one: the internal layer adds the sum
+/// of the lanes to each lane, scaled by its diagonal entry
+pub const INTERNAL_DIAGONAL: [FieldElement; WIDTH] = [
+    uint_to_field(uint!(
+        7626475329478847982857743246276194948757851985510858890691733676098590062311_U256
+    )),
+    uint_to_field(uint!(
+        5498568565063849786384470689962419967523752476452646391422913716315471115275_U256
+    )),
+    uint_to_field(uint!(
+        148936322117705719734052984176402258788283488576388928671173547788498414613_U256
+    )),
+    uint_to_field(uint!(
+        15456385653678559339152734484033356164266089951521103188900320352052358038155_U256
+    )),
+];
+
+/// Allocates the witnesses of the S-boxes of a Poseidon2 permutation of the
+/// given inputs. For each S-box, in the order they are applied, the witnesses
+/// are `x^2`, `x^4` and `x^5`, where `x` is the input of the S-box.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Poseidon2Witnesses {
+    /// The index of the first witness written to
+    pub first_witness_idx: usize,
+    /// The input state of the permutation
+    pub inputs:            [ConstantOrR1CSWitness; WIDTH],
+}
+
+impl Poseidon2Witnesses {
+    /// The number of witnesses written to
+    pub const fn num_witnesses(&self) -> usize {
+        NUM_SBOXES * WITNESSES_PER_SBOX
+    }
+}
+
+/// Applies the external linear layer to the state.
+pub fn external_layer(state: &mut [FieldElement; WIDTH]) {
+    *state = EXTERNAL_MATRIX.map(|row| {
+        row.iter()
+            .zip(state.iter())
+            .map(|(&coeff, &lane)| FieldElement::from(coeff) * lane)
+            .sum()
+    });
+}
+
+/// Applies the internal linear layer to the state.
+pub fn internal_layer(state: &mut [FieldElement; WIDTH]) {
+    let sum: FieldElement = state.iter().sum();
+    for (lane, diagonal) in state.iter_mut().zip(INTERNAL_DIAGONAL) {
+        *lane = *lane * diagonal + sum;
+    }
+}
+
+/// A round of the permutation, with its round constants.
+#[derive(Clone, Copy, Debug)]
+pub enum Round {
+    /// Adds a constant to each lane, applies the S-box to each lane and then
+    /// the external layer.
+    Full([FieldElement; WIDTH]),
+    /// Adds a constant to the first lane, applies the S-box to the first lane
+    /// and then the internal layer.
+    Partial(FieldElement),
+}
+
+/// The rounds of the permutation, which follow an initial external layer.
+pub fn rounds() -> impl Iterator<Item = Round> {
+    let full_rounds =
+        |range: std::ops::Range<usize>| range.map(|round| Round::Full(FULL_ROUND_CONSTANTS[round]));
+    full_rounds(0..FULL_ROUNDS / 2)
+        .chain(PARTIAL_ROUND_CONSTANTS.into_iter().map(Round::Partial))
+        .chain(full_rounds(FULL_ROUNDS / 2..FULL_ROUNDS))
+}
+
+/// The Poseidon2 permutation of the state. The input of each S-box is passed
+/// to `on_sbox`, in the order the S-boxes are applied.
+pub fn permute(state: &mut [FieldElement; WIDTH], mut on_sbox: impl FnMut(FieldElement)) {
+    let mut sbox = |lane: &mut FieldElement, constant: FieldElement| {
+        let x = *lane + constant;
+        on_sbox(x);
+        *lane = x.square().square() * x;
+    };
+
+    external_layer(state);
+    for round in rounds() {
+        match round {
+            Round::Full(constants) => {
+                for (lane, constant) in state.iter_mut().zip(constants) {
+                    sbox(lane, constant);
+                }
+                external_layer(state);
+            }
+            Round::Partial(constant) => {
+                sbox(&mut state[0], constant);
+                internal_layer(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{permute, NUM_SBOXES},
+        crate::{utils::uint_to_field, FieldElement},
+        ruint::uint,
+    };
+
+    #[test]
+    fn test_permutation() {
+        let mut state = [0u64, 1, 2, 3].map(FieldElement::from);
+        let mut num_sboxes = 0;
+        permute(&mut state, |_| num_sboxes += 1);
+        assert_eq!(num_sboxes, NUM_SBOXES);
+        assert_eq!(state, [
+            uint_to_field(uint!(
+                0x01bd538c2ee014ed5141b29e9ae240bf8db3fe5b9a38629a9647cf8d76c01737_U256
+            )),
+            uint_to_field(uint!(
+                0x239b62e7db98aa3a2a8f6a0d2fa1709e7a35959aa6c7034814d9daa90cbac662_U256
+            )),
+            uint_to_field(uint!(
+                0x04cbb44c61d928ed06808456bf758cbf0c18d1e15a7b6dbc8245fa7515d5e3cb_U256
+            )),
+            uint_to_field(uint!(
+                0x2e11c5cff2a22c64d01304b778d78f6998eff1ab73163a35603f54794c30847a_U256
+            )),
+        ]);
+    }
+}
@@ -2,7 +2,13 @@ use {
     crate::{
         utils::{serde_ark, serde_ark_option},
         witness::{
-            binops::BINOP_ATOMIC_BITS, digits::DigitalDecompositionWitnesses, ram::SpiceWitnesses,
+            aes::{Aes128KeyScheduleWitnesses, Aes128RoundWitnesses},
+            digits::DigitalDecompositionWitnesses,
+            embedded_curve::{EmbeddedCurveAddWitnesses, INCOMPLETE_ADD_WITNESSES},
+            keccak::KeccakRoundWitnesses,
+            non_native::NonNativeIdentity,
+            poseidon2::Poseidon2Witnesses,
+            ram::{SpiceMemoryOperation, SpiceWitnesses},
             ConstantOrR1CSWitness,
         },
         FieldElement,
@@ -49,17 +55,14 @@ pub enum WitnessBuilder {
     /// The product of the values at two specified witness indices
     /// (witness index, operand witness index a, operand witness index b)
     Product(usize, usize, usize),
-    /// Solves for the number of times that each memory address occurs in
-    /// read-only memory. Arguments: (first witness index, range size,
-    /// vector of all witness indices for values purported to be in the range)
-    MultiplicitiesForRange(usize, usize, Vec<usize>),
+    /// Solves for the number of times that each row of a lookup table is
+    /// looked up. The row of a query is the sum of its values weighted by the
+    /// row weights. Arguments: (first witness index, number of rows, row
+    /// weights, queries)
+    MultiplicitiesForLookup(usize, usize, Vec<u64>, Vec<Vec<ConstantOrR1CSWitness>>),
     /// A Fiat-Shamir challenge value
     /// (witness index)
     Challenge(usize),
-    /// For solving for the denominator of an indexed lookup.
-    /// Fields are (witness index, sz_challenge, (index_coeff, index),
-    /// rs_challenge, value).
-    IndexedLogUpDenominator(usize, usize, WitnessCoefficient, usize, usize),
     /// The inverse of the value at a specified witness index
     /// (witness index, operand witness index)
     Inverse(usize, usize),
@@ -67,9 +70,11 @@ pub enum WitnessBuilder {
     /// Fields are ProductLinearOperation(witness_idx, (index, a, b), (index, c,
     /// d)) such that we wish to compute (ax + b) * (cx + d).
     ProductLinearOperation(usize, ProductLinearTerm, ProductLinearTerm),
-    /// For solving for the denominator of a lookup (non-indexed).
-    /// Field are (witness index, sz_challenge, (value_coeff, value)).
-    LogUpDenominator(usize, usize, WitnessCoefficient),
+    /// A summand of a LogUp argument: a numerator divided by a linear
+    /// combination of witnesses, which is a query or a row of a table folded
+    /// with the challenges. Fields are (witness index, numerator witness index,
+    /// or 1 if none, terms of the denominator).
+    LogUpQuotient(usize, Option<usize>, Vec<SumTerm>),
     /// Builds the witnesses values required for the mixed base digital
     /// decomposition of other witness values.
     DigitalDecomposition(DigitalDecompositionWitnesses),
@@ -92,22 +97,84 @@ pub enum WitnessBuilder {
     /// (Note that some witness values are already solved for by the ACIR
     /// solver.)
     SpiceWitnesses(SpiceWitnesses),
-    /// A witness value for the denominator of a bin op lookup.
-    /// Arguments: `(witness index, sz_challenge, rs_challenge,
-    /// rs_challenge_sqrd, lhs, rhs, output)`, where `lhs`, `rhs`, and
-    /// `output` are either constant or witness values.
-    BinOpLookupDenominator(
-        usize,
-        usize,
-        usize,
-        usize,
-        ConstantOrR1CSWitness,
-        ConstantOrR1CSWitness,
-        ConstantOrR1CSWitness,
-    ),
-    /// Witness values for the number of times that each pair of input values
-    /// occurs in the bin op.
-    MultiplicitiesForBinOp(usize, Vec<(ConstantOrR1CSWitness, ConstantOrR1CSWitness)>),
+    /// The bitwise AND of two values of at most 128 bits, for
+    /// gadgets whose intermediate words are not ACIR witnesses.
+    /// (witness index, lhs, rhs)
+    And(usize, ConstantOrR1CSWitness, ConstantOrR1CSWitness),
+    /// The bitwise XOR of two values of at most 128 bits.
+    /// (witness index, lhs, rhs)
+    Xor(usize, ConstantOrR1CSWitness, ConstantOrR1CSWitness),
+    /// The inputs of the S-boxes of a Poseidon2 permutation, raised to the
+    /// second, fourth and fifth powers. All the round states are solved in one
+    /// step, as they are linear combinations of these witnesses.
+    Poseidon2(Poseidon2Witnesses),
+    /// The intermediate words of a round of Keccak-f[1600], including the
+    /// words of its output state.
+    KeccakRound(KeccakRoundWitnesses),
+    /// The result, quotient and carries of an identity between integers
+    /// modulo a non-native modulus.
+    NonNativeIdentity(NonNativeIdentity),
+    /// The value at an address of a read-only memory block, for gadgets whose
+    /// reads are not ACIR memory operations.
+    /// (witness index, address witness index, witness indices of the values)
+    MemoryRead(usize, usize, Vec<usize>),
+    /// The intermediate values and result of a complete addition of Grumpkin
+    /// points, either of which may be the point at infinity.
+    EmbeddedCurveAdd(EmbeddedCurveAddWitnesses),
+    /// The inverse of the difference of the x coordinates, the slope of the
+    /// chord and the sum of two finite Grumpkin points with distinct x
+    /// coordinates.
+    /// (witness index, [x1, y1], [x2, y2])
+    EmbeddedCurveChord(usize, [usize; 2], [usize; 2]),
+    /// The square of the x coordinate, the slope of the tangent and the
+    /// double of a finite Grumpkin point.
+    /// (witness index, [x, y])
+    EmbeddedCurveDouble(usize, [usize; 2]),
+    /// The intermediate values and round keys of the AES-128 key schedule.
+    Aes128KeySchedule(Aes128KeyScheduleWitnesses),
+    /// The intermediate values of a round of AES-128, including the words of
+    /// its output state.
+    Aes128Round(Aes128RoundWitnesses),
+    /// The choose function of SHA-256, `(e & f) ^ (!e & g)`, on values of at
+    /// most 64 bits.
+    /// (witness index, e, f, g)
+    Sha256Choose(usize, usize, usize, usize),
+    /// The majority function of SHA-256, `(a & b) ^ (a & c) ^ (b & c)`, on
+    /// values of at most 64 bits.
+    /// (witness index, a, b, c)
+    Sha256Majority(usize, usize, usize, usize),
+    /// The right shift of a value of at most 64 bits.
+    /// (witness index, value witness index, number of bits)
+    ShiftRight(usize, usize, u32),
+}
+
+/// The witness builders of an R1CS instance, partitioned by the phase in
+/// which their witnesses are committed. The `w1` witnesses occupy the indices
+/// `0..w1_size` and are committed before any Fiat-Shamir challenge is derived;
+/// the `w2` witnesses depend on those challenges and occupy the remaining
+/// indices of the R1CS instance. The witnesses that were eliminated from the
+/// R1CS instance follow, and are only solved as inputs to other builders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitWitnessBuilders {
+    pub w1:             Vec<WitnessBuilder>,
+    pub w2:             Vec<WitnessBuilder>,
+    pub w1_size:        usize,
+    pub num_eliminated: usize,
+}
+
+impl SplitWitnessBuilders {
+    /// The number of challenges the `w2` builders consume.
+    pub fn num_challenges(&self) -> usize {
+        self.w2
+            .iter()
+            .filter(|b| matches!(b, WitnessBuilder::Challenge(_)))
+            .count()
+    }
+
+    /// Iterate over all witness builders, in solving order.
+    pub fn iter(&self) -> impl Iterator<Item = &WitnessBuilder> {
+        self.w1.iter().chain(&self.w2)
+    }
 }
 
 impl WitnessBuilder {
@@ -115,13 +182,299 @@ impl WitnessBuilder {
     /// vector.
     pub fn num_witnesses(&self) -> usize {
         match self {
-            WitnessBuilder::MultiplicitiesForRange(_, range_size, _) => *range_size,
+            WitnessBuilder::MultiplicitiesForLookup(_, num_rows, ..) => *num_rows,
             WitnessBuilder::DigitalDecomposition(dd_struct) => dd_struct.num_witnesses,
             WitnessBuilder::SpiceWitnesses(spice_witnesses_struct) => {
                 spice_witnesses_struct.num_witnesses
             }
-            WitnessBuilder::MultiplicitiesForBinOp(..) => 2usize.pow(2 * BINOP_ATOMIC_BITS as u32),
+            WitnessBuilder::Poseidon2(poseidon2) => poseidon2.num_witnesses(),
+            WitnessBuilder::KeccakRound(keccak_round) => keccak_round.num_witnesses(),
+            WitnessBuilder::NonNativeIdentity(identity) => identity.num_witnesses(),
+            WitnessBuilder::EmbeddedCurveAdd(add) => add.num_witnesses(),
+            WitnessBuilder::Aes128KeySchedule(key_schedule) => key_schedule.num_witnesses(),
+            WitnessBuilder::Aes128Round(round) => round.num_witnesses(),
+            WitnessBuilder::EmbeddedCurveChord(..) | WitnessBuilder::EmbeddedCurveDouble(..) => {
+                INCOMPLETE_ADD_WITNESSES
+            }
             _ => 1,
         }
     }
+
+    /// The witness indices that this builder reads when solving.
+    pub fn dependencies(&self) -> Vec<usize> {
+        let witnesses = |operands: &[&ConstantOrR1CSWitness]| {
+            operands
+                .iter()
+                .filter_map(|operand| match operand {
+                    ConstantOrR1CSWitness::Constant(_) => None,
+                    ConstantOrR1CSWitness::Witness(witness) => Some(*witness),
+                })
+                .collect::<Vec<_>>()
+        };
+        match self {
+            WitnessBuilder::Constant(_)
+            | WitnessBuilder::Acir(..)
+            | WitnessBuilder::Challenge(_) => vec![],
+            WitnessBuilder::Sum(_, summands) => summands.iter().map(|term| term.1).collect(),
+            WitnessBuilder::Product(_, a, b) => vec![*a, *b],
+            WitnessBuilder::MultiplicitiesForLookup(_, _, _, queries) => queries
+                .iter()
+                .flat_map(|query| witnesses(&query.iter().collect::<Vec<_>>()))
+                .collect(),
+            WitnessBuilder::Inverse(_, operand) => vec![*operand],
+            WitnessBuilder::ProductLinearOperation(
+                _,
+                ProductLinearTerm(x, ..),
+                ProductLinearTerm(y, ..),
+            ) => vec![*x, *y],
+            WitnessBuilder::LogUpQuotient(_, numerator, denominator) => numerator
+                .iter()
+                .copied()
+                .chain(denominator.iter().map(|term| term.1))
+                .collect(),
+            WitnessBuilder::DigitalDecomposition(dd_struct) => {
+                dd_struct.witnesses_to_decompose.clone()
+            }
+            WitnessBuilder::SpiceMultisetFactor(
+                _,
+                sz_challenge,
+                rs_challenge,
+                WitnessCoefficient(_, addr_witness),
+                value,
+                WitnessCoefficient(_, timer_witness),
+            ) => vec![
+                *sz_challenge,
+                *rs_challenge,
+                *addr_witness,
+                *value,
+                *timer_witness,
+            ],
+            WitnessBuilder::SpiceWitnesses(spice_witnesses) => {
+                // Everything referenced outside of the witnesses written to.
+                let written = spice_witnesses.first_witness_idx
+                    ..spice_witnesses.first_witness_idx + spice_witnesses.num_witnesses;
+                let initial_values = spice_witnesses.initial_values_start
+                    ..spice_witnesses.initial_values_start + spice_witnesses.memory_length;
+                initial_values
+                    .chain(
+                        spice_witnesses
+                            .memory_operations
+                            .iter()
+                            .flat_map(|op| match op {
+                                SpiceMemoryOperation::Load(addr, value, read_timestamp) => {
+                                    vec![*addr, *value, *read_timestamp]
+                                }
+                                SpiceMemoryOperation::Store(
+                                    addr,
+                                    old_value,
+                                    new_value,
+                                    read_timestamp,
+                                ) => vec![*addr, *old_value, *new_value, *read_timestamp],
+                            }),
+                    )
+                    .filter(|witness| !written.contains(witness))
+                    .collect()
+            }
+            WitnessBuilder::And(_, lhs, rhs) | WitnessBuilder::Xor(_, lhs, rhs) => {
+                witnesses(&[lhs, rhs])
+            }
+            WitnessBuilder::Poseidon2(poseidon2) => witnesses(&poseidon2.inputs.each_ref()),
+            WitnessBuilder::KeccakRound(keccak_round) => {
+                keccak_round.state.iter().flatten().copied().collect()
+            }
+            WitnessBuilder::NonNativeIdentity(identity) => {
+                identity.operands().flatten().copied().collect()
+            }
+            WitnessBuilder::MemoryRead(_, address, values) => {
+                let mut dependencies = vec![*address];
+                dependencies.extend(values);
+                dependencies
+            }
+            WitnessBuilder::EmbeddedCurveAdd(add) => {
+                add.lhs.iter().chain(&add.rhs).copied().collect()
+            }
+            WitnessBuilder::EmbeddedCurveChord(_, lhs, rhs) => {
+                lhs.iter().chain(rhs).copied().collect()
+            }
+            WitnessBuilder::EmbeddedCurveDouble(_, point) => point.to_vec(),
+            WitnessBuilder::Aes128KeySchedule(key_schedule) => key_schedule.key.to_vec(),
+            WitnessBuilder::Aes128Round(round) => round
+                .state
+                .iter()
+                .chain(&round.round_key)
+                .copied()
+                .collect(),
+            WitnessBuilder::Sha256Choose(_, x, y, z)
+            | WitnessBuilder::Sha256Majority(_, x, y, z) => vec![*x, *y, *z],
+            WitnessBuilder::ShiftRight(_, value, _) => vec![*value],
+        }
+    }
+
+    /// Renumber every witness index referenced by this builder, both those
+    /// read and those written. Contiguous ranges of witnesses are assumed to
+    /// stay contiguous under the mapping.
+    pub fn remap_witnesses(&mut self, remap: impl Fn(usize) -> usize) {
+        let remap_operand = |operand: &mut ConstantOrR1CSWitness| {
+            if let ConstantOrR1CSWitness::Witness(witness) = operand {
+                *witness = remap(*witness);
+            }
+        };
+        match self {
+            WitnessBuilder::Constant(ConstantTerm(idx, _))
+            | WitnessBuilder::Acir(idx, _)
+            | WitnessBuilder::Challenge(idx) => *idx = remap(*idx),
+            WitnessBuilder::Sum(idx, summands) => {
+                *idx = remap(*idx);
+                summands.iter_mut().for_each(|term| term.1 = remap(term.1));
+            }
+            WitnessBuilder::Product(idx, a, b) => {
+                *idx = remap(*idx);
+                *a = remap(*a);
+                *b = remap(*b);
+            }
+            WitnessBuilder::MultiplicitiesForLookup(start, _, _, queries) => {
+                *start = remap(*start);
+                queries.iter_mut().flatten().for_each(remap_operand);
+            }
+            WitnessBuilder::Inverse(idx, operand) => {
+                *idx = remap(*idx);
+                *operand = remap(*operand);
+            }
+            WitnessBuilder::ProductLinearOperation(
+                idx,
+                ProductLinearTerm(x, ..),
+                ProductLinearTerm(y, ..),
+            ) => {
+                *idx = remap(*idx);
+                *x = remap(*x);
+                *y = remap(*y);
+            }
+            WitnessBuilder::LogUpQuotient(idx, numerator, denominator) => {
+                *idx = remap(*idx);
+                if let Some(numerator) = numerator {
+                    *numerator = remap(*numerator);
+                }
+                denominator
+                    .iter_mut()
+                    .for_each(|term| term.1 = remap(term.1));
+            }
+            WitnessBuilder::DigitalDecomposition(dd_struct) => {
+                dd_struct.first_witness_idx = remap(dd_struct.first_witness_idx);
+                dd_struct
+                    .witnesses_to_decompose
+                    .iter_mut()
+                    .for_each(|witness| *witness = remap(*witness));
+            }
+            WitnessBuilder::SpiceMultisetFactor(
+                idx,
+                sz_challenge,
+                rs_challenge,
+                WitnessCoefficient(_, addr_witness),
+                value,
+                WitnessCoefficient(_, timer_witness),
+            ) => {
+                *idx = remap(*idx);
+                *sz_challenge = remap(*sz_challenge);
+                *rs_challenge = remap(*rs_challenge);
+                *addr_witness = remap(*addr_witness);
+                *value = remap(*value);
+                *timer_witness = remap(*timer_witness);
+            }
+            WitnessBuilder::SpiceWitnesses(spice_witnesses) => {
+                spice_witnesses.initial_values_start = remap(spice_witnesses.initial_values_start);
+                spice_witnesses.rv_final_start = remap(spice_witnesses.rv_final_start);
+                spice_witnesses.rt_final_start = remap(spice_witnesses.rt_final_start);
+                spice_witnesses.first_witness_idx = remap(spice_witnesses.first_witness_idx);
+                for op in &mut spice_witnesses.memory_operations {
+                    match op {
+                        SpiceMemoryOperation::Load(addr, value, read_timestamp) => {
+                            *addr = remap(*addr);
+                            *value = remap(*value);
+                            *read_timestamp = remap(*read_timestamp);
+                        }
+                        SpiceMemoryOperation::Store(addr, old_value, new_value, read_timestamp) => {
+                            *addr = remap(*addr);
+                            *old_value = remap(*old_value);
+                            *new_value = remap(*new_value);
+                            *read_timestamp = remap(*read_timestamp);
+                        }
+                    }
+                }
+            }
+            WitnessBuilder::And(idx, lhs, rhs) | WitnessBuilder::Xor(idx, lhs, rhs) => {
+                *idx = remap(*idx);
+                remap_operand(lhs);
+                remap_operand(rhs);
+            }
+            WitnessBuilder::Poseidon2(poseidon2) => {
+                poseidon2.first_witness_idx = remap(poseidon2.first_witness_idx);
+                poseidon2.inputs.iter_mut().for_each(remap_operand);
+            }
+            WitnessBuilder::KeccakRound(keccak_round) => {
+                keccak_round.first_witness_idx = remap(keccak_round.first_witness_idx);
+                keccak_round
+                    .state
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|word| *word = remap(*word));
+            }
+            WitnessBuilder::NonNativeIdentity(identity) => {
+                identity.first_witness_idx = remap(identity.first_witness_idx);
+                identity
+                    .operands_mut()
+                    .flatten()
+                    .for_each(|limb| *limb = remap(*limb));
+            }
+            WitnessBuilder::MemoryRead(idx, address, values) => {
+                *idx = remap(*idx);
+                *address = remap(*address);
+                values.iter_mut().for_each(|value| *value = remap(*value));
+            }
+            WitnessBuilder::EmbeddedCurveAdd(add) => {
+                add.first_witness_idx = remap(add.first_witness_idx);
+                add.lhs
+                    .iter_mut()
+                    .chain(&mut add.rhs)
+                    .for_each(|coordinate| *coordinate = remap(*coordinate));
+            }
+            WitnessBuilder::EmbeddedCurveChord(idx, lhs, rhs) => {
+                *idx = remap(*idx);
+                lhs.iter_mut()
+                    .chain(rhs)
+                    .for_each(|coordinate| *coordinate = remap(*coordinate));
+            }
+            WitnessBuilder::EmbeddedCurveDouble(idx, point) => {
+                *idx = remap(*idx);
+                point
+                    .iter_mut()
+                    .for_each(|coordinate| *coordinate = remap(*coordinate));
+            }
+            WitnessBuilder::Aes128KeySchedule(key_schedule) => {
+                key_schedule.first_witness_idx = remap(key_schedule.first_witness_idx);
+                key_schedule
+                    .key
+                    .iter_mut()
+                    .for_each(|word| *word = remap(*word));
+            }
+            WitnessBuilder::Aes128Round(round) => {
+                round.first_witness_idx = remap(round.first_witness_idx);
+                round
+                    .state
+                    .iter_mut()
+                    .chain(&mut round.round_key)
+                    .for_each(|word| *word = remap(*word));
+            }
+            WitnessBuilder::Sha256Choose(idx, x, y, z)
+            | WitnessBuilder::Sha256Majority(idx, x, y, z) => {
+                *idx = remap(*idx);
+                *x = remap(*x);
+                *y = remap(*y);
+                *z = remap(*z);
+            }
+            WitnessBuilder::ShiftRight(idx, value, _) => {
+                *idx = remap(*idx);
+                *value = remap(*value);
+            }
+        }
+    }
 }
@@ -1,11 +1,21 @@
 use {
-    crate::utils::serde_jsonify,
+    crate::{utils::serde_jsonify, NoirElement},
+    acir::native_types::{Witness, WitnessMap, WitnessStack},
+    anyhow::{ensure, Result},
     noirc_abi::Abi,
     serde::{Deserialize, Serialize},
     std::num::NonZeroU32,
 };
 
-// TODO: Handling of the return value for the verifier.
+/// An execution of an ACIR function, whose witnesses were inlined into the
+/// witness numbering of the main function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallFrame {
+    /// Index of the function in the ACIR program
+    pub function:       u32,
+    /// Offset of the function's witnesses in the inlined witness numbering
+    pub witness_offset: u32,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NoirWitnessGenerator {
@@ -19,17 +29,55 @@ pub struct NoirWitnessGenerator {
     /// ACIR witness index to R1CS witness index
     /// Index zero is reserved for constant one, so we can use `NonZeroU32`
     pub witness_map: Vec<Option<NonZeroU32>>,
+
+    /// One frame for each item of the witness stack, in the order they are
+    /// pushed during execution: callees before their caller, main last.
+    pub call_frames: Vec<CallFrame>,
 }
 
 impl NoirWitnessGenerator {
     pub fn abi(&self) -> &Abi {
         &self.abi
     }
+
+    /// Merges the witness stack of an execution into a single witness map,
+    /// using the inlined witness numbering of the R1CS compiler.
+    pub fn inline_witness_stack(
+        &self,
+        mut witness_stack: WitnessStack<NoirElement>,
+    ) -> Result<WitnessMap<NoirElement>> {
+        let mut items = Vec::with_capacity(self.call_frames.len());
+        while let Some(item) = witness_stack.pop() {
+            items.push(item);
+        }
+        items.reverse();
+        ensure!(
+            items.len() == self.call_frames.len(),
+            "Expected {} function executions, got {}",
+            self.call_frames.len(),
+            items.len()
+        );
+
+        let mut witness_map = WitnessMap::new();
+        for (item, frame) in items.into_iter().zip(&self.call_frames) {
+            ensure!(
+                item.index == frame.function,
+                "Expected execution of function {}, got {}",
+                frame.function,
+                item.index
+            );
+            for (witness, value) in item.witness {
+                witness_map.insert(Witness(frame.witness_offset + witness.0), value);
+            }
+        }
+        Ok(witness_map)
+    }
 }
 
 impl PartialEq for NoirWitnessGenerator {
     fn eq(&self, other: &Self) -> bool {
         format!("{:?}", self.abi) == format!("{:?}", other.abi)
             && self.witness_map == other.witness_map
+            && self.call_frames == other.call_frames
     }
 }
@@ -0,0 +1,274 @@
+use {
+    crate::witness::{non_native::NonNativeIdentitySolver, witness_builder::WitnessBuilderSolver},
+    acir::native_types::WitnessMap,
+    ark_ff::{BigInteger, Field, PrimeField},
+    ark_std::Zero,
+    provekit_common::{
+        witness::{ConstantOrR1CSWitness, SumTerm, WitnessBuilder},
+        FieldElement, NoirElement, NoirProofScheme, Origin,
+    },
+    rand::{rng, Rng},
+    std::fmt::{self, Display, Formatter},
+    tracing::instrument,
+};
+
+/// A witness that could not be solved, because the witness builder writing it
+/// lacks an input or cannot be evaluated on its inputs, e.g. an inverse of
+/// zero or a lookup of a value outside its table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsolvedWitness {
+    pub witness: usize,
+    pub origin:  Option<Origin>,
+}
+
+/// A constraint `A·z * B·z = C·z` the witness `z` does not satisfy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViolatedConstraint {
+    pub row:    usize,
+    pub a:      FieldElement,
+    pub b:      FieldElement,
+    pub c:      FieldElement,
+    pub origin: Option<Origin>,
+}
+
+/// The result of checking a witness against the R1CS instance of a scheme.
+/// Constraints using unsolved witnesses are not checked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WitnessCheck {
+    /// Why the ACVM failed to execute the program on the inputs, e.g. a failing
+    /// assertion. The R1CS witness is not solved in that case.
+    pub execution_error: Option<String>,
+    pub unsolved:        Vec<UnsolvedWitness>,
+    pub violated:        Vec<ViolatedConstraint>,
+}
+
+impl WitnessCheck {
+    #[must_use]
+    pub fn is_satisfied(&self) -> bool {
+        self.execution_error.is_none() && self.unsolved.is_empty() && self.violated.is_empty()
+    }
+}
+
+/// Solve the R1CS witness from the ACIR witness, with random challenges, and
+/// check it against the constraints. Unlike when proving, witnesses that
+/// cannot be solved are reported rather than filled with random values.
+#[instrument(skip_all)]
+pub(crate) fn check_witness(
+    scheme: &NoirProofScheme,
+    acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
+) -> WitnessCheck {
+    let r1cs = &scheme.r1cs;
+    let witness_builders = &scheme.witness_builders;
+    let mut witness = vec![None; r1cs.num_witnesses() + witness_builders.num_eliminated];
+    let mut rng = rng();
+    let mut challenges = (0..witness_builders.num_challenges())
+        .map(|_| FieldElement::from(rng.random::<u128>()))
+        .collect::<Vec<_>>()
+        .into_iter();
+    for builder in witness_builders.iter() {
+        if is_solvable(builder, &witness, acir_witness_idx_to_value_map) {
+            builder.solve(acir_witness_idx_to_value_map, &mut witness, &mut challenges);
+        }
+    }
+
+    let source_map = scheme.source_map.as_ref();
+    let unsolved = witness
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| value.is_none())
+        .map(|(witness, _)| UnsolvedWitness {
+            witness,
+            origin: source_map.and_then(|map| map.witness_origin(witness).cloned()),
+        })
+        .collect();
+
+    let z = witness[..r1cs.num_witnesses()]
+        .iter()
+        .map(|value| value.unwrap_or_default())
+        .collect::<Vec<_>>();
+    let (a, b, c) = (r1cs.a() * &z[..], r1cs.b() * &z[..], r1cs.c() * &z[..]);
+    let violated = (0..r1cs.num_constraints())
+        .filter(|&row| a[row] * b[row] != c[row])
+        .filter(|&row| {
+            [r1cs.a(), r1cs.b(), r1cs.c()]
+                .iter()
+                .all(|matrix| matrix.iter_row(row).all(|(col, _)| witness[col].is_some()))
+        })
+        .map(|row| ViolatedConstraint {
+            row,
+            a: a[row],
+            b: b[row],
+            c: c[row],
+            origin: source_map.and_then(|map| map.constraint_origin(row).cloned()),
+        })
+        .collect();
+
+    WitnessCheck {
+        execution_error: None,
+        unsolved,
+        violated,
+    }
+}
+
+/// Whether the inputs of a witness builder are solved, and it can be
+/// evaluated on them without failing.
+fn is_solvable(
+    builder: &WitnessBuilder,
+    witness: &[Option<FieldElement>],
+    acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
+) -> bool {
+    if builder
+        .dependencies()
+        .iter()
+        .any(|&dependency| witness[dependency].is_none())
+    {
+        return false;
+    }
+    let value = |operand: &ConstantOrR1CSWitness| match operand {
+        ConstantOrR1CSWitness::Constant(c) => *c,
+        ConstantOrR1CSWitness::Witness(witness_idx) => witness[*witness_idx].unwrap(),
+    };
+    match builder {
+        WitnessBuilder::Acir(_, acir_witness_idx) => acir_witness_idx_to_value_map
+            .get_index(*acir_witness_idx as u32)
+            .is_some(),
+        WitnessBuilder::Inverse(_, operand) => !witness[*operand].unwrap().is_zero(),
+        WitnessBuilder::LogUpQuotient(_, _, denominator) => !denominator
+            .iter()
+            .map(|SumTerm(coeff, witness_idx)| {
+                coeff.unwrap_or(FieldElement::ONE) * witness[*witness_idx].unwrap()
+            })
+            .sum::<FieldElement>()
+            .is_zero(),
+        WitnessBuilder::MultiplicitiesForLookup(_, num_rows, row_weights, queries) => {
+            // The row of a query is the weighted sum of its values, which are
+            // each at most the row.
+            let num_rows = FieldElement::from(*num_rows as u64);
+            queries.iter().all(|query| {
+                let weighted = query
+                    .iter()
+                    .zip(row_weights)
+                    .filter(|(_, weight)| **weight != 0)
+                    .map(|(operand, weight)| (value(operand), FieldElement::from(*weight)))
+                    .collect::<Vec<_>>();
+                weighted.iter().all(|(value, _)| *value < num_rows)
+                    && weighted
+                        .iter()
+                        .map(|(value, weight)| *value * weight)
+                        .sum::<FieldElement>()
+                        < num_rows
+            })
+        }
+        WitnessBuilder::MemoryRead(_, address, values) => {
+            witness[*address].unwrap() < FieldElement::from(values.len() as u64)
+        }
+        WitnessBuilder::DigitalDecomposition(decomposition) => {
+            let num_bits = decomposition.log_bases.iter().sum::<usize>();
+            decomposition
+                .witnesses_to_decompose
+                .iter()
+                .all(|&witness_idx| {
+                    witness[witness_idx].unwrap().into_bigint().num_bits() as usize <= num_bits
+                })
+        }
+        WitnessBuilder::NonNativeIdentity(identity) => identity.is_solvable(witness),
+        _ => true,
+    }
+}
+
+impl Display for WitnessCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_satisfied() {
+            return f.write_str("The witness satisfies all constraints");
+        }
+        if let Some(error) = &self.execution_error {
+            return write!(f, "The program failed to execute on the inputs: {error}");
+        }
+        write!(
+            f,
+            "{} unsolved witnesses, {} violated constraints",
+            self.unsolved.len(),
+            self.violated.len()
+        )?;
+        for unsolved in &self.unsolved {
+            write!(f, "\nUnsolved witness {}", unsolved.witness)?;
+            if let Some(origin) = &unsolved.origin {
+                write!(f, " from {origin}")?;
+            }
+        }
+        for violated in &self.violated {
+            write!(
+                f,
+                "\nViolated constraint {}: A·z = {}, B·z = {}, C·z = {}",
+                violated.row, violated.a, violated.b, violated.c
+            )?;
+            if let Some(origin) = &violated.origin {
+                write!(f, " from {origin}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::is_solvable,
+        acir::native_types::WitnessMap,
+        provekit_common::{
+            witness::{
+                non_native::{NonNativeIdentity, NonNativeOperand, NonNativeTerm, NUM_LIMBS},
+                DigitalDecompositionWitnesses, WitnessBuilder,
+            },
+            FieldElement,
+        },
+    };
+
+    #[test]
+    fn test_digital_decomposition_is_solvable() {
+        let builder = WitnessBuilder::DigitalDecomposition(DigitalDecompositionWitnesses {
+            log_bases:                  vec![4, 4],
+            num_witnesses_to_decompose: 1,
+            witnesses_to_decompose:     vec![0],
+            first_witness_idx:          1,
+            num_witnesses:              2,
+        });
+        let acir_witness = WitnessMap::default();
+
+        let witness = [Some(FieldElement::from(0xffu64)), None, None];
+        assert!(is_solvable(&builder, &witness, &acir_witness));
+
+        // The value does not fit in the digits
+        let witness = [Some(FieldElement::from(0x100u64)), None, None];
+        assert!(!is_solvable(&builder, &witness, &acir_witness));
+    }
+
+    #[test]
+    fn test_non_native_identity_is_solvable() {
+        // The inverse of the value modulo 7
+        let identity = NonNativeIdentity {
+            first_witness_idx: NUM_LIMBS,
+            modulus:           Some([7, 0, 0, 0]),
+            terms:             vec![
+                NonNativeTerm::Product(
+                    1,
+                    NonNativeOperand::Limbs([0, 1, 2, 3]),
+                    NonNativeOperand::Result,
+                ),
+                NonNativeTerm::Constant(-1, [1, 0, 0, 0]),
+            ],
+        };
+        let num_witnesses = NUM_LIMBS + identity.num_witnesses();
+        let builder = WitnessBuilder::NonNativeIdentity(identity);
+        let acir_witness = WitnessMap::default();
+
+        let mut witness = vec![Some(FieldElement::from(0u64)); NUM_LIMBS];
+        witness.resize(num_witnesses, None);
+        witness[0] = Some(FieldElement::from(3u64));
+        assert!(is_solvable(&builder, &witness, &acir_witness));
+
+        // Zero has no inverse
+        witness[0] = Some(FieldElement::from(0u64));
+        assert!(!is_solvable(&builder, &witness, &acir_witness));
+    }
+}
@@ -1,9 +1,14 @@
+mod check;
 mod noir_proof_scheme;
 mod r1cs;
+mod spark;
 mod whir_r1cs;
 mod witness;
 
-pub use noir_proof_scheme::NoirProofSchemeProver;
+pub use {
+    check::{UnsolvedWitness, ViolatedConstraint, WitnessCheck},
+    noir_proof_scheme::NoirProofSchemeProver,
+};
 
 #[cfg(test)]
 mod tests {}
@@ -1,19 +1,16 @@
 use {
     crate::{
+        check::{check_witness, WitnessCheck},
         r1cs::R1CSSolver,
         whir_r1cs::WhirR1CSProver,
-        witness::{fill_witness, witness_io_pattern::WitnessIOPattern},
+        witness::fill_witness,
     },
     acir::native_types::WitnessMap,
     anyhow::{Context, Result},
     bn254_blackbox_solver::Bn254BlackBoxSolver,
     nargo::foreign_calls::DefaultForeignCallBuilder,
     noirc_abi::InputMap,
-    provekit_common::{
-        skyscraper::SkyscraperSponge, utils::noir_to_native, witness::WitnessBuilder, FieldElement,
-        IOPattern, NoirElement, NoirProof, NoirProofScheme,
-    },
-    spongefish::{codecs::arkworks_algebra::FieldToUnitSerialize, ProverState},
+    provekit_common::{FieldElement, NoirElement, NoirProof, NoirProofScheme, ProvingKey},
     tracing::instrument,
 };
 
@@ -22,13 +19,10 @@ pub trait NoirProofSchemeProver {
 
     fn prove(&self, input_map: &InputMap) -> Result<NoirProof>;
 
-    fn create_witness_io_pattern(&self) -> IOPattern;
-
-    fn seed_witness_merlin(
-        &self,
-        merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
-        witness: &WitnessMap<NoirElement>,
-    ) -> Result<()>;
+    /// Solve the witness of the inputs and check it against the constraints,
+    /// reporting an execution failure of the program, the unsolved witnesses
+    /// and the violated constraints.
+    fn check_witness(&self, input_map: &InputMap) -> Result<WitnessCheck>;
 }
 
 impl NoirProofSchemeProver for NoirProofScheme {
@@ -47,88 +41,117 @@ impl NoirProofSchemeProver for NoirProofScheme {
 
         let initial_witness = self.witness_generator.abi().encode(input_map, None)?;
 
-        let mut witness_stack = nargo::ops::execute_program(
+        let witness_stack = nargo::ops::execute_program(
             &self.program,
             initial_witness,
             &solver,
             &mut foreign_call_executor,
         )?;
 
-        Ok(witness_stack
-            .pop()
-            .context("Missing witness results")?
-            .witness)
+        // Calls to other ACIR functions were inlined by the compiler
+        self.witness_generator
+            .inline_witness_stack(witness_stack)
+            .context("while inlining witness stack")
     }
 
     #[instrument(skip_all)]
     fn prove(&self, input_map: &InputMap) -> Result<NoirProof> {
-        let acir_witness_idx_to_value_map = self.generate_witness(input_map)?;
-
-        // Solve R1CS instance
-        let witness_io = self.create_witness_io_pattern();
-        let mut witness_merlin = witness_io.to_prover_state();
-        self.seed_witness_merlin(&mut witness_merlin, &acir_witness_idx_to_value_map)?;
-
-        let partial_witness = self.r1cs.solve_witness_vec(
-            &self.witness_builders,
-            &acir_witness_idx_to_value_map,
-            &mut witness_merlin,
-        );
-        let witness = fill_witness(partial_witness).context("while filling witness")?;
-
-        // Verify witness (redudant with solve)
-        #[cfg(test)]
-        self.r1cs
-            .test_witness_satisfaction(&witness)
-            .context("While verifying R1CS instance")?;
-
-        // Prove R1CS instance
-        let whir_r1cs_proof = self
-            .whir_for_witness
-            .prove(&self.r1cs, witness)
-            .context("While proving R1CS instance")?;
-
-        Ok(NoirProof { whir_r1cs_proof })
+        prove(self, self.digest()?, input_map)
     }
 
-    fn create_witness_io_pattern(&self) -> IOPattern {
-        let circuit = &self.program.functions[0];
-        let public_idxs = circuit.public_inputs().indices();
-        let num_challenges = self
-            .witness_builders
-            .iter()
-            .filter(|b| matches!(b, WitnessBuilder::Challenge(_)))
-            .count();
-
-        // Create witness IO pattern
-        IOPattern::new("📜")
-            .add_shape()
-            .add_public_inputs(public_idxs.len())
-            .add_logup_challenges(num_challenges)
+    #[instrument(skip_all)]
+    fn check_witness(&self, input_map: &InputMap) -> Result<WitnessCheck> {
+        // Inputs failing an assertion of the program already fail in the ACVM
+        let acir_witness_idx_to_value_map = match self.generate_witness(input_map) {
+            Ok(acir_witness_idx_to_value_map) => acir_witness_idx_to_value_map,
+            Err(err) => {
+                return Ok(WitnessCheck {
+                    execution_error: Some(format!("{err:#}")),
+                    ..WitnessCheck::default()
+                })
+            }
+        };
+        Ok(check_witness(self, &acir_witness_idx_to_value_map))
     }
+}
 
-    fn seed_witness_merlin(
-        &self,
-        merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
-        witness: &WitnessMap<NoirElement>,
-    ) -> Result<()> {
-        // Absorb circuit shape
-        let _ = merlin.add_scalars(&[
-            FieldElement::from(self.r1cs.num_constraints() as u64),
-            FieldElement::from(self.r1cs.num_witnesses() as u64),
-        ]);
-
-        // Absorb public inputs (values) in canonical order
-        let circuit = &self.program.functions[0];
-        let public_idxs = circuit.public_inputs().indices();
-        if !public_idxs.is_empty() {
-            let pub_vals: Vec<FieldElement> = public_idxs
-                .iter()
-                .map(|&i| noir_to_native(*witness.get_index(i).expect("missing public input")))
-                .collect();
-            let _ = merlin.add_scalars(&pub_vals);
-        }
+impl NoirProofSchemeProver for ProvingKey {
+    fn generate_witness(&self, input_map: &InputMap) -> Result<WitnessMap<NoirElement>> {
+        self.scheme.generate_witness(input_map)
+    }
 
-        Ok(())
+    #[instrument(skip_all)]
+    fn prove(&self, input_map: &InputMap) -> Result<NoirProof> {
+        prove(&self.scheme, self.digest, input_map)
+    }
+
+    fn check_witness(&self, input_map: &InputMap) -> Result<WitnessCheck> {
+        self.scheme.check_witness(input_map)
     }
 }
+
+fn prove(
+    scheme: &NoirProofScheme,
+    digest: FieldElement,
+    input_map: &InputMap,
+) -> Result<NoirProof> {
+    let acir_witness_idx_to_value_map = scheme.generate_witness(input_map)?;
+    let w1_size = scheme.witness_builders.w1_size;
+
+    // Solve the witnesses that do not depend on any challenge
+    let num_witnesses = scheme.r1cs.num_witnesses();
+    let mut witness = vec![None; num_witnesses + scheme.witness_builders.num_eliminated];
+    scheme.r1cs.solve_witness_vec(
+        &mut witness,
+        &scheme.witness_builders.w1,
+        &acir_witness_idx_to_value_map,
+        &[],
+    );
+    let w1 = fill_witness(witness[..w1_size].to_vec()).context("while filling witness")?;
+    for (value, filled) in witness.iter_mut().zip(&w1) {
+        *value = Some(*filled);
+    }
+
+    let public_inputs = w1[1..=scheme.r1cs.num_public_inputs].to_vec();
+    let public_outputs =
+        w1[scheme.r1cs.num_public_inputs + 1..=scheme.r1cs.num_public_values()].to_vec();
+
+    // Prove R1CS instance, solving the remaining witnesses once the challenges
+    // are known
+    let whir_r1cs_proof = scheme
+        .whir_for_witness
+        .prove(&scheme.r1cs, digest, w1, |challenges| {
+            scheme.r1cs.solve_witness_vec(
+                &mut witness,
+                &scheme.witness_builders.w2,
+                &acir_witness_idx_to_value_map,
+                challenges,
+            );
+            let w2 = fill_witness(witness[w1_size..num_witnesses].to_vec())
+                .context("while filling witness")?;
+
+            // Verify witness (redudant with solve)
+            #[cfg(test)]
+            {
+                let full_witness = witness[..w1_size]
+                    .iter()
+                    .map(|value| value.unwrap())
+                    .chain(w2.iter().copied())
+                    .collect::<Vec<_>>();
+                scheme
+                    .r1cs
+                    .test_witness_satisfaction(&full_witness)
+                    .context("While verifying R1CS instance")?;
+            }
+
+            Ok(w2)
+        })
+        .context("While proving R1CS instance")?;
+
+    Ok(NoirProof {
+        digest,
+        public_inputs,
+        public_outputs,
+        whir_r1cs_proof,
+    })
+}
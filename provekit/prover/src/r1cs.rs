@@ -2,20 +2,20 @@ use {
     crate::witness::witness_builder::WitnessBuilderSolver,
     acir::native_types::WitnessMap,
     anyhow::{ensure, Result},
-    provekit_common::{
-        skyscraper::SkyscraperSponge, witness::WitnessBuilder, FieldElement, NoirElement, R1CS,
-    },
-    spongefish::ProverState,
+    provekit_common::{witness::WitnessBuilder, FieldElement, NoirElement, R1CS},
     tracing::instrument,
 };
 
 pub trait R1CSSolver {
+    /// Solve for the witnesses written by `witness_builder_vec`, consuming
+    /// `challenges` in order for the challenge witnesses.
     fn solve_witness_vec(
         &self,
+        witness: &mut [Option<FieldElement>],
         witness_builder_vec: &[WitnessBuilder],
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
-    ) -> Vec<Option<FieldElement>>;
+        challenges: &[FieldElement],
+    );
 
     fn test_witness_satisfaction(&self, witness: &[FieldElement]) -> Result<()>;
 }
@@ -23,16 +23,17 @@ pub trait R1CSSolver {
 impl R1CSSolver for R1CS {
     fn solve_witness_vec(
         &self,
+        witness: &mut [Option<FieldElement>],
         witness_builder_vec: &[WitnessBuilder],
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
-    ) -> Vec<Option<FieldElement>> {
-        let mut witness = vec![None; self.num_witnesses()];
+        challenges: &[FieldElement],
+    ) {
+        assert!(witness.len() >= self.num_witnesses());
+        let mut challenges = challenges.iter().copied();
         witness_builder_vec.iter().for_each(|witness_builder| {
-            witness_builder.solve(acir_witness_idx_to_value_map, &mut witness, transcript);
+            witness_builder.solve(acir_witness_idx_to_value_map, witness, &mut challenges);
         });
-
-        witness
+        assert!(challenges.next().is_none(), "Unused challenges");
     }
 
     // Tests R1CS Witness satisfaction given the constraints provided by the
@@ -54,7 +55,10 @@ impl R1CSSolver for R1CS {
             .zip(c.into_iter())
             .enumerate()
         {
-            ensure!(a * b == c, "Constraint {row} failed");
+            ensure!(
+                a * b == c,
+                "Constraint {row} failed: A·z = {a}, B·z = {b}, C·z = {c}"
+            );
         }
         Ok(())
     }
@@ -0,0 +1,340 @@
+use {
+    ark_ff::batch_inversion,
+    ark_std::Zero,
+    provekit_common::{
+        skyscraper::{SkyscraperMerkleConfig, SkyscraperSponge},
+        spark::{count_slot, Openings, SparkChallenges, SparkScheme, SumcheckTerm},
+        utils::{
+            sumcheck::{calculate_evaluations_over_boolean_hypercube_for_eq, eval_cubic_poly},
+            HALF,
+        },
+        FieldElement, WhirConfig, R1CS,
+    },
+    rayon::iter::{
+        IntoParallelIterator as _, IntoParallelRefMutIterator as _, ParallelIterator as _,
+    },
+    spongefish::{
+        codecs::arkworks_algebra::{FieldToUnitSerialize, UnitToField},
+        ProverState,
+    },
+    tracing::instrument,
+    whir::{
+        poly_utils::{evals::EvaluationsList, multilinear::MultilinearPoint},
+        whir::{
+            committer::{CommitmentWriter, Witness},
+            prover::Prover,
+            statement::{Statement, Weights},
+        },
+    },
+};
+
+pub trait SparkProver {
+    /// Prove the evaluations of the R1CS matrices at `row_point` and each of
+    /// the `column_points`, one for each witness commitment.
+    fn prove(
+        &self,
+        merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
+        r1cs: &R1CS,
+        w1_size: usize,
+        row_point: &[FieldElement],
+        column_points: &[&[FieldElement]],
+    );
+}
+
+impl SparkProver for SparkScheme {
+    #[instrument(skip_all)]
+    fn prove(
+        &self,
+        merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
+        r1cs: &R1CS,
+        w1_size: usize,
+        row_point: &[FieldElement],
+        column_points: &[&[FieldElement]],
+    ) {
+        let num_lookups = self.num_lookups();
+        let log_slot_size = self.log_slot_size();
+        let entries = [r1cs.a(), r1cs.b(), r1cs.c()].map(|matrix| self.entries(matrix, w1_size));
+        let index = |matrix: usize, d: usize| {
+            if d == 0 {
+                &entries[matrix].rows
+            } else {
+                &entries[matrix].cols
+            }
+        };
+
+        // Commit to the matrices again, the verifier checks the root
+        let matrix_polynomial = self.matrix_polynomial(r1cs, w1_size);
+        let matrix_witness = commit(merlin, &self.whir_matrices, &matrix_polynomial);
+
+        // Commit to the values looked up by each entry
+        let tables = self
+            .table_points(row_point, column_points)
+            .iter()
+            .map(|point| calculate_evaluations_over_boolean_hypercube_for_eq(point))
+            .collect::<Vec<_>>();
+        let mut lookups = vec![Vec::new(); 3 * num_lookups];
+        for matrix in 0..3 {
+            for (d, table) in tables.iter().enumerate() {
+                lookups[self.lookup_slot(matrix, d)] =
+                    index(matrix, d).iter().map(|&i| table[i]).collect();
+            }
+        }
+        let mut lookup_polynomial = lookups.concat();
+        lookup_polynomial.resize(
+            1 << (self.log_lookup_slots() + self.log_num_entries),
+            FieldElement::zero(),
+        );
+        let lookup_witness = commit(merlin, &self.whir_lookups, &lookup_polynomial);
+
+        // Commit to the LogUp inverses of the entries and the tables
+        let mut logup = [FieldElement::zero(); 2];
+        merlin
+            .fill_challenge_scalars(&mut logup)
+            .expect("Failed to extract challenge scalars from Merlin");
+        let [gamma, delta] = logup;
+        let mut entry_inverses = vec![Vec::new(); 3 * num_lookups];
+        for matrix in 0..3 {
+            for d in 0..num_lookups {
+                let slot = self.lookup_slot(matrix, d);
+                let mut inverses = index(matrix, d)
+                    .iter()
+                    .zip(&lookups[slot])
+                    .map(|(&i, &value)| gamma - FieldElement::from(i as u64) - delta * value)
+                    .collect::<Vec<_>>();
+                batch_inversion(&mut inverses);
+                entry_inverses[slot] = inverses;
+            }
+        }
+        let table_inverses = tables
+            .iter()
+            .map(|table| {
+                let mut inverses = table
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| gamma - FieldElement::from(i as u64) - delta * value)
+                    .collect::<Vec<_>>();
+                batch_inversion(&mut inverses);
+                inverses
+            })
+            .collect::<Vec<_>>();
+        let mut inverse_polynomial =
+            vec![FieldElement::zero(); 1 << (self.log_inverse_slots() + log_slot_size)];
+        for (slot, inverses) in entry_inverses.iter().enumerate() {
+            let offset = slot << log_slot_size;
+            inverse_polynomial[offset..offset + inverses.len()].copy_from_slice(inverses);
+        }
+        for (d, inverses) in table_inverses.iter().enumerate() {
+            let offset = self.table_slot(d) << log_slot_size;
+            inverse_polynomial[offset..offset + inverses.len()].copy_from_slice(inverses);
+        }
+        let inverse_witness = commit(merlin, &self.whir_inverses, &inverse_polynomial);
+
+        let sums = entry_inverses
+            .iter()
+            .map(|inverses| inverses.iter().sum())
+            .collect::<Vec<FieldElement>>();
+        merlin
+            .add_scalars(&sums)
+            .expect("Failed to add LogUp sums to Merlin");
+
+        let mut theta = [FieldElement::zero()];
+        merlin
+            .fill_challenge_scalars(&mut theta)
+            .expect("Failed to extract challenge scalars from Merlin");
+        let challenges = SparkChallenges {
+            gamma,
+            delta,
+            theta: theta[0],
+        };
+        let mut tau = vec![FieldElement::zero(); self.log_num_entries + self.log_table_size];
+        merlin
+            .fill_challenge_scalars(&mut tau)
+            .expect("Failed to extract challenge scalars from Merlin");
+        let (tau_entries, tau_table) = tau.split_at(self.log_num_entries);
+
+        // Sumcheck over the entries
+        let to_field = |indices: &[usize]| {
+            indices
+                .iter()
+                .map(|&i| FieldElement::from(i as u64))
+                .collect::<Vec<_>>()
+        };
+        let mut entry_mles = vec![calculate_evaluations_over_boolean_hypercube_for_eq(
+            tau_entries,
+        )];
+        for entries in &entries {
+            entry_mles.push(to_field(&entries.rows));
+            entry_mles.push(to_field(&entries.cols));
+            entry_mles.push(entries.values.clone());
+        }
+        entry_mles.extend(lookups);
+        entry_mles.extend(entry_inverses);
+        let entry_point =
+            run_sumcheck_prover(merlin, &mut entry_mles, &self.entry_terms(challenges));
+        let entry_evaluations = entry_mles[1..].iter().map(|mle| mle[0]).collect::<Vec<_>>();
+        merlin
+            .add_scalars(&entry_evaluations)
+            .expect("Failed to add entry evaluations to Merlin");
+
+        // Sumcheck over the tables
+        let table_size = 1 << self.log_table_size;
+        let mut table_mles = vec![
+            calculate_evaluations_over_boolean_hypercube_for_eq(tau_table),
+            (0..table_size as u64).map(FieldElement::from).collect(),
+        ];
+        table_mles.extend(tables);
+        let counts_offset = table_mles.len();
+        for matrix in 0..3 {
+            for dimension in 0..2 {
+                let offset = count_slot(matrix, dimension) << log_slot_size;
+                table_mles.push(matrix_polynomial[offset..offset + table_size].to_vec());
+            }
+        }
+        table_mles.extend(table_inverses);
+        let table_point =
+            run_sumcheck_prover(merlin, &mut table_mles, &self.table_terms(challenges));
+        let table_evaluations = table_mles[counts_offset..]
+            .iter()
+            .map(|mle| mle[0])
+            .collect::<Vec<_>>();
+        merlin
+            .add_scalars(&table_evaluations)
+            .expect("Failed to add table evaluations to Merlin");
+
+        // Open the commitments at the final sumcheck points
+        let [matrix_openings, lookup_openings, inverse_openings] = self.openings(
+            &entry_point,
+            &entry_evaluations,
+            &table_point,
+            &table_evaluations,
+        );
+        open(merlin, &self.whir_matrices, matrix_witness, matrix_openings);
+        open(merlin, &self.whir_lookups, lookup_witness, lookup_openings);
+        open(
+            merlin,
+            &self.whir_inverses,
+            inverse_witness,
+            inverse_openings,
+        );
+    }
+}
+
+fn commit(
+    merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
+    config: &WhirConfig,
+    evaluations: &[FieldElement],
+) -> Witness<FieldElement, SkyscraperMerkleConfig> {
+    let polynomial = EvaluationsList::new(evaluations.to_vec());
+    CommitmentWriter::new(config.clone())
+        .commit_batch(merlin, &[polynomial.to_coeffs()])
+        .expect("WHIR prover failed to commit")
+}
+
+fn open(
+    merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
+    config: &WhirConfig,
+    witness: Witness<FieldElement, SkyscraperMerkleConfig>,
+    openings: Openings,
+) {
+    let mut statement = Statement::<FieldElement>::new(config.mv_parameters.num_variables);
+    for (point, value) in openings {
+        statement.add_constraint(Weights::evaluation(MultilinearPoint(point)), value);
+    }
+    Prover(config.clone())
+        .prove(merlin, statement, witness)
+        .expect("WHIR prover failed to generate a proof");
+}
+
+/// Runs the sumcheck protocol on the sum of `terms` over the boolean hypercube,
+/// folding `mles` in the leading variable each round. Returns the sumcheck
+/// randomness, at which point `mles` hold their evaluations.
+fn run_sumcheck_prover(
+    merlin: &mut ProverState<SkyscraperSponge, FieldElement>,
+    mles: &mut [Vec<FieldElement>],
+    terms: &[SumcheckTerm],
+) -> Vec<FieldElement> {
+    assert!(terms.iter().all(|(_, factors)| factors.len() <= 3));
+    let size = mles[0].len();
+    assert!(size.is_power_of_two());
+    assert!(mles.iter().all(|mle| mle.len() == size));
+
+    let mut claim = {
+        let mles = &*mles;
+        (0..size)
+            .into_par_iter()
+            .map(|i| {
+                terms
+                    .iter()
+                    .map(|(coefficient, factors)| {
+                        factors
+                            .iter()
+                            .fold(*coefficient, |acc, &j| acc * mles[j][i])
+                    })
+                    .sum::<FieldElement>()
+            })
+            .sum::<FieldElement>()
+    };
+
+    let mut point = Vec::with_capacity(size.ilog2() as usize);
+    while mles[0].len() > 1 {
+        let half = mles[0].len() / 2;
+
+        // Evaluate at 0, -1 and the leading coefficient
+        let [h_0, h_em1, h_inf] = {
+            let mles = &*mles;
+            (0..half)
+                .into_par_iter()
+                .map(|i| {
+                    let mut result = [FieldElement::zero(); 3];
+                    for (coefficient, factors) in terms {
+                        let mut at_0 = *coefficient;
+                        let mut at_em1 = *coefficient;
+                        let mut at_inf = *coefficient;
+                        for &j in factors {
+                            let (p0, p1) = (mles[j][i], mles[j][half + i]);
+                            at_0 *= p0;
+                            at_em1 *= p0 + p0 - p1;
+                            at_inf *= p1 - p0;
+                        }
+                        result[0] += at_0;
+                        result[1] += at_em1;
+                        if factors.len() == 3 {
+                            result[2] += at_inf;
+                        }
+                    }
+                    result
+                })
+                .reduce(
+                    || [FieldElement::zero(); 3],
+                    |a, b| [a[0] + b[0], a[1] + b[1], a[2] + b[2]],
+                )
+        };
+
+        let mut coefficients = [FieldElement::zero(); 4];
+        coefficients[0] = h_0;
+        coefficients[3] = h_inf;
+        coefficients[2] = HALF * (claim + h_em1 - h_0 - h_0 - h_0);
+        coefficients[1] = claim - h_0 - h_0 - coefficients[3] - coefficients[2];
+        merlin
+            .add_scalars(&coefficients)
+            .expect("Failed to add sumcheck polynomial to Merlin");
+
+        let mut challenge = [FieldElement::zero()];
+        merlin
+            .fill_challenge_scalars(&mut challenge)
+            .expect("Failed to extract challenge scalars from Merlin");
+        let [x] = challenge;
+
+        mles.par_iter_mut().for_each(|mle| {
+            let (p0, p1) = mle.split_at_mut(half);
+            p0.iter_mut()
+                .zip(p1.iter())
+                .for_each(|(p0, p1)| *p0 += x * (*p1 - *p0));
+            mle.truncate(half);
+        });
+
+        claim = eval_cubic_poly(&coefficients, &x);
+        point.push(x);
+    }
+    point
+}
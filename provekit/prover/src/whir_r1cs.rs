@@ -1,4 +1,5 @@
 use {
+    crate::spark::SparkProver,
     anyhow::{ensure, Result},
     ark_ff::UniformRand,
     ark_std::{One, Zero},
@@ -8,8 +9,9 @@ use {
             pad_to_power_of_two,
             sumcheck::{
                 calculate_evaluations_over_boolean_hypercube_for_eq,
-                calculate_external_row_of_r1cs_matrices, calculate_witness_bounds, eval_cubic_poly,
-                sumcheck_fold_map_reduce, SumcheckIOPattern,
+                calculate_external_row_of_r1cs_matrices, calculate_public_input_weights,
+                calculate_witness_bounds, eval_cubic_poly, expand_powers, sumcheck_fold_map_reduce,
+                SumcheckIOPattern,
             },
             zk_utils::{create_masked_polynomial, generate_random_multilinear_polynomial},
             HALF,
@@ -34,18 +36,33 @@ use {
 };
 
 pub trait WhirR1CSProver {
-    fn prove(&self, r1cs: &R1CS, witness: Vec<FieldElement>) -> Result<WhirR1CSProof>;
+    /// Prove satisfaction of the R1CS instance identified by `digest`. The
+    /// witnesses `w1` are committed first, then the challenges are derived and
+    /// passed to `solve_w2` to obtain the remaining witnesses.
+    fn prove(
+        &self,
+        r1cs: &R1CS,
+        digest: FieldElement,
+        w1: Vec<FieldElement>,
+        solve_w2: impl FnOnce(&[FieldElement]) -> Result<Vec<FieldElement>>,
+    ) -> Result<WhirR1CSProof>;
 }
 
 impl WhirR1CSProver for WhirR1CSScheme {
     #[instrument(skip_all)]
-    fn prove(&self, r1cs: &R1CS, witness: Vec<FieldElement>) -> Result<WhirR1CSProof> {
+    fn prove(
+        &self,
+        r1cs: &R1CS,
+        digest: FieldElement,
+        w1: Vec<FieldElement>,
+        solve_w2: impl FnOnce(&[FieldElement]) -> Result<Vec<FieldElement>>,
+    ) -> Result<WhirR1CSProof> {
         ensure!(
-            witness.len() == r1cs.num_witnesses(),
-            "Unexpected witness length for R1CS instance"
+            w1.len() == self.w1_size,
+            "Unexpected witness length for first commitment"
         );
         ensure!(
-            r1cs.num_witnesses() <= 1 << self.m,
+            self.w1_size <= 1 << self.m,
             "R1CS witness length exceeds scheme capacity"
         );
         ensure!(
@@ -57,17 +74,57 @@ impl WhirR1CSProver for WhirR1CSScheme {
         let io: IOPattern = self.create_io_pattern();
 
         let mut merlin = io.to_prover_state();
-        let z = pad_to_power_of_two(witness.clone());
-        let witness_polynomial_evals = EvaluationsList::new(z.clone());
 
+        // Bind the circuit to the transcript
+        merlin
+            .add_scalars(&[digest])
+            .expect("Failed to absorb circuit digest");
+
+        // Bind the public inputs and outputs to the transcript
+        if r1cs.num_public_values() > 0 {
+            merlin
+                .add_scalars(&w1[1..=r1cs.num_public_values()])
+                .expect("Failed to absorb public values");
+        }
+
+        // Commit to the witnesses that do not depend on challenges
         let (commitment_to_witness, masked_polynomial, random_polynomial) =
             batch_commit_to_polynomial(
                 self.m,
                 &self.whir_witness,
-                &witness_polynomial_evals,
+                &EvaluationsList::new(pad_to_power_of_two(w1.clone())),
                 &mut merlin,
             );
 
+        // Derive the challenges and commit to the witnesses that depend on them
+        let mut witness = w1;
+        let commitment_2 = if let Some(whir_witness_2) = &self.whir_witness_2 {
+            let mut challenges = vec![FieldElement::zero(); self.num_challenges];
+            merlin
+                .fill_challenge_scalars(&mut challenges)
+                .expect("Failed to extract challenge scalars from Merlin");
+
+            let w2 = solve_w2(&challenges)?;
+            ensure!(
+                self.w1_size + w2.len() == r1cs.num_witnesses(),
+                "Unexpected witness length for second commitment"
+            );
+            let commitment = batch_commit_to_polynomial(
+                whir_witness_2.mv_parameters.num_variables,
+                whir_witness_2,
+                &EvaluationsList::new(pad_to_power_of_two(w2.clone())),
+                &mut merlin,
+            );
+            witness.extend(w2);
+            Some((whir_witness_2, commitment))
+        } else {
+            ensure!(
+                witness.len() == r1cs.num_witnesses(),
+                "Unexpected witness length for R1CS instance"
+            );
+            None
+        };
+
         // First round of sumcheck to reduce R1CS to a batch weighted evaluation of the
         // witness
         let (mut merlin, alpha) = run_zk_sumcheck_prover(
@@ -77,22 +134,69 @@ impl WhirR1CSProver for WhirR1CSScheme {
             self.m_0,
             &self.whir_for_hiding_spartan,
         );
-        // Compute weights from R1CS instance
+        // Compute weights from R1CS instance, split by commitment
         let alphas = calculate_external_row_of_r1cs_matrices(&alpha, r1cs);
-        let (statement, f_sums, g_sums) = create_combined_statement_over_two_polynomials::<3>(
+        let mut weights = alphas
+            .iter()
+            .map(|row| row[..self.w1_size].to_vec())
+            .collect::<Vec<_>>();
+
+        // Open a random linear combination of the public values
+        if r1cs.num_public_values() > 0 {
+            let mut tau = [FieldElement::zero()];
+            merlin
+                .fill_challenge_scalars(&mut tau)
+                .expect("Failed to extract challenge scalars from Merlin");
+            weights.push(calculate_public_input_weights(
+                tau[0],
+                r1cs.num_public_values(),
+                self.w1_size,
+            ));
+        }
+
+        let (statement, f_sums, g_sums) = create_combined_statement_over_two_polynomials(
             self.m,
             &commitment_to_witness,
             &masked_polynomial,
             &random_polynomial,
-            &alphas,
+            &weights,
+        );
+        let _ = merlin.hint::<(Vec<FieldElement>, Vec<FieldElement>)>(&(f_sums, g_sums));
+
+        let opening_2 = commitment_2.map(
+            |(whir_witness_2, (commitment, masked_polynomial, random_polynomial))| {
+                let weights_2 = alphas
+                    .iter()
+                    .map(|row| row[self.w1_size..].to_vec())
+                    .collect::<Vec<_>>();
+                let (statement, f_sums, g_sums) = create_combined_statement_over_two_polynomials(
+                    whir_witness_2.mv_parameters.num_variables,
+                    &commitment,
+                    &masked_polynomial,
+                    &random_polynomial,
+                    &weights_2,
+                );
+                let _ = merlin.hint::<(Vec<FieldElement>, Vec<FieldElement>)>(&(f_sums, g_sums));
+                (whir_witness_2, commitment, statement)
+            },
         );
 
-        let _ = merlin
-            .hint::<(Vec<FieldElement>, Vec<FieldElement>)>(&(f_sums.to_vec(), g_sums.to_vec()));
-
-        // Compute WHIR weighted batch opening proof
-        let (merlin, ..) =
+        // Compute WHIR weighted batch opening proofs
+        let (mut merlin, folding_randomness, _) =
             run_zk_whir_pcs_prover(commitment_to_witness, statement, &self.whir_witness, merlin);
+        let mut column_points = vec![folding_randomness.0];
+        if let Some((whir_witness_2, commitment, statement)) = opening_2 {
+            let (merlin_2, folding_randomness_2, _) =
+                run_zk_whir_pcs_prover(commitment, statement, whir_witness_2, merlin);
+            merlin = merlin_2;
+            column_points.push(folding_randomness_2.0);
+        }
+
+        // Prove the matrix evaluations deferred by the openings
+        if let Some(spark) = &self.spark {
+            let column_points = column_points.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            spark.prove(&mut merlin, r1cs, self.w1_size, &alpha, &column_points);
+        }
 
         let transcript = merlin.narg_string().to_vec();
 
@@ -345,7 +449,7 @@ pub fn run_zk_sumcheck_prover(
     }
 
     let (statement, blinding_mask_polynomial_sum, blinding_blind_polynomial_sum) =
-        create_combined_statement_over_two_polynomials::<1>(
+        create_combined_statement_over_two_polynomials(
             blinding_polynomial_variables + 1,
             &commitment_to_blinding_polynomial,
             &blindings_mask_polynomial,
@@ -368,18 +472,7 @@ pub fn run_zk_sumcheck_prover(
     (merlin, alpha)
 }
 
-fn expand_powers(values: &[FieldElement]) -> Vec<FieldElement> {
-    let mut result = Vec::with_capacity(values.len() * 4);
-    for &value in values {
-        result.push(FieldElement::one());
-        result.push(value);
-        result.push(value * value);
-        result.push(value * value * value);
-    }
-    result
-}
-
-fn create_combined_statement_over_two_polynomials<const N: usize>(
+fn create_combined_statement_over_two_polynomials(
     num_vars: usize,
     witness: &Witness<FieldElement, SkyscraperMerkleConfig>,
     f_polynomial: &EvaluationsList<FieldElement>,
@@ -387,14 +480,14 @@ fn create_combined_statement_over_two_polynomials<const N: usize>(
     alphas: &[Vec<FieldElement>],
 ) -> (
     Statement<FieldElement>,
-    [FieldElement; N],
-    [FieldElement; N],
+    Vec<FieldElement>,
+    Vec<FieldElement>,
 ) {
     let mut statement = Statement::<FieldElement>::new(num_vars);
-    let mut f_sums = [FieldElement::zero(); N];
-    let mut g_sums = [FieldElement::zero(); N];
+    let mut f_sums = Vec::with_capacity(alphas.len());
+    let mut g_sums = Vec::with_capacity(alphas.len());
 
-    for (idx, alpha) in alphas.iter().enumerate() {
+    for alpha in alphas {
         let mut expanded_alphas = pad_to_power_of_two(alpha.clone());
         expanded_alphas.resize(expanded_alphas.len() * 2, FieldElement::zero());
 
@@ -404,8 +497,8 @@ fn create_combined_statement_over_two_polynomials<const N: usize>(
 
         statement.add_constraint(weight, f + witness.batching_randomness * g);
 
-        f_sums[idx] = f;
-        g_sums[idx] = g;
+        f_sums.push(f);
+        g_sums.push(g);
     }
 
     (statement, f_sums, g_sums)
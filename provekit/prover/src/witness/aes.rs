@@ -0,0 +1,124 @@
+use {
+    ark_ff::PrimeField,
+    provekit_common::{
+        witness::{
+            aes::{aes_round, key_schedule, AesOps},
+            byte_table::ByteTable,
+            Aes128KeyScheduleWitnesses, Aes128RoundWitnesses,
+        },
+        FieldElement,
+    },
+};
+
+pub(crate) trait Aes128WitnessesSolver {
+    fn solve(&self, witness: &mut [Option<FieldElement>]);
+}
+
+impl Aes128WitnessesSolver for Aes128KeyScheduleWitnesses {
+    fn solve(&self, witness: &mut [Option<FieldElement>]) {
+        let key = self.key.map(|word| value(witness, word));
+        let mut values = NativeValues::default();
+        key_schedule(&mut values, key);
+        values.write_to(witness, self.first_witness_idx);
+    }
+}
+
+impl Aes128WitnessesSolver for Aes128RoundWitnesses {
+    fn solve(&self, witness: &mut [Option<FieldElement>]) {
+        let state = self.state.map(|word| value(witness, word));
+        let round_key = self.round_key.map(|word| value(witness, word));
+        let mut values = NativeValues::default();
+        aes_round(&mut values, state, round_key, self.round);
+        values.write_to(witness, self.first_witness_idx);
+    }
+}
+
+fn value(witness: &[Option<FieldElement>], idx: usize) -> u32 {
+    witness[idx].unwrap().into_bigint().0[0] as u32
+}
+
+/// Evaluates the operations of AES, recording every value written.
+#[derive(Default)]
+struct NativeValues(Vec<u32>);
+
+impl NativeValues {
+    fn write(&mut self, value: u32) -> u32 {
+        self.0.push(value);
+        value
+    }
+
+    fn write_to(self, witness: &mut [Option<FieldElement>], first_witness_idx: usize) {
+        for (i, value) in self.0.into_iter().enumerate() {
+            witness[first_witness_idx + i] = Some(FieldElement::from(value));
+        }
+    }
+}
+
+impl AesOps for NativeValues {
+    type Value = u32;
+
+    fn xor(&mut self, lhs: u32, rhs: u32) -> u32 {
+        self.write(lhs ^ rhs)
+    }
+
+    fn xor_constant(&mut self, word: u32, constant: u32) -> u32 {
+        self.write(word ^ constant)
+    }
+
+    fn lookup(&mut self, table: ByteTable, byte: u32) -> u32 {
+        self.write(u32::from(table.apply(byte as u8)))
+    }
+
+    fn pack(&mut self, bytes: [u32; 4]) -> u32 {
+        self.write(u32::from_le_bytes(bytes.map(|byte| byte as u8)))
+    }
+
+    fn unpack(&mut self, word: u32) -> [u32; 4] {
+        word.to_le_bytes().map(|byte| self.write(u32::from(byte)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::NativeValues,
+        provekit_common::witness::aes::{
+            aes_round, key_schedule, AesOps, KEY_SCHEDULE_WITNESSES, NUM_ROUNDS,
+            WITNESSES_PER_LAST_ROUND, WITNESSES_PER_ROUND,
+        },
+    };
+
+    /// The example vector of FIPS-197, appendix C.1
+    #[test]
+    fn test_aes128_encryption() {
+        let words = |bytes: [u8; 16]| -> [u32; 4] {
+            std::array::from_fn(|c| u32::from_le_bytes(bytes[4 * c..4 * c + 4].try_into().unwrap()))
+        };
+        let key = words(std::array::from_fn(|i| i as u8));
+        let plaintext = words(std::array::from_fn(|i| (i * 0x11) as u8));
+
+        let mut values = NativeValues::default();
+        let round_keys = key_schedule(&mut values, key);
+        assert_eq!(values.0.len(), KEY_SCHEDULE_WITNESSES);
+
+        let mut state: [u32; 4] =
+            std::array::from_fn(|c| values.xor(plaintext[c], round_keys[0][c]));
+        for (round, round_key) in round_keys.into_iter().enumerate().skip(1) {
+            let mut values = NativeValues::default();
+            state = aes_round(&mut values, state, round_key, round);
+            let expected = if round == NUM_ROUNDS {
+                WITNESSES_PER_LAST_ROUND
+            } else {
+                WITNESSES_PER_ROUND
+            };
+            assert_eq!(values.0.len(), expected);
+        }
+        assert_eq!(
+            state,
+            words([
+                0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4,
+                0xc5, 0x5a
+            ])
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use {
+    ark_ff::PrimeField,
+    provekit_common::{
+        witness::{
+            keccak::{keccak_round, KeccakOps, Lane},
+            KeccakRoundWitnesses,
+        },
+        FieldElement,
+    },
+};
+
+pub(crate) trait KeccakRoundWitnessesSolver {
+    fn solve(&self, witness: &mut [Option<FieldElement>]);
+}
+
+impl KeccakRoundWitnessesSolver for KeccakRoundWitnesses {
+    fn solve(&self, witness: &mut [Option<FieldElement>]) {
+        let state = self
+            .state
+            .map(|lane| lane.map(|word| witness[word].unwrap().into_bigint().0[0] as u32));
+        let mut words = NativeWords::default();
+        keccak_round(&mut words, &state, self.round);
+        for (i, word) in words.0.into_iter().enumerate() {
+            witness[self.first_witness_idx + i] = Some(FieldElement::from(word));
+        }
+    }
+}
+
+/// Evaluates the operations of a round, recording every word written.
+#[derive(Default)]
+struct NativeWords(Vec<u32>);
+
+impl NativeWords {
+    fn write(&mut self, word: u32) -> u32 {
+        self.0.push(word);
+        word
+    }
+}
+
+impl KeccakOps for NativeWords {
+    type Word = u32;
+
+    fn xor(&mut self, lhs: u32, rhs: u32) -> u32 {
+        self.write(lhs ^ rhs)
+    }
+
+    fn xor_constant(&mut self, word: u32, constant: u32) -> u32 {
+        self.write(word ^ constant)
+    }
+
+    fn and_not(&mut self, lhs: u32, rhs: u32) -> u32 {
+        self.write(!lhs & rhs)
+    }
+
+    fn rotate_left(&mut self, [low, high]: Lane<u32>, amount: u32) -> Lane<u32> {
+        let split = |word: u32| (word & ((1 << (32 - amount)) - 1), word >> (32 - amount));
+        let (low_low, low_high) = split(low);
+        let (high_low, high_high) = split(high);
+        for piece in [low_low, low_high, high_low, high_high] {
+            self.write(piece);
+        }
+        [
+            self.write((low_low << amount) | high_high),
+            self.write((high_low << amount) | low_high),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::NativeWords,
+        provekit_common::witness::keccak::{keccak_round, NUM_ROUNDS, WITNESSES_PER_ROUND},
+    };
+
+    #[test]
+    fn test_keccak_permutation() {
+        let mut state = [[0u32; 2]; 25];
+        for round in 0..NUM_ROUNDS {
+            let mut words = NativeWords::default();
+            state = keccak_round(&mut words, &state, round);
+            assert_eq!(words.0.len(), WITNESSES_PER_ROUND);
+        }
+        let lanes = state.map(|[low, high]| u64::from(low) | (u64::from(high) << 32));
+        assert_eq!(lanes[..4], [
+            0xf1258f7940e1dde7,
+            0x84d5ccf933c0478a,
+            0xd598261ea65aa9ee,
+            0xbd1547306f80494d
+        ]);
+        assert_eq!(lanes[24], 0xeaf1ff7b5ceca249);
+    }
+}
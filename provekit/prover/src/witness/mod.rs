@@ -5,10 +5,12 @@ use {
     tracing::{info, instrument},
 };
 
+mod aes;
 mod digits;
+mod keccak;
+pub(crate) mod non_native;
 mod ram;
 pub(crate) mod witness_builder;
-pub(crate) mod witness_io_pattern;
 
 /// Complete a partial witness with random values.
 #[instrument(skip_all, fields(size = witness.len()))]
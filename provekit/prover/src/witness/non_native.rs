@@ -0,0 +1,301 @@
+use {
+    ark_ff::{Field, PrimeField},
+    ark_std::Zero,
+    provekit_common::{
+        witness::non_native::{
+            carry_offset, Limbs, NonNativeIdentity, NonNativeOperand, NonNativeTerm, LIMB_BITS,
+            NUM_COEFFICIENTS, NUM_LIMBS, NUM_QUOTIENT_LIMBS, QUOTIENT_OFFSET,
+        },
+        FieldElement,
+    },
+    ruint::{aliases::U256, Uint},
+};
+
+/// Integers wide enough for the terms of an identity and its offset quotient
+type Wide = Uint<640, 10>;
+
+pub(crate) trait NonNativeIdentitySolver {
+    /// Whether the identity holds for some result and quotient, given the
+    /// values of its other operands.
+    fn is_solvable(&self, witness: &[Option<FieldElement>]) -> bool;
+
+    fn solve(&self, witness: &mut [Option<FieldElement>]);
+}
+
+impl NonNativeIdentitySolver for NonNativeIdentity {
+    fn is_solvable(&self, witness: &[Option<FieldElement>]) -> bool {
+        solve_result_and_quotient(self, witness).is_some()
+    }
+
+    fn solve(&self, witness: &mut [Option<FieldElement>]) {
+        let (result, quotient) =
+            solve_result_and_quotient(self, witness).expect("Non-native identity has no solution");
+        let value = |operand: &NonNativeOperand| match operand {
+            NonNativeOperand::Limbs(limbs) => limbs_value(limbs, witness),
+            NonNativeOperand::Result => result.unwrap(),
+        };
+
+        // The coefficients of the polynomial in the limbs, which vanishes at
+        // `2^LIMB_BITS`.
+        let field_limbs = |operand: &NonNativeOperand| -> [FieldElement; NUM_LIMBS] {
+            value(operand).as_limbs().map(FieldElement::from)
+        };
+        let mut coefficients = [FieldElement::zero(); NUM_COEFFICIENTS];
+        for term in &self.terms {
+            match term {
+                NonNativeTerm::Product(c, lhs, rhs) => {
+                    let (lhs, rhs) = (field_limbs(lhs), field_limbs(rhs));
+                    for (i, lhs) in lhs.iter().enumerate() {
+                        for (j, rhs) in rhs.iter().enumerate() {
+                            coefficients[i + j] += FieldElement::from(*c) * lhs * rhs;
+                        }
+                    }
+                }
+                NonNativeTerm::Linear(c, operand) => {
+                    for (i, limb) in field_limbs(operand).iter().enumerate() {
+                        coefficients[i] += FieldElement::from(*c) * limb;
+                    }
+                }
+                NonNativeTerm::Constant(c, constant) => {
+                    for (i, limb) in constant.iter().enumerate() {
+                        coefficients[i] += FieldElement::from(*c) * FieldElement::from(*limb);
+                    }
+                }
+            }
+        }
+        if let (Some(modulus), Some(quotient)) = (self.modulus, quotient) {
+            for (j, modulus_limb) in modulus.iter().enumerate() {
+                let modulus_limb = FieldElement::from(*modulus_limb);
+                coefficients[NUM_QUOTIENT_LIMBS - 1 + j] +=
+                    FieldElement::from(QUOTIENT_OFFSET) * modulus_limb;
+                for (i, quotient_limb) in
+                    quotient.as_limbs()[..NUM_QUOTIENT_LIMBS].iter().enumerate()
+                {
+                    coefficients[i + j] -= FieldElement::from(*quotient_limb) * modulus_limb;
+                }
+            }
+        }
+
+        // Each carry is exactly divisible by `2^LIMB_BITS` over the integers, so
+        // dividing in the field gives the (possibly negative) integer carry.
+        let shift_inverse = FieldElement::from(2u64)
+            .pow([u64::from(LIMB_BITS)])
+            .inverse()
+            .unwrap();
+        let mut carry = FieldElement::zero();
+        let mut carries = Vec::with_capacity(NUM_COEFFICIENTS - 1);
+        for coefficient in &coefficients[..NUM_COEFFICIENTS - 1] {
+            carry = (*coefficient + carry) * shift_inverse;
+            carries.push(carry + carry_offset());
+        }
+        debug_assert!((coefficients[NUM_COEFFICIENTS - 1] + carry).is_zero());
+
+        if let Some(result) = result {
+            for (i, limb) in result.as_limbs().iter().enumerate() {
+                witness[self.first_witness_idx + i] = Some(FieldElement::from(*limb));
+            }
+        }
+        if let Some(quotient) = quotient {
+            for (i, limb) in quotient.as_limbs()[..NUM_QUOTIENT_LIMBS].iter().enumerate() {
+                witness[self.quotient_start() + i] = Some(FieldElement::from(*limb));
+            }
+        }
+        for (k, carry) in carries.into_iter().enumerate() {
+            witness[self.carries_start() + k] = Some(carry);
+        }
+    }
+}
+
+fn limbs_value(limbs: &Limbs, witness: &[Option<FieldElement>]) -> U256 {
+    U256::from_limbs(limbs.map(|limb| witness[limb].unwrap().into_bigint().0[0]))
+}
+
+/// Solves an identity for its result, if it has one, and for the quotient by
+/// its modulus, if it has one. Returns `None` if the identity has no solution.
+fn solve_result_and_quotient(
+    identity: &NonNativeIdentity,
+    witness: &[Option<FieldElement>],
+) -> Option<(Option<U256>, Option<Wide>)> {
+    let modulus = identity.modulus.map(U256::from_limbs);
+    let result = if identity.has_result() {
+        Some(solve_result(
+            &identity.terms,
+            Ring(modulus),
+            |operand| match operand {
+                NonNativeOperand::Limbs(limbs) => Some(limbs_value(limbs, witness)),
+                NonNativeOperand::Result => None,
+            },
+        )?)
+    } else {
+        None
+    };
+    let value = |operand: &NonNativeOperand| match operand {
+        NonNativeOperand::Limbs(limbs) => limbs_value(limbs, witness),
+        NonNativeOperand::Result => result.unwrap(),
+    };
+
+    // Evaluate the identity over the integers, summing its positive and negative
+    // terms apart.
+    let (mut positive, mut negative) = (Wide::ZERO, Wide::ZERO);
+    for term in &identity.terms {
+        let (coefficient, magnitude) = match term {
+            NonNativeTerm::Product(c, lhs, rhs) => {
+                (*c, Wide::from(value(lhs)) * Wide::from(value(rhs)))
+            }
+            NonNativeTerm::Linear(c, operand) => (*c, Wide::from(value(operand))),
+            NonNativeTerm::Constant(c, constant) => (*c, Wide::from(U256::from_limbs(*constant))),
+        };
+        let magnitude = magnitude * Wide::from(coefficient.unsigned_abs());
+        if coefficient < 0 {
+            negative += magnitude;
+        } else {
+            positive += magnitude;
+        }
+    }
+    let quotient = match modulus {
+        Some(modulus) => {
+            let modulus = Wide::from(modulus);
+            let offset =
+                Wide::from(QUOTIENT_OFFSET) << (LIMB_BITS as usize * (NUM_QUOTIENT_LIMBS - 1));
+            let (quotient, remainder) = (positive + offset * modulus - negative).div_rem(modulus);
+            let in_range = quotient.as_limbs()[NUM_QUOTIENT_LIMBS..]
+                .iter()
+                .all(|limb| *limb == 0);
+            if !remainder.is_zero() || !in_range {
+                return None;
+            }
+            Some(quotient)
+        }
+        None => {
+            if positive != negative {
+                return None;
+            }
+            None
+        }
+    };
+    Some((result, quotient))
+}
+
+/// Arithmetic modulo the modulus of an identity, or modulo `2^256` for an
+/// identity over the integers.
+#[derive(Clone, Copy)]
+struct Ring(Option<U256>);
+
+impl Ring {
+    fn add(self, lhs: U256, rhs: U256) -> U256 {
+        match self.0 {
+            Some(modulus) => lhs.add_mod(rhs, modulus),
+            None => lhs.wrapping_add(rhs),
+        }
+    }
+
+    fn mul(self, lhs: U256, rhs: U256) -> U256 {
+        match self.0 {
+            Some(modulus) => lhs.mul_mod(rhs, modulus),
+            None => lhs.wrapping_mul(rhs),
+        }
+    }
+
+    fn neg(self, value: U256) -> U256 {
+        match self.0 {
+            Some(modulus) => U256::ZERO.add_mod(modulus - value.reduce_mod(modulus), modulus),
+            None => value.wrapping_neg(),
+        }
+    }
+
+    fn scale(self, coefficient: i64, value: U256) -> U256 {
+        let value = self.mul(U256::from(coefficient.unsigned_abs()), value);
+        if coefficient < 0 {
+            self.neg(value)
+        } else {
+            value
+        }
+    }
+
+    fn inverse(self, value: U256) -> Option<U256> {
+        match self.0 {
+            Some(modulus) => value.inv_mod(modulus),
+            // Newton iteration doubles the number of correct low bits, starting
+            // from the three bits of `value * value = 1 (mod 8)`.
+            None => value.bit(0).then(|| {
+                (0..7).fold(value, |inverse, _| {
+                    inverse.wrapping_mul(U256::from(2u64).wrapping_sub(value.wrapping_mul(inverse)))
+                })
+            }),
+        }
+    }
+}
+
+/// Solves an identity `a * result + b = 0` for its result, where `values`
+/// returns the value of any operand other than the result. Returns `None` if
+/// `a` is not invertible.
+fn solve_result(
+    terms: &[NonNativeTerm],
+    ring: Ring,
+    values: impl Fn(&NonNativeOperand) -> Option<U256>,
+) -> Option<U256> {
+    let (mut a, mut b) = (U256::ZERO, U256::ZERO);
+    for term in terms {
+        match term {
+            NonNativeTerm::Product(c, lhs, rhs) => match (values(lhs), values(rhs)) {
+                (Some(lhs), Some(rhs)) => b = ring.add(b, ring.scale(*c, ring.mul(lhs, rhs))),
+                (Some(factor), None) | (None, Some(factor)) => {
+                    a = ring.add(a, ring.scale(*c, factor));
+                }
+                (None, None) => panic!("The result of a non-native identity must appear linearly"),
+            },
+            NonNativeTerm::Linear(c, operand) => match values(operand) {
+                Some(value) => b = ring.add(b, ring.scale(*c, value)),
+                None => a = ring.add(a, ring.scale(*c, U256::from(1u64))),
+            },
+            NonNativeTerm::Constant(c, constant) => {
+                b = ring.add(b, ring.scale(*c, U256::from_limbs(*constant)));
+            }
+        }
+    }
+    Some(ring.mul(ring.neg(b), ring.inverse(a)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::NonNativeIdentitySolver,
+        ark_ff::PrimeField,
+        provekit_common::{
+            witness::non_native::{NonNativeIdentity, NonNativeOperand, NonNativeTerm, NUM_LIMBS},
+            FieldElement,
+        },
+        ruint::{aliases::U256, uint},
+    };
+
+    #[test]
+    fn test_modular_inverse() {
+        let modulus =
+            uint!(0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f_U256);
+        let value = uint!(0x123456789abcdef0123456789abcdef_U256);
+        let mut witness = vec![None; NUM_LIMBS + 20];
+        for (i, limb) in value.as_limbs().iter().enumerate() {
+            witness[i] = Some(FieldElement::from(*limb));
+        }
+        let identity = NonNativeIdentity {
+            first_witness_idx: NUM_LIMBS,
+            modulus:           Some(*modulus.as_limbs()),
+            terms:             vec![
+                NonNativeTerm::Product(
+                    1,
+                    NonNativeOperand::Limbs([0, 1, 2, 3]),
+                    NonNativeOperand::Result,
+                ),
+                NonNativeTerm::Constant(-1, [1, 0, 0, 0]),
+            ],
+        };
+        identity.solve(&mut witness);
+        let inverse = U256::from_limbs(std::array::from_fn(|i| {
+            witness[NUM_LIMBS + i].unwrap().into_bigint().0[0]
+        }));
+        assert_eq!(inverse, value.inv_mod(modulus).unwrap());
+        assert!(witness[NUM_LIMBS..NUM_LIMBS + identity.num_witnesses()]
+            .iter()
+            .all(Option::is_some));
+    }
+}
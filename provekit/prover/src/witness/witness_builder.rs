@@ -1,18 +1,20 @@
 use {
-    crate::witness::{digits::DigitalDecompositionWitnessesSolver, ram::SpiceWitnessesSolver},
+    crate::witness::{
+        aes::Aes128WitnessesSolver, digits::DigitalDecompositionWitnessesSolver,
+        keccak::KeccakRoundWitnessesSolver, non_native::NonNativeIdentitySolver,
+        ram::SpiceWitnessesSolver,
+    },
     acir::native_types::WitnessMap,
     ark_ff::{Field, PrimeField},
-    ark_std::Zero,
+    ark_std::{One, Zero},
     provekit_common::{
-        skyscraper::SkyscraperSponge,
         utils::noir_to_native,
         witness::{
-            ConstantOrR1CSWitness, ConstantTerm, ProductLinearTerm, SumTerm, WitnessBuilder,
-            WitnessCoefficient, BINOP_ATOMIC_BITS,
+            embedded_curve, poseidon2, ConstantOrR1CSWitness, ConstantTerm, ProductLinearTerm,
+            SumTerm, WitnessBuilder, WitnessCoefficient,
         },
         FieldElement, NoirElement,
     },
-    spongefish::{codecs::arkworks_algebra::UnitToField, ProverState},
 };
 
 pub trait WitnessBuilderSolver {
@@ -20,7 +22,7 @@ pub trait WitnessBuilderSolver {
         &self,
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
         witness: &mut [Option<FieldElement>],
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
+        challenges: &mut dyn Iterator<Item = FieldElement>,
     );
 }
 
@@ -29,7 +31,7 @@ impl WitnessBuilderSolver for WitnessBuilder {
         &self,
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
         witness: &mut [Option<FieldElement>],
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
+        challenges: &mut dyn Iterator<Item = FieldElement>,
     ) {
         match self {
             WitnessBuilder::Constant(ConstantTerm(witness_idx, c)) => {
@@ -65,45 +67,44 @@ impl WitnessBuilderSolver for WitnessBuilder {
                 let operand: FieldElement = witness[*operand_idx].unwrap();
                 witness[*witness_idx] = Some(operand.inverse().unwrap());
             }
-            WitnessBuilder::IndexedLogUpDenominator(
-                witness_idx,
-                sz_challenge,
-                WitnessCoefficient(index_coeff, index),
-                rs_challenge,
-                value,
-            ) => {
-                let index = witness[*index].unwrap();
-                let value = witness[*value].unwrap();
-                let rs_challenge = witness[*rs_challenge].unwrap();
-                let sz_challenge = witness[*sz_challenge].unwrap();
-                witness[*witness_idx] =
-                    Some(sz_challenge - (*index_coeff * index + rs_challenge * value));
-            }
-            WitnessBuilder::MultiplicitiesForRange(start_idx, range_size, value_witnesses) => {
-                let mut multiplicities = vec![0u32; *range_size];
-                for value_witness_idx in value_witnesses {
-                    // If the value is representable as just a u64, then it should be the least
-                    // significant value in the BigInt representation.
-                    let value = witness[*value_witness_idx].unwrap().into_bigint().0[0];
-                    multiplicities[value as usize] += 1;
+            WitnessBuilder::MultiplicitiesForLookup(start_idx, num_rows, row_weights, queries) => {
+                let mut multiplicities = vec![0u32; *num_rows];
+                for query in queries {
+                    // The values of the columns of the row index are small, so that they are
+                    // the least significant limb of their BigInt representation.
+                    let row = query
+                        .iter()
+                        .zip(row_weights)
+                        .filter(|(_, weight)| **weight != 0)
+                        .map(|(value, weight)| {
+                            let value = match value {
+                                ConstantOrR1CSWitness::Constant(c) => *c,
+                                ConstantOrR1CSWitness::Witness(witness_idx) => {
+                                    witness[*witness_idx].unwrap()
+                                }
+                            };
+                            value.into_bigint().0[0] * weight
+                        })
+                        .sum::<u64>();
+                    multiplicities[row as usize] += 1;
                 }
                 for (i, count) in multiplicities.iter().enumerate() {
                     witness[start_idx + i] = Some(FieldElement::from(*count));
                 }
             }
             WitnessBuilder::Challenge(witness_idx) => {
-                let mut one = [FieldElement::zero(); 1];
-                let _ = transcript.fill_challenge_scalars(&mut one);
-                witness[*witness_idx] = Some(one[0]);
+                witness[*witness_idx] = Some(challenges.next().expect("Missing challenge"));
             }
-            WitnessBuilder::LogUpDenominator(
-                witness_idx,
-                sz_challenge,
-                WitnessCoefficient(value_coeff, value),
-            ) => {
-                witness[*witness_idx] = Some(
-                    witness[*sz_challenge].unwrap() - (*value_coeff * witness[*value].unwrap()),
-                );
+            WitnessBuilder::LogUpQuotient(witness_idx, numerator, denominator) => {
+                let denominator = denominator
+                    .iter()
+                    .map(|SumTerm(coeff, witness_idx)| {
+                        coeff.unwrap_or(FieldElement::one()) * witness[*witness_idx].unwrap()
+                    })
+                    .sum::<FieldElement>();
+                let numerator =
+                    numerator.map_or(FieldElement::one(), |numerator| witness[numerator].unwrap());
+                witness[*witness_idx] = Some(numerator * denominator.inverse().unwrap());
             }
             WitnessBuilder::ProductLinearOperation(
                 witness_idx,
@@ -137,57 +138,90 @@ impl WitnessBuilderSolver for WitnessBuilder {
             WitnessBuilder::SpiceWitnesses(spice_witnesses) => {
                 spice_witnesses.solve(witness);
             }
-            WitnessBuilder::BinOpLookupDenominator(
-                witness_idx,
-                sz_challenge,
-                rs_challenge,
-                rs_challenge_sqrd,
-                lhs,
-                rhs,
-                output,
-            ) => {
-                let lhs = match lhs {
-                    ConstantOrR1CSWitness::Constant(c) => *c,
-                    ConstantOrR1CSWitness::Witness(witness_idx) => witness[*witness_idx].unwrap(),
+            WitnessBuilder::And(witness_idx, lhs, rhs)
+            | WitnessBuilder::Xor(witness_idx, lhs, rhs) => {
+                let value = |operand: &ConstantOrR1CSWitness| {
+                    let limbs = match operand {
+                        ConstantOrR1CSWitness::Constant(c) => c.into_bigint().0,
+                        ConstantOrR1CSWitness::Witness(witness_idx) => {
+                            witness[*witness_idx].unwrap().into_bigint().0
+                        }
+                    };
+                    u128::from(limbs[0]) | (u128::from(limbs[1]) << 64)
                 };
-                let rhs = match rhs {
-                    ConstantOrR1CSWitness::Constant(c) => *c,
-                    ConstantOrR1CSWitness::Witness(witness_idx) => witness[*witness_idx].unwrap(),
+                let (lhs, rhs) = (value(lhs), value(rhs));
+                let output = if matches!(self, WitnessBuilder::And(..)) {
+                    lhs & rhs
+                } else {
+                    lhs ^ rhs
                 };
-                let output = match output {
+                witness[*witness_idx] = Some(FieldElement::from(output));
+            }
+            WitnessBuilder::Poseidon2(poseidon2) => {
+                let mut state = poseidon2.inputs.each_ref().map(|input| match input {
                     ConstantOrR1CSWitness::Constant(c) => *c,
                     ConstantOrR1CSWitness::Witness(witness_idx) => witness[*witness_idx].unwrap(),
-                };
-                witness[*witness_idx] = Some(
-                    witness[*sz_challenge].unwrap()
-                        - (lhs
-                            + witness[*rs_challenge].unwrap() * rhs
-                            + witness[*rs_challenge_sqrd].unwrap() * output),
-                );
+                });
+                let mut witness_idx = poseidon2.first_witness_idx;
+                poseidon2::permute(&mut state, |x| {
+                    let x2 = x.square();
+                    let x4 = x2.square();
+                    for value in [x2, x4, x4 * x] {
+                        witness[witness_idx] = Some(value);
+                        witness_idx += 1;
+                    }
+                });
             }
-            WitnessBuilder::MultiplicitiesForBinOp(witness_idx, operands) => {
-                let mut multiplicities = vec![0u32; 2usize.pow(2 * BINOP_ATOMIC_BITS as u32)];
-                for (lhs, rhs) in operands {
-                    let lhs = match lhs {
-                        ConstantOrR1CSWitness::Constant(c) => *c,
-                        ConstantOrR1CSWitness::Witness(witness_idx) => {
-                            witness[*witness_idx].unwrap()
-                        }
-                    };
-                    let rhs = match rhs {
-                        ConstantOrR1CSWitness::Constant(c) => *c,
-                        ConstantOrR1CSWitness::Witness(witness_idx) => {
-                            witness[*witness_idx].unwrap()
-                        }
-                    };
-                    let index =
-                        (lhs.into_bigint().0[0] << BINOP_ATOMIC_BITS) + rhs.into_bigint().0[0];
-                    multiplicities[index as usize] += 1;
+            WitnessBuilder::KeccakRound(keccak_round) => {
+                keccak_round.solve(witness);
+            }
+            WitnessBuilder::NonNativeIdentity(identity) => {
+                identity.solve(witness);
+            }
+            WitnessBuilder::MemoryRead(witness_idx, address, values) => {
+                let address = witness[*address].unwrap().into_bigint().0[0] as usize;
+                witness[*witness_idx] = Some(witness[values[address]].unwrap());
+            }
+            WitnessBuilder::EmbeddedCurveAdd(add) => {
+                let [lhs, rhs] = [add.lhs, add.rhs].map(|point| point.map(|c| witness[c].unwrap()));
+                let values = embedded_curve::complete_add(lhs, rhs);
+                for (i, value) in values.into_iter().enumerate() {
+                    witness[add.first_witness_idx + i] = Some(value);
                 }
-                for (i, count) in multiplicities.iter().enumerate() {
-                    witness[witness_idx + i] = Some(FieldElement::from(*count));
+            }
+            WitnessBuilder::EmbeddedCurveChord(witness_idx, lhs, rhs) => {
+                let [lhs, rhs] = [lhs, rhs].map(|point| point.map(|c| witness[c].unwrap()));
+                for (i, value) in embedded_curve::chord(lhs, rhs).into_iter().enumerate() {
+                    witness[witness_idx + i] = Some(value);
+                }
+            }
+            WitnessBuilder::Aes128KeySchedule(key_schedule) => {
+                key_schedule.solve(witness);
+            }
+            WitnessBuilder::Aes128Round(round) => {
+                round.solve(witness);
+            }
+            WitnessBuilder::EmbeddedCurveDouble(witness_idx, point) => {
+                let point = point.map(|c| witness[c].unwrap());
+                for (i, value) in embedded_curve::double(point).into_iter().enumerate() {
+                    witness[witness_idx + i] = Some(value);
                 }
             }
+            WitnessBuilder::Sha256Choose(witness_idx, x, y, z)
+            | WitnessBuilder::Sha256Majority(witness_idx, x, y, z) => {
+                let [x, y, z] =
+                    [x, y, z].map(|operand| witness[*operand].unwrap().into_bigint().0[0]);
+                let output = if matches!(self, WitnessBuilder::Sha256Choose(..)) {
+                    (x & y) ^ (!x & z)
+                } else {
+                    (x & y) ^ (x & z) ^ (y & z)
+                };
+                witness[*witness_idx] = Some(FieldElement::from(output));
+            }
+            WitnessBuilder::ShiftRight(witness_idx, value, num_bits) => {
+                let value = witness[*value].unwrap().into_bigint().0[0];
+                witness[*witness_idx] = Some(FieldElement::from(value >> num_bits));
+            }
         }
     }
 }
@@ -0,0 +1,199 @@
+use {
+    crate::{
+        byte_table::ByteTableLookups,
+        words::{WordBuilder, WORD_BITS},
+    },
+    ark_std::One,
+    provekit_common::{
+        witness::{
+            aes::{aes_round, key_schedule, AesOps, BLOCK_BYTES, KEY_SCHEDULE_WITNESSES},
+            byte_table::ByteTable,
+            Aes128KeyScheduleWitnesses, Aes128RoundWitnesses, ConstantOrR1CSWitness,
+            WitnessBuilder,
+        },
+        FieldElement,
+    },
+};
+
+/// Adds the witnesses and constraints for AES-128 encryption. XORs and range
+/// checks are collected alongside those of the ACIR opcodes, and the byte
+/// table lookups of the S-box and MixColumns alongside those of other
+/// gadgets, and all are constrained together by lookups.
+pub(crate) struct AesBuilder<'a> {
+    pub words:          WordBuilder<'a>,
+    pub byte_table_ops: &'a mut ByteTableLookups,
+}
+
+impl AesBuilder<'_> {
+    /// Add the witnesses and constraints for the AES-128 encryption of the
+    /// inputs in CBC mode with PKCS#7 padding, as by the `aes128_encrypt`
+    /// black box, constraining the ciphertext to equal the outputs.
+    ///
+    /// The state is held as the words of its columns, so that AddRoundKey and
+    /// MixColumns are XORs of words, and is split into bytes at the start of
+    /// each round for the S-box lookups. A witness builder solves for the key
+    /// schedule, and one for each round.
+    pub fn add_encryption(
+        &mut self,
+        inputs: &[ConstantOrR1CSWitness],
+        iv: &[ConstantOrR1CSWitness; BLOCK_BYTES],
+        key: &[ConstantOrR1CSWitness; BLOCK_BYTES],
+        outputs: &[usize],
+    ) {
+        let padding = BLOCK_BYTES - inputs.len() % BLOCK_BYTES;
+        assert_eq!(
+            outputs.len(),
+            inputs.len() + padding,
+            "The ciphertext is the padded plaintext"
+        );
+        let mut plaintext = inputs.to_vec();
+        plaintext.extend(
+            std::iter::repeat(ConstantOrR1CSWitness::Constant(FieldElement::from(
+                padding as u64,
+            )))
+            .take(padding),
+        );
+
+        let key = self.pack_block(key);
+        let first_witness_idx =
+            self.words
+                .compiler
+                .add_witness_builder(WitnessBuilder::Aes128KeySchedule(
+                    Aes128KeyScheduleWitnesses {
+                        first_witness_idx: self.words.compiler.num_witnesses(),
+                        key,
+                    },
+                ));
+        let mut constraints = AesConstraints {
+            builder:      self,
+            next_witness: first_witness_idx,
+        };
+        let round_keys = key_schedule(&mut constraints, key);
+        debug_assert_eq!(
+            constraints.next_witness,
+            first_witness_idx + KEY_SCHEDULE_WITNESSES
+        );
+
+        let mut previous = self.pack_block(iv);
+        for (block, outputs) in plaintext
+            .chunks(BLOCK_BYTES)
+            .zip(outputs.chunks(BLOCK_BYTES))
+        {
+            let block = self.pack_block(block);
+            let mut state: [usize; 4] = std::array::from_fn(|c| {
+                let chained = self.words.xor(block[c], previous[c]);
+                self.words.xor(chained, round_keys[0][c])
+            });
+            for (round, round_key) in round_keys.into_iter().enumerate().skip(1) {
+                let round_witnesses = Aes128RoundWitnesses {
+                    first_witness_idx: self.words.compiler.num_witnesses(),
+                    round,
+                    state,
+                    round_key,
+                };
+                let num_witnesses = round_witnesses.num_witnesses();
+                let first_witness_idx = self
+                    .words
+                    .compiler
+                    .add_witness_builder(WitnessBuilder::Aes128Round(round_witnesses));
+                let mut constraints = AesConstraints {
+                    builder:      self,
+                    next_witness: first_witness_idx,
+                };
+                state = aes_round(&mut constraints, state, round_key, round);
+                debug_assert_eq!(constraints.next_witness, first_witness_idx + num_witnesses);
+            }
+            for (word, bytes) in state.into_iter().zip(outputs.chunks(4)) {
+                self.words.assert_le_bytes(word, bytes);
+            }
+            previous = state;
+        }
+    }
+
+    /// The words of the columns of a block, range checking its bytes.
+    fn pack_block(&mut self, bytes: &[ConstantOrR1CSWitness]) -> [usize; 4] {
+        assert_eq!(bytes.len(), BLOCK_BYTES, "A block has 16 bytes");
+        std::array::from_fn(|c| self.words.pack_le_bytes(&bytes[4 * c..4 * c + 4]))
+    }
+}
+
+/// Constrains the values of the key schedule or of a round, in the order they
+/// are written by its witness builder.
+struct AesConstraints<'a, 'b> {
+    builder:      &'a mut AesBuilder<'b>,
+    next_witness: usize,
+}
+
+impl AesConstraints<'_, '_> {
+    fn next(&mut self) -> usize {
+        self.next_witness += 1;
+        self.next_witness - 1
+    }
+
+    /// Constrains a word to equal its little-endian bytes. The bytes are not
+    /// range checked here: each of them is looked up in a byte table.
+    fn constrain_bytes(&mut self, word: usize, bytes: [usize; 4]) {
+        let compiler = &mut *self.builder.words.compiler;
+        compiler.r1cs.add_constraint(
+            &bytes
+                .iter()
+                .enumerate()
+                .map(|(i, &byte)| (FieldElement::from(1u64 << (8 * i)), byte))
+                .collect::<Vec<_>>(),
+            &[(FieldElement::one(), compiler.witness_one())],
+            &[(FieldElement::one(), word)],
+        );
+    }
+}
+
+impl AesOps for AesConstraints<'_, '_> {
+    type Value = usize;
+
+    fn xor(&mut self, lhs: usize, rhs: usize) -> usize {
+        let output = self.next();
+        self.builder
+            .words
+            .xor_ops
+            .entry(WORD_BITS)
+            .or_default()
+            .push((
+                ConstantOrR1CSWitness::Witness(lhs),
+                ConstantOrR1CSWitness::Witness(rhs),
+                output,
+            ));
+        output
+    }
+
+    fn xor_constant(&mut self, word: usize, constant: u32) -> usize {
+        let output = self.next();
+        self.builder
+            .words
+            .xor_ops
+            .entry(WORD_BITS)
+            .or_default()
+            .push((
+                ConstantOrR1CSWitness::Witness(word),
+                ConstantOrR1CSWitness::Constant(FieldElement::from(constant)),
+                output,
+            ));
+        output
+    }
+
+    fn lookup(&mut self, table: ByteTable, byte: usize) -> usize {
+        let output = self.next();
+        self.builder.byte_table_ops.push((table, byte, output));
+        output
+    }
+
+    fn pack(&mut self, bytes: [usize; 4]) -> usize {
+        let word = self.next();
+        self.constrain_bytes(word, bytes);
+        word
+    }
+
+    fn unpack(&mut self, word: usize) -> [usize; 4] {
+        let bytes = std::array::from_fn(|_| self.next());
+        self.constrain_bytes(word, bytes);
+        bytes
+    }
+}
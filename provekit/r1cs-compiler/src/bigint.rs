@@ -0,0 +1,163 @@
+use {
+    crate::non_native::{operand, NonNativeBuilder},
+    anyhow::{ensure, Result},
+    provekit_common::witness::{
+        non_native::{
+            Limbs, NonNativeOperand::Result as Unknown, NonNativeTerm, LIMB_BITS, NUM_LIMBS,
+            NUM_QUOTIENT_LIMBS,
+        },
+        ConstantOrR1CSWitness,
+    },
+    ruint::aliases::U256,
+};
+
+/// Number of bits of the least modulus for which the operands of arithmetic
+/// need not be reduced: the offset quotient of a product of two 256-bit values
+/// must fit in its limbs.
+const MIN_UNREDUCED_MODULUS_BITS: usize = 2 * 256 + 2 - NUM_QUOTIENT_LIMBS * LIMB_BITS as usize;
+
+/// The limbs of one
+const ONE: [u64; NUM_LIMBS] = [1, 0, 0, 0];
+
+/// A value of the ACIR BigInt opcodes, which is an integer of at most 256 bits
+/// with a modulus. Values created from bytes may not be reduced, while the
+/// results of arithmetic are.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BigInt {
+    pub limbs:   Limbs,
+    pub modulus: U256,
+    /// Whether the value is known to be less than the modulus
+    pub reduced: bool,
+}
+
+/// An arithmetic operation on [BigInt]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BigIntOp {
+    Add,
+    Sub,
+    Mul,
+    /// Multiplication by the inverse of the right-hand side, which must be
+    /// invertible.
+    Div,
+}
+
+/// Adds the witnesses and constraints for the `BigIntFromLeBytes` opcode: the
+/// value with the given little-endian bytes, with the given little-endian
+/// modulus.
+pub(crate) fn add_bigint_from_le_bytes(
+    builder: &mut NonNativeBuilder,
+    bytes: &[ConstantOrR1CSWitness],
+    modulus: &[u8],
+) -> Result<BigInt> {
+    ensure!(
+        bytes.len() <= 32 && modulus.len() <= 32,
+        "BigInt values and moduli must have at most 32 bytes"
+    );
+    let modulus = U256::from_le_slice(modulus);
+    ensure!(
+        modulus > U256::from(1u64),
+        "BigInt moduli must be at least 2"
+    );
+    Ok(BigInt {
+        limbs: builder.pack_le_bytes(bytes),
+        modulus,
+        reduced: false,
+    })
+}
+
+/// Adds the witnesses and constraints for the `BigIntAdd`, `BigIntSub`,
+/// `BigIntMul` and `BigIntDiv` opcodes, whose result is reduced modulo the
+/// modulus of the operands.
+///
+/// The result is the solution of an identity modulo the modulus, such as
+/// `lhs * rhs - result = 0` for a product, and is shown to be less than the
+/// modulus. The negative terms of each identity are less than `2^257`, which
+/// the offset of its quotient covers. For a quotient, the right-hand side is
+/// shown to be invertible by its inverse `rhs * inverse - 1 = 0`, and the
+/// result is `lhs * inverse`. Modulo moduli of fewer than
+/// [MIN_UNREDUCED_MODULUS_BITS] bits, the operands are reduced first, so that
+/// the quotients of products fit in their limbs.
+pub(crate) fn add_bigint_op(
+    builder: &mut NonNativeBuilder,
+    op: BigIntOp,
+    lhs: BigInt,
+    rhs: BigInt,
+) -> Result<BigInt> {
+    ensure!(
+        lhs.modulus == rhs.modulus,
+        "BigInt operands must have the same modulus"
+    );
+    let modulus = lhs.modulus;
+    let [lhs, rhs] = [lhs, rhs].map(|value| {
+        if value.reduced || modulus.bit_len() >= MIN_UNREDUCED_MODULUS_BITS {
+            value.limbs
+        } else {
+            add_reduction(builder, value)
+        }
+    });
+    let (lhs_limbs, rhs_limbs) = (operand(lhs), operand(rhs));
+    let terms = match op {
+        BigIntOp::Add => vec![
+            NonNativeTerm::Linear(1, lhs_limbs),
+            NonNativeTerm::Linear(1, rhs_limbs),
+            NonNativeTerm::Linear(-1, Unknown),
+        ],
+        BigIntOp::Sub => vec![
+            NonNativeTerm::Linear(1, lhs_limbs),
+            NonNativeTerm::Linear(-1, rhs_limbs),
+            NonNativeTerm::Linear(-1, Unknown),
+        ],
+        BigIntOp::Mul => vec![
+            NonNativeTerm::Product(1, lhs_limbs, rhs_limbs),
+            NonNativeTerm::Linear(-1, Unknown),
+        ],
+        BigIntOp::Div => {
+            let inverse = builder
+                .add_identity(Some(modulus), vec![
+                    NonNativeTerm::Product(1, rhs_limbs, Unknown),
+                    NonNativeTerm::Constant(-1, ONE),
+                ])
+                .unwrap();
+            vec![
+                NonNativeTerm::Product(1, lhs_limbs, operand(inverse)),
+                NonNativeTerm::Linear(-1, Unknown),
+            ]
+        }
+    };
+    let limbs = builder.add_identity(Some(modulus), terms).unwrap();
+    builder.assert_less_than(limbs, modulus);
+    Ok(BigInt {
+        limbs,
+        modulus,
+        reduced: true,
+    })
+}
+
+/// Returns the limbs of a value reduced modulo its modulus, the solution of
+/// `value - result = 0`, shown to be less than the modulus.
+fn add_reduction(builder: &mut NonNativeBuilder, value: BigInt) -> Limbs {
+    let limbs = builder
+        .add_identity(Some(value.modulus), vec![
+            NonNativeTerm::Linear(1, operand(value.limbs)),
+            NonNativeTerm::Linear(-1, Unknown),
+        ])
+        .unwrap();
+    builder.assert_less_than(limbs, value.modulus);
+    limbs
+}
+
+/// Adds the constraints for the `BigIntToLeBytes` opcode, constraining the
+/// outputs to be the little-endian bytes of the value.
+pub(crate) fn add_bigint_to_le_bytes(
+    builder: &mut NonNativeBuilder,
+    value: BigInt,
+    outputs: &[usize],
+) -> Result<()> {
+    ensure!(
+        outputs.len() <= 32,
+        "BigInt values have at most 32 bytes, not {}",
+        outputs.len()
+    );
+    builder.assert_le_bytes(value.limbs, outputs);
+    Ok(())
+}
@@ -1,34 +1,92 @@
 use {
     crate::{
         digits::{
-            add_digital_decomposition, decompose_into_digits, DigitalDecompositionWitnessesBuilder,
+            add_digital_decomposition, decompose_into_digits, log_bases_for,
+            DigitalDecompositionWitnessesBuilder,
         },
+        lookup::SharedTable,
         noir_to_r1cs::NoirToR1CSCompiler,
+        words::{BinOpTriples, BinOps},
     },
-    ark_std::One,
     provekit_common::{
-        witness::{ConstantOrR1CSWitness, SumTerm, WitnessBuilder, BINOP_ATOMIC_BITS, NUM_DIGITS},
+        witness::{ConstantOrR1CSWitness, BINOP_ATOMIC_BITS},
         FieldElement,
     },
-    std::ops::Neg,
 };
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BinOp {
     And,
     Xor,
+    /// `!lhs & rhs`, as in the χ step of Keccak
+    AndNot,
 }
 
-/// Add the witnesses and constraints for a [BinOp] (i.e. AND, XOR). Uses a
-/// digital decomposition of the operands and output into [NUM_DIGITS] digits of
-/// [BINOP_ATOMIC_BITS] bits each, followed by a lookup table of size 2x
-/// [BINOP_ATOMIC_BITS].
-pub(crate) fn add_binop(
+/// Add the witnesses and constraints for a [BinOp] (i.e. AND, XOR). Operations
+/// are grouped by the number of bits of their operands, and the operands and
+/// output of each are decomposed into digits of [BINOP_ATOMIC_BITS] bits, the
+/// last of which may be narrower (see [digit_bases]). The digits are then
+/// looked up in a table of the bin op on every pair of [BINOP_ATOMIC_BITS]-bit
+/// digits, which is shared by all the functions of the program.
+pub(crate) fn add_binop(r1cs_compiler: &mut NoirToR1CSCompiler, op: BinOp, ops: BinOps) {
+    // The lookup table is only built for circuits that use the bin op
+    if ops.values().all(Vec::is_empty) {
+        return;
+    }
+    let inputs_and_outputs_atomic = ops
+        .into_iter()
+        .filter(|(_, inputs_and_outputs)| !inputs_and_outputs.is_empty())
+        .flat_map(|(num_bits, inputs_and_outputs)| {
+            decompose_into_atoms(r1cs_compiler, num_bits, inputs_and_outputs)
+        })
+        .collect::<Vec<_>>();
+
+    let table = r1cs_compiler.lookups.add_shared_table(
+        SharedTable::BinOp(op),
+        || {
+            (0..1u32 << BINOP_ATOMIC_BITS).flat_map(move |lh_operand| {
+                (0..1u32 << BINOP_ATOMIC_BITS).map(move |rh_operand| {
+                    let output = match op {
+                        BinOp::And => lh_operand & rh_operand,
+                        BinOp::Xor => lh_operand ^ rh_operand,
+                        BinOp::AndNot => !lh_operand & rh_operand,
+                    };
+                    [lh_operand, rh_operand, output]
+                        .map(FieldElement::from)
+                        .to_vec()
+                })
+            })
+        },
+        vec![1 << BINOP_ATOMIC_BITS, 1, 0],
+    );
+    for (lh, rh, output) in inputs_and_outputs_atomic {
+        r1cs_compiler
+            .lookups
+            .query(table, vec![lh, rh, ConstantOrR1CSWitness::Witness(output)]);
+    }
+}
+
+/// The number of bits of each digit of an operand of `num_bits` bits: as many
+/// digits of [BINOP_ATOMIC_BITS] bits as fit, followed by a narrower one for
+/// the remaining bits, if any.
+pub(crate) fn digit_bases(num_bits: u32) -> Vec<usize> {
+    log_bases_for(num_bits as usize, BINOP_ATOMIC_BITS)
+}
+
+/// Decompose the operands and outputs of bin ops on `num_bits` bits into
+/// [digit_bases], returning the (lhs, rhs, output) triples of the digits in the
+/// same place. Operands of a single digit are their own digit. Digits of
+/// witnesses are constrained by a digital decomposition, while constants are
+/// decomposed natively.
+fn decompose_into_atoms(
     r1cs_compiler: &mut NoirToR1CSCompiler,
-    op: BinOp,
-    inputs_and_outputs: Vec<(ConstantOrR1CSWitness, ConstantOrR1CSWitness, usize)>,
-) {
-    let log_bases = vec![BINOP_ATOMIC_BITS; NUM_DIGITS];
+    num_bits: u32,
+    inputs_and_outputs: BinOpTriples,
+) -> BinOpTriples {
+    let log_bases = digit_bases(num_bits);
+    if log_bases.len() <= 1 {
+        return inputs_and_outputs;
+    }
 
     // Collect all witnesses that require digital decomposition (constants are
     // decomposed separately).
@@ -47,192 +105,80 @@ pub(crate) fn add_binop(
 
     // Match up the digit witnesses and the digits of decompositions of constants to
     // obtain a decomposed version of the inputs and outputs.
-    let mut inputs_and_outputs_atomic = vec![];
     // Track how many witness digital decompositions we've seen so far (for
     // associating the digit witnesses with the original witnesses).
     let mut witness_dd_counter = 0;
-    for (lh, rh, _output) in inputs_and_outputs {
-        let lh_atoms = match lh {
-            ConstantOrR1CSWitness::Witness(_) => {
-                let digit_witnesses = (0..NUM_DIGITS)
-                    .map(|digit_place| {
-                        dd_struct.get_digit_witness_index(digit_place, witness_dd_counter)
-                    })
-                    .collect::<Vec<_>>();
-                witness_dd_counter += 1;
-                digit_witnesses
-                    .iter()
-                    .map(|witness| ConstantOrR1CSWitness::Witness(*witness))
-                    .collect::<Vec<_>>()
-            }
-            ConstantOrR1CSWitness::Constant(value) => {
-                let digits = decompose_into_digits(value, &log_bases);
-                digits
-                    .iter()
-                    .map(|digit| ConstantOrR1CSWitness::Constant(*digit))
-                    .collect::<Vec<_>>()
-            }
-        };
-        let rh_atoms = match rh {
-            ConstantOrR1CSWitness::Witness(_) => {
-                let digit_witnesses = (0..NUM_DIGITS)
-                    .map(|digit_place| {
-                        dd_struct.get_digit_witness_index(digit_place, witness_dd_counter)
-                    })
-                    .collect::<Vec<_>>();
-                witness_dd_counter += 1;
-                digit_witnesses
-                    .iter()
-                    .map(|witness| ConstantOrR1CSWitness::Witness(*witness))
-                    .collect::<Vec<_>>()
-            }
-            ConstantOrR1CSWitness::Constant(value) => {
-                let digits = decompose_into_digits(value, &log_bases);
-                digits
-                    .iter()
-                    .map(|digit| ConstantOrR1CSWitness::Constant(*digit))
-                    .collect::<Vec<_>>()
-            }
-        };
-        let output_atoms = (0..NUM_DIGITS)
-            .map(|digit_place| dd_struct.get_digit_witness_index(digit_place, witness_dd_counter))
+    let digit_witnesses = |witness_dd_counter: &mut usize| {
+        let digits = (0..log_bases.len())
+            .map(|digit_place| dd_struct.get_digit_witness_index(digit_place, *witness_dd_counter))
             .collect::<Vec<_>>();
-        witness_dd_counter += 1;
+        *witness_dd_counter += 1;
+        digits
+    };
 
-        lh_atoms
-            .into_iter()
-            .zip(rh_atoms.into_iter())
-            .zip(output_atoms.into_iter())
-            .for_each(|((lh, rh), output)| {
-                inputs_and_outputs_atomic.push((lh, rh, output));
-            });
+    let mut inputs_and_outputs_atomic = vec![];
+    for (lh, rh, output) in inputs_and_outputs {
+        let [lh_atoms, rh_atoms] = [lh, rh].map(|operand| match operand {
+            ConstantOrR1CSWitness::Witness(_) => digit_witnesses(&mut witness_dd_counter)
+                .into_iter()
+                .map(ConstantOrR1CSWitness::Witness)
+                .collect::<Vec<_>>(),
+            ConstantOrR1CSWitness::Constant(value) => decompose_into_digits(value, &log_bases)
+                .into_iter()
+                .map(ConstantOrR1CSWitness::Constant)
+                .collect::<Vec<_>>(),
+        });
+        let output_atoms = digit_witnesses(&mut witness_dd_counter);
+        inputs_and_outputs_atomic.extend(
+            lh_atoms
+                .into_iter()
+                .zip(rh_atoms)
+                .zip(output_atoms)
+                .map(|((lh, rh), output)| (lh, rh, output)),
+        );
     }
-
-    let multiplicities_wb = WitnessBuilder::MultiplicitiesForBinOp(
-        r1cs_compiler.num_witnesses(),
-        inputs_and_outputs_atomic
-            .iter()
-            .map(|(lh_operand, rh_operand, _output)| (lh_operand.clone(), rh_operand.clone()))
-            .collect(),
-    );
-    let multiplicities_first_witness = r1cs_compiler.add_witness_builder(multiplicities_wb);
-
-    // Add two verifier challenges for the lookup
-    let sz_challenge =
-        r1cs_compiler.add_witness_builder(WitnessBuilder::Challenge(r1cs_compiler.num_witnesses()));
-    let rs_challenge =
-        r1cs_compiler.add_witness_builder(WitnessBuilder::Challenge(r1cs_compiler.num_witnesses()));
-    let rs_challenge_sqrd = r1cs_compiler.add_product(rs_challenge, rs_challenge);
-
-    // Calculate the sum, over all invocations of the bin op, of 1 / denominator
-    let summands_for_bin_op = inputs_and_outputs_atomic
-        .into_iter()
-        .map(|(lh, rh, output)| {
-            add_lookup_summand(
-                r1cs_compiler,
-                sz_challenge,
-                rs_challenge,
-                rs_challenge_sqrd,
-                lh,
-                rh,
-                ConstantOrR1CSWitness::Witness(output),
-            )
-        })
-        .map(|coeff| SumTerm(None, coeff))
-        .collect();
-    let sum_for_bin_op = r1cs_compiler.add_sum(summands_for_bin_op);
-
-    // Calculate the sum over all table elements of multiplicity / denominator
-    let summands_for_table = (0..1 << BINOP_ATOMIC_BITS)
-        .flat_map(|lh_operand: u32| {
-            (0..1 << BINOP_ATOMIC_BITS).map(move |rh_operand: u32| {
-                let output = match op {
-                    BinOp::And => lh_operand & rh_operand,
-                    BinOp::Xor => lh_operand ^ rh_operand,
-                };
-                (lh_operand, rh_operand, output)
-            })
-        })
-        .map(|(lh_operand, rh_operand, output)| {
-            let denominator = add_lookup_summand(
-                r1cs_compiler,
-                sz_challenge,
-                rs_challenge,
-                rs_challenge_sqrd,
-                ConstantOrR1CSWitness::Constant(FieldElement::from(lh_operand)),
-                ConstantOrR1CSWitness::Constant(FieldElement::from(rh_operand)),
-                ConstantOrR1CSWitness::Constant(FieldElement::from(output)),
-            );
-            let multiplicity_witness_idx = multiplicities_first_witness
-                + (lh_operand << BINOP_ATOMIC_BITS) as usize
-                + rh_operand as usize;
-            r1cs_compiler.add_product(multiplicity_witness_idx, denominator)
-        })
-        .map(|coeff| SumTerm(None, coeff))
-        .collect();
-    let sum_for_table = r1cs_compiler.add_sum(summands_for_table);
-
-    // Check that these two sums are equal.
-    r1cs_compiler.r1cs.add_constraint(
-        &[(FieldElement::one(), r1cs_compiler.witness_one())],
-        &[(FieldElement::one(), sum_for_bin_op)],
-        &[(FieldElement::one(), sum_for_table)],
-    );
+    inputs_and_outputs_atomic
 }
 
-// Add and return a new witness `denominator` and constrain it to represent
-// (assuming `output` is a witness):
-// `w[sz_challenge] - (w[lh_operand] + w[rs_challenge] * w[rh_operand] +
-// w[rs_challenge_sqrd] * w[output])` where `w` is the witness vector. If
-// `output` is a constant, then the `rs_challenge_sqrd` is instead scaled by
-// that constant. Finally, adds a new witness for the inverse of `denominator`,
-// constrains it to be such, and returns its index.
-fn add_lookup_summand(
-    r1cs_compiler: &mut NoirToR1CSCompiler,
-    sz_challenge: usize,
-    rs_challenge: usize,
-    rs_challenge_sqrd: usize,
-    lh_operand: ConstantOrR1CSWitness,
-    rh_operand: ConstantOrR1CSWitness,
-    output: ConstantOrR1CSWitness,
-) -> usize {
-    let wb = WitnessBuilder::BinOpLookupDenominator(
-        r1cs_compiler.num_witnesses(),
-        sz_challenge,
-        rs_challenge,
-        rs_challenge_sqrd,
-        lh_operand.clone(),
-        rh_operand.clone(),
-        output.clone(),
-    );
-    let denominator = r1cs_compiler.add_witness_builder(wb);
-    // Add an intermediate witness if the output is a witness (otherwise can just
-    // scale)
-    let rs_challenge_sqrd_summand = match output {
-        ConstantOrR1CSWitness::Constant(value) => (FieldElement::from(value), rs_challenge_sqrd),
-        ConstantOrR1CSWitness::Witness(witness) => (
-            FieldElement::one(),
-            r1cs_compiler.add_product(rs_challenge_sqrd, witness),
-        ),
+#[cfg(test)]
+mod tests {
+    use {
+        super::digit_bases,
+        crate::digits::decompose_into_digits,
+        ark_ff::PrimeField,
+        provekit_common::{witness::BINOP_ATOMIC_BITS, FieldElement},
     };
-    r1cs_compiler.r1cs.add_constraint(
-        &[(FieldElement::one().neg(), rs_challenge)],
-        &[rh_operand.to_tuple()],
-        &[
-            (FieldElement::one(), denominator),
-            (FieldElement::one().neg(), sz_challenge),
-            (lh_operand.to_tuple()),
-            rs_challenge_sqrd_summand,
-        ],
-    );
-    let inverse = r1cs_compiler.add_witness_builder(WitnessBuilder::Inverse(
-        r1cs_compiler.num_witnesses(),
-        denominator,
-    ));
-    r1cs_compiler.r1cs.add_constraint(
-        &[(FieldElement::one(), denominator)],
-        &[(FieldElement::one(), inverse)],
-        &[(FieldElement::one(), r1cs_compiler.witness_one())],
-    );
-    inverse
+
+    #[test]
+    fn test_digitwise_binops() {
+        let (lhs, rhs) = (
+            0xfedc_ba98_7654_3210_0123_4567_89ab_cdef_u128,
+            0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c834_u128,
+        );
+        for num_bits in 1..=128 {
+            let log_bases = digit_bases(num_bits);
+            assert_eq!(log_bases.iter().sum::<usize>(), num_bits as usize);
+            assert!(log_bases.iter().all(|&bits| bits <= BINOP_ATOMIC_BITS));
+
+            let mask = u128::MAX >> (128 - num_bits);
+            let digits = |value: u128| {
+                decompose_into_digits(FieldElement::from(value & mask), &log_bases)
+                    .into_iter()
+                    .map(|digit| digit.into_bigint().0[0] as u128)
+                    .collect::<Vec<_>>()
+            };
+            let recompose = |digits: Vec<u128>| {
+                digits
+                    .into_iter()
+                    .zip(&log_bases)
+                    .rev()
+                    .fold(0, |value, (digit, &bits)| (value << bits) | digit)
+            };
+            let (lhs_digits, rhs_digits) = (digits(lhs), digits(rhs));
+            let and = lhs_digits.iter().zip(&rhs_digits).map(|(l, r)| l & r);
+            let xor = lhs_digits.iter().zip(&rhs_digits).map(|(l, r)| l ^ r);
+            assert_eq!(recompose(and.collect()), lhs & rhs & mask);
+            assert_eq!(recompose(xor.collect()), (lhs ^ rhs) & mask);
+        }
+    }
 }
@@ -0,0 +1,233 @@
+use {
+    crate::words::{Shift::RotateRight, WordBuilder},
+    provekit_common::witness::ConstantOrR1CSWitness,
+};
+
+/// The initialization vector shared by BLAKE2s and BLAKE3
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The message word schedule of each BLAKE2s round
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The permutation of the message words between BLAKE3 rounds
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// Number of bytes in a block
+const BLOCK_LEN: usize = 64;
+
+/// Number of bytes in a BLAKE3 chunk
+const CHUNK_LEN: usize = 1024;
+
+// BLAKE3 domain separation flags
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+/// Add the witnesses and constraints for the BLAKE2s hash of the input bytes,
+/// with a 32-byte digest and no key. Returns the witnesses of the digest
+/// words, in little-endian byte order.
+pub(crate) fn add_blake2s(words: &mut WordBuilder, input: &[ConstantOrR1CSWitness]) -> [usize; 8] {
+    let mut h = IV;
+    h[0] ^= 0x0101_0000 ^ 32;
+    let mut h = h.map(|word| words.constant(word));
+
+    let num_blocks = input.len().div_ceil(BLOCK_LEN).max(1);
+    for (i, block) in blocks(input).enumerate() {
+        let last = i == num_blocks - 1;
+        // The number of bytes hashed so far, including this block
+        let counter = if last {
+            input.len()
+        } else {
+            (i + 1) * BLOCK_LEN
+        } as u64;
+        let flags = if last { u32::MAX } else { 0 };
+        let tail = [
+            IV[0],
+            IV[1],
+            IV[2],
+            IV[3],
+            IV[4] ^ counter as u32,
+            IV[5] ^ (counter >> 32) as u32,
+            IV[6] ^ flags,
+            IV[7],
+        ];
+
+        let message = message_words(words, block);
+        let mut state: [usize; 16] = std::array::from_fn(|i| {
+            if i < 8 {
+                h[i]
+            } else {
+                words.constant(tail[i - 8])
+            }
+        });
+        for sigma in SIGMA {
+            add_round(words, &mut state, &sigma.map(|i| message[i]));
+        }
+        h = std::array::from_fn(|i| {
+            let word = words.xor(h[i], state[i]);
+            words.xor(word, state[i + 8])
+        });
+    }
+    h
+}
+
+/// Add the witnesses and constraints for the BLAKE3 hash of the input bytes,
+/// with a 32-byte digest and no key. Returns the witnesses of the digest
+/// words, in little-endian byte order.
+pub(crate) fn add_blake3(words: &mut WordBuilder, input: &[ConstantOrR1CSWitness]) -> [usize; 8] {
+    let key = IV.map(|word| words.constant(word));
+    add_blake3_subtree(words, &key, input, 0, true)
+}
+
+/// The chaining value of a subtree of chunks, the first of which has the given
+/// index. A subtree of several chunks puts the largest power of two of its
+/// chunks that leaves at least one chunk on the right in its left subtree.
+fn add_blake3_subtree(
+    words: &mut WordBuilder,
+    key: &[usize; 8],
+    input: &[ConstantOrR1CSWitness],
+    first_chunk: u64,
+    root: bool,
+) -> [usize; 8] {
+    let num_chunks = input.len().div_ceil(CHUNK_LEN).max(1);
+    if num_chunks == 1 {
+        return add_blake3_chunk(words, key, input, first_chunk, root);
+    }
+    let left_chunks = 1usize << (num_chunks - 1).ilog2();
+    let (left, right) = input.split_at(left_chunks * CHUNK_LEN);
+    let left = add_blake3_subtree(words, key, left, first_chunk, false);
+    let right = add_blake3_subtree(words, key, right, first_chunk + left_chunks as u64, false);
+    let block: [usize; 16] = std::array::from_fn(|i| if i < 8 { left[i] } else { right[i - 8] });
+    let flags = PARENT | if root { ROOT } else { 0 };
+    add_blake3_compression(words, key, &block, 0, BLOCK_LEN as u32, flags)
+}
+
+/// The chaining value of a chunk of at most [`CHUNK_LEN`] bytes.
+fn add_blake3_chunk(
+    words: &mut WordBuilder,
+    key: &[usize; 8],
+    chunk: &[ConstantOrR1CSWitness],
+    chunk_index: u64,
+    root: bool,
+) -> [usize; 8] {
+    let num_blocks = chunk.len().div_ceil(BLOCK_LEN).max(1);
+    let mut chaining_value = *key;
+    for (i, block) in blocks(chunk).enumerate() {
+        let mut flags = if i == 0 { CHUNK_START } else { 0 };
+        if i == num_blocks - 1 {
+            flags |= CHUNK_END | if root { ROOT } else { 0 };
+        }
+        let message = message_words(words, block);
+        chaining_value = add_blake3_compression(
+            words,
+            &chaining_value,
+            &message,
+            chunk_index,
+            block.len() as u32,
+            flags,
+        );
+    }
+    chaining_value
+}
+
+/// The BLAKE3 compression function, truncated to the chaining value.
+fn add_blake3_compression(
+    words: &mut WordBuilder,
+    chaining_value: &[usize; 8],
+    message: &[usize; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [usize; 8] {
+    let tail = [
+        IV[0],
+        IV[1],
+        IV[2],
+        IV[3],
+        counter as u32,
+        (counter >> 32) as u32,
+        block_len,
+        flags,
+    ];
+    let mut state: [usize; 16] = std::array::from_fn(|i| {
+        if i < 8 {
+            chaining_value[i]
+        } else {
+            words.constant(tail[i - 8])
+        }
+    });
+    let mut message = *message;
+    for _ in 0..7 {
+        add_round(words, &mut state, &message);
+        message = MSG_PERMUTATION.map(|i| message[i]);
+    }
+    std::array::from_fn(|i| words.xor(state[i], state[i + 8]))
+}
+
+/// The blocks of the input, with a single empty block for an empty input.
+fn blocks(input: &[ConstantOrR1CSWitness]) -> impl Iterator<Item = &[ConstantOrR1CSWitness]> {
+    let empty_block: Option<&[ConstantOrR1CSWitness]> = input.is_empty().then_some(&[][..]);
+    input.chunks(BLOCK_LEN).chain(empty_block)
+}
+
+/// The little-endian words of a block, padded with zero bytes.
+fn message_words(words: &mut WordBuilder, block: &[ConstantOrR1CSWitness]) -> [usize; 16] {
+    std::array::from_fn(|i| {
+        let bytes = block
+            .iter()
+            .skip(4 * i)
+            .take(4)
+            .cloned()
+            .collect::<Vec<_>>();
+        words.pack_le_bytes(&bytes)
+    })
+}
+
+/// A round of the BLAKE2s and BLAKE3 compression functions: the mixing
+/// function applied to the columns and then to the diagonals of the state.
+fn add_round(words: &mut WordBuilder, state: &mut [usize; 16], message: &[usize; 16]) {
+    for (i, [a, b, c, d]) in [
+        [0, 4, 8, 12],
+        [1, 5, 9, 13],
+        [2, 6, 10, 14],
+        [3, 7, 11, 15],
+        [0, 5, 10, 15],
+        [1, 6, 11, 12],
+        [2, 7, 8, 13],
+        [3, 4, 9, 14],
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let (x, y) = (message[2 * i], message[2 * i + 1]);
+        state[a] = words.add(&[state[a], state[b], x], 0);
+        state[d] = rotate_right(words, state[d], state[a], 16);
+        state[c] = words.add(&[state[c], state[d]], 0);
+        state[b] = rotate_right(words, state[b], state[c], 12);
+        state[a] = words.add(&[state[a], state[b], y], 0);
+        state[d] = rotate_right(words, state[d], state[a], 8);
+        state[c] = words.add(&[state[c], state[d]], 0);
+        state[b] = rotate_right(words, state[b], state[c], 7);
+    }
+}
+
+/// `(lhs ^ rhs) >>> amount`
+fn rotate_right(words: &mut WordBuilder, lhs: usize, rhs: usize, amount: u32) -> usize {
+    let word = words.xor(lhs, rhs);
+    let [rotated] = words.shifts(word, [RotateRight(amount)]);
+    rotated
+}
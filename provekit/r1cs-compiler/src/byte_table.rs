@@ -0,0 +1,39 @@
+use {
+    crate::{lookup::SharedTable, noir_to_r1cs::NoirToR1CSCompiler},
+    provekit_common::{
+        witness::{byte_table::ByteTable, ConstantOrR1CSWitness},
+        FieldElement,
+    },
+};
+
+/// (table, input, output) triples of byte table lookups, constrained once the
+/// whole circuit has been compiled.
+pub(crate) type ByteTableLookups = Vec<(ByteTable, usize, usize)>;
+
+/// Add the lookups of bytes in the [ByteTable]s, each of which is a table of
+/// its `(input, output)` entries shared by all the functions of the program.
+/// The inputs are not range checked: a lookup of an input that is not a byte
+/// fails.
+pub(crate) fn add_byte_table_lookups(
+    r1cs_compiler: &mut NoirToR1CSCompiler,
+    lookups: ByteTableLookups,
+) {
+    for (table, input, output) in lookups {
+        let table_id = r1cs_compiler.lookups.add_shared_table(
+            SharedTable::Byte(table),
+            || {
+                (0..=u8::MAX).map(|input| {
+                    vec![
+                        FieldElement::from(input),
+                        FieldElement::from(table.apply(input)),
+                    ]
+                })
+            },
+            vec![1, 0],
+        );
+        r1cs_compiler.lookups.query(table_id, vec![
+            ConstantOrR1CSWitness::Witness(input),
+            ConstantOrR1CSWitness::Witness(output),
+        ]);
+    }
+}
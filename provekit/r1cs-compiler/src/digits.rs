@@ -92,6 +92,17 @@ pub(crate) fn add_digital_decomposition(
     dd_struct
 }
 
+/// The log bases of the decomposition of a value of `num_bits` bits into as
+/// many digits of `digit_bits` bits as fit, followed by a narrower digit for
+/// the remaining bits, if any.
+pub(crate) fn log_bases_for(num_bits: usize, digit_bits: usize) -> Vec<usize> {
+    let mut log_bases = vec![digit_bits; num_bits / digit_bits];
+    if num_bits % digit_bits != 0 {
+        log_bases.push(num_bits % digit_bits);
+    }
+    log_bases
+}
+
 /// Compute a mixed-base decomposition of a field element into its digits, using
 /// the given log bases. Decomposition is little-endian.
 /// Panics if the value provided can not be represented in the given bases.
@@ -0,0 +1,459 @@
+use {
+    crate::{
+        digits::{add_digital_decomposition, DigitalDecompositionWitnessesBuilder},
+        memory::{add_rom_checking, MemoryBlock, MemoryOperation},
+        non_native::{operand, NonNativeBuilder},
+    },
+    ark_std::One,
+    provekit_common::{
+        witness::{
+            non_native::{
+                Limbs,
+                NonNativeOperand::Result as Unknown,
+                NonNativeTerm::{Constant, Linear, Product},
+                LIMB_BITS, NUM_LIMBS,
+            },
+            ConstantOrR1CSWitness, WitnessBuilder,
+        },
+        FieldElement,
+    },
+    ruint::{aliases::U256, uint},
+};
+
+/// Number of bits of a window of a scalar
+const WINDOW_BITS: usize = 4;
+
+/// Number of windows of a 256-bit scalar
+const NUM_WINDOWS: usize = 256 / WINDOW_BITS;
+
+/// The limbs of one
+const ONE: [u64; NUM_LIMBS] = [1, 0, 0, 0];
+
+/// A short Weierstrass curve `y^2 = x^3 + a * x + b` of prime order, over a
+/// 256-bit prime field.
+pub(crate) struct Curve {
+    /// The modulus of the base field
+    p:         U256,
+    /// The order of the group
+    n:         U256,
+    a:         i64,
+    b:         U256,
+    generator: Point,
+    /// Whether signatures must have `s <= n / 2`
+    low_s:     bool,
+}
+
+pub(crate) const SECP256K1: Curve = Curve {
+    p:         uint!(0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f_U256),
+    n:         uint!(0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141_U256),
+    a:         0,
+    b:         uint!(7_U256),
+    generator: Point(
+        uint!(0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798_U256),
+        uint!(0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8_U256),
+    ),
+    low_s:     true,
+};
+
+pub(crate) const SECP256R1: Curve = Curve {
+    p:         uint!(0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff_U256),
+    n:         uint!(0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551_U256),
+    a:         -3,
+    b:         uint!(0x5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b_U256),
+    generator: Point(
+        uint!(0x6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296_U256),
+        uint!(0x4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5_U256),
+    ),
+    low_s:     false,
+};
+
+/// A point of a curve in affine coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Point(U256, U256);
+
+/// The limbs of the affine coordinates of a point
+type PointLimbs = [Limbs; 2];
+
+/// Arithmetic on the points of a curve, to compute the constant tables and
+/// offsets of the circuit. The point at infinity is never reached.
+impl Curve {
+    fn sub(&self, lhs: U256, rhs: U256) -> U256 {
+        lhs.add_mod(self.p - rhs, self.p)
+    }
+
+    /// `a` modulo `p`
+    fn a(&self) -> U256 {
+        if self.a < 0 {
+            self.p - U256::from(self.a.unsigned_abs())
+        } else {
+            U256::from(self.a as u64)
+        }
+    }
+
+    fn neg(&self, point: Point) -> Point {
+        Point(point.0, self.sub(U256::ZERO, point.1))
+    }
+
+    fn add(&self, lhs: Point, rhs: Point) -> Point {
+        if lhs == rhs {
+            return self.double(lhs);
+        }
+        assert_ne!(lhs.0, rhs.0, "Sum of points is at infinity");
+        let lambda = self
+            .sub(rhs.1, lhs.1)
+            .mul_mod(self.sub(rhs.0, lhs.0).inv_mod(self.p).unwrap(), self.p);
+        self.chord(lambda, lhs, rhs.0)
+    }
+
+    fn double(&self, point: Point) -> Point {
+        let numerator = U256::from(3u64)
+            .mul_mod(point.0.mul_mod(point.0, self.p), self.p)
+            .add_mod(self.a(), self.p);
+        let lambda = numerator.mul_mod(
+            point.1.add_mod(point.1, self.p).inv_mod(self.p).unwrap(),
+            self.p,
+        );
+        self.chord(lambda, point, point.0)
+    }
+
+    /// The sum of a point and another with the given x coordinate, on the line
+    /// through them of the given slope.
+    fn chord(&self, lambda: U256, point: Point, x: U256) -> Point {
+        let x3 = self.sub(self.sub(lambda.mul_mod(lambda, self.p), point.0), x);
+        let y3 = self.sub(lambda.mul_mod(self.sub(point.0, x3), self.p), point.1);
+        Point(x3, y3)
+    }
+
+    /// `scalar * point`, for a nonzero scalar
+    fn mul(&self, point: Point, scalar: U256) -> Point {
+        let top = scalar.bit_len() - 1;
+        (0..top).rev().fold(point, |result, i| {
+            let result = self.double(result);
+            if scalar.bit(i) {
+                self.add(result, point)
+            } else {
+                result
+            }
+        })
+    }
+
+    /// The point with the least x coordinate of at least `x`. Its discrete
+    /// logarithm is unknown, as the points are not derived from the generator.
+    /// The base fields have `p = 3 (mod 4)`, so square roots are powers.
+    fn find_point(&self, mut x: U256) -> Point {
+        let exponent = (self.p + U256::from(1u64)) >> 2;
+        loop {
+            let rhs = x
+                .mul_mod(x, self.p)
+                .add_mod(self.a(), self.p)
+                .mul_mod(x, self.p)
+                .add_mod(self.b, self.p);
+            let y = rhs.pow_mod(exponent, self.p);
+            if y.mul_mod(y, self.p) == rhs {
+                return Point(x, y);
+            }
+            x += U256::from(1u64);
+        }
+    }
+}
+
+/// Add the witnesses and constraints for the verification of an ECDSA
+/// signature `(r, s)` of a hashed message `z` by the public key `Q`, all
+/// given as big-endian bytes, and constrain the output to be true. Only valid
+/// signatures can be proven, so the circuit must assert the output to be true.
+///
+/// The public key must be on the curve and its coordinates less than `p`,
+/// and the signature must have `0 < r, s < n`. With `w = s^-1`, `u1 = z * w`
+/// and `u2 = r * w` (mod n), the point `R = u1 * G + u2 * Q` must have an x
+/// coordinate equal to `r` modulo `n`.
+pub(crate) fn add_ecdsa_verification(
+    builder: &mut NonNativeBuilder,
+    curve: &Curve,
+    public_key_x: &[ConstantOrR1CSWitness],
+    public_key_y: &[ConstantOrR1CSWitness],
+    signature: &[ConstantOrR1CSWitness],
+    hashed_message: &[ConstantOrR1CSWitness],
+    output: usize,
+) {
+    let (p, n) = (Some(curve.p), Some(curve.n));
+    let x = builder.pack_be_bytes(public_key_x);
+    let y = builder.pack_be_bytes(public_key_y);
+    let r = builder.pack_be_bytes(&signature[..32]);
+    let s = builder.pack_be_bytes(&signature[32..]);
+    let z = builder.pack_be_bytes(hashed_message);
+
+    builder.assert_less_than(x, curve.p);
+    builder.assert_less_than(y, curve.p);
+    builder.assert_less_than(r, curve.n);
+    let s_bound = if curve.low_s {
+        (curve.n >> 1) + U256::from(1u64)
+    } else {
+        curve.n
+    };
+    builder.assert_less_than(s, s_bound);
+
+    // The public key is on the curve
+    let xx = builder
+        .add_identity(p, vec![
+            Product(1, operand(x), operand(x)),
+            Linear(-1, Unknown),
+        ])
+        .unwrap();
+    let mut on_curve = vec![
+        Product(1, operand(y), operand(y)),
+        Product(-1, operand(xx), operand(x)),
+        Constant(-1, *curve.b.as_limbs()),
+    ];
+    if curve.a != 0 {
+        on_curve.push(Linear(-curve.a, operand(x)));
+    }
+    builder.add_identity(p, on_curve);
+
+    // r and s are invertible, so nonzero
+    builder.add_identity(n, vec![Product(1, operand(r), Unknown), Constant(-1, ONE)]);
+    let w = builder
+        .add_identity(n, vec![Product(1, operand(s), Unknown), Constant(-1, ONE)])
+        .unwrap();
+    let u1 = builder
+        .add_identity(n, vec![
+            Product(1, operand(z), operand(w)),
+            Linear(-1, Unknown),
+        ])
+        .unwrap();
+    let u2 = builder
+        .add_identity(n, vec![
+            Product(1, operand(r), operand(w)),
+            Linear(-1, Unknown),
+        ])
+        .unwrap();
+
+    let [point_x, _] = add_double_scalar_multiplication(builder, curve, u1, u2, [x, y]);
+    builder.assert_less_than(point_x, curve.p);
+    builder.add_identity(n, vec![Linear(1, operand(point_x)), Linear(-1, operand(r))]);
+
+    let witness_one = builder.compiler.witness_one();
+    builder.compiler.r1cs.add_constraint(
+        &[(FieldElement::one(), output)],
+        &[(FieldElement::one(), witness_one)],
+        &[(FieldElement::one(), witness_one)],
+    );
+}
+
+/// `u1 * G + u2 * Q`, by a joint windowed scalar multiplication.
+///
+/// The points of a window are read from tables of `H + i * G` and `H + i * Q`
+/// for an offset point `H`, so that no table entry is at infinity, using a
+/// read-only memory per limb of a coordinate. The accumulator starts from
+/// another offset point `O`. After all windows, it holds
+/// `2^256 * O + (2 + 2 * 16 + ... + 2 * 16^63) * H + u1 * G + u2 * Q`, from
+/// which the constant offsets are subtracted. As the offset points have
+/// unknown discrete logarithms, the additions are between distinct points,
+/// except with negligible probability.
+fn add_double_scalar_multiplication(
+    builder: &mut NonNativeBuilder,
+    curve: &Curve,
+    u1: Limbs,
+    u2: Limbs,
+    public_key: PointLimbs,
+) -> PointLimbs {
+    let accumulator_offset = curve.find_point(U256::from(1u64));
+    let table_offset = curve.find_point(accumulator_offset.0 + U256::from(1u64));
+
+    let mut generator_table = vec![table_offset];
+    for _ in 1..1 << WINDOW_BITS {
+        generator_table.push(curve.add(*generator_table.last().unwrap(), curve.generator));
+    }
+    let generator_table = generator_table
+        .into_iter()
+        .map(|point| constant_point(builder, point))
+        .collect::<Vec<_>>();
+    let mut public_key_table = vec![constant_point(builder, table_offset)];
+    for _ in 1..1 << WINDOW_BITS {
+        let point = add_points(
+            builder,
+            curve,
+            *public_key_table.last().unwrap(),
+            public_key,
+        );
+        public_key_table.push(point);
+    }
+
+    // The windows of u1 and u2, most significant first
+    let windows_per_limb = LIMB_BITS as usize / WINDOW_BITS;
+    let dd_struct = add_digital_decomposition(
+        builder.compiler,
+        vec![WINDOW_BITS; windows_per_limb],
+        u1.into_iter().chain(u2).collect(),
+    );
+    let windows = |scalar: usize| {
+        (0..NUM_WINDOWS)
+            .rev()
+            .map(|i| {
+                dd_struct.get_digit_witness_index(
+                    i % windows_per_limb,
+                    scalar * NUM_LIMBS + i / windows_per_limb,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let generator_points = add_table_reads(builder, &generator_table, &windows(0));
+    let public_key_points = add_table_reads(builder, &public_key_table, &windows(1));
+
+    let mut accumulator = constant_point(
+        builder,
+        curve.mul(accumulator_offset, U256::from(1u64 << WINDOW_BITS)),
+    );
+    for (i, (generator_point, public_key_point)) in generator_points
+        .into_iter()
+        .zip(public_key_points)
+        .enumerate()
+    {
+        if i > 0 {
+            for _ in 0..WINDOW_BITS {
+                accumulator = double_point(builder, curve, accumulator);
+            }
+        }
+        accumulator = add_points(builder, curve, accumulator, generator_point);
+        accumulator = add_points(builder, curve, accumulator, public_key_point);
+    }
+
+    let accumulator_multiple = (0..256).fold(accumulator_offset, |point, _| curve.double(point));
+    // 2 * (16^64 - 1) / 15
+    let table_multiple = U256::MAX / U256::from((1u64 << WINDOW_BITS) - 1) * U256::from(2u64);
+    let offset = curve.add(
+        accumulator_multiple,
+        curve.mul(table_offset, table_multiple),
+    );
+    let offset = constant_point(builder, curve.neg(offset));
+    add_points(builder, curve, accumulator, offset)
+}
+
+fn constant_point(builder: &mut NonNativeBuilder, point: Point) -> PointLimbs {
+    [builder.constant(point.0), builder.constant(point.1)]
+}
+
+/// Reads the points of a table at the given addresses, checking the reads
+/// with a read-only memory per limb of a coordinate.
+fn add_table_reads(
+    builder: &mut NonNativeBuilder,
+    table: &[PointLimbs],
+    addresses: &[usize],
+) -> Vec<PointLimbs> {
+    let mut points = vec![[[0; NUM_LIMBS]; 2]; addresses.len()];
+    for coordinate in 0..2 {
+        for limb in 0..NUM_LIMBS {
+            let mut block = MemoryBlock::new();
+            block.initial_value_witnesses =
+                table.iter().map(|point| point[coordinate][limb]).collect();
+            for (point, address) in points.iter_mut().zip(addresses) {
+                let value = builder
+                    .compiler
+                    .add_witness_builder(WitnessBuilder::MemoryRead(
+                        builder.compiler.num_witnesses(),
+                        *address,
+                        block.initial_value_witnesses.clone(),
+                    ));
+                block
+                    .operations
+                    .push(MemoryOperation::Load(*address, value));
+                point[coordinate][limb] = value;
+            }
+            add_rom_checking(builder.compiler, &block);
+        }
+    }
+    points
+}
+
+/// The sum of two points with distinct x coordinates. The difference of the
+/// x coordinates is shown to be invertible, as the slope is otherwise
+/// unconstrained.
+fn add_points(
+    builder: &mut NonNativeBuilder,
+    curve: &Curve,
+    [x1, y1]: PointLimbs,
+    [x2, y2]: PointLimbs,
+) -> PointLimbs {
+    let p = Some(curve.p);
+    builder.add_identity(p, vec![
+        Product(1, operand(x2), Unknown),
+        Product(-1, operand(x1), Unknown),
+        Constant(-1, ONE),
+    ]);
+    let lambda = builder
+        .add_identity(p, vec![
+            Product(1, Unknown, operand(x2)),
+            Product(-1, Unknown, operand(x1)),
+            Linear(-1, operand(y2)),
+            Linear(1, operand(y1)),
+        ])
+        .unwrap();
+    add_chord(builder, curve, lambda, [x1, y1], x2)
+}
+
+/// The double of a point. Points of a curve of odd order have a nonzero y
+/// coordinate, so the slope is determined.
+fn double_point(builder: &mut NonNativeBuilder, curve: &Curve, [x, y]: PointLimbs) -> PointLimbs {
+    let lambda = builder
+        .add_identity(Some(curve.p), vec![
+            Product(2, Unknown, operand(y)),
+            Product(-3, operand(x), operand(x)),
+            Constant(-curve.a, ONE),
+        ])
+        .unwrap();
+    add_chord(builder, curve, lambda, [x, y], x)
+}
+
+/// The sum of a point and another with x coordinate `x2`, on the line through
+/// them with slope `lambda`.
+fn add_chord(
+    builder: &mut NonNativeBuilder,
+    curve: &Curve,
+    lambda: Limbs,
+    [x1, y1]: PointLimbs,
+    x2: Limbs,
+) -> PointLimbs {
+    let p = Some(curve.p);
+    let x3 = builder
+        .add_identity(p, vec![
+            Product(1, operand(lambda), operand(lambda)),
+            Linear(-1, operand(x1)),
+            Linear(-1, operand(x2)),
+            Linear(-1, Unknown),
+        ])
+        .unwrap();
+    let y3 = builder
+        .add_identity(p, vec![
+            Product(1, operand(lambda), operand(x1)),
+            Product(-1, operand(lambda), operand(x3)),
+            Linear(-1, operand(y1)),
+            Linear(-1, Unknown),
+        ])
+        .unwrap();
+    [x3, y3]
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Point, SECP256K1, SECP256R1},
+        ruint::aliases::U256,
+    };
+
+    #[test]
+    fn test_generators() {
+        for curve in [SECP256K1, SECP256R1] {
+            let Point(x, y) = curve.generator;
+            let point = curve.find_point(x);
+            assert!(point == curve.generator || point == curve.neg(curve.generator));
+            assert_eq!(
+                curve.mul(curve.generator, curve.n - U256::from(1u64)),
+                curve.neg(curve.generator)
+            );
+            assert_eq!(
+                curve.add(curve.generator, curve.generator),
+                curve.mul(curve.generator, U256::from(2u64))
+            );
+            assert!(y < curve.p);
+        }
+    }
+}
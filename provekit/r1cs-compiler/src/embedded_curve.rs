@@ -0,0 +1,541 @@
+use {
+    crate::{
+        digits::add_digital_decomposition,
+        memory::{add_rom_checking, MemoryBlock, MemoryOperation},
+        noir_to_r1cs::NoirToR1CSCompiler,
+    },
+    ark_ff::Field,
+    ark_std::{One, Zero},
+    provekit_common::{
+        witness::{
+            embedded_curve::{self, find_point, EmbeddedCurveAddWitnesses, B, GENERATOR},
+            ConstantOrR1CSWitness, ConstantTerm, DigitalDecompositionWitnessesBuilder,
+            ProductLinearTerm, SumTerm, WitnessBuilder,
+        },
+        FieldElement,
+    },
+    ruint::aliases::U256,
+    std::{collections::BTreeMap, ops::Neg},
+};
+
+/// Number of bits of a window of a scalar
+const WINDOW_BITS: usize = 4;
+
+/// Number of bits of each of the two halves of a scalar
+const HALF_BITS: usize = 128;
+
+/// Number of windows of a scalar
+const NUM_WINDOWS: usize = 2 * HALF_BITS / WINDOW_BITS;
+
+/// The witnesses of the coordinates of a finite point
+type Point = [usize; 2];
+
+/// The witnesses of the coordinates and infinity flag of a point
+type FlaggedPoint = [usize; 3];
+
+/// Merges the terms of a row of an R1CS matrix on the same witness, which
+/// would otherwise overwrite each other.
+fn row(terms: &[(FieldElement, usize)]) -> Vec<(FieldElement, usize)> {
+    let mut row = BTreeMap::<usize, FieldElement>::new();
+    for (coefficient, witness) in terms {
+        *row.entry(*witness).or_default() += coefficient;
+    }
+    row.into_iter()
+        .filter(|(_, coefficient)| !coefficient.is_zero())
+        .map(|(witness, coefficient)| (coefficient, witness))
+        .collect()
+}
+
+/// Returns a witness constrained to equal a constant.
+fn add_constant(compiler: &mut NoirToR1CSCompiler, value: FieldElement) -> usize {
+    let witness = compiler.add_witness_builder(WitnessBuilder::Constant(ConstantTerm(
+        compiler.num_witnesses(),
+        value,
+    )));
+    compiler.r1cs.add_constraint(
+        &[(FieldElement::one(), witness)],
+        &[(FieldElement::one(), compiler.witness_one())],
+        &[(value, compiler.witness_one())],
+    );
+    witness
+}
+
+fn add_input(compiler: &mut NoirToR1CSCompiler, input: &ConstantOrR1CSWitness) -> usize {
+    match input {
+        ConstantOrR1CSWitness::Constant(value) => add_constant(compiler, *value),
+        ConstantOrR1CSWitness::Witness(witness) => *witness,
+    }
+}
+
+/// Constrains the infinity flag of a point to be boolean, and the point to be
+/// on the curve unless it is the point at infinity.
+fn add_point_check(compiler: &mut NoirToR1CSCompiler, [x, y, is_infinite]: FlaggedPoint) {
+    let one = compiler.witness_one();
+    compiler.r1cs.add_constraint(
+        &[(FieldElement::one(), is_infinite)],
+        &[(FieldElement::one(), is_infinite)],
+        &[(FieldElement::one(), is_infinite)],
+    );
+    // (1 - is_infinite) * (x^3 - y^2 + B) = 0
+    let xx = compiler.add_product(x, x);
+    let xxx = compiler.add_product(xx, x);
+    let yy = compiler.add_product(y, y);
+    compiler.r1cs.add_constraint(
+        &[
+            (FieldElement::one(), one),
+            (FieldElement::one().neg(), is_infinite),
+        ],
+        &[
+            (FieldElement::one(), xxx),
+            (FieldElement::one().neg(), yy),
+            (FieldElement::from(B).neg(), one),
+        ],
+        &[],
+    );
+}
+
+/// The sum of two finite points with distinct x coordinates. The difference
+/// of the x coordinates is shown to be invertible, as the slope is otherwise
+/// unconstrained.
+fn add_chord(compiler: &mut NoirToR1CSCompiler, [x1, y1]: Point, [x2, y2]: Point) -> Point {
+    let start = compiler.add_witness_builder(WitnessBuilder::EmbeddedCurveChord(
+        compiler.num_witnesses(),
+        [x1, y1],
+        [x2, y2],
+    ));
+    let [inverse_dx, lambda, x3, y3] = std::array::from_fn(|i| start + i);
+    let (one, minus_one) = (FieldElement::one(), FieldElement::one().neg());
+    let dx = [(one, x2), (minus_one, x1)];
+    compiler
+        .r1cs
+        .add_constraint(&dx, &[(one, inverse_dx)], &[(one, compiler.witness_one())]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &dx, &[(one, y2), (minus_one, y1)]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &[(one, lambda)], &[
+            (one, x3),
+            (one, x1),
+            (one, x2),
+        ]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &[(one, x1), (minus_one, x3)], &[
+            (one, y3),
+            (one, y1),
+        ]);
+    [x3, y3]
+}
+
+/// The double of a finite point, which has a non-zero y coordinate as the
+/// group has odd order.
+fn add_double(compiler: &mut NoirToR1CSCompiler, [x, y]: Point) -> Point {
+    let start = compiler.add_witness_builder(WitnessBuilder::EmbeddedCurveDouble(
+        compiler.num_witnesses(),
+        [x, y],
+    ));
+    let [xx, lambda, x3, y3] = std::array::from_fn(|i| start + i);
+    let one = FieldElement::one();
+    compiler
+        .r1cs
+        .add_constraint(&[(one, x)], &[(one, x)], &[(one, xx)]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &[(one.double(), y)], &[(
+            FieldElement::from(3u64),
+            xx,
+        )]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &[(one, lambda)], &[
+            (one, x3),
+            (one.double(), x),
+        ]);
+    compiler
+        .r1cs
+        .add_constraint(&[(one, lambda)], &[(one, x), (one.neg(), x3)], &[
+            (one, y3),
+            (one, y),
+        ]);
+    [x3, y3]
+}
+
+/// The sum of two points on the curve, either of which may be the point at
+/// infinity. See [`embedded_curve::complete_add`] for the witnesses.
+///
+/// The slope is that of the chord when the x coordinates differ, and of the
+/// tangent otherwise, with the flags of the two cases shown by their inverses.
+/// The sum is then selected from the third point on the line, the inputs and
+/// the point at infinity by the infinity flags and whether the points are
+/// opposite.
+fn add_complete(
+    compiler: &mut NoirToR1CSCompiler,
+    lhs: FlaggedPoint,
+    rhs: FlaggedPoint,
+) -> FlaggedPoint {
+    let start = compiler.add_witness_builder(WitnessBuilder::EmbeddedCurveAdd(
+        EmbeddedCurveAddWitnesses {
+            first_witness_idx: compiler.num_witnesses(),
+            lhs,
+            rhs,
+        },
+    ));
+    let [inverse_dx, equal_x, inverse_sum_y, zero_sum_y, opposite] =
+        std::array::from_fn(|i| start + i);
+    let [xx, tangent_y, tangent_correction, lambda, x3, y3] =
+        std::array::from_fn(|i| start + 5 + i);
+    let [finite, chord] = std::array::from_fn(|i| start + 11 + i);
+    let [chord_x, lhs_x, rhs_x, chord_y, lhs_y, rhs_y] = std::array::from_fn(|i| start + 13 + i);
+    let [x, y, is_infinite] = std::array::from_fn(|i| start + 19 + i);
+    let [x1, y1, infinite1] = lhs;
+    let [x2, y2, infinite2] = rhs;
+    let witness_one = compiler.witness_one();
+    let (one, minus_one) = (FieldElement::one(), FieldElement::one().neg());
+    let mut constrain =
+        |a: &[(FieldElement, usize)], b: &[(FieldElement, usize)], c: &[(FieldElement, usize)]| {
+            compiler.r1cs.add_constraint(&row(a), &row(b), &row(c));
+        };
+
+    // Flags of equal x coordinates and of opposite y coordinates, which are
+    // zero exactly when the difference or sum has an inverse.
+    let dx = [(one, x2), (minus_one, x1)];
+    let sum_y = [(one, y1), (one, y2)];
+    constrain(&dx, &[(one, inverse_dx)], &[
+        (one, witness_one),
+        (minus_one, equal_x),
+    ]);
+    constrain(&dx, &[(one, equal_x)], &[]);
+    constrain(&sum_y, &[(one, inverse_sum_y)], &[
+        (one, witness_one),
+        (minus_one, zero_sum_y),
+    ]);
+    constrain(&sum_y, &[(one, zero_sum_y)], &[]);
+    constrain(&[(one, equal_x)], &[(one, zero_sum_y)], &[(one, opposite)]);
+
+    // The slope: `λ (x2 - x1) = y2 - y1` for a chord, and `λ 2 y1 = 3 x1^2` for
+    // a tangent.
+    constrain(&[(one, x1)], &[(one, x1)], &[(one, xx)]);
+    constrain(&[(one, equal_x)], &[(one, y1)], &[(one, tangent_y)]);
+    constrain(
+        &[(one, equal_x)],
+        &[(FieldElement::from(3u64), xx), (minus_one, y2), (one, y1)],
+        &[(one, tangent_correction)],
+    );
+    constrain(
+        &[(one, lambda)],
+        &[(one, x2), (minus_one, x1), (one.double(), tangent_y)],
+        &[(one, y2), (minus_one, y1), (one, tangent_correction)],
+    );
+    constrain(&[(one, lambda)], &[(one, lambda)], &[
+        (one, x3),
+        (one, x1),
+        (one, x2),
+    ]);
+    constrain(&[(one, lambda)], &[(one, x1), (minus_one, x3)], &[
+        (one, y3),
+        (one, y1),
+    ]);
+
+    // Selection of the sum: the third point when both points are finite and
+    // not opposite, the other point when one of them is infinite.
+    constrain(
+        &[(one, witness_one), (minus_one, infinite1)],
+        &[(one, witness_one), (minus_one, infinite2)],
+        &[(one, finite)],
+    );
+    constrain(
+        &[(one, finite)],
+        &[(one, witness_one), (minus_one, opposite)],
+        &[(one, chord)],
+    );
+    let only_lhs = [
+        (one, witness_one),
+        (minus_one, infinite1),
+        (minus_one, finite),
+    ];
+    let only_rhs = [
+        (one, witness_one),
+        (minus_one, infinite2),
+        (minus_one, finite),
+    ];
+    for (coordinate, lhs_coordinate, rhs_coordinate, chord_term, lhs_term, rhs_term) in [
+        (x3, x1, x2, chord_x, lhs_x, rhs_x),
+        (y3, y1, y2, chord_y, lhs_y, rhs_y),
+    ] {
+        constrain(&[(one, chord)], &[(one, coordinate)], &[(one, chord_term)]);
+        constrain(&only_lhs, &[(one, lhs_coordinate)], &[(one, lhs_term)]);
+        constrain(&only_rhs, &[(one, rhs_coordinate)], &[(one, rhs_term)]);
+    }
+    constrain(
+        &[(one, chord_x), (one, lhs_x), (one, rhs_x)],
+        &[(one, witness_one)],
+        &[(one, x)],
+    );
+    constrain(
+        &[(one, chord_y), (one, lhs_y), (one, rhs_y)],
+        &[(one, witness_one)],
+        &[(one, y)],
+    );
+    constrain(
+        &[
+            (one, infinite1),
+            (one, infinite2),
+            (one.double(), finite),
+            (minus_one, witness_one),
+            (minus_one, chord),
+        ],
+        &[(one, witness_one)],
+        &[(one, is_infinite)],
+    );
+    [x, y, is_infinite]
+}
+
+/// Adds the witnesses and constraints for the `embedded_curve_add` black box:
+/// the complete sum of two points on the curve, constrained to equal the
+/// outputs.
+pub(crate) fn add_embedded_curve_add(
+    compiler: &mut NoirToR1CSCompiler,
+    lhs: &[ConstantOrR1CSWitness; 3],
+    rhs: &[ConstantOrR1CSWitness; 3],
+    outputs: [usize; 3],
+) {
+    let lhs = lhs.each_ref().map(|input| add_input(compiler, input));
+    let rhs = rhs.each_ref().map(|input| add_input(compiler, input));
+    add_point_check(compiler, lhs);
+    add_point_check(compiler, rhs);
+    let sum = add_complete(compiler, lhs, rhs);
+    assert_equal(compiler, sum, outputs);
+}
+
+fn assert_equal(compiler: &mut NoirToR1CSCompiler, values: [usize; 3], outputs: [usize; 3]) {
+    for (value, output) in values.into_iter().zip(outputs) {
+        compiler.r1cs.add_constraint(
+            &[(FieldElement::one(), value)],
+            &[(FieldElement::one(), compiler.witness_one())],
+            &[(FieldElement::one(), output)],
+        );
+    }
+}
+
+/// Adds the witnesses and constraints for the `multi_scalar_mul` black box:
+/// the sum of the points, each given as (x, y, is_infinite), times the
+/// scalars, each given as its low and high 128 bits. The sum is constrained to
+/// equal the outputs.
+///
+/// The points at infinity are replaced by the generator, with a zero scalar.
+/// Each scalar is decomposed into windows of [`WINDOW_BITS`] bits, which are
+/// looked up in tables of the multiples of its point, and the windows of all
+/// the scalars are accumulated in one pass of doublings. To keep every
+/// addition and doubling incomplete, the accumulator starts at a point `O`
+/// and each table is offset by a point `H`, neither of which has a known
+/// discrete logarithm. The offsets are removed by a final complete addition,
+/// which may give the point at infinity.
+pub(crate) fn add_multi_scalar_mul(
+    compiler: &mut NoirToR1CSCompiler,
+    points: &[[ConstantOrR1CSWitness; 3]],
+    scalars: &[[ConstantOrR1CSWitness; 2]],
+    outputs: [usize; 3],
+) {
+    let one = FieldElement::one();
+    let witness_one = compiler.witness_one();
+    let accumulator_offset = find_point(FieldElement::from(2u64));
+    let table_offset = find_point(accumulator_offset[0] + one);
+
+    // Substitute the generator for the points at infinity, and zero for their
+    // scalars.
+    let mut bases = Vec::with_capacity(points.len());
+    let mut halves = Vec::with_capacity(2 * scalars.len());
+    for (point, scalar) in points.iter().zip(scalars) {
+        let point = point.each_ref().map(|input| add_input(compiler, input));
+        add_point_check(compiler, point);
+        let [x, y, is_infinite] = point;
+        bases.push(
+            [(x, GENERATOR[0]), (y, GENERATOR[1])].map(|(coordinate, generator)| {
+                // coordinate + is_infinite * (generator - coordinate)
+                let correction =
+                    compiler.add_witness_builder(WitnessBuilder::ProductLinearOperation(
+                        compiler.num_witnesses(),
+                        ProductLinearTerm(is_infinite, one, FieldElement::zero()),
+                        ProductLinearTerm(coordinate, one.neg(), generator),
+                    ));
+                compiler.r1cs.add_constraint(
+                    &[(one, is_infinite)],
+                    &[(one.neg(), coordinate), (generator, witness_one)],
+                    &[(one, correction)],
+                );
+                compiler.add_sum(vec![SumTerm(None, coordinate), SumTerm(None, correction)])
+            }),
+        );
+        for half in scalar {
+            let half = add_input(compiler, half);
+            let scaled = compiler.add_witness_builder(WitnessBuilder::ProductLinearOperation(
+                compiler.num_witnesses(),
+                ProductLinearTerm(is_infinite, one.neg(), one),
+                ProductLinearTerm(half, one, FieldElement::zero()),
+            ));
+            compiler.r1cs.add_constraint(
+                &[(one, witness_one), (one.neg(), is_infinite)],
+                &[(one, half)],
+                &[(one, scaled)],
+            );
+            halves.push(scaled);
+        }
+    }
+    if bases.is_empty() {
+        let infinity = [FieldElement::zero(), FieldElement::zero(), one]
+            .map(|value| add_constant(compiler, value));
+        assert_equal(compiler, infinity, outputs);
+        return;
+    }
+
+    // The windows of each scalar, most significant first. Decomposing each
+    // half into windows also shows it to have at most 128 bits.
+    let dd_struct =
+        add_digital_decomposition(compiler, vec![WINDOW_BITS; HALF_BITS / WINDOW_BITS], halves);
+    let windows_per_half = HALF_BITS / WINDOW_BITS;
+    let windows = (0..bases.len())
+        .map(|scalar| {
+            (0..NUM_WINDOWS)
+                .rev()
+                .map(|i| {
+                    dd_struct.get_digit_witness_index(
+                        i % windows_per_half,
+                        2 * scalar + i / windows_per_half,
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // The tables `H + i P` of each point, and the entries for its windows
+    let table_offset_witnesses = table_offset.map(|value| add_constant(compiler, value));
+    let entries = bases
+        .iter()
+        .zip(&windows)
+        .map(|(base, windows)| {
+            let mut table = vec![table_offset_witnesses];
+            for _ in 1..1 << WINDOW_BITS {
+                let entry = add_chord(compiler, *table.last().unwrap(), *base);
+                table.push(entry);
+            }
+            add_table_reads(compiler, &table, windows)
+        })
+        .collect::<Vec<_>>();
+
+    let mut accumulator = accumulator_offset.map(|value| add_constant(compiler, value));
+    for window in 0..NUM_WINDOWS {
+        if window > 0 {
+            for _ in 0..WINDOW_BITS {
+                accumulator = add_double(compiler, accumulator);
+            }
+        }
+        for entries in &entries {
+            accumulator = add_chord(compiler, accumulator, entries[window]);
+        }
+    }
+
+    // Remove the offsets, `2^(256 - WINDOW_BITS) O` and `H` times the sum of
+    // the window weights for each point.
+    let mut offset = accumulator_offset;
+    for _ in 0..2 * HALF_BITS - WINDOW_BITS {
+        offset = double(offset);
+    }
+    let table_offset_sum = mul(table_offset, windows_sum());
+    for _ in 0..bases.len() {
+        offset = add(offset, table_offset_sum);
+    }
+    let zero = add_constant(compiler, FieldElement::zero());
+    let negated_offset = [offset[0], offset[1].neg()].map(|value| add_constant(compiler, value));
+    let sum = add_complete(compiler, [accumulator[0], accumulator[1], zero], [
+        negated_offset[0],
+        negated_offset[1],
+        zero,
+    ]);
+    assert_equal(compiler, sum, outputs);
+}
+
+/// Reads the entries of a table of points at the given addresses, with a
+/// read-only memory block for each coordinate.
+fn add_table_reads(
+    compiler: &mut NoirToR1CSCompiler,
+    table: &[Point],
+    addresses: &[usize],
+) -> Vec<Point> {
+    let mut points = vec![[0; 2]; addresses.len()];
+    for coordinate in 0..2 {
+        let mut block = MemoryBlock::new();
+        block.initial_value_witnesses = table.iter().map(|point| point[coordinate]).collect();
+        for (point, address) in points.iter_mut().zip(addresses) {
+            let value = compiler.add_witness_builder(WitnessBuilder::MemoryRead(
+                compiler.num_witnesses(),
+                *address,
+                block.initial_value_witnesses.clone(),
+            ));
+            block
+                .operations
+                .push(MemoryOperation::Load(*address, value));
+            point[coordinate] = value;
+        }
+        add_rom_checking(compiler, &block);
+    }
+    points
+}
+
+/// The sum of the weights `2^(WINDOW_BITS i)` of the windows of a scalar,
+/// which is `(2^256 - 1) / (2^WINDOW_BITS - 1)`
+fn windows_sum() -> U256 {
+    U256::MAX / U256::from((1u64 << WINDOW_BITS) - 1)
+}
+
+/// The double of a finite point, computed natively
+fn double(point: [FieldElement; 2]) -> [FieldElement; 2] {
+    let [.., x, y] = embedded_curve::double(point);
+    [x, y]
+}
+
+/// The sum of two finite points that are not opposite, computed natively
+fn add(lhs: [FieldElement; 2], rhs: [FieldElement; 2]) -> [FieldElement; 2] {
+    if lhs == rhs {
+        return double(lhs);
+    }
+    let [.., x, y] = embedded_curve::chord(lhs, rhs);
+    [x, y]
+}
+
+/// A multiple of a finite point by a non-zero scalar, computed natively for
+/// the offsets
+fn mul(point: [FieldElement; 2], scalar: U256) -> [FieldElement; 2] {
+    let mut result = point;
+    for i in (0..scalar.bit_len() - 1).rev() {
+        result = double(result);
+        if scalar.bit(i) {
+            result = add(result, point);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{add, double, mul, windows_sum, NUM_WINDOWS, WINDOW_BITS},
+        provekit_common::witness::embedded_curve::GENERATOR,
+        ruint::aliases::U256,
+    };
+
+    #[test]
+    fn test_native_arithmetic() {
+        let twice = double(GENERATOR);
+        assert_eq!(mul(GENERATOR, U256::from(2u64)), twice);
+        assert_eq!(
+            mul(GENERATOR, U256::from(5u64)),
+            add(double(twice), GENERATOR)
+        );
+
+        let weights = (0..NUM_WINDOWS).fold(U256::ZERO, |sum, i| {
+            sum + (U256::from(1u64) << (WINDOW_BITS * i))
+        });
+        assert_eq!(windows_sum(), weights);
+    }
+}
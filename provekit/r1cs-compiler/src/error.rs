@@ -0,0 +1,188 @@
+use {
+    crate::source_map::opcode_source,
+    noirc_artifacts::program::ProgramArtifact,
+    provekit_common::SourceLocation,
+    std::fmt::{self, Display, Formatter},
+};
+
+/// An opcode of a function of an ACIR program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcirLocation {
+    pub function: u32,
+    pub opcode:   usize,
+}
+
+/// Why an ACIR opcode could not be compiled to R1CS.
+#[derive(Debug)]
+pub enum OpcodeError {
+    /// An opcode without an R1CS translation
+    UnsupportedOpcode(String),
+    /// A black box function without a gadget
+    UnsupportedBlackBox(String),
+    /// A black box function whose inputs or outputs do not have the expected
+    /// shape
+    UnsupportedBlackBoxInputs(String),
+    /// A memory block other than a plain `Memory` block
+    UnsupportedBlockType(String),
+    /// A memory block initialized twice
+    MemoryBlockReinitialized(usize),
+    /// A memory operation on a block that was not initialized
+    MemoryBlockUninitialized(usize),
+    /// A memory operation with a predicate, which the Noir compiler no longer
+    /// emits
+    MemoryOpPredicate,
+    /// A memory operation whose index or value is an expression rather than a
+    /// single witness
+    MemoryOpExpression,
+    /// A range check of a constant that does not fit in the number of bits
+    RangeCheckedConstant(String, u32),
+    /// A call to another ACIR function that cannot be inlined
+    UnsupportedCall(String),
+    /// A call whose predicate is a witness, as emitted for calls to `#[fold]`
+    /// functions in a branch. Callees are inlined, so their constraints would
+    /// hold even when the ACVM skips the call, and programs that take the
+    /// other branch could not be proven. Calls with a constant predicate are
+    /// supported.
+    CallWitnessPredicate,
+    /// An ECDSA verification whose result is not asserted to be true by an
+    /// `AssertZero` opcode of the same function, as in
+    /// `assert(verify_signature(..))`. The gadget only proves valid signatures,
+    /// so results that are returned, branched on or asserted by a caller are
+    /// not supported.
+    UnassertedEcdsaVerification,
+    /// An error of the gadget compiling the opcode
+    Gadget(anyhow::Error),
+}
+
+/// An error compiling a Noir program to R1CS.
+#[derive(Debug)]
+pub enum CompileError {
+    /// The program has no functions
+    NoFunctions,
+    /// An opcode of the program could not be compiled
+    Opcode {
+        /// The opcode, preceded by the calls leading to it, outermost first
+        call_stack: Vec<AcirLocation>,
+        /// The Noir source locations of the call stack, outermost first, once
+        /// resolved from the debug symbols of the program
+        source:     Vec<SourceLocation>,
+        error:      OpcodeError,
+    },
+}
+
+impl CompileError {
+    /// Add the calling opcode of the error's call stack.
+    pub(crate) fn called_from(mut self, location: AcirLocation) -> Self {
+        if let Self::Opcode { call_stack, .. } = &mut self {
+            call_stack.insert(0, location);
+        }
+        self
+    }
+
+    /// Resolve the Noir source locations of the error's call stack from the
+    /// debug symbols of the program.
+    pub fn resolve_source(&mut self, program: &ProgramArtifact) {
+        let Self::Opcode {
+            call_stack, source, ..
+        } = self
+        else {
+            return;
+        };
+        *source = call_stack
+            .iter()
+            .flat_map(|location| opcode_source(program, location.function, location.opcode))
+            .collect();
+    }
+}
+
+impl From<OpcodeError> for CompileError {
+    fn from(error: OpcodeError) -> Self {
+        Self::Opcode {
+            call_stack: vec![],
+            source: vec![],
+            error,
+        }
+    }
+}
+
+impl From<anyhow::Error> for CompileError {
+    fn from(error: anyhow::Error) -> Self {
+        OpcodeError::Gadget(error).into()
+    }
+}
+
+impl Display for OpcodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedOpcode(opcode) => write!(f, "Unsupported opcode {opcode}"),
+            Self::UnsupportedBlackBox(name) => {
+                write!(f, "Unsupported black box function {name}")
+            }
+            Self::UnsupportedBlackBoxInputs(reason) => f.write_str(reason),
+            Self::UnsupportedBlockType(block_type) => {
+                write!(f, "Unsupported memory block type {block_type}")
+            }
+            Self::MemoryBlockReinitialized(block) => {
+                write!(f, "Memory block {block} already initialized")
+            }
+            Self::MemoryBlockUninitialized(block) => {
+                write!(f, "Memory block {block} not initialized before use")
+            }
+            Self::MemoryOpPredicate => f.write_str("Memory operations with a predicate"),
+            Self::MemoryOpExpression => f.write_str(
+                "Memory operations must have a single witness as index and value, not a more \
+                 general expression",
+            ),
+            Self::RangeCheckedConstant(constant, num_bits) => {
+                write!(
+                    f,
+                    "Range check of constant {constant}, which does not fit in {num_bits} bits"
+                )
+            }
+            Self::UnsupportedCall(reason) => f.write_str(reason),
+            Self::CallWitnessPredicate => f.write_str(
+                "Calls with a witness predicate, as to a `#[fold]` function in a branch, are not \
+                 supported: the inlined callee is constrained whether or not it is called",
+            ),
+            Self::UnassertedEcdsaVerification => f.write_str(
+                "ECDSA verification whose result is not asserted to be true in the same function, \
+                 as in `assert(verify_signature(..))`: only valid signatures can be proven",
+            ),
+            Self::Gadget(error) => write!(f, "{error:#}"),
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFunctions => f.write_str("Program has no functions"),
+            Self::Opcode {
+                call_stack,
+                source,
+                error,
+            } => {
+                write!(f, "{error}")?;
+                for location in call_stack.iter().rev() {
+                    write!(
+                        f,
+                        "\n  at ACIR opcode {} of function {}",
+                        location.opcode, location.function
+                    )?;
+                }
+                for location in source.iter().rev() {
+                    let line = location.line.to_string();
+                    let padding = " ".repeat(line.len());
+                    write!(
+                        f,
+                        "\n{padding}--> {}:{}:{}\n{padding} |\n{line} | {}",
+                        location.path, location.line, location.column, location.text
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
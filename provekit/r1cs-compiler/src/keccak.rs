@@ -0,0 +1,198 @@
+use {
+    crate::{
+        digits::{add_digital_decomposition, DigitalDecompositionWitnessesBuilder},
+        noir_to_r1cs::NoirToR1CSCompiler,
+        words::BinOps,
+    },
+    ark_std::One,
+    provekit_common::{
+        witness::{
+            keccak::{keccak_round, KeccakOps, Lane, NUM_LANES, NUM_ROUNDS, WITNESSES_PER_ROUND},
+            ConstantOrR1CSWitness, ConstantTerm, KeccakRoundWitnesses, WitnessBuilder,
+        },
+        FieldElement,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Number of bits in a word of a lane
+const WORD_BITS: u32 = 32;
+
+/// Adds the witnesses and constraints for the Keccak-f[1600] permutation.
+/// Bitwise operations and range checks are collected alongside those of the
+/// ACIR opcodes, and constrained together by lookups. The NOT-AND of χ has a
+/// lookup table of its own.
+pub(crate) struct KeccakBuilder<'a> {
+    pub compiler:     &'a mut NoirToR1CSCompiler,
+    pub xor_ops:      &'a mut BinOps,
+    pub and_not_ops:  &'a mut BinOps,
+    pub range_checks: &'a mut BTreeMap<u32, Vec<usize>>,
+}
+
+impl KeccakBuilder<'_> {
+    /// Add the witnesses and constraints for the permutation of the input
+    /// lanes, constraining its result to equal the output lanes.
+    ///
+    /// Each lane is split into two 32-bit words, so that the bitwise operations
+    /// fit the lookups. A witness builder solves for all the words of a round
+    /// at once. Rotations split each word of a lane in two range checked
+    /// pieces, which are recombined in the rotated lane.
+    pub fn add_permutation(
+        &mut self,
+        inputs: [ConstantOrR1CSWitness; NUM_LANES],
+        outputs: [usize; NUM_LANES],
+    ) {
+        let lanes =
+            inputs
+                .into_iter()
+                .map(|input| match input {
+                    ConstantOrR1CSWitness::Witness(witness) => witness,
+                    ConstantOrR1CSWitness::Constant(value) => {
+                        let witness = self.compiler.add_witness_builder(WitnessBuilder::Constant(
+                            ConstantTerm(self.compiler.num_witnesses(), value),
+                        ));
+                        self.compiler.r1cs.add_constraint(
+                            &[(FieldElement::one(), witness)],
+                            &[(FieldElement::one(), self.compiler.witness_one())],
+                            &[(value, self.compiler.witness_one())],
+                        );
+                        witness
+                    }
+                })
+                .collect::<Vec<_>>();
+        let dd_struct = add_digital_decomposition(
+            self.compiler,
+            vec![WORD_BITS as usize, WORD_BITS as usize],
+            lanes,
+        );
+        let mut state: [Lane<usize>; NUM_LANES] = std::array::from_fn(|i| {
+            [0, 1].map(|digit_place| dd_struct.get_digit_witness_index(digit_place, i))
+        });
+        self.range_checks
+            .entry(WORD_BITS)
+            .or_default()
+            .extend(state.iter().flatten());
+
+        for round in 0..NUM_ROUNDS {
+            let first_witness_idx = self
+                .compiler
+                .add_witness_builder(WitnessBuilder::KeccakRound(KeccakRoundWitnesses {
+                    first_witness_idx: self.compiler.num_witnesses(),
+                    round,
+                    state,
+                }));
+            let mut round_constraints = RoundConstraints {
+                builder:      self,
+                next_witness: first_witness_idx,
+            };
+            state = keccak_round(&mut round_constraints, &state, round);
+            debug_assert_eq!(
+                round_constraints.next_witness,
+                first_witness_idx + WITNESSES_PER_ROUND
+            );
+        }
+
+        for ([low, high], output) in state.into_iter().zip(outputs) {
+            self.compiler.r1cs.add_constraint(
+                &[
+                    (FieldElement::one(), low),
+                    (FieldElement::from(1u64 << WORD_BITS), high),
+                ],
+                &[(FieldElement::one(), self.compiler.witness_one())],
+                &[(FieldElement::one(), output)],
+            );
+        }
+    }
+}
+
+/// Constrains the words of a round, in the order they are written by the
+/// witness builder of the round.
+struct RoundConstraints<'a, 'b> {
+    builder:      &'a mut KeccakBuilder<'b>,
+    next_witness: usize,
+}
+
+impl RoundConstraints<'_, '_> {
+    fn next(&mut self) -> usize {
+        self.next_witness += 1;
+        self.next_witness - 1
+    }
+}
+
+impl KeccakOps for RoundConstraints<'_, '_> {
+    type Word = usize;
+
+    fn xor(&mut self, lhs: usize, rhs: usize) -> usize {
+        let output = self.next();
+        self.builder.xor_ops.entry(WORD_BITS).or_default().push((
+            ConstantOrR1CSWitness::Witness(lhs),
+            ConstantOrR1CSWitness::Witness(rhs),
+            output,
+        ));
+        output
+    }
+
+    fn xor_constant(&mut self, word: usize, constant: u32) -> usize {
+        let output = self.next();
+        self.builder.xor_ops.entry(WORD_BITS).or_default().push((
+            ConstantOrR1CSWitness::Witness(word),
+            ConstantOrR1CSWitness::Constant(FieldElement::from(constant)),
+            output,
+        ));
+        output
+    }
+
+    fn and_not(&mut self, lhs: usize, rhs: usize) -> usize {
+        let output = self.next();
+        self.builder
+            .and_not_ops
+            .entry(WORD_BITS)
+            .or_default()
+            .push((
+                ConstantOrR1CSWitness::Witness(lhs),
+                ConstantOrR1CSWitness::Witness(rhs),
+                output,
+            ));
+        output
+    }
+
+    fn rotate_left(&mut self, [low, high]: Lane<usize>, amount: u32) -> Lane<usize> {
+        let one = FieldElement::one();
+        let witness_one = self.builder.compiler.witness_one();
+        let low_width = WORD_BITS - amount;
+        let [low_low, low_high, high_low, high_high] = std::array::from_fn(|_| self.next());
+        for (word, word_low, word_high) in [(low, low_low, low_high), (high, high_low, high_high)] {
+            self.builder.compiler.r1cs.add_constraint(
+                &[
+                    (one, word_low),
+                    (FieldElement::from(1u64 << low_width), word_high),
+                ],
+                &[(one, witness_one)],
+                &[(one, word)],
+            );
+            self.builder
+                .range_checks
+                .entry(low_width)
+                .or_default()
+                .push(word_low);
+            self.builder
+                .range_checks
+                .entry(amount)
+                .or_default()
+                .push(word_high);
+        }
+
+        [(low_low, high_high), (high_low, low_high)].map(|(shifted, wrapped)| {
+            let rotated = self.next();
+            self.builder.compiler.r1cs.add_constraint(
+                &[
+                    (FieldElement::from(1u64 << amount), shifted),
+                    (one, wrapped),
+                ],
+                &[(one, witness_one)],
+                &[(one, rotated)],
+            );
+            rotated
+        })
+    }
+}
@@ -1,14 +1,37 @@
+mod aes;
+mod bigint;
 mod binops;
+mod blake;
+mod byte_table;
 mod digits;
+mod ecdsa;
+mod embedded_curve;
+mod error;
+mod keccak;
+mod lint;
+mod lookup;
 mod memory;
 mod noir_proof_scheme;
 mod noir_to_r1cs;
+mod non_native;
+mod optimize;
+mod poseidon2;
 mod range_check;
+mod sha256;
+mod source_map;
+mod spark;
 mod whir_r1cs;
 mod witness_generator;
+mod words;
 
 pub use {
-    noir_proof_scheme::NoirProofSchemeBuilder, noir_to_r1cs::noir_to_r1cs,
+    error::{AcirLocation, CompileError, OpcodeError},
+    lint::{lint, lint_scheme, Lint, LintKind},
+    noir_proof_scheme::NoirProofSchemeBuilder,
+    noir_to_r1cs::{
+        noir_to_r1cs, noir_to_r1cs_without_product_reuse, CompiledProgram, ProductStats,
+    },
+    provekit_common::SourceLocation,
     whir_r1cs::WhirR1CSSchemeBuilder,
 };
 
@@ -0,0 +1,211 @@
+use {
+    ark_std::Zero,
+    provekit_common::{
+        witness::{SplitWitnessBuilders, WitnessBuilder},
+        NoirProofScheme, Origin, SourceMap, R1CS,
+    },
+    std::{
+        fmt::{self, Display, Formatter},
+        num::NonZeroU32,
+    },
+};
+
+/// Why a witness is underconstrained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// The witness appears in no constraint
+    Unconstrained,
+    /// The witness appears only in constraints that do not determine it: with
+    /// zero coefficients, or in a product whose other factor is zero
+    Undetermined,
+}
+
+/// A witness of an R1CS instance the prover can choose freely.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub witness:        usize,
+    pub kind:           LintKind,
+    /// The ACIR witnesses mapped to the witness
+    pub acir_witnesses: Vec<usize>,
+    pub origin:         Option<Origin>,
+}
+
+/// Find the underconstrained witnesses of a scheme.
+pub fn lint_scheme(scheme: &NoirProofScheme) -> Vec<Lint> {
+    lint(
+        &scheme.r1cs,
+        &scheme.witness_builders,
+        &scheme.witness_generator.witness_map,
+        scheme.source_map.as_ref(),
+    )
+}
+
+/// Find the witnesses of an R1CS instance that appear in no constraint, or
+/// only in constraints that do not determine them. The constant one witness,
+/// the public inputs and outputs and the challenges are fixed by the verifier,
+/// and the eliminated witnesses are not part of the instance.
+pub fn lint(
+    r1cs: &R1CS,
+    witness_builders: &SplitWitnessBuilders,
+    witness_map: &[Option<NonZeroU32>],
+    source_map: Option<&SourceMap>,
+) -> Vec<Lint> {
+    let num_witnesses = r1cs.num_witnesses();
+    let mut appears = vec![false; num_witnesses];
+    let mut determined = vec![false; num_witnesses];
+    let (a, b, c) = (r1cs.a(), r1cs.b(), r1cs.c());
+    for row in 0..r1cs.num_constraints() {
+        let [a, b, c] = [&a, &b, &c].map(|matrix| {
+            matrix
+                .iter_row(row)
+                .filter(|(_, coeff)| !coeff.is_zero())
+                .map(|(col, _)| col)
+                .collect::<Vec<_>>()
+        });
+        // A side of a product only constrains its witnesses if the other side
+        // is not zero.
+        for (witnesses, determines) in [(&a, !b.is_empty()), (&b, !a.is_empty()), (&c, true)] {
+            for &witness in witnesses {
+                appears[witness] = true;
+                determined[witness] |= determines;
+            }
+        }
+    }
+
+    let mut fixed = vec![false; num_witnesses];
+    fixed[..=r1cs.num_public_values()].fill(true);
+    for builder in witness_builders.iter() {
+        if let WitnessBuilder::Challenge(witness) = builder {
+            fixed[*witness] = true;
+        }
+    }
+
+    // ACIR witnesses mapped past the instance were eliminated
+    let mut acir_witnesses = vec![vec![]; num_witnesses];
+    for (acir_witness, witness) in witness_map.iter().enumerate() {
+        if let Some(witness) = witness.filter(|witness| (witness.get() as usize) < num_witnesses) {
+            acir_witnesses[witness.get() as usize].push(acir_witness);
+        }
+    }
+
+    (0..num_witnesses)
+        .filter(|&witness| !fixed[witness] && !determined[witness])
+        .map(|witness| Lint {
+            witness,
+            kind: if appears[witness] {
+                LintKind::Undetermined
+            } else {
+                LintKind::Unconstrained
+            },
+            acir_witnesses: std::mem::take(&mut acir_witnesses[witness]),
+            origin: source_map.and_then(|map| map.witness_origin(witness).cloned()),
+        })
+        .collect()
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Witness {}", self.witness)?;
+        if !self.acir_witnesses.is_empty() {
+            let acir_witnesses = self
+                .acir_witnesses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            write!(f, " (ACIR witness {})", acir_witnesses.join(", "))?;
+        }
+        f.write_str(match self.kind {
+            LintKind::Unconstrained => " appears in no constraint",
+            LintKind::Undetermined => " is not determined by the constraints it appears in",
+        })?;
+        if let Some(origin) = &self.origin {
+            write!(f, ", from {origin}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{lint, LintKind},
+        crate::{noir_to_r1cs::NoirToR1CSCompiler, optimize::eliminate_linear_constraints},
+        acir::native_types::Witness as NoirWitness,
+        ark_std::{One, Zero},
+        provekit_common::{witness::WitnessBuilder, FieldElement},
+    };
+
+    #[test]
+    fn test_lint() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.fetch_r1cs_witness_index(NoirWitness(0));
+        let y = compiler.fetch_r1cs_witness_index(NoirWitness(1));
+        let _ = compiler.add_product(x, x);
+        // Multiplied by zero
+        compiler.r1cs.add_constraint(
+            &[(FieldElement::one(), y)],
+            &[(FieldElement::zero(), compiler.witness_one())],
+            &[],
+        );
+        let challenge =
+            compiler.add_witness_builder(WitnessBuilder::Challenge(compiler.num_witnesses()));
+        let unused = compiler
+            .add_witness_builder(WitnessBuilder::Inverse(compiler.num_witnesses(), challenge));
+
+        let (r1cs, witness_map, witness_builders, source_map) = compiler.finalize();
+        let lints = lint(&r1cs, &witness_builders, &witness_map, Some(&source_map));
+
+        assert_eq!(
+            lints
+                .iter()
+                .map(|lint| (lint.witness, lint.kind, lint.acir_witnesses.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (y, LintKind::Undetermined, vec![1]),
+                (unused, LintKind::Unconstrained, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_public_values() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        // An unused public input
+        let x = compiler.fetch_r1cs_witness_index(NoirWitness(0));
+        let y = compiler.fetch_r1cs_witness_index(NoirWitness(1));
+        let _ = compiler.add_product(y, y);
+
+        let (mut r1cs, witness_map, witness_builders, source_map) = compiler.finalize();
+        assert_eq!(x, 1);
+        r1cs.num_public_inputs = 1;
+        let lints = lint(&r1cs, &witness_builders, &witness_map, Some(&source_map));
+        assert!(lints.is_empty());
+    }
+
+    #[test]
+    fn test_lint_eliminated_acir_witness() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.fetch_r1cs_witness_index(NoirWitness(0));
+        let y = compiler.fetch_r1cs_witness_index(NoirWitness(1));
+        // x = y
+        compiler.r1cs.add_constraint(
+            &[(FieldElement::one(), x), (-FieldElement::one(), y)],
+            &[(FieldElement::one(), compiler.witness_one())],
+            &[],
+        );
+        let _ = compiler.add_product(x, x);
+
+        let (r1cs, mut witness_map, mut witness_builders, mut source_map) = compiler.finalize();
+        let r1cs = eliminate_linear_constraints(
+            r1cs,
+            &mut witness_map,
+            &mut witness_builders,
+            &mut source_map,
+        );
+        assert_eq!(witness_builders.num_eliminated, 1);
+        assert!(witness_map[1].unwrap().get() as usize >= r1cs.num_witnesses());
+
+        let lints = lint(&r1cs, &witness_builders, &witness_map, Some(&source_map));
+        assert!(lints.is_empty());
+    }
+}
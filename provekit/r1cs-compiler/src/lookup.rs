@@ -0,0 +1,277 @@
+use {
+    crate::{binops::BinOp, noir_to_r1cs::NoirToR1CSCompiler},
+    ark_std::{One, Zero},
+    provekit_common::{
+        witness::{byte_table::ByteTable, ConstantOrR1CSWitness, SumTerm, WitnessBuilder},
+        FieldElement,
+    },
+    std::{collections::BTreeMap, ops::Neg},
+};
+
+/// Identifies a table registered with [Lookups]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TableId(usize);
+
+/// The kinds of constant tables, which are registered once and shared by all
+/// the functions of a program
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum SharedTable {
+    /// The values of `num_bits` bits
+    Range(u32),
+    /// A bin op on every pair of `BINOP_ATOMIC_BITS`-bit digits
+    BinOp(BinOp),
+    /// The `(input, output)` entries of a byte table
+    Byte(ByteTable),
+    /// The SHA-256 choose function on every triple of digits of the SHA-256
+    /// gadget
+    Sha256Choose,
+    /// The SHA-256 majority function on every triple of digits of the
+    /// SHA-256 gadget
+    Sha256Majority,
+    /// Every digit of the SHA-256 gadget and its right shift by a number of
+    /// bits
+    Sha256DigitShift(u32),
+}
+
+/// A table and the queries against it
+#[derive(Debug, Clone)]
+struct Table {
+    rows:        Vec<Vec<ConstantOrR1CSWitness>>,
+    row_weights: Vec<u64>,
+    queries:     Vec<Vec<ConstantOrR1CSWitness>>,
+    /// The origin in the source map of each query
+    origins:     Vec<usize>,
+}
+
+/// The lookup tables of a circuit and the queries against them, which are
+/// constrained by LogUp arguments when the compiler is finalized.
+///
+/// A table has rows of one or more columns, each either a constant or a
+/// witness, and a query is a row purported to be in a table. Each table has
+/// row weights, such that the sum of the values of its row `i` weighted by
+/// them is `i`: the prover counts the lookups of each row from the weighted
+/// sums of the queries. The weighted columns of the rows are constants.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Lookups {
+    tables:            Vec<Table>,
+    /// The ids of the shared tables registered so far
+    shared:            BTreeMap<SharedTable, TableId>,
+    /// The origin in the source map of the queries being added, to which the
+    /// constraints of their LogUp quotients are attributed
+    pub(crate) origin: usize,
+}
+
+impl Lookups {
+    /// Registers a table of constant rows, returning its id.
+    pub fn add_fixed_table(
+        &mut self,
+        rows: impl IntoIterator<Item = Vec<FieldElement>>,
+        row_weights: Vec<u64>,
+    ) -> TableId {
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(ConstantOrR1CSWitness::Constant)
+                    .collect()
+            })
+            .collect();
+        self.add_table(rows, row_weights)
+    }
+
+    /// Returns the id of a shared table, registering it with the constant rows
+    /// and row weights if it is not registered yet.
+    pub fn add_shared_table<I: IntoIterator<Item = Vec<FieldElement>>>(
+        &mut self,
+        kind: SharedTable,
+        rows: impl FnOnce() -> I,
+        row_weights: Vec<u64>,
+    ) -> TableId {
+        if let Some(&table) = self.shared.get(&kind) {
+            return table;
+        }
+        let table = self.add_fixed_table(rows(), row_weights);
+        self.shared.insert(kind, table);
+        table
+    }
+
+    /// Whether a shared table has been registered.
+    pub fn has_shared_table(&self, kind: SharedTable) -> bool {
+        self.shared.contains_key(&kind)
+    }
+
+    /// Registers a table whose rows may contain witnesses, such as the
+    /// contents of a read-only memory block, returning its id.
+    pub fn add_table(
+        &mut self,
+        rows: Vec<Vec<ConstantOrR1CSWitness>>,
+        row_weights: Vec<u64>,
+    ) -> TableId {
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                row_weights.len(),
+                "Rows have a value for each column"
+            );
+            let index = row
+                .iter()
+                .zip(&row_weights)
+                .filter(|(_, weight)| **weight != 0)
+                .map(|(value, weight)| match value {
+                    ConstantOrR1CSWitness::Constant(value) => *value * FieldElement::from(*weight),
+                    ConstantOrR1CSWitness::Witness(_) => {
+                        panic!("The weighted columns of a table are constants")
+                    }
+                })
+                .sum::<FieldElement>();
+            assert_eq!(
+                index,
+                FieldElement::from(i as u64),
+                "The weighted sum of each row is its index"
+            );
+        }
+        self.tables.push(Table {
+            rows,
+            row_weights,
+            queries: vec![],
+            origins: vec![],
+        });
+        TableId(self.tables.len() - 1)
+    }
+
+    /// Adds a query, constraining the values to be a row of the table.
+    pub fn query(&mut self, table: TableId, values: Vec<ConstantOrR1CSWitness>) {
+        let table = &mut self.tables[table.0];
+        assert_eq!(
+            values.len(),
+            table.row_weights.len(),
+            "Queries have a value for each column"
+        );
+        table.queries.push(values);
+        table.origins.push(self.origin);
+    }
+}
+
+/// Add the witnesses and constraints for the LogUp arguments of the lookups.
+/// Tables without queries are not paid for.
+///
+/// The multiplicities of the rows of every table are solved for before the
+/// challenges, which all tables share: `sz`, and `rs` whose powers fold the
+/// values of a row into `v_0 + rs * v_1 + ... + rs^k * v_k`. The check of each
+/// table is then a single constraint, that the sum over its queries of
+/// `1 / (sz - query)` equals the sum over its rows of
+/// `multiplicity / (sz - row)`.
+pub(crate) fn add_lookups(r1cs_compiler: &mut NoirToR1CSCompiler, lookups: Lookups) {
+    let tables = lookups
+        .tables
+        .into_iter()
+        .filter(|table| !table.queries.is_empty())
+        .collect::<Vec<_>>();
+    let Some(num_columns) = tables.iter().map(|table| table.row_weights.len()).max() else {
+        return;
+    };
+    let multiplicities = tables
+        .iter()
+        .map(|table| {
+            r1cs_compiler.add_witness_builder(WitnessBuilder::MultiplicitiesForLookup(
+                r1cs_compiler.num_witnesses(),
+                table.rows.len(),
+                table.row_weights.clone(),
+                table.queries.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    // Add the verifier challenges, and the powers of `rs` for the columns
+    let sz_challenge =
+        r1cs_compiler.add_witness_builder(WitnessBuilder::Challenge(r1cs_compiler.num_witnesses()));
+    let mut rs_powers = vec![r1cs_compiler.witness_one()];
+    if num_columns > 1 {
+        let rs_challenge = r1cs_compiler
+            .add_witness_builder(WitnessBuilder::Challenge(r1cs_compiler.num_witnesses()));
+        rs_powers.push(rs_challenge);
+        while rs_powers.len() < num_columns {
+            let power = r1cs_compiler.add_product(*rs_powers.last().unwrap(), rs_challenge);
+            rs_powers.push(power);
+        }
+    }
+
+    for (table, multiplicities_first_witness) in tables.into_iter().zip(multiplicities) {
+        let mut summands = vec![];
+        for (query, &origin) in table.queries.iter().zip(&table.origins) {
+            let logup = r1cs_compiler.resume_origin(origin);
+            let quotient =
+                add_lookup_quotient(r1cs_compiler, sz_challenge, &rs_powers, None, query);
+            r1cs_compiler.resume_origin(logup);
+            summands.push((FieldElement::one(), quotient));
+        }
+        for (i, row) in table.rows.iter().enumerate() {
+            let quotient = add_lookup_quotient(
+                r1cs_compiler,
+                sz_challenge,
+                &rs_powers,
+                Some(multiplicities_first_witness + i),
+                row,
+            );
+            summands.push((FieldElement::one().neg(), quotient));
+        }
+
+        // Check that the two sums are equal.
+        r1cs_compiler.r1cs.add_constraint(
+            &[(FieldElement::one(), r1cs_compiler.witness_one())],
+            &summands,
+            &[],
+        );
+    }
+}
+
+/// Add and return a witness for `numerator / (sz - (v_0 + rs * v_1 + ...))`,
+/// where the `v_i` are the values of a query or row and the numerator is 1 if
+/// not given, constraining it to be such. A product is added for each witness
+/// value after the first.
+fn add_lookup_quotient(
+    r1cs_compiler: &mut NoirToR1CSCompiler,
+    sz_challenge: usize,
+    rs_powers: &[usize],
+    numerator: Option<usize>,
+    values: &[ConstantOrR1CSWitness],
+) -> usize {
+    // Merge the terms of the denominator by witness
+    let mut denominator = BTreeMap::from([(sz_challenge, FieldElement::one())]);
+    for (value, &rs_power) in values.iter().zip(rs_powers) {
+        let (coeff, witness) = match value {
+            ConstantOrR1CSWitness::Constant(value) => (*value, rs_power),
+            ConstantOrR1CSWitness::Witness(witness) if rs_power == r1cs_compiler.witness_one() => {
+                (FieldElement::one(), *witness)
+            }
+            ConstantOrR1CSWitness::Witness(witness) => (
+                FieldElement::one(),
+                r1cs_compiler.add_product(rs_power, *witness),
+            ),
+        };
+        *denominator.entry(witness).or_default() -= coeff;
+    }
+    let denominator = denominator
+        .into_iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|(witness, coeff)| SumTerm(Some(coeff), witness))
+        .collect::<Vec<_>>();
+
+    let quotient = r1cs_compiler.add_witness_builder(WitnessBuilder::LogUpQuotient(
+        r1cs_compiler.num_witnesses(),
+        numerator,
+        denominator.clone(),
+    ));
+    r1cs_compiler.r1cs.add_constraint(
+        &denominator
+            .iter()
+            .map(|SumTerm(coeff, witness)| (coeff.unwrap(), *witness))
+            .collect::<Vec<_>>(),
+        &[(FieldElement::one(), quotient)],
+        &[(
+            FieldElement::one(),
+            numerator.unwrap_or(r1cs_compiler.witness_one()),
+        )],
+    );
+    quotient
+}
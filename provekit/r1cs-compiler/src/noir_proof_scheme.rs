@@ -1,12 +1,14 @@
 use {
     crate::{
-        noir_to_r1cs, whir_r1cs::WhirR1CSSchemeBuilder,
-        witness_generator::NoirWitnessGeneratorBuilder,
+        noir_to_r1cs, source_map::resolve_source_map, spark::SparkSchemeBuilder,
+        whir_r1cs::WhirR1CSSchemeBuilder, witness_generator::NoirWitnessGeneratorBuilder,
+        CompileError,
     },
-    anyhow::{ensure, Context as _, Result},
+    anyhow::{Context as _, Result},
     noirc_artifacts::program::ProgramArtifact,
     provekit_common::{
-        utils::PrintAbi, witness::NoirWitnessGenerator, NoirProofScheme, WhirR1CSScheme,
+        spark::SparkScheme, utils::PrintAbi, witness::NoirWitnessGenerator, NoirProofScheme,
+        SourceMap, WhirR1CSScheme,
     },
     std::{fs::File, path::Path},
     tracing::{info, instrument},
@@ -17,9 +19,12 @@ pub trait NoirProofSchemeBuilder {
     where
         Self: Sized;
 
-    fn from_program(program: ProgramArtifact) -> Result<Self>
+    fn from_program(program: ProgramArtifact) -> Result<Self, CompileError>
     where
         Self: Sized;
+
+    /// Commit to the R1CS matrices, so that verifiers do not need them.
+    fn commit_to_matrices(&mut self);
 }
 
 impl NoirProofSchemeBuilder for NoirProofScheme {
@@ -28,43 +33,62 @@ impl NoirProofSchemeBuilder for NoirProofScheme {
         let file = File::open(path).context("while opening Noir program")?;
         let program = serde_json::from_reader(file).context("while reading Noir program")?;
 
-        Self::from_program(program)
+        Ok(Self::from_program(program)?)
     }
 
     #[instrument(skip_all)]
-    fn from_program(program: ProgramArtifact) -> Result<Self> {
+    fn from_program(program: ProgramArtifact) -> Result<Self, CompileError> {
         info!("Program noir version: {}", program.noir_version);
         info!("Program entry point: fn main{};", PrintAbi(&program.abi));
-        ensure!(
-            program.bytecode.functions.len() == 1,
-            "Program must have one entry point."
-        );
 
         // Extract bits from Program Artifact.
-        let main = &program.bytecode.functions[0];
+        for (index, function) in program.bytecode.functions.iter().enumerate() {
+            info!(
+                "ACIR function {index}: {} witnesses, {} opcodes.",
+                function.current_witness_index,
+                function.opcodes.len()
+            );
+        }
+
+        // Compile to R1CS schemes, inlining calls between ACIR functions
+        let (r1cs, witness_map, witness_builders, mut source_map, call_frames, product_stats) =
+            noir_to_r1cs(&program.bytecode).map_err(|mut error| {
+                error.resolve_source(&program);
+                error
+            })?;
+        resolve_source_map(&mut source_map, &program);
+        log_profile(&source_map);
         info!(
-            "ACIR: {} witnesses, {} opcodes.",
-            main.current_witness_index,
-            main.opcodes.len()
+            "Saved {} constraints on products: {} reused, {} factored",
+            product_stats.saved(),
+            product_stats.reused,
+            product_stats.factored
         );
-
-        // Compile to R1CS schemes
-        let (r1cs, witness_map, witness_builders) = noir_to_r1cs(main)?;
         info!(
-            "R1CS {} constraints, {} witnesses, A {} entries, B {} entries, C {} entries",
+            "R1CS {} constraints, {} witnesses ({} before challenges), A {} entries, B {} \
+             entries, C {} entries",
             r1cs.num_constraints(),
             r1cs.num_witnesses(),
+            witness_builders.w1_size,
             r1cs.a.num_entries(),
             r1cs.b.num_entries(),
             r1cs.c.num_entries()
         );
 
         // Configure witness generator
-        let witness_generator =
-            NoirWitnessGenerator::new(&program, witness_map, r1cs.num_witnesses());
+        let witness_generator = NoirWitnessGenerator::new(
+            &program,
+            witness_map,
+            r1cs.num_witnesses() + witness_builders.num_eliminated,
+            call_frames,
+        );
 
         // Configure Whir
-        let whir_for_witness = WhirR1CSScheme::new_for_r1cs(&r1cs);
+        let whir_for_witness = WhirR1CSScheme::new_for_r1cs(
+            &r1cs,
+            witness_builders.w1_size,
+            witness_builders.num_challenges(),
+        );
 
         Ok(Self {
             program: program.bytecode,
@@ -72,8 +96,51 @@ impl NoirProofSchemeBuilder for NoirProofScheme {
             witness_builders,
             witness_generator,
             whir_for_witness,
+            source_map: Some(source_map),
         })
     }
+
+    #[instrument(skip_all)]
+    fn commit_to_matrices(&mut self) {
+        let spark = SparkScheme::new_for_r1cs(&self.r1cs, &self.whir_for_witness);
+        info!(
+            "Spark: 2^{} entries per matrix, 2^{} table entries",
+            spark.log_num_entries, spark.log_table_size
+        );
+        self.whir_for_witness.spark = Some(spark);
+    }
+}
+
+/// Number of source lines listed in the constraint profile
+const PROFILE_LINES: usize = 10;
+
+/// Log the numbers of constraints and witnesses of each gadget, ACIR function
+/// and, for the most expensive, Noir source line.
+fn log_profile(source_map: &SourceMap) {
+    for (gadget, (constraints, witnesses)) in source_map.profile(|origin| origin.gadget.clone()) {
+        info!("Gadget {gadget}: {constraints} constraints, {witnesses} witnesses");
+    }
+    for (function, (constraints, witnesses)) in source_map.profile(|origin| origin.function) {
+        info!(
+            "Function {}: {constraints} constraints, {witnesses} witnesses",
+            source_map.function_name(function)
+        );
+    }
+    let mut lines = source_map
+        .profile(|origin| {
+            let source = origin.source.as_ref()?;
+            Some((source.path.clone(), source.line, source.text.clone()))
+        })
+        .into_iter()
+        .filter_map(|(line, counts)| Some((line?, counts)))
+        .collect::<Vec<_>>();
+    lines.sort_by_key(|(_, (constraints, _))| std::cmp::Reverse(*constraints));
+    for ((path, line, text), (constraints, witnesses)) in lines.into_iter().take(PROFILE_LINES) {
+        info!(
+            "{path}:{line}: {constraints} constraints, {witnesses} witnesses: {}",
+            text.trim()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -114,6 +181,23 @@ mod tests {
         test_serde(&proof_schema.witness_builders);
         test_serde(&proof_schema.witness_generator);
         test_serde(&proof_schema.whir_for_witness);
+        test_serde(&proof_schema.source_map);
+    }
+
+    #[test]
+    fn test_noir_proof_scheme_digest() {
+        let path = PathBuf::from("../../tooling/provekit-bench/benches/poseidon_rounds.json");
+        let mut proof_schema = NoirProofScheme::from_file(path).unwrap();
+        let digest = proof_schema.digest().unwrap();
+
+        // Stable under serialization
+        let bin = postcard::to_allocvec(&proof_schema).unwrap();
+        let deserialized: NoirProofScheme = postcard::from_bytes(&bin).unwrap();
+        assert_eq!(deserialized.digest().unwrap(), digest);
+
+        // Sensitive to the circuit
+        std::mem::swap(&mut proof_schema.r1cs.a, &mut proof_schema.r1cs.b);
+        assert_ne!(proof_schema.digest().unwrap(), digest);
     }
 
     #[test]
@@ -1,26 +1,51 @@
 use {
     crate::{
+        aes::AesBuilder,
+        bigint::{
+            add_bigint_from_le_bytes, add_bigint_op, add_bigint_to_le_bytes, BigInt, BigIntOp,
+        },
         binops::{add_binop, BinOp},
+        blake::{add_blake2s, add_blake3},
+        byte_table::{add_byte_table_lookups, ByteTableLookups},
+        ecdsa::{add_ecdsa_verification, SECP256K1, SECP256R1},
+        embedded_curve::{add_embedded_curve_add, add_multi_scalar_mul},
+        error::{AcirLocation, CompileError, OpcodeError},
+        keccak::KeccakBuilder,
+        lookup::{add_lookups, Lookups},
         memory::{add_ram_checking, add_rom_checking, MemoryBlock, MemoryOperation},
+        non_native::NonNativeBuilder,
+        optimize::eliminate_linear_constraints,
+        poseidon2::add_poseidon2_permutation,
         range_check::add_range_checks,
+        sha256::add_sha256_compression,
+        words::{BinOps, WordBuilder},
     },
     acir::{
         circuit::{
             opcodes::{
                 BlackBoxFuncCall, BlockType, ConstantOrWitnessEnum as ConstantOrACIRWitness,
+                FunctionInput,
             },
-            Circuit, Opcode,
+            Circuit, Opcode, Program,
         },
         native_types::{Expression, Witness as NoirWitness},
+        AcirField,
     },
-    anyhow::{bail, Result},
-    ark_std::One,
+    anyhow::{Context as _, Result},
+    ark_std::{One, Zero},
     provekit_common::{
         utils::noir_to_native,
-        witness::{ConstantOrR1CSWitness, ConstantTerm, SumTerm, WitnessBuilder},
-        FieldElement, NoirElement, R1CS,
+        witness::{
+            keccak::NUM_LANES, poseidon2::WIDTH, CallFrame, ConstantOrR1CSWitness, ConstantTerm,
+            SplitWitnessBuilders, SumTerm, WitnessBuilder,
+        },
+        FieldElement, NoirElement, Origin, SourceMap, R1CS,
+    },
+    std::{
+        cmp::Reverse,
+        collections::{BTreeMap, BTreeSet},
+        num::NonZeroU32,
     },
-    std::{collections::BTreeMap, num::NonZeroU32, ops::Neg},
 };
 
 /// Compiles an ACIR circuit into an [R1CS] instance, comprising of the A, B,
@@ -34,22 +59,187 @@ pub(crate) struct NoirToR1CSCompiler {
     /// Maps indices of ACIR witnesses to indices of R1CS witnesses
     acir_to_r1cs_witness_map: BTreeMap<usize, usize>,
 
-    /// The ACIR witness indices of the initial values of the memory blocks
-    pub initial_memories: BTreeMap<usize, Vec<usize>>,
+    /// The ACIR witness indices of the initial values of the memory blocks,
+    /// keyed by the witness offset of the function and the block id
+    pub initial_memories: BTreeMap<(usize, usize), Vec<usize>>,
+
+    /// Offset of the witnesses of the function being compiled. Called
+    /// functions are inlined, each call with a fresh range of ACIR witnesses.
+    witness_offset: usize,
+
+    /// Start of the next unused range of ACIR witnesses
+    next_witness_offset: usize,
+
+    /// The functions being compiled, innermost last
+    call_stack: Vec<u32>,
+
+    /// The inlined function executions, in the order of the witness stack
+    call_frames: Vec<CallFrame>,
+
+    /// The lookup tables and their queries, constrained when finalizing
+    pub(crate) lookups: Lookups,
+
+    /// Mapping the log of the range size k to the vector of witness indices
+    /// that are to be constrained within the range [0..2^k], across all the
+    /// inlined functions. They are planned and constrained when finalizing.
+    pub(crate) range_checks: BTreeMap<u32, Vec<usize>>,
+
+    /// Whether products are reused and factored, as opposed to adding a
+    /// product for every multiplication term
+    reuse_products: bool,
+
+    /// The products of pairs of witnesses, keyed on the sorted operands
+    product_cache: BTreeMap<(usize, usize), usize>,
+
+    /// The product constraints saved by reusing and factoring products
+    product_stats: ProductStats,
+
+    /// The origins of the constraints and witnesses added so far
+    source_map: SourceMap,
+
+    /// The origin of the constraints and witnesses being added
+    origin: usize,
 }
 
-/// Compile a Noir circuit to a R1CS relation, returning the R1CS and a map from
-/// Noir witness indices to R1CS witness indices.
-pub fn noir_to_r1cs(
-    circuit: &Circuit<NoirElement>,
-) -> Result<(R1CS, Vec<Option<NonZeroU32>>, Vec<WitnessBuilder>)> {
+/// The product constraints saved by the compiler, compared to multiplying the
+/// operands of every product separately.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProductStats {
+    /// Products of the same operands reusing an earlier product
+    pub reused:   usize,
+    /// Products replaced by factoring out an operand shared by the terms of an
+    /// assert zero expression
+    pub factored: usize,
+}
+
+impl ProductStats {
+    /// The number of constraints (and witnesses) saved
+    pub const fn saved(&self) -> usize {
+        self.reused + self.factored
+    }
+}
+
+/// The operations of a circuit that are constrained once all its opcodes have
+/// been compiled, the values of its BigInt opcodes, and the witnesses it
+/// asserts to be true.
+#[derive(Default)]
+struct CircuitOps {
+    /// Memory blocks, keyed by their ids
+    memory_blocks:  BTreeMap<usize, MemoryBlock>,
+    /// Mapping the log of the range size k to the vector of witness indices
+    /// that are to be constrained within the range [0..2^k]. They are added to
+    /// the range checks of the program once the circuit has been compiled.
+    range_checks:   BTreeMap<u32, Vec<usize>>,
+    /// (input, input, output) tuples for AND, XOR and AND-NOT operations, keyed
+    /// by the number of bits of their operands. Inputs may be either constants
+    /// or R1CS witnesses. Outputs are always R1CS witnesses.
+    and_ops:        BinOps,
+    xor_ops:        BinOps,
+    and_not_ops:    BinOps,
+    byte_table_ops: ByteTableLookups,
+    /// The values of the BigInt opcodes, keyed by their ids
+    bigints:        BTreeMap<u32, BigInt>,
+    /// The witnesses the circuit asserts to be one
+    asserted_true:  BTreeSet<NoirWitness>,
+}
+
+/// The witnesses asserted to be one by an `AssertZero` opcode of the circuit,
+/// i.e. `k * w - k = 0`.
+fn asserted_true(circuit: &Circuit<NoirElement>) -> BTreeSet<NoirWitness> {
+    circuit
+        .opcodes
+        .iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::AssertZero(expr) => match expr.linear_combinations[..] {
+                [(coeff, witness)] if expr.mul_terms.is_empty() => {
+                    let coeff = noir_to_native(coeff);
+                    (!coeff.is_zero() && noir_to_native(expr.q_c) == -coeff).then_some(witness)
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// The gadget compiling an opcode, as recorded in the source map.
+fn opcode_gadget(opcode: &Opcode<NoirElement>) -> String {
+    match opcode {
+        Opcode::AssertZero(_) => "assert zero".to_owned(),
+        Opcode::BlackBoxFuncCall(black_box_func_call) => black_box_func_call.name().to_owned(),
+        Opcode::MemoryInit { .. } => "memory init".to_owned(),
+        Opcode::MemoryOp { .. } => "memory access".to_owned(),
+        Opcode::BrilligCall { .. } => "Brillig call".to_owned(),
+        Opcode::Call { .. } => "call".to_owned(),
+        _ => "unsupported opcode".to_owned(),
+    }
+}
+
+/// Compile a Noir program to a R1CS relation, returning the R1CS, a map from
+/// Noir witness indices to R1CS witness indices, the origins of the
+/// constraints and witnesses and the layout of the witness stack, together
+/// with the constraints saved on products. Calls to other ACIR functions are
+/// inlined, and the linear constraints are eliminated from the result.
+pub fn noir_to_r1cs(program: &Program<NoirElement>) -> Result<CompiledProgram, CompileError> {
+    compile_program(program, true)
+}
+
+/// Compile a Noir program as [noir_to_r1cs] does, but with a product for
+/// every multiplication term, as a baseline for the products saved.
+pub fn noir_to_r1cs_without_product_reuse(
+    program: &Program<NoirElement>,
+) -> Result<CompiledProgram, CompileError> {
+    compile_program(program, false)
+}
+
+/// The results of [noir_to_r1cs].
+pub type CompiledProgram = (
+    R1CS,
+    Vec<Option<NonZeroU32>>,
+    SplitWitnessBuilders,
+    SourceMap,
+    Vec<CallFrame>,
+    ProductStats,
+);
+
+fn compile_program(
+    program: &Program<NoirElement>,
+    reuse_products: bool,
+) -> Result<CompiledProgram, CompileError> {
+    let main = program.functions.first().ok_or(CompileError::NoFunctions)?;
     let mut compiler = NoirToR1CSCompiler::new();
-    compiler.add_circuit(circuit)?;
-    Ok(compiler.finalize())
+    compiler.reuse_products = reuse_products;
+    compiler.next_witness_offset = main.current_witness_index as usize + 1;
+    compiler.call_stack.push(0);
+    compiler.set_origin(None, "public inputs");
+    compiler.add_public_inputs(main);
+    compiler.add_circuit(&program.functions, main)?;
+    compiler.call_frames.push(CallFrame {
+        function:       0,
+        witness_offset: 0,
+    });
+
+    let call_frames = std::mem::take(&mut compiler.call_frames);
+    let product_stats = compiler.product_stats;
+    let (r1cs, mut witness_map, mut witness_builders, mut source_map) = compiler.finalize();
+    let r1cs = eliminate_linear_constraints(
+        r1cs,
+        &mut witness_map,
+        &mut witness_builders,
+        &mut source_map,
+    );
+    Ok((
+        r1cs,
+        witness_map,
+        witness_builders,
+        source_map,
+        call_frames,
+        product_stats,
+    ))
 }
 
 impl NoirToR1CSCompiler {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         let mut r1cs = R1CS::new();
         // Grow the matrices to account for the constant one witness.
         r1cs.add_witnesses(1);
@@ -66,11 +256,74 @@ impl NoirToR1CSCompiler {
             ))],
             acir_to_r1cs_witness_map: BTreeMap::new(),
             initial_memories: BTreeMap::new(),
+            witness_offset: 0,
+            next_witness_offset: 0,
+            call_stack: Vec::new(),
+            call_frames: Vec::new(),
+            lookups: Lookups::default(),
+            range_checks: BTreeMap::new(),
+            reuse_products: true,
+            product_cache: BTreeMap::new(),
+            product_stats: ProductStats::default(),
+            source_map: SourceMap {
+                origins: vec![Origin {
+                    function: 0,
+                    opcode:   None,
+                    gadget:   "constant one".to_owned(),
+                    source:   None,
+                }],
+                ..SourceMap::default()
+            },
+            origin: 0,
         }
     }
 
-    /// Returns the R1CS and the witness map
-    pub fn finalize(self) -> (R1CS, Vec<Option<NonZeroU32>>, Vec<WitnessBuilder>) {
+    /// Attribute the constraints and witnesses added from now on to a new
+    /// origin in the function being compiled.
+    pub(crate) fn set_origin(&mut self, opcode: Option<usize>, gadget: impl Into<String>) {
+        self.source_map.origins.push(Origin {
+            function: self.call_stack.last().copied().unwrap_or_default(),
+            opcode,
+            gadget: gadget.into(),
+            source: None,
+        });
+        self.resume_origin(self.source_map.origins.len() - 1);
+    }
+
+    /// Attribute the constraints and witnesses added from now on to an
+    /// existing origin, returning the current one.
+    pub(crate) fn resume_origin(&mut self, origin: usize) -> usize {
+        let current = self.origin;
+        self.source_map
+            .constraints
+            .resize(self.r1cs.num_constraints(), current);
+        self.source_map
+            .witnesses
+            .resize(self.num_witnesses(), current);
+        self.origin = origin;
+        self.lookups.origin = origin;
+        current
+    }
+
+    /// Returns the R1CS, the witness map and the source map, after
+    /// constraining the range checks and the lookups. The witnesses are
+    /// reordered so that those that do not depend on any challenge come first.
+    pub fn finalize(
+        mut self,
+    ) -> (
+        R1CS,
+        Vec<Option<NonZeroU32>>,
+        SplitWitnessBuilders,
+        SourceMap,
+    ) {
+        let range_checks = std::mem::take(&mut self.range_checks);
+        add_range_checks(&mut self, range_checks);
+        let lookups = std::mem::take(&mut self.lookups);
+        self.set_origin(None, "LogUp");
+        add_lookups(&mut self, lookups);
+        self.resume_origin(self.origin);
+        let witness_builders = self.split_witness_builders();
+
         // Convert witness map to vector
         let len = self
             .acir_to_r1cs_witness_map
@@ -83,7 +336,76 @@ impl NoirToR1CSCompiler {
             map[acir_witness_idx] =
                 Some(NonZeroU32::new(r1cs_witness_idx as u32).expect("Index zero is reserved"));
         }
-        (self.r1cs, map, self.witness_builders)
+        (self.r1cs, map, witness_builders, self.source_map)
+    }
+
+    /// Partition the witness builders into those that can be solved before
+    /// any challenge is derived and those that (transitively) depend on a
+    /// challenge. Witnesses are renumbered so that the first partition
+    /// occupies a prefix of the witness vector, preserving the relative order
+    /// within each partition.
+    fn split_witness_builders(&mut self) -> SplitWitnessBuilders {
+        // Builders write to consecutive ranges of witnesses, in order.
+        let mut starts = Vec::with_capacity(self.witness_builders.len());
+        let mut next = 0;
+        for builder in &self.witness_builders {
+            starts.push(next);
+            next += builder.num_witnesses();
+        }
+        assert_eq!(next, self.num_witnesses(), "Unaccounted witnesses");
+
+        // Propagate the dependency on challenges.
+        let mut is_w2 = vec![false; self.num_witnesses()];
+        let builder_is_w2 = self
+            .witness_builders
+            .iter()
+            .zip(&starts)
+            .map(|(builder, &start)| {
+                let w2 = matches!(builder, WitnessBuilder::Challenge(_))
+                    || builder.dependencies().iter().any(|&w| is_w2[w]);
+                is_w2[start..start + builder.num_witnesses()].fill(w2);
+                w2
+            })
+            .collect::<Vec<_>>();
+
+        // Stable partition of the witnesses.
+        let w1_size = is_w2.iter().filter(|&&w2| !w2).count();
+        let mut remap = vec![0; self.num_witnesses()];
+        let (mut next_w1, mut next_w2) = (0, w1_size);
+        for (old, &w2) in is_w2.iter().enumerate() {
+            let next = if w2 { &mut next_w2 } else { &mut next_w1 };
+            remap[old] = *next;
+            *next += 1;
+        }
+        debug_assert!(
+            (0..=self.r1cs.num_public_values()).all(|i| remap[i] == i),
+            "Constant one and public values must stay in place"
+        );
+
+        let remap = |old: usize| remap[old];
+        self.r1cs.remap_witnesses(remap);
+        self.source_map.remap_witnesses(remap);
+        self.acir_to_r1cs_witness_map
+            .values_mut()
+            .for_each(|witness| *witness = remap(*witness));
+
+        let mut w1 = vec![];
+        let mut w2 = vec![];
+        for (mut builder, w2_builder) in self.witness_builders.drain(..).zip(builder_is_w2) {
+            builder.remap_witnesses(remap);
+            if w2_builder {
+                w2.push(builder);
+            } else {
+                w1.push(builder);
+            }
+        }
+
+        SplitWitnessBuilders {
+            w1,
+            w2,
+            w1_size,
+            num_eliminated: 0,
+        }
     }
 
     /// Index of the constant one witness
@@ -115,17 +437,38 @@ impl NoirToR1CSCompiler {
     // Return the R1CS witness index corresponding to the AcirWitness provided,
     // creating a new R1CS witness (and builder) if required.
     pub fn fetch_r1cs_witness_index(&mut self, acir_witness_index: NoirWitness) -> usize {
+        let acir_witness_index = self.witness_offset + acir_witness_index.as_usize();
         self.acir_to_r1cs_witness_map
-            .get(&acir_witness_index.as_usize())
+            .get(&acir_witness_index)
             .copied()
             .unwrap_or_else(|| {
                 self.add_witness_builder(WitnessBuilder::Acir(
                     self.num_witnesses(),
-                    acir_witness_index.as_usize(),
+                    acir_witness_index,
                 ))
             })
     }
 
+    // Make the ACIR witness refer to the given R1CS witness, or constrain the
+    // two to be equal if it already has one.
+    fn bind_acir_witness(&mut self, acir_witness_index: NoirWitness, r1cs_witness: usize) {
+        let acir_witness_index = self.witness_offset + acir_witness_index.as_usize();
+        match self.acir_to_r1cs_witness_map.get(&acir_witness_index) {
+            Some(&existing) if existing != r1cs_witness => {
+                self.r1cs.add_constraint(
+                    &[(FieldElement::one(), existing)],
+                    &[(FieldElement::one(), self.witness_one())],
+                    &[(FieldElement::one(), r1cs_witness)],
+                );
+            }
+            Some(_) => {}
+            None => {
+                self.acir_to_r1cs_witness_map
+                    .insert(acir_witness_index, r1cs_witness);
+            }
+        }
+    }
+
     // Convert a ConstantOrACIRWitness into a ConstantOrR1CSWitness, creating a new
     // R1CS witness (and builder) if required.
     fn fetch_constant_or_r1cs_witness(
@@ -144,8 +487,14 @@ impl NoirToR1CSCompiler {
     }
 
     /// Add a new witness representing the product of two existing witnesses,
-    /// and add an R1CS constraint enforcing this.
+    /// and add an R1CS constraint enforcing this. The product of the same
+    /// operands is reused if it was added before.
     pub(crate) fn add_product(&mut self, operand_a: usize, operand_b: usize) -> usize {
+        let key = (operand_a.min(operand_b), operand_a.max(operand_b));
+        if let Some(&product) = self.product_cache.get(&key) {
+            self.product_stats.reused += 1;
+            return product;
+        }
         let product = self.add_witness_builder(WitnessBuilder::Product(
             self.num_witnesses(),
             operand_a,
@@ -156,6 +505,9 @@ impl NoirToR1CSCompiler {
             &[(FieldElement::one(), operand_b)],
             &[(FieldElement::one(), product)],
         );
+        if self.reuse_products {
+            self.product_cache.insert(key, product);
+        }
         product
     }
 
@@ -178,233 +530,1160 @@ impl NoirToR1CSCompiler {
         sum
     }
 
-    /// Add an ACIR assert zero constraint.
+    /// Add an ACIR assert zero constraint. The multiplication terms are grouped
+    /// by a shared operand, which is factored out. One group is constrained
+    /// directly by the assert zero constraint, with the other operands of its
+    /// terms on the B side.
     pub fn add_acir_assert_zero(&mut self, expr: &Expression<NoirElement>) {
-        // Create individual constraints for all the multiplication terms and collect
-        // their outputs
-        let mut linear: Vec<(FieldElement, usize)> = vec![];
-        let mut a: Vec<(FieldElement, usize)> = vec![];
-        let mut b: Vec<(FieldElement, usize)> = vec![];
-
-        if !expr.mul_terms.is_empty() {
-            // Process all except the last multiplication term
-            linear = expr
-                .mul_terms
+        // Merge the multiplication terms with the same operands
+        let mut products = BTreeMap::<(usize, usize), FieldElement>::new();
+        for (coeff, acir_witness_a, acir_witness_b) in &expr.mul_terms {
+            let a = self.fetch_r1cs_witness_index(*acir_witness_a);
+            let b = self.fetch_r1cs_witness_index(*acir_witness_b);
+            *products.entry((a.min(b), a.max(b))).or_default() += noir_to_native(*coeff);
+        }
+        products.retain(|_, coeff| !coeff.is_zero());
+
+        // Greedily factor out the witness shared by the most remaining terms
+        let mut groups: Vec<(usize, Vec<(FieldElement, usize)>)> = vec![];
+        if !self.reuse_products {
+            groups = std::mem::take(&mut products)
+                .into_iter()
+                .map(|((a, b), coeff)| (a, vec![(coeff, b)]))
+                .collect();
+        }
+        while !products.is_empty() {
+            let mut counts = BTreeMap::<usize, usize>::new();
+            for &(a, b) in products.keys() {
+                *counts.entry(a).or_default() += 1;
+                if b != a {
+                    *counts.entry(b).or_default() += 1;
+                }
+            }
+            let (shared, _) = counts
+                .into_iter()
+                .max_by_key(|&(witness, count)| (count, Reverse(witness)))
+                .unwrap();
+            let mut group = vec![];
+            products.retain(|&(a, b), coeff| {
+                let other = match (a == shared, b == shared) {
+                    (true, _) => b,
+                    (false, true) => a,
+                    (false, false) => return true,
+                };
+                group.push((*coeff, other));
+                false
+            });
+            groups.push((shared, group));
+        }
+
+        // The number of products of a group that were not added before
+        let uncached = |compiler: &Self, shared: usize, group: &[(FieldElement, usize)]| {
+            group
                 .iter()
-                .take(expr.mul_terms.len() - 1)
-                .map(|(coeff, acir_witness_a, acir_witness_b)| {
-                    let a = self.fetch_r1cs_witness_index(*acir_witness_a);
-                    let b = self.fetch_r1cs_witness_index(*acir_witness_b);
-                    (-noir_to_native(*coeff), self.add_product(a, b))
+                .filter(|&&(_, other)| {
+                    !compiler
+                        .product_cache
+                        .contains_key(&(other.min(shared), other.max(shared)))
                 })
-                .collect::<Vec<_>>();
-
-            // Handle the last multiplication term directly
-            let (final_coeff, final_acir_witness_a, final_acir_witness_b) =
-                &expr.mul_terms[expr.mul_terms.len() - 1];
-            a = vec![(
-                noir_to_native(*final_coeff),
-                self.fetch_r1cs_witness_index(*final_acir_witness_a),
-            )];
-            b = vec![(
-                FieldElement::one(),
-                self.fetch_r1cs_witness_index(*final_acir_witness_b),
-            )];
-        }
+                .count()
+        };
 
-        // Extend with linear combinations
-        linear.extend(expr.linear_combinations.iter().map(|term| {
-            (
-                noir_to_native(term.0).neg(),
-                self.fetch_r1cs_witness_index(term.1),
-            )
-        }));
+        // Constrain the group with the most new products directly
+        let direct = (0..groups.len())
+            .rev()
+            .max_by_key(|&i| uncached(self, groups[i].0, &groups[i].1))
+            .map(|i| groups.remove(i));
+        let (a, b) = match direct {
+            Some((shared, group)) => {
+                let new_products = uncached(self, shared, &group);
+                self.product_stats.factored += new_products.saturating_sub(1);
+                (vec![(FieldElement::one(), shared)], group)
+            }
+            None => (vec![], vec![]),
+        };
+
+        // Multiply out the other groups, factoring those with more than two new
+        // products into a product with a sum
+        let mut linear = BTreeMap::<usize, FieldElement>::new();
+        for (shared, group) in groups {
+            let new_products = uncached(self, shared, &group);
+            if new_products > 2 {
+                let sum = self.add_sum(
+                    group
+                        .into_iter()
+                        .map(|(coeff, other)| SumTerm(Some(coeff), other))
+                        .collect(),
+                );
+                *linear.entry(self.add_product(shared, sum)).or_default() -= FieldElement::one();
+                self.product_stats.factored += new_products - 2;
+            } else {
+                for (coeff, other) in group {
+                    *linear.entry(self.add_product(shared, other)).or_default() -= coeff;
+                }
+            }
+        }
 
-        // Add constant by multipliying with constant value one.
-        linear.push((noir_to_native(expr.q_c).neg(), self.witness_one()));
+        // Move the linear combinations and the constant to the C side
+        for (coeff, acir_witness) in &expr.linear_combinations {
+            let witness = self.fetch_r1cs_witness_index(*acir_witness);
+            *linear.entry(witness).or_default() -= noir_to_native(*coeff);
+        }
+        *linear.entry(self.witness_one()).or_default() -= noir_to_native(expr.q_c);
+        let linear = linear
+            .into_iter()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .map(|(witness, coeff)| (coeff, witness))
+            .collect::<Vec<_>>();
 
-        // Add a single linear constraint. We could avoid this by substituting
-        // back into the last multiplication constraint.
         self.r1cs.add_constraint(&a, &b, &linear);
     }
 
-    pub fn add_circuit(&mut self, circuit: &Circuit<NoirElement>) -> Result<()> {
-        // Read-only memory blocks (used for building the memory lookup constraints at
-        // the end)
-        let mut memory_blocks: BTreeMap<usize, MemoryBlock> = BTreeMap::new();
-        // Mapping the log of the range size k to the vector of witness indices that
-        // are to be constrained within the range [0..2^k].
-        // These will be digitally decomposed into smaller ranges, if necessary.
-        let mut range_checks: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
-        // (input, input, output) tuples for AND and XOR operations.
-        // Inputs may be either constants or R1CS witnesses.
-        // Outputs are always R1CS witnesses.
-        let mut and_ops = vec![];
-        let mut xor_ops = vec![];
-
-        for opcode in &circuit.opcodes {
-            match opcode {
-                Opcode::AssertZero(expr) => self.add_acir_assert_zero(expr),
-
-                // Brillig is only for witness generation and does not produce constraints.
-                Opcode::BrilligCall { .. } => {}
-
-                Opcode::MemoryInit {
-                    block_id,
-                    init,
-                    block_type,
-                } => {
-                    if *block_type != BlockType::Memory {
-                        panic!("MemoryInit block type must be Memory")
-                    }
-                    let block_id = block_id.0 as usize;
-                    assert!(
-                        !memory_blocks.contains_key(&block_id),
-                        "Memory block {} already initialized",
-                        block_id
-                    );
-                    self.initial_memories
-                        .insert(block_id, init.iter().map(|w| w.0 as usize).collect());
-                    let mut block = MemoryBlock::new();
-                    init.iter().for_each(|acir_witness| {
-                        let r1cs_witness = self.fetch_r1cs_witness_index(*acir_witness);
-                        block.initial_value_witnesses.push(r1cs_witness);
-                    });
-                    memory_blocks.insert(block_id, block);
-                }
-
-                Opcode::MemoryOp {
-                    block_id,
-                    op,
-                    predicate,
-                } => {
-                    // Panic if the predicate is set (according to Noir developers, predicate is
-                    // always None and will soon be removed).
-                    assert!(predicate.is_none());
-
-                    let block_id = block_id.0 as usize;
-                    assert!(
-                        memory_blocks.contains_key(&block_id),
-                        "Memory block {} not initialized before read",
-                        block_id
-                    );
-                    let block = memory_blocks.get_mut(&block_id).unwrap();
-
-                    // `op.index` is _always_ just a single ACIR witness, not a more complicated
-                    // expression, and not a constant. See [here](https://discord.com/channels/1113924620781883405/1356865341065531446)
-                    // Static reads are hard-wired into the circuit, or instead rendered as a
-                    // dummy dynamic read by introducing a new witness constrained to have the value
-                    // of the static address.
-                    let addr = op.index.to_witness().map_or_else(
-                        || {
-                            unimplemented!(
-                                "MemoryOp index must be a single witness, not a more general \
-                                 Expression"
-                            )
-                        },
-                        |acir_witness| self.fetch_r1cs_witness_index(acir_witness),
-                    );
+    /// Lay out the public inputs of the circuit directly after the constant one
+    /// witness, in ascending ACIR witness order, followed by the return values,
+    /// so that the verifier can find them at fixed indices.
+    pub fn add_public_inputs(&mut self, circuit: &Circuit<NoirElement>) {
+        assert_eq!(
+            self.num_witnesses(),
+            1,
+            "Public inputs must be added before any other witness"
+        );
+        for acir_witness in circuit.public_parameters.indices() {
+            self.fetch_r1cs_witness_index(NoirWitness(acir_witness));
+        }
+        self.r1cs.num_public_inputs = self.num_witnesses() - 1;
+        for acir_witness in circuit.return_values.indices() {
+            self.fetch_r1cs_witness_index(NoirWitness(acir_witness));
+        }
+        self.r1cs.num_public_outputs = self.num_witnesses() - 1 - self.r1cs.num_public_inputs;
+    }
 
-                    let op = if op.operation.is_zero() {
-                        // Create a new (as yet unconstrained) witness `result_of_read` for the
-                        // result of the read; it will be constrained by later memory block
-                        // processing.
-                        // "In read operations, [op.value] corresponds to the witness index at which
-                        // the value from memory will be written." (from the Noir codebase)
-                        // At R1CS solving time, only need to map over the value of the
-                        // corresponding ACIR witness, whose value is already determined by the ACIR
-                        // solver.
-                        let result_of_read =
-                            self.fetch_r1cs_witness_index(op.value.to_witness().unwrap());
-                        MemoryOperation::Load(addr, result_of_read)
-                    } else {
-                        let new_value =
-                            self.fetch_r1cs_witness_index(op.value.to_witness().unwrap());
-                        MemoryOperation::Store(addr, new_value)
-                    };
-                    block.operations.push(op);
+    /// Inline a call to another ACIR function. The callee's parameters, in the
+    /// order of their witnesses, are bound to the arguments, and its return
+    /// values to the outputs.
+    fn add_call(
+        &mut self,
+        functions: &[Circuit<NoirElement>],
+        function: u32,
+        inputs: &[NoirWitness],
+        outputs: &[NoirWitness],
+        predicate: Option<&Expression<NoirElement>>,
+    ) -> Result<(), CompileError> {
+        // The ACVM skips calls with a false predicate and sets the outputs to zero
+        if let Some(predicate) = predicate {
+            if !predicate.is_const() {
+                return Err(OpcodeError::CallWitnessPredicate.into());
+            }
+            if noir_to_native(predicate.q_c).is_zero() {
+                for output in outputs {
+                    self.add_acir_assert_zero(&Expression::from(*output));
                 }
+                return Ok(());
+            }
+        }
 
-                Opcode::BlackBoxFuncCall(black_box_func_call) => match black_box_func_call {
-                    BlackBoxFuncCall::RANGE {
-                        input: function_input,
-                    } => {
-                        let input = function_input.input();
-                        let num_bits = function_input.num_bits();
-                        let input_witness = match input {
-                            ConstantOrACIRWitness::Constant(_) => {
-                                panic!(
-                                    "We should never be range-checking a constant value, as this \
-                                     should already be done by the noir-ACIR compiler"
-                                );
-                            }
-                            ConstantOrACIRWitness::Witness(witness) => {
-                                self.fetch_r1cs_witness_index(witness)
-                            }
-                        };
-                        println!(
-                            "RANGE CHECK of witness {} to {} bits",
-                            input_witness, num_bits
-                        );
-                        // Add the entry into the range blocks.
-                        range_checks
-                            .entry(num_bits)
-                            .or_default()
-                            .push(input_witness);
-                    }
+        let callee = functions.get(function as usize).ok_or_else(|| {
+            OpcodeError::UnsupportedCall(format!("Call to unknown function {function}"))
+        })?;
+        if self.call_stack.contains(&function) {
+            return Err(OpcodeError::UnsupportedCall(format!(
+                "Recursive call to function {function} is not supported"
+            ))
+            .into());
+        }
+        let parameters = callee
+            .private_parameters
+            .iter()
+            .chain(&callee.public_parameters.0)
+            .copied()
+            .collect::<BTreeSet<_>>();
+        if inputs.len() != parameters.len() {
+            return Err(OpcodeError::UnsupportedCall(format!(
+                "Call to function {function} has {} inputs, expected {}",
+                inputs.len(),
+                parameters.len()
+            ))
+            .into());
+        }
+        if outputs.len() != callee.return_values.0.len() {
+            return Err(OpcodeError::UnsupportedCall(format!(
+                "Call to function {function} has {} outputs, expected {}",
+                outputs.len(),
+                callee.return_values.0.len()
+            ))
+            .into());
+        }
 
-                    // Binary operations:
-                    // The inputs and outputs will have already been solved for by the ACIR solver.
-                    // Collect the R1CS witnesses indices so that we can later constrain them
-                    // appropriately.
-                    BlackBoxFuncCall::AND { lhs, rhs, output } => {
-                        and_ops.push((
-                            self.fetch_constant_or_r1cs_witness(lhs.input()),
-                            self.fetch_constant_or_r1cs_witness(rhs.input()),
-                            self.fetch_r1cs_witness_index(*output),
-                        ));
-                    }
-                    BlackBoxFuncCall::XOR { lhs, rhs, output } => {
-                        xor_ops.push((
-                            self.fetch_constant_or_r1cs_witness(lhs.input()),
-                            self.fetch_constant_or_r1cs_witness(rhs.input()),
-                            self.fetch_r1cs_witness_index(*output),
-                        ));
-                    }
+        let arguments = inputs
+            .iter()
+            .map(|&input| self.fetch_r1cs_witness_index(input))
+            .collect::<Vec<_>>();
 
-                    _ => {
-                        unimplemented!("Other black box function: {:?}", black_box_func_call);
-                    }
-                },
+        // Compile the callee in a fresh range of ACIR witnesses
+        let caller_offset = self.witness_offset;
+        let callee_offset = self.next_witness_offset;
+        self.next_witness_offset += callee.current_witness_index as usize + 1;
+        self.witness_offset = callee_offset;
+        for (parameter, argument) in parameters.into_iter().zip(arguments) {
+            self.bind_acir_witness(parameter, argument);
+        }
+        let call_origin = self.origin;
+        self.call_stack.push(function);
+        self.add_circuit(functions, callee)?;
+        self.call_stack.pop();
+        self.resume_origin(call_origin);
+        let return_values = callee
+            .return_values
+            .indices()
+            .into_iter()
+            .map(|acir_witness| self.fetch_r1cs_witness_index(NoirWitness(acir_witness)))
+            .collect::<Vec<_>>();
+        self.witness_offset = caller_offset;
 
-                op => bail!("Unsupported Opcode {op}"),
-            }
+        for (&output, return_value) in outputs.iter().zip(return_values) {
+            self.bind_acir_witness(output, return_value);
         }
+        self.call_frames.push(CallFrame {
+            function,
+            witness_offset: callee_offset as u32,
+        });
+        Ok(())
+    }
+
+    pub fn add_circuit(
+        &mut self,
+        functions: &[Circuit<NoirElement>],
+        circuit: &Circuit<NoirElement>,
+    ) -> Result<(), CompileError> {
+        let function = *self
+            .call_stack
+            .last()
+            .expect("Circuits are compiled within a call");
+        let mut ops = CircuitOps {
+            asserted_true: asserted_true(circuit),
+            ..CircuitOps::default()
+        };
+        for (index, opcode) in circuit.opcodes.iter().enumerate() {
+            self.set_origin(Some(index), opcode_gadget(opcode));
+            self.add_opcode(functions, opcode, &mut ops)
+                .map_err(|error| {
+                    error.called_from(AcirLocation {
+                        function,
+                        opcode: index,
+                    })
+                })?;
+        }
+        let CircuitOps {
+            memory_blocks,
+            range_checks,
+            and_ops,
+            xor_ops,
+            and_not_ops,
+            byte_table_ops,
+            ..
+        } = ops;
 
         // For each memory block, add appropriate constraints (depending on whether it
         // is read-only or not)
         memory_blocks.iter().for_each(|(_, block)| {
             if block.is_read_only() {
                 // Use a lookup to enforce that the reads are correct.
+                self.set_origin(None, "ROM lookup");
                 add_rom_checking(self, block);
             } else {
                 // Read/write memory block - use Spice offline memory checking.
                 // Returns witnesses that need to be range checked.
+                self.set_origin(None, "RAM Spice");
                 let (num_bits, witnesses_to_range_check) = add_ram_checking(self, block);
-                let range_check = range_checks.entry(num_bits).or_default();
+                let range_check = self.range_checks.entry(num_bits).or_default();
                 witnesses_to_range_check
                     .iter()
                     .for_each(|value| range_check.push(*value));
             }
         });
 
-        // For the AND and XOR operations, add the appropriate constraints.
+        // For the AND, XOR and AND-NOT operations, add the appropriate constraints.
+        self.set_origin(None, "AND via lookup");
         add_binop(self, BinOp::And, and_ops);
+        self.set_origin(None, "XOR via lookup");
         add_binop(self, BinOp::Xor, xor_ops);
+        self.set_origin(None, "AND-NOT via lookup");
+        add_binop(self, BinOp::AndNot, and_not_ops);
+        self.set_origin(None, "byte table lookup");
+        add_byte_table_lookups(self, byte_table_ops);
+
+        // The range checks of all the functions are planned together
+        for (num_bits, values) in range_checks {
+            self.range_checks
+                .entry(num_bits)
+                .or_default()
+                .extend(values);
+        }
+
+        Ok(())
+    }
+
+    /// Compile an opcode of a circuit. The memory, binary operation and byte
+    /// table constraints are added once the whole circuit has been compiled,
+    /// and the range checks once the whole program has.
+    fn add_opcode(
+        &mut self,
+        functions: &[Circuit<NoirElement>],
+        opcode: &Opcode<NoirElement>,
+        ops: &mut CircuitOps,
+    ) -> Result<(), CompileError> {
+        let CircuitOps {
+            memory_blocks,
+            range_checks,
+            and_ops,
+            xor_ops,
+            and_not_ops,
+            byte_table_ops,
+            bigints,
+            asserted_true,
+        } = ops;
+        match opcode {
+            Opcode::AssertZero(expr) => self.add_acir_assert_zero(expr),
+
+            // Brillig is only for witness generation and does not produce constraints.
+            Opcode::BrilligCall { .. } => {}
+
+            Opcode::MemoryInit {
+                block_id,
+                init,
+                block_type,
+            } => {
+                if *block_type != BlockType::Memory {
+                    return Err(OpcodeError::UnsupportedBlockType(format!("{block_type:?}")).into());
+                }
+                let block_id = block_id.0 as usize;
+                if memory_blocks.contains_key(&block_id) {
+                    return Err(OpcodeError::MemoryBlockReinitialized(block_id).into());
+                }
+                self.initial_memories.insert(
+                    (self.witness_offset, block_id),
+                    init.iter()
+                        .map(|w| self.witness_offset + w.as_usize())
+                        .collect(),
+                );
+                let mut block = MemoryBlock::new();
+                init.iter().for_each(|acir_witness| {
+                    let r1cs_witness = self.fetch_r1cs_witness_index(*acir_witness);
+                    block.initial_value_witnesses.push(r1cs_witness);
+                });
+                memory_blocks.insert(block_id, block);
+            }
+
+            Opcode::MemoryOp {
+                block_id,
+                op,
+                predicate,
+            } => {
+                // According to Noir developers, the predicate is always None and will soon
+                // be removed.
+                if predicate.is_some() {
+                    return Err(OpcodeError::MemoryOpPredicate.into());
+                }
+
+                let block_id = block_id.0 as usize;
+                let block = memory_blocks
+                    .get_mut(&block_id)
+                    .ok_or(OpcodeError::MemoryBlockUninitialized(block_id))?;
+
+                // `op.index` is _always_ just a single ACIR witness, not a more complicated
+                // expression, and not a constant. See [here](https://discord.com/channels/1113924620781883405/1356865341065531446)
+                // Static reads are hard-wired into the circuit, or instead rendered as a
+                // dummy dynamic read by introducing a new witness constrained to have the value
+                // of the static address.
+                let addr = op
+                    .index
+                    .to_witness()
+                    .ok_or(OpcodeError::MemoryOpExpression)?;
+                let addr = self.fetch_r1cs_witness_index(addr);
+
+                let op = if op.operation.is_zero() {
+                    // Create a new (as yet unconstrained) witness `result_of_read` for the
+                    // result of the read; it will be constrained by later memory block
+                    // processing.
+                    // "In read operations, [op.value] corresponds to the witness index at which
+                    // the value from memory will be written." (from the Noir codebase)
+                    // At R1CS solving time, only need to map over the value of the
+                    // corresponding ACIR witness, whose value is already determined by the ACIR
+                    // solver.
+                    let value = op
+                        .value
+                        .to_witness()
+                        .ok_or(OpcodeError::MemoryOpExpression)?;
+                    let result_of_read = self.fetch_r1cs_witness_index(value);
+                    MemoryOperation::Load(addr, result_of_read)
+                } else {
+                    let value = op
+                        .value
+                        .to_witness()
+                        .ok_or(OpcodeError::MemoryOpExpression)?;
+                    let new_value = self.fetch_r1cs_witness_index(value);
+                    MemoryOperation::Store(addr, new_value)
+                };
+                block.operations.push(op);
+            }
+
+            Opcode::BlackBoxFuncCall(black_box_func_call) => match black_box_func_call {
+                BlackBoxFuncCall::RANGE {
+                    input: function_input,
+                } => {
+                    let input = function_input.input();
+                    let num_bits = function_input.num_bits();
+                    let input_witness = match input {
+                        // A constant in range needs no constraint
+                        ConstantOrACIRWitness::Constant(constant) => {
+                            if constant.num_bits() <= num_bits {
+                                return Ok(());
+                            }
+                            return Err(OpcodeError::RangeCheckedConstant(
+                                noir_to_native(constant).to_string(),
+                                num_bits,
+                            )
+                            .into());
+                        }
+                        ConstantOrACIRWitness::Witness(witness) => {
+                            self.fetch_r1cs_witness_index(witness)
+                        }
+                    };
+                    println!(
+                        "RANGE CHECK of witness {} to {} bits",
+                        input_witness, num_bits
+                    );
+                    // Add the entry into the range blocks.
+                    range_checks
+                        .entry(num_bits)
+                        .or_default()
+                        .push(input_witness);
+                }
+
+                // Binary operations:
+                // The inputs and outputs will have already been solved for by the ACIR solver.
+                // Collect the R1CS witnesses indices so that we can later constrain them
+                // appropriately.
+                BlackBoxFuncCall::AND { lhs, rhs, output } => {
+                    and_ops.entry(lhs.num_bits()).or_default().push((
+                        self.fetch_constant_or_r1cs_witness(lhs.input()),
+                        self.fetch_constant_or_r1cs_witness(rhs.input()),
+                        self.fetch_r1cs_witness_index(*output),
+                    ));
+                }
+                BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+                    xor_ops.entry(lhs.num_bits()).or_default().push((
+                        self.fetch_constant_or_r1cs_witness(lhs.input()),
+                        self.fetch_constant_or_r1cs_witness(rhs.input()),
+                        self.fetch_r1cs_witness_index(*output),
+                    ));
+                }
+
+                BlackBoxFuncCall::Sha256Compression {
+                    inputs,
+                    hash_values,
+                    outputs,
+                } => {
+                    let inputs: [_; 16] = std::array::from_fn(|i| {
+                        self.fetch_constant_or_r1cs_witness(inputs[i].input())
+                    });
+                    let hash_values: [_; 8] = std::array::from_fn(|i| {
+                        self.fetch_constant_or_r1cs_witness(hash_values[i].input())
+                    });
+                    let outputs: [_; 8] =
+                        std::array::from_fn(|i| self.fetch_r1cs_witness_index(outputs[i]));
+
+                    let mut words = WordBuilder {
+                        compiler: self,
+                        xor_ops,
+                        range_checks,
+                    };
+                    let inputs = inputs.map(|input| words.input(input));
+                    let hash_values = hash_values.map(|value| words.input(value));
+                    let state = add_sha256_compression(&mut words, &inputs, &hash_values);
+                    for (word, output) in state.into_iter().zip(outputs) {
+                        words.assert_equal(word, output);
+                    }
+                }
+
+                BlackBoxFuncCall::Blake2s { inputs, outputs }
+                | BlackBoxFuncCall::Blake3 { inputs, outputs } => {
+                    let inputs = inputs
+                        .iter()
+                        .map(|input| self.fetch_constant_or_r1cs_witness(input.input()))
+                        .collect::<Vec<_>>();
+                    let outputs = outputs
+                        .iter()
+                        .map(|output| self.fetch_r1cs_witness_index(*output))
+                        .collect::<Vec<_>>();
+
+                    let mut words = WordBuilder {
+                        compiler: self,
+                        xor_ops,
+                        range_checks,
+                    };
+                    let digest = if matches!(black_box_func_call, BlackBoxFuncCall::Blake2s { .. })
+                    {
+                        add_blake2s(&mut words, &inputs)
+                    } else {
+                        add_blake3(&mut words, &inputs)
+                    };
+                    for (word, bytes) in digest.into_iter().zip(outputs.chunks(4)) {
+                        words.assert_le_bytes(word, bytes);
+                    }
+                }
+
+                BlackBoxFuncCall::Poseidon2Permutation {
+                    inputs, outputs, ..
+                } => {
+                    if !(inputs.len() == WIDTH && outputs.len() == WIDTH) {
+                        return Err(OpcodeError::UnsupportedBlackBoxInputs(format!(
+                            "Poseidon2 permutation of width {} is not supported",
+                            inputs.len()
+                        ))
+                        .into());
+                    }
+                    let inputs: [_; WIDTH] = std::array::from_fn(|i| {
+                        self.fetch_constant_or_r1cs_witness(inputs[i].input())
+                    });
+                    let outputs: [_; WIDTH] =
+                        std::array::from_fn(|i| self.fetch_r1cs_witness_index(outputs[i]));
+                    add_poseidon2_permutation(self, inputs, outputs);
+                }
 
-        // Perform all range checks
-        add_range_checks(self, range_checks);
+                BlackBoxFuncCall::Keccakf1600 { inputs, outputs } => {
+                    let inputs: [_; NUM_LANES] = std::array::from_fn(|i| {
+                        self.fetch_constant_or_r1cs_witness(inputs[i].input())
+                    });
+                    let outputs: [_; NUM_LANES] =
+                        std::array::from_fn(|i| self.fetch_r1cs_witness_index(outputs[i]));
+                    KeccakBuilder {
+                        compiler: self,
+                        xor_ops,
+                        and_not_ops,
+                        range_checks,
+                    }
+                    .add_permutation(inputs, outputs);
+                }
+
+                BlackBoxFuncCall::EcdsaSecp256k1 {
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    output,
+                }
+                | BlackBoxFuncCall::EcdsaSecp256r1 {
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    output,
+                } => {
+                    if !(public_key_x.len() == 32
+                        && public_key_y.len() == 32
+                        && signature.len() == 64
+                        && hashed_message.len() == 32)
+                    {
+                        return Err(OpcodeError::UnsupportedBlackBoxInputs(
+                            "ECDSA inputs must be 32-byte values and a 64-byte signature"
+                                .to_owned(),
+                        )
+                        .into());
+                    }
+                    // Only valid signatures can be proven, so the output must be
+                    // asserted to be true.
+                    if !asserted_true.contains(output) {
+                        return Err(OpcodeError::UnassertedEcdsaVerification.into());
+                    }
+                    let curve =
+                        if matches!(black_box_func_call, BlackBoxFuncCall::EcdsaSecp256k1 { .. }) {
+                            &SECP256K1
+                        } else {
+                            &SECP256R1
+                        };
+                    let mut fetch_bytes = |bytes: &[FunctionInput<NoirElement>]| {
+                        bytes
+                            .iter()
+                            .map(|byte| self.fetch_constant_or_r1cs_witness(byte.input()))
+                            .collect::<Vec<_>>()
+                    };
+                    let public_key_x = fetch_bytes(&public_key_x[..]);
+                    let public_key_y = fetch_bytes(&public_key_y[..]);
+                    let signature = fetch_bytes(&signature[..]);
+                    let hashed_message = fetch_bytes(&hashed_message[..]);
+                    let output = self.fetch_r1cs_witness_index(*output);
+                    add_ecdsa_verification(
+                        &mut NonNativeBuilder {
+                            compiler: self,
+                            range_checks,
+                        },
+                        curve,
+                        &public_key_x,
+                        &public_key_y,
+                        &signature,
+                        &hashed_message,
+                        output,
+                    );
+                }
 
+                BlackBoxFuncCall::MultiScalarMul {
+                    points,
+                    scalars,
+                    outputs,
+                } => {
+                    if !(points.len() % 3 == 0
+                        && scalars.len() % 2 == 0
+                        && points.len() / 3 == scalars.len() / 2)
+                    {
+                        return Err(OpcodeError::UnsupportedBlackBoxInputs(format!(
+                            "Multi-scalar multiplication of {} point inputs by {} scalar inputs \
+                             is not supported",
+                            points.len(),
+                            scalars.len()
+                        ))
+                        .into());
+                    }
+                    let points = points
+                        .chunks_exact(3)
+                        .map(|point| {
+                            std::array::from_fn(|i| {
+                                self.fetch_constant_or_r1cs_witness(point[i].input())
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let scalars = scalars
+                        .chunks_exact(2)
+                        .map(|scalar| {
+                            std::array::from_fn(|i| {
+                                self.fetch_constant_or_r1cs_witness(scalar[i].input())
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let outputs = [outputs.0, outputs.1, outputs.2]
+                        .map(|output| self.fetch_r1cs_witness_index(output));
+                    add_multi_scalar_mul(self, &points, &scalars, outputs);
+                }
+
+                BlackBoxFuncCall::EmbeddedCurveAdd {
+                    input1,
+                    input2,
+                    outputs,
+                } => {
+                    let input1 = input1
+                        .each_ref()
+                        .map(|input| self.fetch_constant_or_r1cs_witness(input.input()));
+                    let input2 = input2
+                        .each_ref()
+                        .map(|input| self.fetch_constant_or_r1cs_witness(input.input()));
+                    let outputs = [outputs.0, outputs.1, outputs.2]
+                        .map(|output| self.fetch_r1cs_witness_index(output));
+                    add_embedded_curve_add(self, &input1, &input2, outputs);
+                }
+
+                BlackBoxFuncCall::AES128Encrypt {
+                    inputs,
+                    iv,
+                    key,
+                    outputs,
+                } => {
+                    if outputs.len() != inputs.len() + 16 - inputs.len() % 16 {
+                        return Err(OpcodeError::UnsupportedBlackBoxInputs(format!(
+                            "AES-128 ciphertext of {} bytes does not match a plaintext of {} bytes",
+                            outputs.len(),
+                            inputs.len()
+                        ))
+                        .into());
+                    }
+                    let inputs = inputs
+                        .iter()
+                        .map(|input| self.fetch_constant_or_r1cs_witness(input.input()))
+                        .collect::<Vec<_>>();
+                    let iv: [_; 16] =
+                        std::array::from_fn(|i| self.fetch_constant_or_r1cs_witness(iv[i].input()));
+                    let key: [_; 16] = std::array::from_fn(|i| {
+                        self.fetch_constant_or_r1cs_witness(key[i].input())
+                    });
+                    let outputs = outputs
+                        .iter()
+                        .map(|output| self.fetch_r1cs_witness_index(*output))
+                        .collect::<Vec<_>>();
+                    AesBuilder {
+                        words: WordBuilder {
+                            compiler: self,
+                            xor_ops,
+                            range_checks,
+                        },
+                        byte_table_ops,
+                    }
+                    .add_encryption(&inputs, &iv, &key, &outputs);
+                }
+
+                BlackBoxFuncCall::BigIntFromLeBytes {
+                    inputs,
+                    modulus,
+                    output,
+                } => {
+                    let inputs = inputs
+                        .iter()
+                        .map(|input| self.fetch_constant_or_r1cs_witness(input.input()))
+                        .collect::<Vec<_>>();
+                    let value = add_bigint_from_le_bytes(
+                        &mut NonNativeBuilder {
+                            compiler: self,
+                            range_checks,
+                        },
+                        &inputs,
+                        modulus,
+                    )?;
+                    bigints.insert(*output, value);
+                }
+
+                BlackBoxFuncCall::BigIntAdd { lhs, rhs, output }
+                | BlackBoxFuncCall::BigIntSub { lhs, rhs, output }
+                | BlackBoxFuncCall::BigIntMul { lhs, rhs, output }
+                | BlackBoxFuncCall::BigIntDiv { lhs, rhs, output } => {
+                    let op = match black_box_func_call {
+                        BlackBoxFuncCall::BigIntAdd { .. } => BigIntOp::Add,
+                        BlackBoxFuncCall::BigIntSub { .. } => BigIntOp::Sub,
+                        BlackBoxFuncCall::BigIntMul { .. } => BigIntOp::Mul,
+                        _ => BigIntOp::Div,
+                    };
+                    let [lhs, rhs] = [lhs, rhs].map(|id| {
+                        bigints
+                            .get(id)
+                            .copied()
+                            .with_context(|| format!("BigInt {id} used before it is defined"))
+                    });
+                    let value = add_bigint_op(
+                        &mut NonNativeBuilder {
+                            compiler: self,
+                            range_checks,
+                        },
+                        op,
+                        lhs?,
+                        rhs?,
+                    )?;
+                    bigints.insert(*output, value);
+                }
+
+                BlackBoxFuncCall::BigIntToLeBytes { input, outputs } => {
+                    let value = *bigints
+                        .get(input)
+                        .with_context(|| format!("BigInt {input} used before it is defined"))?;
+                    let outputs = outputs
+                        .iter()
+                        .map(|output| self.fetch_r1cs_witness_index(*output))
+                        .collect::<Vec<_>>();
+                    add_bigint_to_le_bytes(
+                        &mut NonNativeBuilder {
+                            compiler: self,
+                            range_checks,
+                        },
+                        value,
+                        &outputs,
+                    )?;
+                }
+
+                _ => {
+                    return Err(OpcodeError::UnsupportedBlackBox(
+                        black_box_func_call.name().to_owned(),
+                    )
+                    .into());
+                }
+            },
+
+            Opcode::Call {
+                id,
+                inputs,
+                outputs,
+                predicate,
+            } => self.add_call(functions, id.0, inputs, outputs, predicate.as_ref())?,
+
+            op => return Err(OpcodeError::UnsupportedOpcode(op.to_string()).into()),
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{noir_to_r1cs, NoirToR1CSCompiler, ProductStats},
+        crate::{AcirLocation, CompileError, OpcodeError},
+        acir::{
+            circuit::{
+                opcodes::{AcirFunctionId, BlackBoxFuncCall, BlockId, BlockType, FunctionInput},
+                Circuit, Opcode, Program, PublicInputs,
+            },
+            native_types::{Expression, Witness as NoirWitness},
+            AcirField,
+        },
+        ark_std::{One, Zero},
+        provekit_common::{
+            witness::{ConstantOrR1CSWitness, ConstantTerm, SumTerm, WitnessBuilder},
+            FieldElement, NoirElement, Origin,
+        },
+    };
+
+    #[test]
+    fn test_split_witness_builders() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.add_witness_builder(WitnessBuilder::Acir(1, 0));
+        let challenge =
+            compiler.add_witness_builder(WitnessBuilder::Challenge(compiler.num_witnesses()));
+        let _ = compiler.add_sum(vec![SumTerm(None, x), SumTerm(None, challenge)]);
+        let y = compiler.add_witness_builder(WitnessBuilder::Acir(compiler.num_witnesses(), 1));
+        let _ = compiler.add_product(x, y);
+
+        let (r1cs, witness_map, witness_builders, _) = compiler.finalize();
+
+        assert_eq!(witness_builders.w1_size, 4);
+        assert_eq!(witness_builders.num_challenges(), 1);
+        assert_eq!(witness_builders.w1, vec![
+            WitnessBuilder::Constant(ConstantTerm(0, FieldElement::one())),
+            WitnessBuilder::Acir(1, 0),
+            WitnessBuilder::Acir(2, 1),
+            WitnessBuilder::Product(3, 1, 2),
+        ]);
+        assert_eq!(witness_builders.w2, vec![
+            WitnessBuilder::Challenge(4),
+            WitnessBuilder::Sum(5, vec![SumTerm(None, 1), SumTerm(None, 4)]),
+        ]);
+        assert_eq!(
+            witness_map
+                .iter()
+                .map(|w| w.map(|w| w.get()))
+                .collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+
+        // The constraints follow the witnesses
+        let a = r1cs.a();
+        assert_eq!(a.iter_row(0).map(|(col, _)| col).collect::<Vec<_>>(), vec![
+            1, 4
+        ]);
+        assert_eq!(
+            r1cs.c().iter_row(0).map(|(col, _)| col).collect::<Vec<_>>(),
+            vec![5]
+        );
+        assert_eq!(
+            r1cs.c().iter_row(1).map(|(col, _)| col).collect::<Vec<_>>(),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_compile_error_location() {
+        let main = Circuit {
+            opcodes: vec![Opcode::AssertZero(Expression::default()), Opcode::Call {
+                id:        AcirFunctionId(1),
+                inputs:    vec![],
+                outputs:   vec![],
+                predicate: None,
+            }],
+            ..Circuit::default()
+        };
+        let callee = Circuit {
+            opcodes: vec![Opcode::MemoryInit {
+                block_id:   BlockId(0),
+                init:       vec![],
+                block_type: BlockType::CallData(0),
+            }],
+            ..Circuit::default()
+        };
+        let program = Program {
+            functions:               vec![main, callee],
+            unconstrained_functions: vec![],
+        };
+
+        let Err(error) = noir_to_r1cs(&program) else {
+            panic!("Call data blocks are not supported");
+        };
+        let CompileError::Opcode {
+            call_stack, error, ..
+        } = error
+        else {
+            panic!("Error is not located at an opcode");
+        };
+        assert_eq!(call_stack, vec![
+            AcirLocation {
+                function: 0,
+                opcode:   1,
+            },
+            AcirLocation {
+                function: 1,
+                opcode:   0,
+            },
+        ]);
+        assert!(matches!(error, OpcodeError::UnsupportedBlockType(_)));
+    }
+
+    #[test]
+    fn test_call_arity() {
+        let main = Circuit {
+            current_witness_index: 1,
+            opcodes: vec![Opcode::Call {
+                id:        AcirFunctionId(1),
+                inputs:    vec![NoirWitness(0), NoirWitness(1)],
+                outputs:   vec![],
+                predicate: None,
+            }],
+            ..Circuit::default()
+        };
+        let callee = Circuit {
+            current_witness_index: 0,
+            private_parameters: [NoirWitness(0)].into(),
+            ..Circuit::default()
+        };
+        let program = Program {
+            functions:               vec![main, callee],
+            unconstrained_functions: vec![],
+        };
+
+        let Err(CompileError::Opcode { error, .. }) = noir_to_r1cs(&program) else {
+            panic!("The call has more inputs than the callee has parameters");
+        };
+        assert!(matches!(error, OpcodeError::UnsupportedCall(_)));
+    }
+
+    #[test]
+    fn test_call_witness_predicate() {
+        let main = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::Call {
+                id:        AcirFunctionId(1),
+                inputs:    vec![NoirWitness(0)],
+                outputs:   vec![NoirWitness(1)],
+                predicate: Some(Expression::from(NoirWitness(2))),
+            }],
+            ..Circuit::default()
+        };
+        let callee = Circuit {
+            current_witness_index: 0,
+            private_parameters: [NoirWitness(0)].into(),
+            return_values: PublicInputs([NoirWitness(0)].into()),
+            ..Circuit::default()
+        };
+        let program = Program {
+            functions:               vec![main, callee],
+            unconstrained_functions: vec![],
+        };
+
+        let Err(CompileError::Opcode { error, .. }) = noir_to_r1cs(&program) else {
+            panic!("The callee would be constrained when the call is skipped");
+        };
+        assert!(matches!(error, OpcodeError::CallWitnessPredicate));
+    }
+
+    #[test]
+    fn test_unasserted_ecdsa_verification() {
+        let inputs = |offset: u32| {
+            std::array::from_fn(|i| FunctionInput::witness(NoirWitness(offset + i as u32), 8))
+        };
+        let main = Circuit {
+            current_witness_index: 160,
+            opcodes: vec![Opcode::BlackBoxFuncCall(BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x:   Box::new(inputs(0)),
+                public_key_y:   Box::new(inputs(32)),
+                signature:      Box::new(inputs(64)),
+                hashed_message: Box::new(inputs(128)),
+                output:         NoirWitness(160),
+            })],
+            return_values: PublicInputs([NoirWitness(160)].into()),
+            ..Circuit::default()
+        };
+        let program = Program {
+            functions:               vec![main],
+            unconstrained_functions: vec![],
+        };
+
+        // The result is returned rather than asserted
+        let Err(CompileError::Opcode { error, .. }) = noir_to_r1cs(&program) else {
+            panic!("The signature may be invalid");
+        };
+        assert!(matches!(error, OpcodeError::UnassertedEcdsaVerification));
+    }
+
+    #[test]
+    fn test_source_map() {
+        // a * b - c = 0
+        let product = |a, b, c| {
+            Opcode::AssertZero(Expression {
+                mul_terms:           vec![(NoirElement::one(), NoirWitness(a), NoirWitness(b))],
+                linear_combinations: vec![(-NoirElement::one(), NoirWitness(c))],
+                q_c:                 NoirElement::zero(),
+            })
+        };
+        let main = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![product(0, 1, 2), Opcode::Call {
+                id:        AcirFunctionId(1),
+                inputs:    vec![NoirWitness(2)],
+                outputs:   vec![NoirWitness(3)],
+                predicate: None,
+            }],
+            ..Circuit::default()
+        };
+        let callee = Circuit {
+            current_witness_index: 1,
+            opcodes: vec![product(0, 0, 1)],
+            private_parameters: [NoirWitness(0)].into(),
+            return_values: PublicInputs([NoirWitness(1)].into()),
+            ..Circuit::default()
+        };
+        let program = Program {
+            functions:               vec![main, callee],
+            unconstrained_functions: vec![],
+        };
+
+        let (r1cs, _, witness_builders, source_map, ..) = noir_to_r1cs(&program).unwrap();
+
+        assert_eq!(source_map.constraints.len(), r1cs.num_constraints());
+        assert_eq!(
+            source_map.witnesses.len(),
+            r1cs.num_witnesses() + witness_builders.num_eliminated
+        );
+        let assert_zero = |function| Origin {
+            function,
+            opcode: Some(0),
+            gadget: "assert zero".to_owned(),
+            source: None,
+        };
+        assert_eq!(source_map.constraint_origin(0), Some(&assert_zero(0)));
+        assert_eq!(source_map.constraint_origin(1), Some(&assert_zero(1)));
+        assert_eq!(
+            source_map
+                .witnesses
+                .iter()
+                .map(|&origin| source_map.origins[origin].gadget.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "constant one",
+                "assert zero",
+                "assert zero",
+                "assert zero",
+                "assert zero"
+            ]
+        );
+        assert_eq!(source_map.witness_origin(4), Some(&assert_zero(1)));
+    }
+
+    #[test]
+    fn test_products_are_reused_and_factored() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.fetch_r1cs_witness_index(NoirWitness(0));
+        let y = compiler.fetch_r1cs_witness_index(NoirWitness(1));
+        let xy = compiler.add_product(x, y);
+        assert_eq!(compiler.add_product(y, x), xy);
+
+        // x·y + x·z + x·w + y·z + y·w + y·v - u = 0
+        let terms = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (1, 4)];
+        let expr = Expression {
+            mul_terms:           terms
+                .iter()
+                .map(|&(a, b)| (NoirElement::one(), NoirWitness(a), NoirWitness(b)))
+                .collect(),
+            linear_combinations: vec![(-NoirElement::one(), NoirWitness(5))],
+            q_c:                 NoirElement::zero(),
+        };
+        let num_constraints = compiler.r1cs.num_constraints();
+        compiler.add_acir_assert_zero(&expr);
+
+        // y·(x + z + w + v) is constrained directly, and x·z and x·w separately
+        assert_eq!(compiler.r1cs.num_constraints() - num_constraints, 3);
+        assert_eq!(compiler.product_stats, ProductStats {
+            reused:   1,
+            factored: 2,
+        });
+
+        let (r1cs, _, witness_builders, _) = compiler.finalize();
+        let mut values = [2, 3, 5, 7, 11, 0].map(FieldElement::from);
+        values[5] = terms
+            .iter()
+            .map(|&(a, b)| values[a as usize] * values[b as usize])
+            .sum();
+        let mut witness = vec![FieldElement::zero(); r1cs.num_witnesses()];
+        for builder in &witness_builders.w1 {
+            match builder {
+                WitnessBuilder::Constant(ConstantTerm(i, value)) => witness[*i] = *value,
+                WitnessBuilder::Acir(i, acir_witness) => witness[*i] = values[*acir_witness],
+                WitnessBuilder::Product(i, a, b) => witness[*i] = witness[*a] * witness[*b],
+                _ => unreachable!(),
+            }
+        }
+        let a = r1cs.a() * &witness[..];
+        let b = r1cs.b() * &witness[..];
+        let c = r1cs.c() * &witness[..];
+        assert!(a
+            .iter()
+            .zip(&b)
+            .zip(&c)
+            .all(|((a, b), c)| (*a * b - c).is_zero()));
+    }
+
+    #[test]
+    fn test_products_without_reuse() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        compiler.reuse_products = false;
+        let x = compiler.fetch_r1cs_witness_index(NoirWitness(0));
+        let y = compiler.fetch_r1cs_witness_index(NoirWitness(1));
+        assert_ne!(compiler.add_product(x, y), compiler.add_product(y, x));
+
+        // x·y + x·z + x·w + y·z + y·w + y·v - u = 0
+        let terms = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (1, 4)];
+        let expr = Expression {
+            mul_terms:           terms
+                .iter()
+                .map(|&(a, b)| (NoirElement::one(), NoirWitness(a), NoirWitness(b)))
+                .collect(),
+            linear_combinations: vec![(-NoirElement::one(), NoirWitness(5))],
+            q_c:                 NoirElement::zero(),
+        };
+        let num_constraints = compiler.r1cs.num_constraints();
+        compiler.add_acir_assert_zero(&expr);
+
+        // A product for each term but the one constrained directly
+        assert_eq!(compiler.r1cs.num_constraints() - num_constraints, 6);
+        assert_eq!(compiler.product_stats, ProductStats::default());
+    }
+
+    #[test]
+    fn test_lookups_share_challenges() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.add_witness_builder(WitnessBuilder::Acir(1, 0));
+        let y = compiler.add_witness_builder(WitnessBuilder::Acir(2, 1));
+        let range = compiler
+            .lookups
+            .add_fixed_table((0..4u64).map(|v| vec![FieldElement::from(v)]), vec![1]);
+        let squares = compiler.lookups.add_fixed_table(
+            (0..4u64).map(|v| vec![FieldElement::from(v), FieldElement::from(v * v)]),
+            vec![1, 0],
+        );
+        let _unused = compiler
+            .lookups
+            .add_fixed_table((0..8u64).map(|v| vec![FieldElement::from(v)]), vec![1]);
+        compiler
+            .lookups
+            .query(range, vec![ConstantOrR1CSWitness::Witness(x)]);
+        compiler.lookups.query(squares, vec![
+            ConstantOrR1CSWitness::Witness(x),
+            ConstantOrR1CSWitness::Witness(y),
+        ]);
+
+        let (r1cs, _, witness_builders, _) = compiler.finalize();
+
+        // The multiplicities of the tables that are looked up are solved before
+        // the challenges `sz` and `rs`, which both tables use.
+        let multiplicities = witness_builders
+            .w1
+            .iter()
+            .filter(|builder| matches!(builder, WitnessBuilder::MultiplicitiesForLookup(..)))
+            .count();
+        assert_eq!(multiplicities, 2);
+        assert_eq!(witness_builders.num_challenges(), 2);
+        // A quotient for each query and row, the product `rs * y`, and a sum
+        // check for each table
+        assert_eq!(r1cs.num_constraints(), (1 + 4) + (1 + 4) + 1 + 2);
+    }
+}
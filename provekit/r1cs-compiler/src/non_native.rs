@@ -0,0 +1,235 @@
+use {
+    crate::noir_to_r1cs::NoirToR1CSCompiler,
+    ark_ff::Field,
+    ark_std::{One, Zero},
+    provekit_common::{
+        witness::{
+            non_native::{
+                carry_offset, Limbs, NonNativeOperand, NonNativeTerm, CARRY_BITS, LIMB_BITS,
+                NUM_COEFFICIENTS, NUM_QUOTIENT_LIMBS, QUOTIENT_OFFSET,
+            },
+            ConstantOrR1CSWitness, ConstantTerm, NonNativeIdentity, SumTerm, WitnessBuilder,
+        },
+        FieldElement,
+    },
+    ruint::aliases::U256,
+    std::{collections::BTreeMap, ops::Neg},
+};
+
+/// Adds the witnesses and constraints for arithmetic modulo integers of up to
+/// 256 bits other than the native field modulus. Limbs, quotients and carries
+/// are range checked alongside the range checks of the ACIR opcodes, and so by
+/// lookups.
+pub(crate) struct NonNativeBuilder<'a> {
+    pub compiler:     &'a mut NoirToR1CSCompiler,
+    pub range_checks: &'a mut BTreeMap<u32, Vec<usize>>,
+}
+
+impl NonNativeBuilder<'_> {
+    /// Returns the limbs of a constant, as witnesses constrained to the
+    /// constant limbs.
+    pub fn constant(&mut self, value: U256) -> Limbs {
+        value.as_limbs().map(|limb| {
+            let value = FieldElement::from(limb);
+            let witness =
+                self.compiler
+                    .add_witness_builder(WitnessBuilder::Constant(ConstantTerm(
+                        self.compiler.num_witnesses(),
+                        value,
+                    )));
+            self.compiler.r1cs.add_constraint(
+                &[(FieldElement::one(), witness)],
+                &[(FieldElement::one(), self.compiler.witness_one())],
+                &[(value, self.compiler.witness_one())],
+            );
+            witness
+        })
+    }
+
+    /// Returns the limbs of the 256-bit value with the given big-endian
+    /// bytes, range checking the bytes.
+    pub fn pack_be_bytes(&mut self, bytes: &[ConstantOrR1CSWitness]) -> Limbs {
+        assert_eq!(bytes.len(), 32, "A value has 32 bytes");
+        let bytes = bytes.iter().rev().cloned().collect::<Vec<_>>();
+        self.pack_le_bytes(&bytes)
+    }
+
+    /// Returns the limbs of the value with the given little-endian bytes, of
+    /// which there are at most 32, range checking the bytes.
+    pub fn pack_le_bytes(&mut self, bytes: &[ConstantOrR1CSWitness]) -> Limbs {
+        assert!(bytes.len() <= 32, "A value has at most 32 bytes");
+        std::array::from_fn(|i| {
+            let mut constant = FieldElement::zero();
+            let mut summands = vec![];
+            for (k, byte) in bytes.iter().skip(8 * i).take(8).enumerate() {
+                let shift = FieldElement::from(1u64 << (8 * k));
+                match byte {
+                    ConstantOrR1CSWitness::Constant(value) => constant += shift * value,
+                    ConstantOrR1CSWitness::Witness(witness) => {
+                        self.range_checks.entry(8).or_default().push(*witness);
+                        summands.push(SumTerm(Some(shift), *witness));
+                    }
+                }
+            }
+            if !constant.is_zero() {
+                summands.push(SumTerm(Some(constant), self.compiler.witness_one()));
+            }
+            self.compiler.add_sum(summands)
+        })
+    }
+
+    /// Constrains the little-endian bytes of a value, of which there are at
+    /// most 32, range checking the bytes. The value must fit in the bytes.
+    pub fn assert_le_bytes(&mut self, value: Limbs, bytes: &[usize]) {
+        assert!(bytes.len() <= 32, "A value has at most 32 bytes");
+        self.range_checks
+            .entry(8)
+            .or_default()
+            .extend_from_slice(bytes);
+        for (i, limb) in value.into_iter().enumerate() {
+            let mut terms = vec![(FieldElement::one().neg(), limb)];
+            for (k, byte) in bytes.iter().skip(8 * i).take(8).enumerate() {
+                terms.push((FieldElement::from(1u64 << (8 * k)), *byte));
+            }
+            self.compiler.r1cs.add_constraint(
+                &terms,
+                &[(FieldElement::one(), self.compiler.witness_one())],
+                &[],
+            );
+        }
+    }
+
+    /// Add the witnesses and constraints for an identity modulo the given
+    /// modulus, or over the integers if there is none, returning the limbs of
+    /// its result if it has one. The result is range checked, but only
+    /// guaranteed to be less than `2^256`, not reduced.
+    ///
+    /// The identity is the polynomial in the limbs whose coefficients are the
+    /// sums of the products of limbs, minus the quotient times the modulus.
+    /// Each coefficient, with the carry from the previous one, is constrained
+    /// to be the next carry times `2^LIMB_BITS`, and the last carry to be
+    /// zero. As the coefficients are much smaller than the native modulus,
+    /// this shows that the polynomial vanishes at `2^LIMB_BITS` over the
+    /// integers.
+    pub fn add_identity(
+        &mut self,
+        modulus: Option<U256>,
+        terms: Vec<NonNativeTerm>,
+    ) -> Option<Limbs> {
+        let identity = NonNativeIdentity {
+            first_witness_idx: self.compiler.num_witnesses(),
+            modulus: modulus.map(|modulus| *modulus.as_limbs()),
+            terms,
+        };
+        self.compiler
+            .add_witness_builder(WitnessBuilder::NonNativeIdentity(identity.clone()));
+        let result: Option<Limbs> = identity
+            .has_result()
+            .then(|| std::array::from_fn(|i| identity.first_witness_idx + i));
+        let quotient: Option<[usize; NUM_QUOTIENT_LIMBS]> =
+            modulus.map(|_| std::array::from_fn(|i| identity.quotient_start() + i));
+        let carries: [usize; NUM_COEFFICIENTS - 1] =
+            std::array::from_fn(|i| identity.carries_start() + i);
+        self.range_checks
+            .entry(LIMB_BITS)
+            .or_default()
+            .extend(result.iter().flatten().chain(quotient.iter().flatten()));
+        self.range_checks
+            .entry(CARRY_BITS)
+            .or_default()
+            .extend(carries);
+
+        let witness_one = self.compiler.witness_one();
+        let limbs = |operand: &NonNativeOperand| match operand {
+            NonNativeOperand::Limbs(limbs) => *limbs,
+            NonNativeOperand::Result => result.unwrap(),
+        };
+        let mut coefficients: [BTreeMap<usize, FieldElement>; NUM_COEFFICIENTS] =
+            Default::default();
+        let mut add = |k: usize, coefficient: FieldElement, witness: usize| {
+            *coefficients[k].entry(witness).or_default() += coefficient;
+        };
+        // Products of limbs, shared between terms and between the two orders of
+        // their operands.
+        let mut products: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        for term in &identity.terms {
+            match term {
+                NonNativeTerm::Product(c, lhs, rhs) => {
+                    for (i, lhs) in limbs(lhs).into_iter().enumerate() {
+                        for (j, rhs) in limbs(rhs).into_iter().enumerate() {
+                            let product = *products
+                                .entry((lhs.min(rhs), lhs.max(rhs)))
+                                .or_insert_with(|| self.compiler.add_product(lhs, rhs));
+                            add(i + j, FieldElement::from(*c), product);
+                        }
+                    }
+                }
+                NonNativeTerm::Linear(c, operand) => {
+                    for (i, limb) in limbs(operand).into_iter().enumerate() {
+                        add(i, FieldElement::from(*c), limb);
+                    }
+                }
+                NonNativeTerm::Constant(c, constant) => {
+                    for (i, limb) in constant.iter().enumerate() {
+                        add(
+                            i,
+                            FieldElement::from(*c) * FieldElement::from(*limb),
+                            witness_one,
+                        );
+                    }
+                }
+            }
+        }
+        if let (Some(modulus), Some(quotient)) = (modulus, quotient) {
+            for (j, modulus_limb) in modulus.as_limbs().iter().enumerate() {
+                let modulus_limb = FieldElement::from(*modulus_limb);
+                add(
+                    NUM_QUOTIENT_LIMBS - 1 + j,
+                    FieldElement::from(QUOTIENT_OFFSET) * modulus_limb,
+                    witness_one,
+                );
+                for (i, quotient_limb) in quotient.into_iter().enumerate() {
+                    add(i + j, modulus_limb.neg(), quotient_limb);
+                }
+            }
+        }
+
+        // coefficient + carry_in - 2^LIMB_BITS * carry_out = 0, with offset carries
+        let shift = FieldElement::from(2u64).pow([u64::from(LIMB_BITS)]);
+        for (k, mut coefficient) in coefficients.into_iter().enumerate() {
+            if k > 0 {
+                *coefficient.entry(carries[k - 1]).or_default() += FieldElement::one();
+                *coefficient.entry(witness_one).or_default() -= carry_offset();
+            }
+            if k < NUM_COEFFICIENTS - 1 {
+                *coefficient.entry(carries[k]).or_default() -= shift;
+                *coefficient.entry(witness_one).or_default() += shift * carry_offset();
+            }
+            self.compiler.r1cs.add_constraint(
+                &coefficient
+                    .into_iter()
+                    .filter(|(_, coeff)| !coeff.is_zero())
+                    .map(|(witness, coeff)| (coeff, witness))
+                    .collect::<Vec<_>>(),
+                &[(FieldElement::one(), witness_one)],
+                &[],
+            );
+        }
+        result
+    }
+
+    /// Constrains a value to be less than a bound, by showing that
+    /// `bound - 1 - value` has range checked limbs.
+    pub fn assert_less_than(&mut self, value: Limbs, bound: U256) {
+        self.add_identity(None, vec![
+            NonNativeTerm::Linear(1, NonNativeOperand::Limbs(value)),
+            NonNativeTerm::Linear(1, NonNativeOperand::Result),
+            NonNativeTerm::Constant(-1, *(bound - U256::from(1u64)).as_limbs()),
+        ]);
+    }
+}
+
+/// Limbs as an operand
+pub(crate) const fn operand(limbs: Limbs) -> NonNativeOperand {
+    NonNativeOperand::Limbs(limbs)
+}
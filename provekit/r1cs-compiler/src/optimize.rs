@@ -0,0 +1,281 @@
+use {
+    ark_ff::Field,
+    ark_std::Zero,
+    provekit_common::{
+        witness::{SplitWitnessBuilders, WitnessBuilder},
+        FieldElement, SourceMap, R1CS,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet, VecDeque},
+        num::NonZeroU32,
+    },
+    tracing::info,
+};
+
+/// Largest number of entries that eliminating a witness may add to the other
+/// constraints, as estimated by the Markowitz count
+const MAX_FILL_IN: usize = 32;
+
+/// The entries of a constraint in the A, B and C matrices
+type Row = [BTreeMap<usize, FieldElement>; 3];
+
+/// Eliminate the linear constraints of an R1CS instance, i.e. those where the
+/// A or B side is a multiple of the constant one witness. Each eliminated
+/// constraint defines one of its witnesses as a linear combination of the
+/// others, which is substituted into every other constraint using it.
+///
+/// The eliminated witnesses are removed from the R1CS instance and moved after
+/// its witnesses, where their builders still solve them as inputs to other
+/// builders. Only witnesses written by single-witness builders are
+/// eliminated, so that the witnesses of a builder stay contiguous. The
+/// constant one witness, the public values and the challenges are kept.
+pub(crate) fn eliminate_linear_constraints(
+    r1cs: R1CS,
+    witness_map: &mut [Option<NonZeroU32>],
+    witness_builders: &mut SplitWitnessBuilders,
+    source_map: &mut SourceMap,
+) -> R1CS {
+    let num_witnesses = r1cs.num_witnesses();
+    let mut rows = (0..r1cs.num_constraints())
+        .map(|row| {
+            Some([r1cs.a(), r1cs.b(), r1cs.c()].map(|matrix| matrix.iter_row(row).collect()))
+        })
+        .collect::<Vec<Option<Row>>>();
+
+    // The constraints using each witness
+    let mut uses = vec![BTreeSet::new(); num_witnesses];
+    for (row, entries) in rows.iter().enumerate() {
+        for col in columns(entries.as_ref().unwrap()) {
+            uses[col].insert(row);
+        }
+    }
+
+    // Witnesses written by single-witness builders, other than the constant
+    // one witness, the public values and the challenges
+    assert_eq!(
+        witness_builders.num_eliminated, 0,
+        "Linear constraints were already eliminated"
+    );
+    let mut eliminable = vec![false; num_witnesses];
+    let mut next = 0;
+    for builder in witness_builders.iter() {
+        if builder.num_witnesses() == 1 && !matches!(builder, WitnessBuilder::Challenge(_)) {
+            eliminable[next] = next > r1cs.num_public_values();
+        }
+        next += builder.num_witnesses();
+    }
+    assert_eq!(next, num_witnesses, "Unaccounted witnesses");
+
+    let mut eliminated = vec![false; num_witnesses];
+    let mut queue = (0..rows.len()).collect::<VecDeque<_>>();
+    while let Some(row) = queue.pop_front() {
+        let Some(relation) = rows[row].as_ref().and_then(linear_relation) else {
+            continue;
+        };
+
+        // Pick the witness whose elimination adds the fewest entries
+        let Some((fill_in, witness)) = relation
+            .keys()
+            .filter(|&&w| eliminable[w])
+            .map(|&w| ((uses[w].len() - 1) * (relation.len() - 1), w))
+            .min_by_key(|&(fill_in, w)| (fill_in, std::cmp::Reverse(w)))
+        else {
+            continue;
+        };
+        if fill_in > MAX_FILL_IN {
+            continue;
+        }
+
+        // witness = Σ -k_i / k_w · w_i
+        let scale = -relation[&witness].inverse().unwrap();
+        let definition = relation
+            .iter()
+            .filter(|(&w, _)| w != witness)
+            .map(|(&w, &k)| (w, k * scale))
+            .collect::<Vec<_>>();
+
+        for col in columns(rows[row].as_ref().unwrap()) {
+            uses[col].remove(&row);
+        }
+        rows[row] = None;
+
+        for other in std::mem::take(&mut uses[witness]) {
+            let entries = rows[other].as_mut().unwrap();
+            for col in columns(entries) {
+                uses[col].remove(&other);
+            }
+            for matrix in entries.iter_mut() {
+                let Some(coeff) = matrix.remove(&witness) else {
+                    continue;
+                };
+                for &(w, k) in &definition {
+                    let entry = matrix.entry(w).or_insert_with(FieldElement::zero);
+                    *entry += coeff * k;
+                    if entry.is_zero() {
+                        matrix.remove(&w);
+                    }
+                }
+            }
+            for col in columns(entries) {
+                uses[col].insert(other);
+            }
+            queue.push_back(other);
+        }
+        eliminated[witness] = true;
+    }
+
+    // Keep the remaining witnesses in order, followed by the eliminated ones
+    let num_kept = eliminated.iter().filter(|&&e| !e).count();
+    let mut remap = vec![0; num_witnesses];
+    let (mut next_kept, mut next_eliminated) = (0, num_kept);
+    for (old, &e) in eliminated.iter().enumerate() {
+        let next = if e {
+            &mut next_eliminated
+        } else {
+            &mut next_kept
+        };
+        remap[old] = *next;
+        *next += 1;
+    }
+    let remap = |old: usize| remap[old];
+
+    let mut optimized = R1CS::new();
+    optimized.num_public_inputs = r1cs.num_public_inputs;
+    optimized.num_public_outputs = r1cs.num_public_outputs;
+    optimized.add_witnesses(num_kept);
+    let remap_entries = |matrix: &BTreeMap<usize, FieldElement>| {
+        matrix
+            .iter()
+            .map(|(&w, &k)| (k, remap(w)))
+            .collect::<Vec<_>>()
+    };
+    for [a, b, c] in rows.iter().flatten() {
+        optimized.add_constraint(&remap_entries(a), &remap_entries(b), &remap_entries(c));
+    }
+
+    let num_eliminated = num_witnesses - num_kept;
+    witness_builders.w1_size = eliminated[..witness_builders.w1_size]
+        .iter()
+        .filter(|&&e| !e)
+        .count();
+    witness_builders.num_eliminated = num_eliminated;
+    witness_builders
+        .w1
+        .iter_mut()
+        .chain(&mut witness_builders.w2)
+        .for_each(|builder| builder.remap_witnesses(remap));
+    source_map.retain_constraints(|row| rows[row].is_some());
+    source_map.remap_witnesses(remap);
+    witness_map.iter_mut().flatten().for_each(|witness| {
+        *witness =
+            NonZeroU32::new(remap(witness.get() as usize) as u32).expect("Index zero is reserved");
+    });
+
+    info!("Eliminated {num_eliminated} linear constraints and witnesses");
+    optimized
+}
+
+/// The witnesses used by a constraint
+fn columns(row: &Row) -> BTreeSet<usize> {
+    row.iter()
+        .flat_map(|matrix| matrix.keys().copied())
+        .collect()
+}
+
+/// The coefficients `k` of a constraint equivalent to `Σ k_i · w_i = 0`, if
+/// it is linear.
+fn linear_relation([a, b, c]: &Row) -> Option<BTreeMap<usize, FieldElement>> {
+    let constant = |matrix: &BTreeMap<usize, FieldElement>| {
+        matrix
+            .keys()
+            .all(|&w| w == 0)
+            .then(|| matrix.get(&0).copied().unwrap_or_default())
+    };
+    let (scale, linear) = match (constant(a), constant(b)) {
+        (_, Some(scale)) => (scale, a),
+        (Some(scale), None) => (scale, b),
+        (None, None) => return None,
+    };
+    let mut relation = BTreeMap::new();
+    for (&w, &k) in linear {
+        *relation.entry(w).or_insert_with(FieldElement::zero) += scale * k;
+    }
+    for (&w, &k) in c {
+        *relation.entry(w).or_insert_with(FieldElement::zero) -= k;
+    }
+    relation.retain(|_, k| !k.is_zero());
+    Some(relation)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::eliminate_linear_constraints,
+        crate::noir_to_r1cs::NoirToR1CSCompiler,
+        ark_std::{One, Zero},
+        provekit_common::{
+            witness::{SumTerm, WitnessBuilder},
+            FieldElement,
+        },
+    };
+
+    #[test]
+    fn test_eliminate_linear_constraints() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let x = compiler.add_witness_builder(WitnessBuilder::Acir(1, 0));
+        compiler.r1cs.num_public_inputs = 1;
+        let y = compiler.add_witness_builder(WitnessBuilder::Acir(compiler.num_witnesses(), 1));
+        let challenge =
+            compiler.add_witness_builder(WitnessBuilder::Challenge(compiler.num_witnesses()));
+        let _ = compiler.add_sum(vec![SumTerm(None, x), SumTerm(None, challenge)]);
+        let sum = compiler.add_sum(vec![SumTerm(None, x), SumTerm(None, y)]);
+        let product = compiler.add_product(sum, sum);
+        let _ = compiler.add_product(product, y);
+
+        let (r1cs, mut witness_map, mut witness_builders, mut source_map) = compiler.finalize();
+        assert_eq!(r1cs.num_constraints(), 4);
+        assert_eq!(r1cs.num_witnesses(), 8);
+        let r1cs = eliminate_linear_constraints(
+            r1cs,
+            &mut witness_map,
+            &mut witness_builders,
+            &mut source_map,
+        );
+
+        // The two sums are eliminated, and the products are kept
+        assert_eq!(r1cs.num_constraints(), 2);
+        assert_eq!(r1cs.num_witnesses(), 6);
+        assert_eq!(witness_builders.num_eliminated, 2);
+        assert_eq!(witness_builders.w1_size, 5);
+        assert_eq!(witness_builders.num_challenges(), 1);
+        assert!(witness_builders.w2.contains(&WitnessBuilder::Challenge(5)));
+        assert_eq!(source_map.constraints.len(), 2);
+        assert_eq!(source_map.witnesses.len(), 8);
+        assert_eq!(
+            witness_map
+                .iter()
+                .map(|w| w.map(|w| w.get()))
+                .collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+
+        // The remaining constraints are satisfied by the remaining witnesses
+        let (x, y) = (FieldElement::from(3), FieldElement::from(5));
+        let witness = [
+            FieldElement::one(),
+            x,
+            y,
+            (x + y) * (x + y),
+            (x + y) * (x + y) * y,
+            FieldElement::from(7),
+        ];
+        let a = r1cs.a() * &witness[..];
+        let b = r1cs.b() * &witness[..];
+        let c = r1cs.c() * &witness[..];
+        assert!(a
+            .iter()
+            .zip(&b)
+            .zip(&c)
+            .all(|((a, b), c)| (*a * b - c).is_zero()));
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    crate::noir_to_r1cs::NoirToR1CSCompiler,
+    ark_std::{One, Zero},
+    provekit_common::{
+        witness::{
+            poseidon2::{
+                rounds, Round, EXTERNAL_MATRIX, INTERNAL_DIAGONAL, WIDTH, WITNESSES_PER_SBOX,
+            },
+            ConstantOrR1CSWitness, Poseidon2Witnesses, WitnessBuilder,
+        },
+        FieldElement,
+    },
+    std::collections::BTreeMap,
+};
+
+/// A linear combination of witnesses, keyed by witness index. Constants are
+/// coefficients of the constant one witness.
+#[derive(Clone, Debug, Default)]
+struct LinearCombination(BTreeMap<usize, FieldElement>);
+
+impl LinearCombination {
+    fn add_term(&mut self, coeff: FieldElement, witness: usize) {
+        *self.0.entry(witness).or_insert(FieldElement::zero()) += coeff;
+    }
+
+    fn add_scaled(&mut self, other: &Self, scale: FieldElement) {
+        for (&witness, &coeff) in &other.0 {
+            self.add_term(scale * coeff, witness);
+        }
+    }
+
+    /// The terms with a non-zero coefficient, as a row of an R1CS matrix.
+    fn terms(&self) -> Vec<(FieldElement, usize)> {
+        self.0
+            .iter()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .map(|(&witness, &coeff)| (coeff, witness))
+            .collect()
+    }
+}
+
+fn external_layer(state: &[LinearCombination; WIDTH]) -> [LinearCombination; WIDTH] {
+    EXTERNAL_MATRIX.map(|row| {
+        let mut lane = LinearCombination::default();
+        for (&coeff, other) in row.iter().zip(state) {
+            lane.add_scaled(other, FieldElement::from(coeff));
+        }
+        lane
+    })
+}
+
+fn internal_layer(state: &mut [LinearCombination; WIDTH]) {
+    let mut sum = LinearCombination::default();
+    for lane in state.iter() {
+        sum.add_scaled(lane, FieldElement::one());
+    }
+    for (lane, diagonal) in state.iter_mut().zip(INTERNAL_DIAGONAL) {
+        let mut new_lane = sum.clone();
+        new_lane.add_scaled(lane, diagonal);
+        *lane = new_lane;
+    }
+}
+
+/// Add the witnesses and constraints for the Poseidon2 permutation of the
+/// inputs, constraining its result to equal the outputs.
+///
+/// Each S-box `x^5` takes three constraints, `x * x = x^2`, `x^2 * x^2 = x^4`
+/// and `x^4 * x = x^5`. The linear layers and round constants are not given
+/// constraints of their own: the state is tracked as linear combinations of
+/// the S-box outputs, which are folded into the rows of the next S-box. This
+/// trades denser rows in the partial rounds for fewer constraints and
+/// witnesses.
+pub(crate) fn add_poseidon2_permutation(
+    compiler: &mut NoirToR1CSCompiler,
+    inputs: [ConstantOrR1CSWitness; WIDTH],
+    outputs: [usize; WIDTH],
+) {
+    let witness_one = compiler.witness_one();
+    let mut next_witness =
+        compiler.add_witness_builder(WitnessBuilder::Poseidon2(Poseidon2Witnesses {
+            first_witness_idx: compiler.num_witnesses(),
+            inputs:            inputs.clone(),
+        }));
+    let mut sbox = |compiler: &mut NoirToR1CSCompiler, lane: &mut LinearCombination| {
+        let [x2, x4, x5]: [usize; WITNESSES_PER_SBOX] = std::array::from_fn(|i| next_witness + i);
+        next_witness += WITNESSES_PER_SBOX;
+        let x = lane.terms();
+        let one = FieldElement::one();
+        compiler.r1cs.add_constraint(&x, &x, &[(one, x2)]);
+        compiler
+            .r1cs
+            .add_constraint(&[(one, x2)], &[(one, x2)], &[(one, x4)]);
+        compiler.r1cs.add_constraint(&[(one, x4)], &x, &[(one, x5)]);
+        *lane = LinearCombination::default();
+        lane.add_term(one, x5);
+    };
+
+    let state = inputs.map(|input| {
+        let (coeff, witness) = input.to_tuple();
+        let mut lane = LinearCombination::default();
+        lane.add_term(coeff, witness);
+        lane
+    });
+    let mut state = external_layer(&state);
+    for round in rounds() {
+        match round {
+            Round::Full(constants) => {
+                for (lane, constant) in state.iter_mut().zip(constants) {
+                    lane.add_term(constant, witness_one);
+                    sbox(compiler, lane);
+                }
+                state = external_layer(&state);
+            }
+            Round::Partial(constant) => {
+                state[0].add_term(constant, witness_one);
+                sbox(compiler, &mut state[0]);
+                internal_layer(&mut state);
+            }
+        }
+    }
+
+    for (lane, output) in state.iter().zip(outputs) {
+        compiler
+            .r1cs
+            .add_constraint(&lane.terms(), &[(FieldElement::one(), witness_one)], &[(
+                FieldElement::one(),
+                output,
+            )]);
+    }
+}
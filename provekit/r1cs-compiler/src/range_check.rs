@@ -1,196 +1,327 @@
 use {
     crate::{
-        digits::{add_digital_decomposition, DigitalDecompositionWitnessesBuilder},
+        digits::{add_digital_decomposition, log_bases_for, DigitalDecompositionWitnessesBuilder},
+        lookup::{SharedTable, TableId},
         noir_to_r1cs::NoirToR1CSCompiler,
     },
     ark_std::{One, Zero},
     provekit_common::{
-        witness::{ProductLinearTerm, SumTerm, WitnessBuilder, WitnessCoefficient},
+        witness::{ConstantOrR1CSWitness, ProductLinearTerm, SumTerm, WitnessBuilder},
         FieldElement,
     },
-    std::{collections::BTreeMap, ops::Neg},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        ops::{Add, AddAssign, Neg},
+    },
+    tracing::info,
 };
 
-const NUM_WITNESS_THRESHOLD_FOR_LOOKUP_TABLE: usize = 5;
-pub const NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP: u32 = 8;
+/// Largest number of bits of the digits of decompositions, whose lookup table
+/// has `2^MAX_DIGIT_BITS` rows
+const MAX_DIGIT_BITS: u32 = 16;
+
+/// Numbers of witnesses and constraints added to the R1CS instance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Cost {
+    pub witnesses:   usize,
+    pub constraints: usize,
+}
+
+impl Cost {
+    const fn new(witnesses: usize, constraints: usize) -> Self {
+        Self {
+            witnesses,
+            constraints,
+        }
+    }
+
+    /// The measure by which strategies are compared
+    pub const fn total(self) -> usize {
+        self.witnesses + self.constraints
+    }
+
+    const fn times(self, count: usize) -> Self {
+        Self::new(self.witnesses * count, self.constraints * count)
+    }
+}
+
+impl Add for Cost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.witnesses + other.witnesses,
+            self.constraints + other.constraints,
+        )
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// How the values of a number of bits, including the digits of
+/// decompositions, are range checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BucketCheck {
+    /// A product that vanishes exactly on the range, for each value
+    Naive,
+    /// Lookups in a table of the range
+    Lookup,
+    /// Lookups of each value and of its multiple by `2^(digit_bits -
+    /// num_bits)` in the table of the digits, which holds for the values of
+    /// the range only
+    Scaled,
+}
+
+/// A strategy for the range checks of a circuit, with the numbers of
+/// witnesses and constraints that it adds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RangeCheckPlan {
+    /// Values of more bits are decomposed into digits of this many bits, the
+    /// last of which may be narrower
+    pub digit_bits: u32,
+    /// The check of the values of each number of bits, after decomposition
+    pub buckets:    BTreeMap<u32, BucketCheck>,
+    pub cost:       Cost,
+}
+
+impl RangeCheckPlan {
+    /// The numbers of bits of the range tables that the plan looks up
+    pub fn tables(&self) -> BTreeSet<u32> {
+        self.buckets
+            .iter()
+            .filter_map(|(&num_bits, check)| match check {
+                BucketCheck::Naive => None,
+                BucketCheck::Lookup => Some(num_bits),
+                BucketCheck::Scaled => Some(self.digit_bits),
+            })
+            .collect()
+    }
+}
+
+/// Chooses the strategy of least cost for range checks of the given numbers
+/// of values of each number of bits, over every size of digits up to
+/// [MAX_DIGIT_BITS]. The range tables of the numbers of bits in `registered`
+/// are already paid for.
+pub(crate) fn plan_range_checks(
+    counts: &BTreeMap<u32, usize>,
+    registered: &BTreeSet<u32>,
+) -> RangeCheckPlan {
+    (1..=MAX_DIGIT_BITS)
+        .map(|digit_bits| plan_with_digit_bits(counts, digit_bits, registered))
+        .min_by_key(|plan| plan.cost.total())
+        .unwrap()
+}
+
+/// Chooses the strategy of least cost for range checks of the given numbers
+/// of values of each number of bits, decomposing values of more than
+/// `digit_bits` bits. Each number of bits is checked naively or by a table of
+/// its own, or, if the table of the digits is shared, by scaling. Each table
+/// is paid for once, unless it is in `registered`.
+pub(crate) fn plan_with_digit_bits(
+    counts: &BTreeMap<u32, usize>,
+    digit_bits: u32,
+    registered: &BTreeSet<u32>,
+) -> RangeCheckPlan {
+    let table_cost = |num_bits: u32| {
+        if registered.contains(&num_bits) {
+            Cost::default()
+        } else {
+            lookup_table_cost(num_bits)
+        }
+    };
+    let mut decomposition = Cost::default();
+    let mut atomic_counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for (&num_bits, &count) in counts.iter().filter(|(_, count)| **count > 0) {
+        if num_bits <= digit_bits {
+            *atomic_counts.entry(num_bits).or_default() += count;
+            continue;
+        }
+        let log_bases = log_bases_for(num_bits as usize, digit_bits as usize);
+        decomposition += Cost::new(log_bases.len(), 1).times(count);
+        for log_base in log_bases {
+            *atomic_counts.entry(log_base as u32).or_default() += count;
+        }
+    }
+
+    let plan = |share_digit_table: bool| {
+        let mut cost = decomposition;
+        if share_digit_table {
+            cost += table_cost(digit_bits);
+        }
+        let buckets = atomic_counts
+            .iter()
+            .map(|(&num_bits, &count)| {
+                let mut options = vec![
+                    (
+                        BucketCheck::Naive,
+                        naive_range_check_cost(num_bits).times(count),
+                    ),
+                    (
+                        BucketCheck::Lookup,
+                        table_cost(num_bits) + LOOKUP_COST.times(count),
+                    ),
+                ];
+                if share_digit_table && num_bits == digit_bits {
+                    options = vec![(BucketCheck::Lookup, LOOKUP_COST.times(count))];
+                } else if share_digit_table {
+                    options.push((BucketCheck::Scaled, SCALED_LOOKUP_COST.times(count)));
+                }
+                let (check, check_cost) = options
+                    .into_iter()
+                    .min_by_key(|(_, cost)| cost.total())
+                    .unwrap();
+                cost += check_cost;
+                (num_bits, check)
+            })
+            .collect();
+        RangeCheckPlan {
+            digit_bits,
+            buckets,
+            cost,
+        }
+    };
+    [plan(false), plan(true)]
+        .into_iter()
+        .min_by_key(|plan| plan.cost.total())
+        .unwrap()
+}
+
+/// The cost of a lookup in a range table: its quotient
+const LOOKUP_COST: Cost = Cost::new(1, 1);
+
+/// The cost of a scaled lookup: the scaled value, and two lookups
+const SCALED_LOOKUP_COST: Cost = Cost::new(3, 3);
+
+/// The cost of a table of the values of `num_bits` bits: the multiplicity and
+/// quotient of each row, and the check of the sums.
+const fn lookup_table_cost(num_bits: u32) -> Cost {
+    Cost::new(2 << num_bits, (1 << num_bits) + 1)
+}
+
+/// The cost of [add_naive_range_check], a product of `2^num_bits` factors
+const fn naive_range_check_cost(num_bits: u32) -> Cost {
+    let witnesses = (1usize << num_bits).saturating_sub(2);
+    Cost::new(witnesses, witnesses + 1)
+}
 
 /// Add witnesses and constraints that ensure that the values of the witness
-/// belong to a range 0..2^k (for some k). If k is larger than
-/// `NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP`, then a digital decomposition is
-/// performed: witnesses are allocated for the digits of the decomposition, a
+/// belong to a range 0..2^k (for some k), following the [RangeCheckPlan] of
+/// least cost. Values of more bits than the digits of the plan are digitally
+/// decomposed: witnesses are allocated for the digits of the decomposition, a
 /// constraint is added that enforces the correctness of the digital
 /// decomposition, and then the digits themselves are range checked.
 /// `range_checks` is a map from the number of bits k to the vector of witness
-/// indices that are to be constrained within the range [0..2^k].
+/// indices that are to be constrained within the range [0..2^k], for the
+/// whole program, so that each table is planned and paid for once.
 pub(crate) fn add_range_checks(
     r1cs: &mut NoirToR1CSCompiler,
     range_checks: BTreeMap<u32, Vec<usize>>,
-) {
-    // Do a pass through everything that needs to be range checked,
-    // decomposing each value into digits that are at most
-    // [NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP] and creating a map
-    // `atomic_range_blocks` of each `num_bits` from 1 to the
-    // NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP (inclusive) to the vec of witness
-    // indices that are constrained to that range.
+) -> RangeCheckPlan {
+    let counts: BTreeMap<u32, usize> = range_checks
+        .iter()
+        .map(|(&num_bits, values)| (num_bits, values.len()))
+        .collect();
+    let registered: BTreeSet<u32> = (1..=MAX_DIGIT_BITS)
+        .filter(|&num_bits| r1cs.lookups.has_shared_table(SharedTable::Range(num_bits)))
+        .collect();
+    let plan = plan_range_checks(&counts, &registered);
+    if range_checks.values().any(|values| !values.is_empty()) {
+        info!(
+            "Range checks: {} values, {}-bit digits, range tables of {:?} bits ({:?} shared), {} \
+             witnesses and {} constraints",
+            counts.values().sum::<usize>(),
+            plan.digit_bits,
+            plan.tables(),
+            plan.tables().intersection(&registered).collect::<Vec<_>>(),
+            plan.cost.witnesses,
+            plan.cost.constraints
+        );
+    }
 
     // Mapping the log of the range size k to the vector of witness indices that
     // are to be constrained within the range [0..2^k].
     // The witnesses of all small range op codes are added to this map, along with
     // witnesses of digits for digital decompositions of larger range checks.
-    let mut atomic_range_checks: Vec<Vec<Vec<usize>>> =
-        vec![vec![vec![]]; NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP as usize + 1];
+    let mut atomic_range_checks: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (num_bits, values_to_lookup) in range_checks {
+        if values_to_lookup.is_empty() {
+            continue;
+        }
+        if num_bits <= plan.digit_bits {
+            atomic_range_checks
+                .entry(num_bits)
+                .or_default()
+                .extend(values_to_lookup);
+            continue;
+        }
+        r1cs.set_origin(
+            None,
+            format!("range check {num_bits}-bit via decomposition"),
+        );
+        let log_bases = log_bases_for(num_bits as usize, plan.digit_bits as usize);
+        let dd_struct = add_digital_decomposition(r1cs, log_bases, values_to_lookup);
 
-    range_checks
-        .into_iter()
-        .for_each(|(num_bits, values_to_lookup)| {
-            if num_bits > NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP {
-                let num_big_digits = num_bits / NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP;
-                let logbase_of_remainder_digit = num_bits % NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP;
-                let mut log_bases =
-                    vec![NUM_BITS_THRESHOLD_FOR_DIGITAL_DECOMP as usize; num_big_digits as usize];
-                if logbase_of_remainder_digit != 0 {
-                    log_bases.push(logbase_of_remainder_digit as usize);
-                }
-                let dd_struct =
-                    add_digital_decomposition(r1cs, log_bases.clone(), values_to_lookup.clone());
-
-                // Add the witness indices for the digits to the atomic range checks
-                dd_struct
-                    .log_bases
-                    .iter()
-                    .enumerate()
-                    .map(|(digit_place, log_base)| {
-                        (
-                            *log_base as u32,
-                            (0..dd_struct.num_witnesses_to_decompose)
-                                .map(|i| dd_struct.get_digit_witness_index(digit_place, i))
-                                .collect::<Vec<_>>(),
-                        )
-                    })
-                    .for_each(|(log_base, digit_witnesses)| {
-                        atomic_range_checks[log_base as usize].push(digit_witnesses);
-                    });
-            } else {
-                atomic_range_checks[num_bits as usize].push(values_to_lookup);
-            }
-        });
+        // Add the witness indices for the digits to the atomic range checks
+        for (digit_place, log_base) in dd_struct.log_bases.iter().enumerate() {
+            atomic_range_checks
+                .entry(*log_base as u32)
+                .or_default()
+                .extend(
+                    (0..dd_struct.num_witnesses_to_decompose)
+                        .map(|i| dd_struct.get_digit_witness_index(digit_place, i)),
+                );
+        }
+    }
 
     // For each of the atomic range checks, add the range check constraints.
-    // Use logup if the range is large; otherwise use a naive range check.
-    atomic_range_checks
-        .iter()
-        .enumerate()
-        .for_each(|(num_bits, all_values_to_lookup)| {
-            let values_to_lookup = all_values_to_lookup
-                .iter()
-                .flat_map(|v| v.iter())
-                .copied()
-                .collect::<Vec<_>>();
-            if values_to_lookup.len() > NUM_WITNESS_THRESHOLD_FOR_LOOKUP_TABLE {
-                add_range_check_via_lookup(r1cs, num_bits as u32, &values_to_lookup);
-            } else {
-                values_to_lookup.iter().for_each(|value| {
-                    add_naive_range_check(r1cs, num_bits as u32, *value);
-                })
+    for (num_bits, values_to_lookup) in atomic_range_checks {
+        match plan.buckets[&num_bits] {
+            BucketCheck::Naive => {
+                r1cs.set_origin(None, format!("range check {num_bits}-bit naively"));
+                for value in values_to_lookup {
+                    add_naive_range_check(r1cs, num_bits, value);
+                }
             }
-        });
+            BucketCheck::Lookup => {
+                r1cs.set_origin(None, format!("range check {num_bits}-bit via lookup"));
+                let table = range_table(r1cs, num_bits);
+                for value in values_to_lookup {
+                    r1cs.lookups
+                        .query(table, vec![ConstantOrR1CSWitness::Witness(value)]);
+                }
+            }
+            BucketCheck::Scaled => {
+                r1cs.set_origin(
+                    None,
+                    format!("range check {num_bits}-bit via scaled lookup"),
+                );
+                let table = range_table(r1cs, plan.digit_bits);
+                let scale = FieldElement::from(1u64 << (plan.digit_bits - num_bits));
+                for value in values_to_lookup {
+                    let scaled = r1cs.add_sum(vec![SumTerm(Some(scale), value)]);
+                    for value in [value, scaled] {
+                        r1cs.lookups
+                            .query(table, vec![ConstantOrR1CSWitness::Witness(value)]);
+                    }
+                }
+            }
+        }
+    }
+    plan
 }
 
-/// Helper function which computes all the terms of the summation for
-/// each side (LHS and RHS) of the log-derivative multiset check.
-///
-/// Checks that both sums (LHS and RHS) are equal at the end.
-fn add_range_check_via_lookup(
-    r1cs_compiler: &mut NoirToR1CSCompiler,
-    num_bits: u32,
-    values_to_lookup: &[usize],
-) {
-    // Add witnesses for the multiplicities
-    let wb = WitnessBuilder::MultiplicitiesForRange(
-        r1cs_compiler.num_witnesses(),
-        1 << num_bits,
-        values_to_lookup.into(),
-    );
-    let multiplicities_first_witness = r1cs_compiler.add_witness_builder(wb);
-    // Sample the Schwartz-Zippel challenge for the log derivative
-    // multiset check.
-    let sz_challenge =
-        r1cs_compiler.add_witness_builder(WitnessBuilder::Challenge(r1cs_compiler.num_witnesses()));
-
-    // Compute all the terms in the summation for multiplicity/(X - table_value)
-    // for each table value.
-    let table_summands = (0..(1 << num_bits))
-        .map(|table_value| {
-            let table_denom = add_lookup_factor(
-                r1cs_compiler,
-                sz_challenge,
-                FieldElement::from(table_value as u64),
-                r1cs_compiler.witness_one(),
-            );
-            let multiplicity_witness = multiplicities_first_witness + table_value;
-            SumTerm(
-                None,
-                r1cs_compiler.add_product(table_denom, multiplicity_witness),
-            )
-        })
-        .collect();
-    let sum_for_table = r1cs_compiler.add_sum(table_summands);
-    // Compute all the terms in the summation for 1/(X - witness_value) for each
-    // witness value.
-    let witness_summands = values_to_lookup
-        .iter()
-        .map(|value| {
-            let witness_idx =
-                add_lookup_factor(r1cs_compiler, sz_challenge, FieldElement::one(), *value);
-            SumTerm(None, witness_idx)
-        })
-        .collect();
-    let sum_for_witness = r1cs_compiler.add_sum(witness_summands);
-    // Check that these two sums are equal.
-    r1cs_compiler.r1cs.add_constraint(
-        &[
-            (FieldElement::one(), sum_for_table),
-            (FieldElement::one().neg(), sum_for_witness),
-        ],
-        &[(FieldElement::one(), r1cs_compiler.witness_one())],
-        &[(FieldElement::zero(), r1cs_compiler.witness_one())],
-    );
-}
-
-/// Helper function that computes the LogUp denominator either for
-/// the table values: (X - t_j), or for the witness values:
-/// (X - w_i). Computes the inverse and also checks that this is
-/// the appropriate inverse.
-pub(crate) fn add_lookup_factor(
-    r1cs_compiler: &mut NoirToR1CSCompiler,
-    sz_challenge: usize,
-    value_coeff: FieldElement,
-    value_witness: usize,
-) -> usize {
-    let denom_wb = WitnessBuilder::LogUpDenominator(
-        r1cs_compiler.num_witnesses(),
-        sz_challenge,
-        WitnessCoefficient(value_coeff, value_witness),
-    );
-    let denominator = r1cs_compiler.add_witness_builder(denom_wb);
-    r1cs_compiler.r1cs.add_constraint(
-        &[
-            (FieldElement::one(), sz_challenge),
-            (FieldElement::one().neg() * value_coeff, value_witness),
-        ],
-        &[(FieldElement::one(), r1cs_compiler.witness_one())],
-        &[(FieldElement::one(), denominator)],
-    );
-    let inverse = r1cs_compiler.add_witness_builder(WitnessBuilder::Inverse(
-        r1cs_compiler.num_witnesses(),
-        denominator,
-    ));
-    r1cs_compiler.r1cs.add_constraint(
-        &[(FieldElement::one(), denominator)],
-        &[(FieldElement::one(), inverse)],
-        &[(FieldElement::one(), r1cs_compiler.witness_one())],
-    );
-    inverse
+/// The lookup table of the `2^num_bits` values in the range, shared by all
+/// the functions of the program.
+fn range_table(r1cs_compiler: &mut NoirToR1CSCompiler, num_bits: u32) -> TableId {
+    r1cs_compiler.lookups.add_shared_table(
+        SharedTable::Range(num_bits),
+        || (0..1u64 << num_bits).map(|value| vec![FieldElement::from(value)]),
+        vec![1],
+    )
 }
 
 /// A naive range check helper function, computing the
@@ -240,3 +371,124 @@ fn add_naive_range_check(
         &[(FieldElement::zero(), r1cs_compiler.witness_one())],
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{add_range_checks, plan_range_checks, plan_with_digit_bits, BucketCheck},
+        crate::noir_to_r1cs::NoirToR1CSCompiler,
+        provekit_common::witness::WitnessBuilder,
+        std::collections::{BTreeMap, BTreeSet},
+    };
+
+    #[test]
+    fn test_plan_depends_on_range_checks() {
+        // Many 16-bit values share a table of 8-bit digits
+        let many_u16 = plan_range_checks(&BTreeMap::from([(16, 10_000)]), &BTreeSet::new());
+        assert_eq!(many_u16.digit_bits, 8);
+        assert_eq!(many_u16.buckets[&8], BucketCheck::Lookup);
+
+        // A few 64-bit values are better off with a smaller table
+        let few_u64 = plan_range_checks(&BTreeMap::from([(64, 3)]), &BTreeSet::new());
+        assert!(few_u64.digit_bits < 8);
+        assert!(
+            few_u64.cost.total()
+                < plan_with_digit_bits(&BTreeMap::from([(64, 3)]), 8, &BTreeSet::new())
+                    .cost
+                    .total()
+        );
+
+        // A single small value is checked naively, without any table
+        let one_u2 = plan_range_checks(&BTreeMap::from([(2, 1)]), &BTreeSet::new());
+        assert!(one_u2
+            .buckets
+            .values()
+            .all(|&check| check == BucketCheck::Naive));
+    }
+
+    #[test]
+    fn test_plan_cost_matches_circuit() {
+        let counts = BTreeMap::from([(3, 2), (5, 40), (12, 30), (40, 3)]);
+        let mut compiler = NoirToR1CSCompiler::new();
+        let mut next_acir_witness = 0;
+        let range_checks = counts
+            .iter()
+            .map(|(&num_bits, &count)| {
+                let values = (0..count)
+                    .map(|_| {
+                        next_acir_witness += 1;
+                        compiler.add_witness_builder(WitnessBuilder::Acir(
+                            compiler.num_witnesses(),
+                            next_acir_witness,
+                        ))
+                    })
+                    .collect();
+                (num_bits, values)
+            })
+            .collect();
+        let num_witnesses = compiler.num_witnesses();
+
+        let plan = add_range_checks(&mut compiler, range_checks);
+        assert_eq!(plan, plan_range_checks(&counts, &BTreeSet::new()));
+        assert!(plan
+            .buckets
+            .values()
+            .any(|&check| check != BucketCheck::Naive));
+        let (r1cs, ..) = compiler.finalize();
+
+        // The lookups also add their challenge
+        assert_eq!(
+            r1cs.num_witnesses(),
+            num_witnesses + plan.cost.witnesses + 1
+        );
+        assert_eq!(r1cs.num_constraints(), plan.cost.constraints);
+    }
+
+    #[test]
+    fn test_registered_tables_are_free() {
+        let counts = BTreeMap::from([(16, 3)]);
+        let unregistered = plan_range_checks(&counts, &BTreeSet::new());
+        assert!(!unregistered.tables().contains(&8));
+
+        // A table of 8-bit values registered by another gadget
+        let registered = plan_range_checks(&counts, &BTreeSet::from([8]));
+        assert_eq!(registered.tables(), BTreeSet::from([8]));
+        assert!(registered.cost.total() < unregistered.cost.total());
+    }
+
+    #[test]
+    fn test_range_checks_of_calls_are_planned_together() {
+        let mut compiler = NoirToR1CSCompiler::new();
+        let mut next_acir_witness = 0;
+        // As if each were the range checks of an inlined call
+        for _ in 0..2 {
+            let values = (0..1000)
+                .map(|_| {
+                    next_acir_witness += 1;
+                    compiler.add_witness_builder(WitnessBuilder::Acir(
+                        compiler.num_witnesses(),
+                        next_acir_witness,
+                    ))
+                })
+                .collect::<Vec<_>>();
+            compiler.range_checks.entry(8).or_default().extend(values);
+        }
+        let num_witnesses = compiler.num_witnesses();
+        let plan = plan_range_checks(&BTreeMap::from([(8, 2000)]), &BTreeSet::new());
+        assert_eq!(plan.tables(), BTreeSet::from([8]));
+        let (r1cs, _, witness_builders, _) = compiler.finalize();
+
+        // A single table, paid for once, and the challenge of the lookups
+        let tables = witness_builders
+            .w1
+            .iter()
+            .filter(|builder| matches!(builder, WitnessBuilder::MultiplicitiesForLookup(..)))
+            .count();
+        assert_eq!(tables, 1);
+        assert_eq!(
+            r1cs.num_witnesses(),
+            num_witnesses + plan.cost.witnesses + 1
+        );
+        assert_eq!(r1cs.num_constraints(), plan.cost.constraints);
+    }
+}
@@ -0,0 +1,311 @@
+use {
+    crate::{
+        digits::{add_digital_decomposition, DigitalDecompositionWitnessesBuilder},
+        lookup::SharedTable,
+        words::{
+            piece_positions,
+            Shift::{self, RotateRight, ShiftRight},
+            WordBuilder, WORD_BITS,
+        },
+    },
+    ark_std::Zero,
+    provekit_common::{
+        witness::{ConstantOrR1CSWitness, SumTerm, WitnessBuilder},
+        FieldElement,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Number of bits of the digits that words are split into for lookups
+const DIGIT_BITS: u32 = 4;
+
+/// Number of digits of a word
+const NUM_DIGITS: usize = (WORD_BITS / DIGIT_BITS) as usize;
+
+/// The SHA-256 round constants
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Add the witnesses and constraints for the SHA-256 compression function,
+/// applied to a 16-word message block and an 8-word state. Returns the
+/// witnesses of the new state.
+///
+/// Words are split into digits of [DIGIT_BITS] bits, once each. The choose
+/// and majority functions are looked up digit by digit, and rotations and
+/// shifts are linear combinations of the digits and of their looked up right
+/// shifts. The XORs of the rotations are bin ops, and the sums are range
+/// checked.
+pub(crate) fn add_sha256_compression(
+    words: &mut WordBuilder,
+    inputs: &[usize; 16],
+    hash_values: &[usize; 8],
+) -> [usize; 8] {
+    let mut sha256 = Sha256Builder {
+        words,
+        digits: BTreeMap::new(),
+        shifted_digits: BTreeMap::new(),
+    };
+
+    // Message schedule
+    let mut schedule = inputs.to_vec();
+    for t in 16..64 {
+        let [r7, r18, s3] = sha256.shifts(schedule[t - 15], [
+            RotateRight(7),
+            RotateRight(18),
+            ShiftRight(3),
+        ]);
+        let sigma0 = sha256.words.xor(r7, r18);
+        let sigma0 = sha256.words.xor(sigma0, s3);
+        let [r17, r19, s10] = sha256.shifts(schedule[t - 2], [
+            RotateRight(17),
+            RotateRight(19),
+            ShiftRight(10),
+        ]);
+        let sigma1 = sha256.words.xor(r17, r19);
+        let sigma1 = sha256.words.xor(sigma1, s10);
+        let word = sha256
+            .words
+            .add(&[sigma1, schedule[t - 7], sigma0, schedule[t - 16]], 0);
+        schedule.push(word);
+    }
+
+    // Rounds
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *hash_values;
+    for (&round_constant, &word) in ROUND_CONSTANTS.iter().zip(&schedule) {
+        let [r6, r11, r25] = sha256.shifts(e, [RotateRight(6), RotateRight(11), RotateRight(25)]);
+        let big_sigma1 = sha256.words.xor(r6, r11);
+        let big_sigma1 = sha256.words.xor(big_sigma1, r25);
+        let ch = sha256.choose(e, f, g);
+
+        let [r2, r13, r22] = sha256.shifts(a, [RotateRight(2), RotateRight(13), RotateRight(22)]);
+        let big_sigma0 = sha256.words.xor(r2, r13);
+        let big_sigma0 = sha256.words.xor(big_sigma0, r22);
+        let maj = sha256.majority(a, b, c);
+
+        // temp1 = h + big_sigma1 + ch + k + w, temp2 = big_sigma0 + maj
+        let temp1 = [h, big_sigma1, ch, word];
+        let new_e = sha256
+            .words
+            .add(&[&[d][..], &temp1].concat(), round_constant);
+        let new_a = sha256
+            .words
+            .add(&[&temp1[..], &[big_sigma0, maj]].concat(), round_constant);
+
+        h = g;
+        g = f;
+        f = e;
+        e = new_e;
+        d = c;
+        c = b;
+        b = a;
+        a = new_a;
+    }
+
+    let state = [a, b, c, d, e, f, g, h];
+    std::array::from_fn(|i| sha256.words.add(&[hash_values[i], state[i]], 0))
+}
+
+/// Adds the lookups of the SHA-256 gadget, keeping the digits of each word and
+/// the right shifts of each digit, as most words are rotated or passed to the
+/// choose and majority functions more than once.
+struct Sha256Builder<'a, 'b> {
+    words:          &'a mut WordBuilder<'b>,
+    /// The digits of each word, least significant first
+    digits:         BTreeMap<usize, [usize; NUM_DIGITS]>,
+    /// The right shift of each digit by each number of bits
+    shifted_digits: BTreeMap<(usize, u32), usize>,
+}
+
+impl Sha256Builder<'_, '_> {
+    /// The digits of a word, least significant first. They are not range
+    /// checked: each is looked up in a table of digits by every caller.
+    fn digits(&mut self, word: usize) -> [usize; NUM_DIGITS] {
+        if let Some(&digits) = self.digits.get(&word) {
+            return digits;
+        }
+        let dd_struct = add_digital_decomposition(
+            self.words.compiler,
+            vec![DIGIT_BITS as usize; NUM_DIGITS],
+            vec![word],
+        );
+        let digits =
+            std::array::from_fn(|digit_place| dd_struct.get_digit_witness_index(digit_place, 0));
+        self.digits.insert(word, digits);
+        digits
+    }
+
+    /// `ch(e, f, g) = (e & f) ^ (!e & g)`
+    fn choose(&mut self, e: usize, f: usize, g: usize) -> usize {
+        self.digitwise(
+            SharedTable::Sha256Choose,
+            WitnessBuilder::Sha256Choose,
+            |e, f, g| (e & f) ^ (!e & g),
+            [e, f, g],
+        )
+    }
+
+    /// `maj(a, b, c) = (a & b) ^ (a & c) ^ (b & c)`
+    fn majority(&mut self, a: usize, b: usize, c: usize) -> usize {
+        self.digitwise(
+            SharedTable::Sha256Majority,
+            WitnessBuilder::Sha256Majority,
+            |a, b, c| (a & b) ^ (a & c) ^ (b & c),
+            [a, b, c],
+        )
+    }
+
+    /// A bitwise function of three words, whose digits are looked up in the
+    /// table of the function on every triple of digits. The result is the sum
+    /// of the output digits, so it needs no range check.
+    fn digitwise(
+        &mut self,
+        kind: SharedTable,
+        builder: fn(usize, usize, usize, usize) -> WitnessBuilder,
+        apply: fn(u64, u64, u64) -> u64,
+        operands: [usize; 3],
+    ) -> usize {
+        let [x, y, z] = operands.map(|word| self.digits(word));
+        let compiler = &mut *self.words.compiler;
+        let table = compiler.lookups.add_shared_table(
+            kind,
+            move || {
+                let mask = (1 << DIGIT_BITS) - 1;
+                (0..1u64 << (3 * DIGIT_BITS)).map(move |row| {
+                    let [x, y, z] = [2, 1, 0].map(|i| (row >> (i * DIGIT_BITS)) & mask);
+                    [x, y, z, apply(x, y, z)].map(FieldElement::from).to_vec()
+                })
+            },
+            vec![1 << (2 * DIGIT_BITS), 1 << DIGIT_BITS, 1, 0],
+        );
+        let mut summands = vec![];
+        for (i, ((x, y), z)) in x.into_iter().zip(y).zip(z).enumerate() {
+            let output = compiler.add_witness_builder(builder(compiler.num_witnesses(), x, y, z));
+            compiler.lookups.query(
+                table,
+                [x, y, z, output]
+                    .map(ConstantOrR1CSWitness::Witness)
+                    .to_vec(),
+            );
+            summands.push(SumTerm(
+                Some(FieldElement::from(1u64 << (i as u32 * DIGIT_BITS))),
+                output,
+            ));
+        }
+        compiler.add_sum(summands)
+    }
+
+    /// Rotations and shifts of a word. Each digit is split into a low and a
+    /// high part at the shift amount modulo [DIGIT_BITS], the high part being
+    /// its looked up right shift, so that each shift is a linear combination
+    /// of the digits and their shifts. Every shift of SHA-256 is by an amount
+    /// that is not a multiple of [DIGIT_BITS], so each digit is looked up.
+    fn shifts<const N: usize>(&mut self, word: usize, shifts: [Shift; N]) -> [usize; N] {
+        let digits = self.digits(word);
+        shifts.map(|shift| {
+            let (RotateRight(amount) | ShiftRight(amount)) = shift;
+            let num_bits = amount % DIGIT_BITS;
+            assert_ne!(num_bits, 0, "Shift {shift:?} splits no digit");
+            let mut summands = vec![];
+            for (i, &digit) in digits.iter().enumerate() {
+                let start = i as u32 * DIGIT_BITS;
+                let high = self.shifted_digit(digit, num_bits);
+                let [low_position, high_position] = digit_part_positions(start, num_bits, shift);
+
+                // digit = low + high * 2^num_bits, so low is moved to its position
+                // by the digit, and high by its own term less its part of the digit
+                summands.push(SumTerm(Some(low_position), digit));
+                summands.push(SumTerm(
+                    Some(high_position - low_position * FieldElement::from(1u64 << num_bits)),
+                    high,
+                ));
+            }
+            summands.retain(|SumTerm(coefficient, _)| !coefficient.unwrap().is_zero());
+            self.words.compiler.add_sum(summands)
+        })
+    }
+
+    /// The right shift of a digit by a number of bits, looked up in the table
+    /// of every digit and its shift.
+    fn shifted_digit(&mut self, digit: usize, num_bits: u32) -> usize {
+        if let Some(&shifted) = self.shifted_digits.get(&(digit, num_bits)) {
+            return shifted;
+        }
+        let compiler = &mut *self.words.compiler;
+        let shifted = compiler.add_witness_builder(WitnessBuilder::ShiftRight(
+            compiler.num_witnesses(),
+            digit,
+            num_bits,
+        ));
+        let table = compiler.lookups.add_shared_table(
+            SharedTable::Sha256DigitShift(num_bits),
+            move || {
+                (0..1u64 << DIGIT_BITS)
+                    .map(move |digit| [digit, digit >> num_bits].map(FieldElement::from).to_vec())
+            },
+            vec![1, 0],
+        );
+        compiler.lookups.query(table, vec![
+            ConstantOrR1CSWitness::Witness(digit),
+            ConstantOrR1CSWitness::Witness(shifted),
+        ]);
+        self.shifted_digits.insert((digit, num_bits), shifted);
+        shifted
+    }
+}
+
+/// The weights of the low and high parts of the digit starting at bit `start`,
+/// split after `num_bits` bits, in the shifted word: the power of two of their
+/// position, or zero if they are shifted out.
+fn digit_part_positions(start: u32, num_bits: u32, shift: Shift) -> [FieldElement; 2] {
+    let positions = piece_positions(&[start, start + num_bits], shift);
+    std::array::from_fn(|part| match positions[part] {
+        Some(position) => FieldElement::from(1u64 << position),
+        None => FieldElement::zero(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{digit_part_positions, DIGIT_BITS, NUM_DIGITS},
+        crate::words::Shift,
+        ark_ff::PrimeField,
+        provekit_common::FieldElement,
+    };
+
+    #[test]
+    fn test_digit_part_positions() {
+        let word = 0x9e37_79b9_u32;
+        for shift in (1..32)
+            .filter(|amount| amount % DIGIT_BITS != 0)
+            .flat_map(|amount| [Shift::RotateRight(amount), Shift::ShiftRight(amount)])
+        {
+            let (Shift::RotateRight(amount) | Shift::ShiftRight(amount)) = shift;
+            let num_bits = amount % DIGIT_BITS;
+
+            // Recombine the digits and their shifts as the constraint does
+            let shifted = (0..NUM_DIGITS as u32)
+                .map(|i| {
+                    let digit = (word >> (i * DIGIT_BITS)) & 0xf;
+                    let [low, high] = digit_part_positions(i * DIGIT_BITS, num_bits, shift);
+                    low * FieldElement::from(digit)
+                        + (high - low * FieldElement::from(1u64 << num_bits))
+                            * FieldElement::from(digit >> num_bits)
+                })
+                .sum::<FieldElement>();
+            let expected = match shift {
+                Shift::RotateRight(amount) => word.rotate_right(amount),
+                Shift::ShiftRight(amount) => word >> amount,
+            };
+            assert_eq!(shifted.into_bigint().0[0], u64::from(expected), "{shift:?}");
+        }
+    }
+}
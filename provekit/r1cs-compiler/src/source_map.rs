@@ -0,0 +1,49 @@
+use {
+    acir::circuit::OpcodeLocation,
+    noirc_artifacts::program::ProgramArtifact,
+    provekit_common::{SourceLocation, SourceMap},
+};
+
+/// The Noir source locations of an ACIR opcode, from the debug symbols of the
+/// program: the call stack of inlined Noir functions, outermost first.
+pub(crate) fn opcode_source(
+    program: &ProgramArtifact,
+    function: u32,
+    opcode: usize,
+) -> Vec<SourceLocation> {
+    program
+        .debug_symbols
+        .debug_infos
+        .get(function as usize)
+        .and_then(|debug_info| debug_info.opcode_location(&OpcodeLocation::Acir(opcode)))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|location| {
+            let file = program.file_map.get(&location.file)?;
+            let offset = (location.span.start() as usize).min(file.source.len());
+            let before = &file.source[..offset];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Some(SourceLocation {
+                path:   file.path.display().to_string(),
+                line:   before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+                text:   file.source[line_start..]
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Resolve the function names and the innermost Noir source locations of the
+/// opcodes of a source map.
+pub(crate) fn resolve_source_map(source_map: &mut SourceMap, program: &ProgramArtifact) {
+    source_map.functions = program.names.clone();
+    for origin in &mut source_map.origins {
+        origin.source = origin
+            .opcode
+            .and_then(|opcode| opcode_source(program, origin.function, opcode).pop());
+    }
+}
@@ -0,0 +1,83 @@
+use {
+    crate::whir_r1cs::WhirR1CSSchemeBuilder,
+    ark_std::Zero,
+    provekit_common::{
+        spark::{SparkScheme, LOG_MATRIX_SLOTS},
+        utils::next_power_of_two,
+        FieldElement, IOPattern, WhirR1CSScheme, R1CS,
+    },
+    whir::{
+        poly_utils::evals::EvaluationsList,
+        whir::{
+            committer::{CommitmentReader, CommitmentWriter},
+            domainsep::WhirDomainSeparator,
+        },
+    },
+};
+
+pub trait SparkSchemeBuilder {
+    fn new_for_r1cs(r1cs: &R1CS, whir_r1cs: &WhirR1CSScheme) -> Self;
+}
+
+impl SparkSchemeBuilder for SparkScheme {
+    fn new_for_r1cs(r1cs: &R1CS, whir_r1cs: &WhirR1CSScheme) -> Self {
+        let max_num_entries = [&r1cs.a, &r1cs.b, &r1cs.c]
+            .iter()
+            .map(|matrix| matrix.num_entries())
+            .max()
+            .unwrap_or_default();
+        let log_num_entries = next_power_of_two(max_num_entries).max(1);
+
+        // The column table covers the hypercube of each witness commitment. With a
+        // second commitment, its columns are placed in the upper half.
+        let (log_columns, column_offset_2) = match &whir_r1cs.whir_witness_2 {
+            Some(whir_witness_2) => {
+                let log_half = whir_r1cs.m.max(whir_witness_2.mv_parameters.num_variables);
+                (log_half + 1, Some(1 << log_half))
+            }
+            None => (whir_r1cs.m, None),
+        };
+        let log_table_size = whir_r1cs.m_0.max(log_columns).max(1);
+
+        let num_lookups = if column_offset_2.is_some() { 3 } else { 2 };
+        let log_slot_size = log_num_entries.max(log_table_size);
+        let mut scheme = Self {
+            log_num_entries,
+            log_table_size,
+            column_offset_2,
+            matrix_root: FieldElement::zero(),
+            whir_matrices: WhirR1CSScheme::new_whir_config_for_size(
+                LOG_MATRIX_SLOTS + log_slot_size,
+                1,
+            ),
+            whir_lookups: WhirR1CSScheme::new_whir_config_for_size(
+                next_power_of_two(3 * num_lookups) + log_num_entries,
+                1,
+            ),
+            whir_inverses: WhirR1CSScheme::new_whir_config_for_size(
+                next_power_of_two(4 * num_lookups) + log_slot_size,
+                1,
+            ),
+        };
+        scheme.matrix_root = commit_to_matrices(&scheme, r1cs, whir_r1cs.w1_size);
+        scheme
+    }
+}
+
+/// Computes the Merkle root of the commitment to the matrix polynomial. The
+/// commitment is deterministic, so the prover reproduces the same root.
+fn commit_to_matrices(scheme: &SparkScheme, r1cs: &R1CS, w1_size: usize) -> FieldElement {
+    let polynomial = EvaluationsList::new(scheme.matrix_polynomial(r1cs, w1_size));
+
+    let io = IOPattern::new("🌪️").commit_statement(&scheme.whir_matrices);
+    let mut merlin = io.to_prover_state();
+    CommitmentWriter::new(scheme.whir_matrices.clone())
+        .commit_batch(&mut merlin, &[polynomial.to_coeffs()])
+        .expect("WHIR prover failed to commit");
+
+    let mut arthur = io.to_verifier_state(merlin.narg_string());
+    CommitmentReader::new(&scheme.whir_matrices)
+        .parse_commitment(&mut arthur)
+        .expect("Failed to parse matrix commitment")
+        .root
+}
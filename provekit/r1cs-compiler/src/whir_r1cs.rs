@@ -6,16 +6,24 @@ use {
 };
 
 pub trait WhirR1CSSchemeBuilder {
-    fn new_for_r1cs(r1cs: &R1CS) -> Self;
+    fn new_for_r1cs(r1cs: &R1CS, w1_size: usize, num_challenges: usize) -> Self;
 
     fn new_whir_config_for_size(num_variables: usize, batch_size: usize) -> WhirConfig;
 }
 
 impl WhirR1CSSchemeBuilder for WhirR1CSScheme {
-    fn new_for_r1cs(r1cs: &R1CS) -> Self {
-        // m is equal to ceiling(log(number of variables in constraint system)). It is
-        // equal to the log of the width of the matrices.
-        let m = next_power_of_two(r1cs.num_witnesses());
+    fn new_for_r1cs(r1cs: &R1CS, w1_size: usize, num_challenges: usize) -> Self {
+        assert!(w1_size <= r1cs.num_witnesses());
+
+        // m is equal to ceiling(log(number of witnesses committed in the first
+        // phase)). Without challenges this is the log of the width of the matrices.
+        let m = next_power_of_two(w1_size);
+
+        // The witnesses that depend on challenges are committed separately.
+        let whir_witness_2 = (num_challenges > 0).then(|| {
+            let m_2 = next_power_of_two(r1cs.num_witnesses() - w1_size);
+            Self::new_whir_config_for_size(m_2 + 1, 2)
+        });
 
         // m_0 is equal to ceiling(log(number_of_constraints)). It is equal to the
         // number of variables in the multilinear polynomial we are running our sumcheck
@@ -27,11 +35,17 @@ impl WhirR1CSSchemeBuilder for WhirR1CSScheme {
             m: m + 1,
             m_0,
             a_num_terms: next_power_of_two(r1cs.a().iter().count()),
+            num_public_inputs: r1cs.num_public_inputs,
+            num_public_outputs: r1cs.num_public_outputs,
+            w1_size,
+            num_challenges,
             whir_witness: Self::new_whir_config_for_size(m + 1, 2),
+            whir_witness_2,
             whir_for_hiding_spartan: Self::new_whir_config_for_size(
                 next_power_of_two(4 * m_0) + 1,
                 2,
             ),
+            spark: None,
         }
     }
 
@@ -6,7 +6,11 @@ use {
         Abi, AbiType,
     },
     noirc_artifacts::program::ProgramArtifact,
-    provekit_common::{utils::noir_to_native, witness::NoirWitnessGenerator, FieldElement},
+    provekit_common::{
+        utils::noir_to_native,
+        witness::{CallFrame, NoirWitnessGenerator},
+        FieldElement,
+    },
     std::num::NonZeroU32,
     tracing::instrument,
 };
@@ -16,6 +20,7 @@ pub trait NoirWitnessGeneratorBuilder {
         program: &ProgramArtifact,
         witness_map: Vec<Option<NonZeroU32>>,
         r1cs_witnesses: usize,
+        call_frames: Vec<CallFrame>,
     ) -> Self;
 
     fn witness_map(&self) -> &[Option<NonZeroU32>];
@@ -30,6 +35,7 @@ impl NoirWitnessGeneratorBuilder for NoirWitnessGenerator {
         program: &ProgramArtifact,
         mut witness_map: Vec<Option<NonZeroU32>>,
         r1cs_witnesses: usize,
+        call_frames: Vec<CallFrame>,
     ) -> Self {
         let abi = program.abi.clone();
         assert!(witness_map
@@ -40,7 +46,11 @@ impl NoirWitnessGeneratorBuilder for NoirWitnessGenerator {
         // Take only the prefix of witness map relevant for Noir inputs
         let num_inputs = abi.field_count() as usize;
         witness_map.truncate(num_inputs);
-        Self { abi, witness_map }
+        Self {
+            abi,
+            witness_map,
+            call_frames,
+        }
     }
 
     fn witness_map(&self) -> &[Option<NonZeroU32>] {
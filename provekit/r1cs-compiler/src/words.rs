@@ -0,0 +1,272 @@
+use {
+    crate::{
+        digits::{add_digital_decomposition, DigitalDecompositionWitnessesBuilder},
+        noir_to_r1cs::NoirToR1CSCompiler,
+    },
+    ark_ff::PrimeField,
+    ark_std::One,
+    provekit_common::{
+        witness::{ConstantOrR1CSWitness, ConstantTerm, SumTerm, WitnessBuilder, BINOP_BITS},
+        FieldElement,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Number of bits in a word
+pub(crate) const WORD_BITS: u32 = BINOP_BITS as u32;
+
+/// (lhs, rhs, output) tuples of a bitwise operation, constrained by a lookup
+/// once the whole circuit has been compiled.
+pub(crate) type BinOpTriples = Vec<(ConstantOrR1CSWitness, ConstantOrR1CSWitness, usize)>;
+
+/// [BinOpTriples] keyed by the number of bits of their operands
+pub(crate) type BinOps = BTreeMap<u32, BinOpTriples>;
+
+/// A right rotation or right shift of a word by a number of bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Shift {
+    RotateRight(u32),
+    ShiftRight(u32),
+}
+
+/// Adds the witnesses and constraints for operations on 32-bit words, for
+/// gadgets whose intermediate words are not ACIR witnesses. Bitwise
+/// operations and range checks are collected alongside those of the ACIR
+/// opcodes, and constrained together by lookups.
+pub(crate) struct WordBuilder<'a> {
+    pub compiler:     &'a mut NoirToR1CSCompiler,
+    pub xor_ops:      &'a mut BinOps,
+    pub range_checks: &'a mut BTreeMap<u32, Vec<usize>>,
+}
+
+impl WordBuilder<'_> {
+    /// Returns a witness for an input word, range checking it. Constant words
+    /// are given a witness constrained to the constant.
+    pub fn input(&mut self, input: ConstantOrR1CSWitness) -> usize {
+        match input {
+            ConstantOrR1CSWitness::Witness(witness) => {
+                self.range_checks
+                    .entry(WORD_BITS)
+                    .or_default()
+                    .push(witness);
+                witness
+            }
+            ConstantOrR1CSWitness::Constant(value) => {
+                let witness =
+                    self.compiler
+                        .add_witness_builder(WitnessBuilder::Constant(ConstantTerm(
+                            self.compiler.num_witnesses(),
+                            value,
+                        )));
+                self.compiler.r1cs.add_constraint(
+                    &[(FieldElement::one(), witness)],
+                    &[(FieldElement::one(), self.compiler.witness_one())],
+                    &[(value, self.compiler.witness_one())],
+                );
+                witness
+            }
+        }
+    }
+
+    /// Returns a witness constrained to a constant word.
+    pub fn constant(&mut self, value: u32) -> usize {
+        self.input(ConstantOrR1CSWitness::Constant(FieldElement::from(value)))
+    }
+
+    /// Returns a witness for the word with the given little-endian bytes,
+    /// range checking them.
+    pub fn pack_le_bytes(&mut self, bytes: &[ConstantOrR1CSWitness]) -> usize {
+        assert!(bytes.len() <= 4, "Too many bytes for a word");
+        let mut constant = 0;
+        let mut summands = vec![];
+        for (i, byte) in bytes.iter().enumerate() {
+            match byte {
+                ConstantOrR1CSWitness::Constant(value) => {
+                    constant += value.into_bigint().0[0] << (8 * i);
+                }
+                ConstantOrR1CSWitness::Witness(witness) => {
+                    self.range_checks.entry(8).or_default().push(*witness);
+                    summands.push(SumTerm(Some(FieldElement::from(1u64 << (8 * i))), *witness));
+                }
+            }
+        }
+        if summands.is_empty() {
+            return self.constant(constant as u32);
+        }
+        if constant != 0 {
+            summands.push(SumTerm(
+                Some(FieldElement::from(constant)),
+                self.compiler.witness_one(),
+            ));
+        }
+        self.compiler.add_sum(summands)
+    }
+
+    /// Constrains a word to equal its little-endian bytes, range checking
+    /// them.
+    pub fn assert_le_bytes(&mut self, word: usize, bytes: &[usize]) {
+        assert!(bytes.len() == 4, "A word has four bytes");
+        let az = bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| (FieldElement::from(1u64 << (8 * i)), byte))
+            .collect::<Vec<_>>();
+        self.range_checks.entry(8).or_default().extend(bytes);
+        self.compiler.r1cs.add_constraint(
+            &az,
+            &[(FieldElement::one(), self.compiler.witness_one())],
+            &[(FieldElement::one(), word)],
+        );
+    }
+
+    /// Constrains two words to be equal.
+    pub fn assert_equal(&mut self, lhs: usize, rhs: usize) {
+        self.compiler.r1cs.add_constraint(
+            &[(FieldElement::one(), lhs)],
+            &[(FieldElement::one(), self.compiler.witness_one())],
+            &[(FieldElement::one(), rhs)],
+        );
+    }
+
+    /// The bitwise XOR of two words.
+    pub fn xor(&mut self, lhs: usize, rhs: usize) -> usize {
+        let lhs = ConstantOrR1CSWitness::Witness(lhs);
+        let rhs = ConstantOrR1CSWitness::Witness(rhs);
+        let output = self.compiler.add_witness_builder(WitnessBuilder::Xor(
+            self.compiler.num_witnesses(),
+            lhs.clone(),
+            rhs.clone(),
+        ));
+        self.xor_ops
+            .entry(WORD_BITS)
+            .or_default()
+            .push((lhs, rhs, output));
+        output
+    }
+
+    /// The sum of words and a constant, modulo 2^32. The carry is range
+    /// checked, as is the result.
+    pub fn add(&mut self, words: &[usize], constant: u32) -> usize {
+        let mut summands = words
+            .iter()
+            .map(|&word| SumTerm(None, word))
+            .collect::<Vec<_>>();
+        if constant != 0 {
+            summands.push(SumTerm(
+                Some(FieldElement::from(constant)),
+                self.compiler.witness_one(),
+            ));
+        }
+        assert!(summands.len() > 1, "Nothing to add");
+
+        // Each summand is less than 2^32, so the carry is less than their number
+        let carry_bits = summands.len().next_power_of_two().ilog2();
+        let sum = self.compiler.add_sum(summands);
+        let dd_struct = add_digital_decomposition(
+            self.compiler,
+            vec![WORD_BITS as usize, carry_bits as usize],
+            vec![sum],
+        );
+        let result = dd_struct.get_digit_witness_index(0, 0);
+        let carry = dd_struct.get_digit_witness_index(1, 0);
+        self.range_checks.entry(WORD_BITS).or_default().push(result);
+        self.range_checks.entry(carry_bits).or_default().push(carry);
+        result
+    }
+
+    /// Rotations and shifts of a word. The word is split into pieces at every
+    /// shift amount, so that each shift is a linear combination of the pieces.
+    /// The pieces are range checked, which also range checks the word.
+    pub fn shifts<const N: usize>(&mut self, word: usize, shifts: [Shift; N]) -> [usize; N] {
+        let starts = piece_starts(&shifts);
+        let widths = starts
+            .iter()
+            .zip(starts.iter().skip(1).chain([&WORD_BITS]))
+            .map(|(start, end)| (end - start) as usize)
+            .collect::<Vec<_>>();
+        let dd_struct = add_digital_decomposition(self.compiler, widths.clone(), vec![word]);
+        let pieces = (0..widths.len())
+            .map(|digit_place| dd_struct.get_digit_witness_index(digit_place, 0))
+            .collect::<Vec<_>>();
+        for (&piece, &width) in pieces.iter().zip(&widths) {
+            self.range_checks
+                .entry(width as u32)
+                .or_default()
+                .push(piece);
+        }
+
+        shifts.map(|shift| {
+            let summands = piece_positions(&starts, shift)
+                .into_iter()
+                .zip(&pieces)
+                .filter_map(|(position, &piece)| {
+                    position
+                        .map(|position| SumTerm(Some(FieldElement::from(1u64 << position)), piece))
+                })
+                .collect();
+            self.compiler.add_sum(summands)
+        })
+    }
+}
+
+/// The first bit of each piece a word is split into, so that no piece
+/// straddles a shift amount.
+fn piece_starts(shifts: &[Shift]) -> Vec<u32> {
+    let mut starts = vec![0];
+    for shift in shifts {
+        let (Shift::RotateRight(amount) | Shift::ShiftRight(amount)) = *shift;
+        assert!(amount > 0 && amount < WORD_BITS, "Invalid shift {shift:?}");
+        starts.push(amount);
+    }
+    starts.sort_unstable();
+    starts.dedup();
+    starts
+}
+
+/// The position of each piece in the shifted word, or `None` if the piece is
+/// shifted out.
+pub(crate) fn piece_positions(starts: &[u32], shift: Shift) -> Vec<Option<u32>> {
+    starts
+        .iter()
+        .map(|&start| match shift {
+            Shift::RotateRight(amount) => Some((start + WORD_BITS - amount) % WORD_BITS),
+            Shift::ShiftRight(amount) => start.checked_sub(amount),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{piece_positions, piece_starts, Shift};
+
+    #[test]
+    fn test_piece_positions() {
+        let shifts = [
+            Shift::RotateRight(7),
+            Shift::RotateRight(18),
+            Shift::ShiftRight(3),
+        ];
+        let starts = piece_starts(&shifts);
+        assert_eq!(starts, vec![0, 3, 7, 18]);
+
+        // Recombine the pieces of a word for each shift
+        let word = 0x9e37_79b9_u32;
+        let pieces = starts
+            .iter()
+            .zip(starts.iter().skip(1).chain([&32]))
+            .map(|(&start, &end)| (word >> start) & ((1u64 << (end - start)) - 1) as u32)
+            .collect::<Vec<_>>();
+        let shifted = shifts.map(|shift| {
+            piece_positions(&starts, shift)
+                .into_iter()
+                .zip(&pieces)
+                .filter_map(|(position, &piece)| position.map(|position| piece << position))
+                .sum::<u32>()
+        });
+        assert_eq!(shifted, [
+            word.rotate_right(7),
+            word.rotate_right(18),
+            word >> 3
+        ]);
+    }
+}
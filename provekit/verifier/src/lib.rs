@@ -1,4 +1,5 @@
 mod noir_proof_scheme;
+mod spark;
 mod whir_r1cs;
 
 pub use noir_proof_scheme::NoirProofSchemeVerifier;
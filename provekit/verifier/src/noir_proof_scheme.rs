@@ -1,18 +1,81 @@
 use {
     crate::whir_r1cs::WhirR1CSVerifier,
-    anyhow::Result,
-    provekit_common::{NoirProof, NoirProofScheme},
+    anyhow::{ensure, Result},
+    provekit_common::{
+        FieldElement, NoirProof, NoirProofScheme, VerificationKey, WhirR1CSScheme, R1CS,
+    },
     tracing::instrument,
 };
 
 pub trait NoirProofSchemeVerifier {
-    fn verify(&self, proof: &NoirProof) -> Result<()>;
+    /// Verify the proof for the given public inputs and, if provided, the
+    /// expected return values of the program.
+    fn verify(
+        &self,
+        proof: &NoirProof,
+        public_inputs: &[FieldElement],
+        public_outputs: Option<&[FieldElement]>,
+    ) -> Result<()>;
 }
 
 impl NoirProofSchemeVerifier for NoirProofScheme {
     #[instrument(skip_all)]
-    fn verify(&self, proof: &NoirProof) -> Result<()> {
-        self.whir_for_witness.verify(&proof.whir_r1cs_proof)?;
-        Ok(())
+    fn verify(
+        &self,
+        proof: &NoirProof,
+        public_inputs: &[FieldElement],
+        public_outputs: Option<&[FieldElement]>,
+    ) -> Result<()> {
+        verify(
+            &self.whir_for_witness,
+            Some(&self.r1cs),
+            self.digest()?,
+            proof,
+            public_inputs,
+            public_outputs,
+        )
     }
 }
+
+impl NoirProofSchemeVerifier for VerificationKey {
+    #[instrument(skip_all)]
+    fn verify(
+        &self,
+        proof: &NoirProof,
+        public_inputs: &[FieldElement],
+        public_outputs: Option<&[FieldElement]>,
+    ) -> Result<()> {
+        verify(
+            &self.whir_for_witness,
+            self.r1cs.as_ref(),
+            self.digest,
+            proof,
+            public_inputs,
+            public_outputs,
+        )
+    }
+}
+
+fn verify(
+    whir_for_witness: &WhirR1CSScheme,
+    r1cs: Option<&R1CS>,
+    digest: FieldElement,
+    proof: &NoirProof,
+    public_inputs: &[FieldElement],
+    public_outputs: Option<&[FieldElement]>,
+) -> Result<()> {
+    ensure!(proof.digest == digest, "Proof is for a different circuit");
+    ensure!(
+        proof.public_inputs == public_inputs,
+        "Proof is for different public inputs"
+    );
+    if let Some(public_outputs) = public_outputs {
+        ensure!(
+            proof.public_outputs == public_outputs,
+            "Proof is for different public outputs"
+        );
+    }
+    let public_values = [public_inputs, &proof.public_outputs].concat();
+    whir_for_witness.verify(&proof.whir_r1cs_proof, digest, &public_values, r1cs)?;
+    Ok(())
+}
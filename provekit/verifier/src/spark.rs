@@ -0,0 +1,200 @@
+use {
+    crate::whir_r1cs::run_whir_pcs_verifier,
+    anyhow::{ensure, Context, Result},
+    ark_std::{One, Zero},
+    provekit_common::{
+        skyscraper::SkyscraperSponge,
+        spark::{evaluate_index, evaluate_terms, Openings, SparkChallenges, SparkScheme},
+        utils::sumcheck::{calculate_eq, eval_cubic_poly},
+        FieldElement, WhirConfig,
+    },
+    spongefish::{
+        codecs::arkworks_algebra::{FieldToUnitDeserialize, UnitToField},
+        VerifierState,
+    },
+    tracing::instrument,
+    whir::{
+        poly_utils::multilinear::MultilinearPoint,
+        whir::{
+            committer::{reader::ParsedCommitment, CommitmentReader},
+            statement::{Statement, Weights},
+        },
+    },
+};
+
+pub trait SparkVerifier {
+    /// Verify that the R1CS matrices evaluate to `matrix_claims[p]` at
+    /// `row_point` and `column_points[p]`, one for each witness commitment.
+    fn verify(
+        &self,
+        arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+        row_point: &[FieldElement],
+        column_points: &[&[FieldElement]],
+        matrix_claims: &[[FieldElement; 3]],
+    ) -> Result<()>;
+}
+
+impl SparkVerifier for SparkScheme {
+    #[instrument(skip_all)]
+    fn verify(
+        &self,
+        arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+        row_point: &[FieldElement],
+        column_points: &[&[FieldElement]],
+        matrix_claims: &[[FieldElement; 3]],
+    ) -> Result<()> {
+        let num_lookups = self.num_lookups();
+        ensure!(
+            column_points.len() + 1 == num_lookups && matrix_claims.len() + 1 == num_lookups,
+            "Unexpected number of witness commitments"
+        );
+
+        // The commitment to the matrices must be the one made when preparing
+        let matrix_commitment = parse_commitment(arthur, &self.whir_matrices)
+            .context("while parsing matrix commitment")?;
+        ensure!(
+            matrix_commitment.root == self.matrix_root,
+            "Matrix commitment does not match scheme"
+        );
+        let lookup_commitment = parse_commitment(arthur, &self.whir_lookups)
+            .context("while parsing lookup commitment")?;
+
+        let mut logup = [FieldElement::zero(); 2];
+        arthur.fill_challenge_scalars(&mut logup)?;
+        let [gamma, delta] = logup;
+        let inverse_commitment = parse_commitment(arthur, &self.whir_inverses)
+            .context("while parsing inverse commitment")?;
+
+        let mut sums = vec![FieldElement::zero(); 3 * num_lookups];
+        arthur.fill_next_scalars(&mut sums)?;
+
+        let mut theta = [FieldElement::zero()];
+        arthur.fill_challenge_scalars(&mut theta)?;
+        let challenges = SparkChallenges {
+            gamma,
+            delta,
+            theta: theta[0],
+        };
+        let mut tau = vec![FieldElement::zero(); self.log_num_entries + self.log_table_size];
+        arthur.fill_challenge_scalars(&mut tau)?;
+        let (tau_entries, tau_table) = tau.split_at(self.log_num_entries);
+
+        // Sumcheck over the entries
+        let (entry_point, entry_value) = run_sumcheck_verifier(
+            arthur,
+            self.log_num_entries,
+            self.entry_claim(challenges, matrix_claims, &sums),
+        )
+        .context("while verifying entry sumcheck")?;
+        let mut entry_evaluations = vec![FieldElement::zero(); 9 + 6 * num_lookups];
+        arthur.fill_next_scalars(&mut entry_evaluations)?;
+        let mut evaluations = vec![calculate_eq(tau_entries, &entry_point)];
+        evaluations.extend_from_slice(&entry_evaluations);
+        ensure!(
+            evaluate_terms(&self.entry_terms(challenges), &evaluations) == entry_value,
+            "Entry sumcheck does not match evaluations"
+        );
+
+        // Sumcheck over the tables
+        let (table_point, table_value) = run_sumcheck_verifier(
+            arthur,
+            self.log_table_size,
+            self.table_claim(challenges, &sums),
+        )
+        .context("while verifying table sumcheck")?;
+        let mut table_evaluations = vec![FieldElement::zero(); 6 + num_lookups];
+        arthur.fill_next_scalars(&mut table_evaluations)?;
+        let mut evaluations = vec![
+            calculate_eq(tau_table, &table_point),
+            evaluate_index(&table_point),
+        ];
+        evaluations.extend(
+            self.table_points(row_point, column_points)
+                .iter()
+                .map(|point| calculate_eq(point, &table_point)),
+        );
+        evaluations.extend_from_slice(&table_evaluations);
+        ensure!(
+            evaluate_terms(&self.table_terms(challenges), &evaluations) == table_value,
+            "Table sumcheck does not match evaluations"
+        );
+
+        // Check the evaluations against the commitments
+        let [matrix_openings, lookup_openings, inverse_openings] = self.openings(
+            &entry_point,
+            &entry_evaluations,
+            &table_point,
+            &table_evaluations,
+        );
+        open(
+            arthur,
+            &matrix_commitment,
+            &self.whir_matrices,
+            matrix_openings,
+        )
+        .context("while opening matrix commitment")?;
+        open(
+            arthur,
+            &lookup_commitment,
+            &self.whir_lookups,
+            lookup_openings,
+        )
+        .context("while opening lookup commitment")?;
+        open(
+            arthur,
+            &inverse_commitment,
+            &self.whir_inverses,
+            inverse_openings,
+        )
+        .context("while opening inverse commitment")?;
+
+        Ok(())
+    }
+}
+
+fn parse_commitment(
+    arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+    config: &WhirConfig,
+) -> Result<ParsedCommitment<FieldElement, FieldElement>> {
+    Ok(CommitmentReader::new(config).parse_commitment(arthur)?)
+}
+
+fn open(
+    arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+    commitment: &ParsedCommitment<FieldElement, FieldElement>,
+    config: &WhirConfig,
+    openings: Openings,
+) -> Result<()> {
+    let mut statement = Statement::<FieldElement>::new(config.mv_parameters.num_variables);
+    for (point, value) in openings {
+        statement.add_constraint(Weights::evaluation(MultilinearPoint(point)), value);
+    }
+    run_whir_pcs_verifier(arthur, commitment, config, &statement)?;
+    Ok(())
+}
+
+/// Verifies the cubic sumcheck polynomials of each round against the claim.
+/// Returns the sumcheck randomness and the claimed value of the summand there.
+fn run_sumcheck_verifier(
+    arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+    num_variables: usize,
+    mut claim: FieldElement,
+) -> Result<(Vec<FieldElement>, FieldElement)> {
+    let mut point = Vec::with_capacity(num_variables);
+    for _ in 0..num_variables {
+        let mut coefficients = [FieldElement::zero(); 4];
+        arthur.fill_next_scalars(&mut coefficients)?;
+        ensure!(
+            eval_cubic_poly(&coefficients, &FieldElement::zero())
+                + eval_cubic_poly(&coefficients, &FieldElement::one())
+                == claim,
+            "Sumcheck equality assertion failed"
+        );
+
+        let mut challenge = [FieldElement::zero()];
+        arthur.fill_challenge_scalars(&mut challenge)?;
+        claim = eval_cubic_poly(&coefficients, &challenge[0]);
+        point.push(challenge[0]);
+    }
+    Ok((point, claim))
+}
@@ -1,10 +1,18 @@
 use {
+    crate::spark::SparkVerifier,
     anyhow::{ensure, Context, Result},
     ark_std::{One, Zero},
     provekit_common::{
         skyscraper::SkyscraperSponge,
-        utils::sumcheck::{calculate_eq, eval_cubic_poly},
-        FieldElement, WhirConfig, WhirR1CSProof, WhirR1CSScheme,
+        utils::{
+            pad_to_power_of_two,
+            sumcheck::{
+                calculate_eq, calculate_evaluations_over_boolean_hypercube_for_eq,
+                calculate_external_row_of_r1cs_matrices, calculate_public_input_weights,
+                eval_cubic_poly, expand_powers,
+            },
+        },
+        FieldElement, WhirConfig, WhirR1CSProof, WhirR1CSScheme, R1CS,
     },
     spongefish::{
         codecs::arkworks_algebra::{FieldToUnitDeserialize, UnitToField},
@@ -29,19 +37,97 @@ pub struct DataFromSumcheckVerifier {
 }
 
 pub trait WhirR1CSVerifier {
-    fn verify(&self, proof: &WhirR1CSProof) -> Result<()>;
+    fn verify(
+        &self,
+        proof: &WhirR1CSProof,
+        digest: FieldElement,
+        public_values: &[FieldElement],
+        r1cs: Option<&R1CS>,
+    ) -> Result<()>;
 }
 
 impl WhirR1CSVerifier for WhirR1CSScheme {
     #[instrument(skip_all)]
-    #[allow(unused)] // TODO: Fix implementation
-    fn verify(&self, proof: &WhirR1CSProof) -> Result<()> {
+    fn verify(
+        &self,
+        proof: &WhirR1CSProof,
+        digest: FieldElement,
+        public_values: &[FieldElement],
+        r1cs: Option<&R1CS>,
+    ) -> Result<()> {
+        ensure!(
+            public_values.len() == self.num_public_values(),
+            "Unexpected number of public values"
+        );
+        ensure!(
+            self.w1_size <= 1 << self.m,
+            "R1CS witness length exceeds scheme capacity"
+        );
+
+        // Without a commitment to the matrices, the verifier evaluates them itself
+        let r1cs = match &self.spark {
+            Some(_) => None,
+            None => Some(r1cs.context("R1CS instance required to verify without Spark")?),
+        };
+        if let Some(r1cs) = r1cs {
+            ensure!(
+                r1cs.num_public_inputs == self.num_public_inputs
+                    && r1cs.num_public_outputs == self.num_public_outputs,
+                "Unexpected number of public values"
+            );
+            ensure!(
+                self.w1_size <= r1cs.num_witnesses(),
+                "R1CS witness length exceeds scheme capacity"
+            );
+            ensure!(
+                self.whir_witness_2.is_some() || self.w1_size == r1cs.num_witnesses(),
+                "R1CS witness length does not match scheme"
+            );
+            ensure!(
+                r1cs.num_constraints() <= 1 << self.m_0,
+                "R1CS constraints exceed scheme capacity"
+            );
+        }
+
         // Set up transcript
         let io = self.create_io_pattern();
         let mut arthur = io.to_verifier_state(&proof.transcript);
 
+        // The proof must be for this circuit
+        let [transcript_digest] = arthur.next_scalars()?;
+        ensure!(
+            transcript_digest == digest,
+            "Proof is for a different circuit"
+        );
+
+        // The public inputs and outputs are bound to the transcript
+        if !public_values.is_empty() {
+            let mut transcript_public_values = vec![FieldElement::zero(); public_values.len()];
+            arthur.fill_next_scalars(&mut transcript_public_values)?;
+            ensure!(
+                transcript_public_values == public_values,
+                "Public values do not match transcript"
+            );
+        }
+
         let commitment_reader = CommitmentReader::new(&self.whir_witness);
-        let parsed_commitment = commitment_reader.parse_commitment(&mut arthur).unwrap();
+        let parsed_commitment = commitment_reader
+            .parse_commitment(&mut arthur)
+            .context("while parsing witness commitment")?;
+
+        // The challenges are derived after the first commitment, and are only used
+        // by the prover to solve for the second part of the witness.
+        let parsed_commitment_2 = match &self.whir_witness_2 {
+            Some(whir_witness_2) => {
+                let mut challenges = vec![FieldElement::zero(); self.num_challenges];
+                arthur.fill_challenge_scalars(&mut challenges)?;
+                let parsed_commitment_2 = CommitmentReader::new(whir_witness_2)
+                    .parse_commitment(&mut arthur)
+                    .context("while parsing second witness commitment")?;
+                Some((whir_witness_2, parsed_commitment_2))
+            }
+            None => None,
+        };
 
         let data_from_sumcheck_verifier = run_sumcheck_verifier(
             &mut arthur,
@@ -51,19 +137,63 @@ impl WhirR1CSVerifier for WhirR1CSScheme {
         )
         .context("while verifying sumcheck")?;
 
-        let whir_query_answer_sum_vectors: (Vec<FieldElement>, Vec<FieldElement>) =
-            arthur.hint().unwrap();
+        // Compute weights from R1CS instance, same as the prover. With a commitment to
+        // the matrices, the weights are never evaluated by the verifier: their
+        // evaluations are deferred by WHIR and checked by the Spark proof, so they
+        // are left empty.
+        let alphas = r1cs.map(|r1cs| {
+            calculate_external_row_of_r1cs_matrices(&data_from_sumcheck_verifier.alpha, r1cs)
+        });
+        let mut weights = match &alphas {
+            Some(alphas) => alphas
+                .iter()
+                .map(|row| row[..self.w1_size].to_vec())
+                .collect::<Vec<_>>(),
+            None => vec![Vec::new(); 3],
+        };
 
-        let whir_query_answer_sums = (
-            whir_query_answer_sum_vectors.0.try_into().unwrap(),
-            whir_query_answer_sum_vectors.1.try_into().unwrap(),
-        );
+        // Random linear combination of the public values
+        let mut tau = [FieldElement::zero()];
+        if !public_values.is_empty() {
+            arthur.fill_challenge_scalars(&mut tau)?;
+            weights.push(match r1cs {
+                Some(_) => {
+                    calculate_public_input_weights(tau[0], self.num_public_values(), self.w1_size)
+                }
+                None => Vec::new(),
+            });
+        }
 
-        let statement_verifier = prepare_statement_for_witness_verifier::<3>(
-            self.m,
-            &parsed_commitment,
-            &whir_query_answer_sums,
-        );
+        let (claimed_evaluations, statement_verifier) =
+            read_claimed_evaluations(&mut arthur, self.m, &parsed_commitment, &weights)
+                .context("while reading claimed evaluations")?;
+
+        let opening_2 = match parsed_commitment_2 {
+            Some((whir_witness_2, parsed_commitment_2)) => {
+                let weights_2 = match &alphas {
+                    Some(alphas) => alphas
+                        .iter()
+                        .map(|row| row[self.w1_size..].to_vec())
+                        .collect::<Vec<_>>(),
+                    None => vec![Vec::new(); 3],
+                };
+                let (claimed_evaluations_2, statement_verifier_2) = read_claimed_evaluations(
+                    &mut arthur,
+                    whir_witness_2.mv_parameters.num_variables,
+                    &parsed_commitment_2,
+                    &weights_2,
+                )
+                .context("while reading second claimed evaluations")?;
+                Some((
+                    whir_witness_2,
+                    parsed_commitment_2,
+                    weights_2,
+                    claimed_evaluations_2,
+                    statement_verifier_2,
+                ))
+            }
+            None => None,
+        };
 
         let (folding_randomness, deferred) = run_whir_pcs_verifier(
             &mut arthur,
@@ -72,12 +202,100 @@ impl WhirR1CSVerifier for WhirR1CSScheme {
             &statement_verifier,
         )
         .context("while verifying WHIR proof")?;
+        if r1cs.is_some() {
+            check_deferred_weights(&weights, &folding_randomness, &deferred)
+                .context("while verifying WHIR proof")?;
+        } else {
+            ensure!(
+                deferred.len() == weights.len(),
+                "Unexpected number of deferred weight evaluations"
+            );
+            if !public_values.is_empty() {
+                ensure!(
+                    deferred[3]
+                        == evaluate_public_input_weight(
+                            tau[0],
+                            self.num_public_values(),
+                            &folding_randomness.0
+                        ),
+                    "Deferred weight evaluation does not match"
+                );
+            }
+        }
+        let mut column_points = vec![folding_randomness.0];
+        let mut matrix_claims = vec![[deferred[0], deferred[1], deferred[2]]];
+
+        // The claimed evaluations of z = (w1, w2) against the R1CS matrices
+        let mut matrix_evaluations = [
+            claimed_evaluations[0],
+            claimed_evaluations[1],
+            claimed_evaluations[2],
+        ];
+        if let Some((
+            whir_witness_2,
+            parsed_commitment_2,
+            weights_2,
+            claimed_evaluations_2,
+            statement_verifier_2,
+        )) = opening_2
+        {
+            let (folding_randomness_2, deferred_2) = run_whir_pcs_verifier(
+                &mut arthur,
+                &parsed_commitment_2,
+                whir_witness_2,
+                &statement_verifier_2,
+            )
+            .context("while verifying second WHIR proof")?;
+            if r1cs.is_some() {
+                check_deferred_weights(&weights_2, &folding_randomness_2, &deferred_2)
+                    .context("while verifying second WHIR proof")?;
+            } else {
+                ensure!(
+                    deferred_2.len() == weights_2.len(),
+                    "Unexpected number of deferred weight evaluations"
+                );
+            }
+            column_points.push(folding_randomness_2.0);
+            matrix_claims.push([deferred_2[0], deferred_2[1], deferred_2[2]]);
+
+            for (evaluation, evaluation_2) in
+                matrix_evaluations.iter_mut().zip(claimed_evaluations_2)
+            {
+                *evaluation += evaluation_2;
+            }
+        }
+
+        // Check the matrix evaluations deferred by the openings
+        if let Some(spark) = &self.spark {
+            let column_points = column_points.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            spark
+                .verify(
+                    &mut arthur,
+                    &data_from_sumcheck_verifier.alpha,
+                    &column_points,
+                    &matrix_claims,
+                )
+                .context("while verifying matrix evaluations")?;
+        }
+
+        // Check the opening of the public values.
+        if !public_values.is_empty() {
+            let mut expected = FieldElement::zero();
+            let mut power = FieldElement::one();
+            for value in public_values {
+                expected += power * value;
+                power *= tau[0];
+            }
+            ensure!(
+                claimed_evaluations[3] == expected,
+                "Public values do not match witness"
+            );
+        }
 
         // Check the Spartan sumcheck relation.
         ensure!(
             data_from_sumcheck_verifier.last_sumcheck_val
-                == (whir_query_answer_sums.0[0] * whir_query_answer_sums.0[1]
-                    - whir_query_answer_sums.0[2])
+                == (matrix_evaluations[0] * matrix_evaluations[1] - matrix_evaluations[2])
                     * calculate_eq(
                         &data_from_sumcheck_verifier.r,
                         &data_from_sumcheck_verifier.alpha
@@ -89,23 +307,109 @@ impl WhirR1CSVerifier for WhirR1CSScheme {
     }
 }
 
-fn prepare_statement_for_witness_verifier<const N: usize>(
+/// Read the claimed evaluations of a committed witness against `weights` from
+/// the transcript, and build the corresponding WHIR statement.
+fn read_claimed_evaluations(
+    arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
+    num_variables: usize,
+    parsed_commitment: &ParsedCommitment<FieldElement, FieldElement>,
+    weights: &[Vec<FieldElement>],
+) -> Result<(Vec<FieldElement>, Statement<FieldElement>)> {
+    let whir_query_answer_sums: (Vec<FieldElement>, Vec<FieldElement>) = arthur.hint()?;
+    ensure!(
+        whir_query_answer_sums.0.len() == weights.len()
+            && whir_query_answer_sums.1.len() == weights.len(),
+        "expected {} claimed evaluations",
+        weights.len()
+    );
+
+    let statement_verifier = prepare_statement_for_witness_verifier(
+        num_variables,
+        parsed_commitment,
+        &whir_query_answer_sums,
+        weights,
+    );
+
+    Ok((whir_query_answer_sums.0, statement_verifier))
+}
+
+/// WHIR defers the evaluation of the linear weights at the folding randomness
+/// to the caller. Check them against the weights of the statement.
+fn check_deferred_weights(
+    weights: &[Vec<FieldElement>],
+    folding_randomness: &MultilinearPoint<FieldElement>,
+    deferred: &[FieldElement],
+) -> Result<()> {
+    ensure!(
+        deferred.len() == weights.len(),
+        "Unexpected number of deferred weight evaluations"
+    );
+    for (weight, deferred) in weights.iter().zip(deferred) {
+        ensure!(
+            *deferred == evaluate_weight(weight, &folding_randomness.0),
+            "Deferred weight evaluation does not match"
+        );
+    }
+    Ok(())
+}
+
+fn prepare_statement_for_witness_verifier(
     m: usize,
     parsed_commitment: &ParsedCommitment<FieldElement, FieldElement>,
-    whir_query_answer_sums: &([FieldElement; N], [FieldElement; N]),
+    whir_query_answer_sums: &(Vec<FieldElement>, Vec<FieldElement>),
+    alphas: &[Vec<FieldElement>],
 ) -> Statement<FieldElement> {
     let mut statement_verifier = Statement::<FieldElement>::new(m);
-    for i in 0..whir_query_answer_sums.0.len() {
+    for (i, alpha) in alphas.iter().enumerate() {
         let claimed_sum = whir_query_answer_sums.0[i]
             + whir_query_answer_sums.1[i] * parsed_commitment.batching_randomness;
+
+        // The committed polynomial is the masked witness, so the weight only covers
+        // the first half of the hypercube. WHIR only accepts linear weights over the
+        // full hypercube, even when their evaluation is deferred, so the empty
+        // weights of the Spark mode still expand to zeros here.
+        let expanded_alphas = if alpha.is_empty() {
+            vec![FieldElement::zero(); 1 << m]
+        } else {
+            let mut expanded_alphas = pad_to_power_of_two(alpha.clone());
+            expanded_alphas.resize(1 << m, FieldElement::zero());
+            expanded_alphas
+        };
+
         statement_verifier.add_constraint(
-            Weights::linear(EvaluationsList::new(vec![FieldElement::zero(); 1 << m])),
+            Weights::linear(EvaluationsList::new(expanded_alphas)),
             claimed_sum,
         );
     }
     statement_verifier
 }
 
+/// Evaluates the multilinear extension of the public input weights at `point`,
+/// without expanding them over the hypercube.
+fn evaluate_public_input_weight(
+    tau: FieldElement,
+    num_public_values: usize,
+    point: &[FieldElement],
+) -> FieldElement {
+    let mut result = FieldElement::zero();
+    let mut power = FieldElement::one();
+    for index in 1..=num_public_values {
+        let bits = (0..point.len())
+            .rev()
+            .map(|bit| FieldElement::from(((index >> bit) & 1) as u64))
+            .collect::<Vec<_>>();
+        result += power * calculate_eq(point, &bits);
+        power *= tau;
+    }
+    result
+}
+
+/// Evaluates the multilinear extension of `weight` at `point`.
+fn evaluate_weight(weight: &[FieldElement], point: &[FieldElement]) -> FieldElement {
+    let eq = calculate_evaluations_over_boolean_hypercube_for_eq(point);
+    weight.iter().zip(&eq).map(|(w, e)| *w * e).sum()
+}
+
 #[instrument(skip_all)]
 pub fn run_sumcheck_verifier(
     arthur: &mut VerifierState<SkyscraperSponge, FieldElement>,
@@ -117,7 +421,9 @@ pub fn run_sumcheck_verifier(
     let _ = arthur.fill_challenge_scalars(&mut r);
 
     let commitment_reader = CommitmentReader::new(whir_for_spartan_blinding_config);
-    let parsed_commitment = commitment_reader.parse_commitment(arthur).unwrap();
+    let parsed_commitment = commitment_reader
+        .parse_commitment(arthur)
+        .context("while parsing blinding commitment")?;
 
     let mut sum_g_buf = [FieldElement::zero()];
     arthur.fill_next_scalars(&mut sum_g_buf)?;
@@ -147,20 +453,24 @@ pub fn run_sumcheck_verifier(
     let mut values_of_polynomial_sums = [FieldElement::zero(); 2];
     let _ = arthur.fill_next_scalars(&mut values_of_polynomial_sums);
 
-    let statement_verifier = prepare_statement_for_witness_verifier::<1>(
+    let blinding_weights = [expand_powers(&alpha)];
+    let statement_verifier = prepare_statement_for_witness_verifier(
         whir_for_spartan_blinding_config.mv_parameters.num_variables,
         &parsed_commitment,
-        &([values_of_polynomial_sums[0]], [
-            values_of_polynomial_sums[1]
+        &(vec![values_of_polynomial_sums[0]], vec![
+            values_of_polynomial_sums[1],
         ]),
+        &blinding_weights,
     );
-    run_whir_pcs_verifier(
+    let (folding_randomness, deferred) = run_whir_pcs_verifier(
         arthur,
         &parsed_commitment,
         whir_for_spartan_blinding_config,
         &statement_verifier,
     )
     .context("while verifying WHIR")?;
+    check_deferred_weights(&blinding_weights, &folding_randomness, &deferred)
+        .context("while verifying WHIR")?;
 
     let f_at_alpha = saved_val_for_sumcheck_equality_assertion - rho * values_of_polynomial_sums[0];
 
@@ -2,6 +2,7 @@ package circuit
 
 import (
 	"log"
+	"math/big"
 	"os"
 
 	"reilabs/whir-verifier-circuit/app/typeConverters"
@@ -32,12 +33,25 @@ type Circuit struct {
 	WHIRParamsWitness                       WHIRParams
 	WHIRParamsHidingSpartan                 WHIRParams
 
+	// Commitment to the witnesses that depend on the challenges, if any
+	HasWitness2                        bool
+	W1Size                             int
+	NumChallenges                      int
+	Witness2LinearStatementEvaluations []frontend.Variable
+	Witness2ClaimedEvaluations         []frontend.Variable
+	Witness2BlindingEvaluations        []frontend.Variable
+	Witness2Merkle                     Merkle
+	Witness2FirstRound                 Merkle
+	WHIRParamsWitness2                 WHIRParams
+
 	MatrixA []MatrixCell
 	MatrixB []MatrixCell
 	MatrixC []MatrixCell
 	// Public Input
-	IO         []byte
-	Transcript []uints.U8 `gnark:",public"`
+	IO            []byte
+	CircuitDigest frontend.Variable   `gnark:",public"`
+	PublicValues  []frontend.Variable `gnark:",public"`
+	Transcript    []uints.U8          `gnark:",public"`
 }
 
 func (circuit *Circuit) Define(api frontend.API) error {
@@ -46,12 +60,46 @@ func (circuit *Circuit) Define(api frontend.API) error {
 		return err
 	}
 
+	circuitDigest := make([]frontend.Variable, 1)
+	if err := arthur.FillNextScalars(circuitDigest); err != nil {
+		return err
+	}
+	api.AssertIsEqual(circuitDigest[0], circuit.CircuitDigest)
+
+	// The public inputs and outputs are bound to the transcript
+	if len(circuit.PublicValues) > 0 {
+		publicValues := make([]frontend.Variable, len(circuit.PublicValues))
+		if err := arthur.FillNextScalars(publicValues); err != nil {
+			return err
+		}
+		for i := range publicValues {
+			api.AssertIsEqual(publicValues[i], circuit.PublicValues[i])
+		}
+	}
+
 	rootHash, batchingRandomness, initialOODQueries, initialOODAnswers, err := parseBatchedCommitment(arthur, circuit.WHIRParamsWitness)
 
 	if err != nil {
 		return err
 	}
 
+	// The challenges are derived after the first commitment, and are only used
+	// by the prover to solve for the second part of the witness.
+	var rootHash2, batchingRandomness2 frontend.Variable
+	var initialOODQueries2 []frontend.Variable
+	var initialOODAnswers2 [][]frontend.Variable
+	if circuit.HasWitness2 {
+		challenges := make([]frontend.Variable, circuit.NumChallenges)
+		if err = arthur.FillChallengeScalars(challenges); err != nil {
+			return err
+		}
+
+		rootHash2, batchingRandomness2, initialOODQueries2, initialOODAnswers2, err = parseBatchedCommitment(arthur, circuit.WHIRParamsWitness2)
+		if err != nil {
+			return err
+		}
+	}
+
 	tRand := make([]frontend.Variable, circuit.LogNumConstraints)
 	err = arthur.FillChallengeScalars(tRand)
 	if err != nil {
@@ -63,26 +111,73 @@ func (circuit *Circuit) Define(api frontend.API) error {
 		return err
 	}
 
+	// Randomness for combining the public values into a single opening
+	publicInputsRand := make([]frontend.Variable, 1)
+	if len(circuit.PublicValues) > 0 {
+		if err = arthur.FillChallengeScalars(publicInputsRand); err != nil {
+			return err
+		}
+	}
+
 	whirFoldingRandomness, err := RunZKWhir(api, arthur, uapi, sc, circuit.WitnessMerkle, circuit.WitnessFirstRound, circuit.WHIRParamsWitness, [][]frontend.Variable{circuit.WitnessClaimedEvaluations, circuit.WitnessBlindingEvaluations}, circuit.WitnessLinearStatementEvaluations, batchingRandomness, initialOODQueries, initialOODAnswers, rootHash)
 
 	if err != nil {
 		return err
 	}
 
-	x := api.Mul(api.Sub(api.Mul(circuit.WitnessClaimedEvaluations[0], circuit.WitnessClaimedEvaluations[1]), circuit.WitnessClaimedEvaluations[2]), calculateEQ(api, spartanSumcheckRand, tRand))
+	// The claimed evaluations of z = (w1, w2) against the R1CS matrices
+	matrixEvaluations := make([]frontend.Variable, 3)
+	copy(matrixEvaluations, circuit.WitnessClaimedEvaluations[:3])
+
+	var whirFoldingRandomness2 []frontend.Variable
+	if circuit.HasWitness2 {
+		whirFoldingRandomness2, err = RunZKWhir(api, arthur, uapi, sc, circuit.Witness2Merkle, circuit.Witness2FirstRound, circuit.WHIRParamsWitness2, [][]frontend.Variable{circuit.Witness2ClaimedEvaluations, circuit.Witness2BlindingEvaluations}, circuit.Witness2LinearStatementEvaluations, batchingRandomness2, initialOODQueries2, initialOODAnswers2, rootHash2)
+
+		if err != nil {
+			return err
+		}
+
+		for i := range matrixEvaluations {
+			matrixEvaluations[i] = api.Add(matrixEvaluations[i], circuit.Witness2ClaimedEvaluations[i])
+		}
+	}
+
+	// Check the opening of the public values
+	if len(circuit.PublicValues) > 0 {
+		expected := frontend.Variable(0)
+		power := frontend.Variable(1)
+		for _, value := range circuit.PublicValues {
+			expected = api.Add(expected, api.Mul(power, value))
+			power = api.Mul(power, publicInputsRand[0])
+		}
+		api.AssertIsEqual(circuit.WitnessClaimedEvaluations[3], expected)
+		api.AssertIsEqual(circuit.WitnessLinearStatementEvaluations[3], evaluatePublicInputWeight(api, publicInputsRand[0], len(circuit.PublicValues), whirFoldingRandomness))
+	}
+
+	x := api.Mul(api.Sub(api.Mul(matrixEvaluations[0], matrixEvaluations[1]), matrixEvaluations[2]), calculateEQ(api, spartanSumcheckRand, tRand))
 	api.AssertIsEqual(spartanSumcheckLastValue, x)
 
-	matrixExtensionEvals := evaluateR1CSMatrixExtension(api, circuit, spartanSumcheckRand, whirFoldingRandomness)
+	// The first commitment covers the columns below W1Size, the second the rest
+	rowEval := calculateEQOverBooleanHypercube(api, spartanSumcheckRand)
+	matrixExtensionEvals := evaluateR1CSMatrixExtension(api, circuit, rowEval, whirFoldingRandomness, 0, circuit.W1Size)
 
 	for i := 0; i < 3; i++ {
 		api.AssertIsEqual(matrixExtensionEvals[i], circuit.WitnessLinearStatementEvaluations[i])
 	}
 
+	if circuit.HasWitness2 {
+		matrixExtensionEvals2 := evaluateR1CSMatrixExtension(api, circuit, rowEval, whirFoldingRandomness2, circuit.W1Size, 1<<len(whirFoldingRandomness2))
+
+		for i := 0; i < 3; i++ {
+			api.AssertIsEqual(matrixExtensionEvals2[i], circuit.Witness2LinearStatementEvaluations[i])
+		}
+	}
+
 	return nil
 }
 
 func verifyCircuit(
-	deferred []Fp256, cfg Config, hints Hints, pk *groth16.ProvingKey, vk *groth16.VerifyingKey, outputCcsPath string, claimedEvaluations ClaimedEvaluations, internedR1CS R1CS, interner Interner,
+	deferred []Fp256, cfg Config, hints Hints, pk *groth16.ProvingKey, vk *groth16.VerifyingKey, outputCcsPath string, claimedEvaluations ClaimedEvaluations, claimedEvaluations2 ClaimedEvaluations, internedR1CS R1CS, interner Interner,
 ) {
 	transcriptT := make([]uints.U8, cfg.TranscriptLen)
 	contTranscript := make([]uints.U8, cfg.TranscriptLen)
@@ -91,15 +186,40 @@ func verifyCircuit(
 		transcriptT[i] = uints.NewU8(cfg.Transcript[i])
 	}
 
-	witnessLinearStatementEvaluations := make([]frontend.Variable, 3)
+	// The matrix rows, followed by the public value weights if there are public values
+	numWitnessWeights := len(claimedEvaluations.FSums)
+
+	witnessLinearStatementEvaluations := make([]frontend.Variable, numWitnessWeights)
 	hidingSpartanLinearStatementEvaluations := make([]frontend.Variable, 1)
-	contWitnessLinearStatementEvaluations := make([]frontend.Variable, 3)
+	contWitnessLinearStatementEvaluations := make([]frontend.Variable, numWitnessWeights)
 	contHidingSpartanLinearStatementEvaluations := make([]frontend.Variable, 1)
 
 	hidingSpartanLinearStatementEvaluations[0] = typeConverters.LimbsToBigIntMod(deferred[0].Limbs)
-	witnessLinearStatementEvaluations[0] = typeConverters.LimbsToBigIntMod(deferred[1].Limbs)
-	witnessLinearStatementEvaluations[1] = typeConverters.LimbsToBigIntMod(deferred[2].Limbs)
-	witnessLinearStatementEvaluations[2] = typeConverters.LimbsToBigIntMod(deferred[3].Limbs)
+	for i := range numWitnessWeights {
+		witnessLinearStatementEvaluations[i] = typeConverters.LimbsToBigIntMod(deferred[1+i].Limbs)
+	}
+
+	// The second commitment only opens against the matrix rows
+	numWitness2Weights := len(claimedEvaluations2.FSums)
+
+	witness2LinearStatementEvaluations := make([]frontend.Variable, numWitness2Weights)
+	contWitness2LinearStatementEvaluations := make([]frontend.Variable, numWitness2Weights)
+	for i := range numWitness2Weights {
+		witness2LinearStatementEvaluations[i] = typeConverters.LimbsToBigIntMod(deferred[1+numWitnessWeights+i].Limbs)
+	}
+
+	fSums2, gSums2 := parseClaimedEvaluations(claimedEvaluations2, true)
+
+	whirParamsWitness2 := WHIRParams{StartingDomainBackingDomainGenerator: 0}
+	if cfg.WHIRConfigWitness2 != nil {
+		whirParamsWitness2 = NewWhirParams(*cfg.WHIRConfigWitness2)
+	}
+
+	publicValues := make([]frontend.Variable, len(cfg.PublicValues))
+	contPublicValues := make([]frontend.Variable, len(cfg.PublicValues))
+	for i, value := range cfg.PublicValues {
+		publicValues[i], _ = new(big.Int).SetString(value, 10)
+	}
 
 	fSums, gSums := parseClaimedEvaluations(claimedEvaluations, true)
 
@@ -150,6 +270,7 @@ func verifyCircuit(
 
 	var circuit = Circuit{
 		IO:                                      []byte(cfg.IOPattern),
+		PublicValues:                            contPublicValues,
 		Transcript:                              contTranscript,
 		LogNumConstraints:                       cfg.LogNumConstraints,
 		LogNumVariables:                         cfg.LogNumVariables,
@@ -166,6 +287,16 @@ func verifyCircuit(
 		WHIRParamsWitness:       NewWhirParams(cfg.WHIRConfigWitness),
 		WHIRParamsHidingSpartan: NewWhirParams(cfg.WHIRConfigHidingSpartan),
 
+		HasWitness2:                        cfg.WHIRConfigWitness2 != nil,
+		W1Size:                             cfg.W1Size,
+		NumChallenges:                      cfg.NumChallenges,
+		Witness2ClaimedEvaluations:         fSums2,
+		Witness2BlindingEvaluations:        gSums2,
+		Witness2LinearStatementEvaluations: contWitness2LinearStatementEvaluations,
+		Witness2Merkle:                     newMerkle(hints.witness2Hints.roundHints, true),
+		Witness2FirstRound:                 newMerkle(hints.witness2Hints.firstRoundMerklePaths.path, true),
+		WHIRParamsWitness2:                 whirParamsWitness2,
+
 		MatrixA: matrixA,
 		MatrixB: matrixB,
 		MatrixC: matrixC,
@@ -199,9 +330,14 @@ func verifyCircuit(
 	}
 
 	fSums, gSums = parseClaimedEvaluations(claimedEvaluations, false)
+	fSums2, gSums2 = parseClaimedEvaluations(claimedEvaluations2, false)
+
+	circuitDigest, _ := new(big.Int).SetString(cfg.CircuitDigest, 10)
 
 	assignment := Circuit{
 		IO:                []byte(cfg.IOPattern),
+		CircuitDigest:     circuitDigest,
+		PublicValues:      publicValues,
 		Transcript:        transcriptT,
 		LogNumConstraints: cfg.LogNumConstraints,
 
@@ -218,6 +354,16 @@ func verifyCircuit(
 		WHIRParamsWitness:       NewWhirParams(cfg.WHIRConfigWitness),
 		WHIRParamsHidingSpartan: NewWhirParams(cfg.WHIRConfigHidingSpartan),
 
+		HasWitness2:                        cfg.WHIRConfigWitness2 != nil,
+		W1Size:                             cfg.W1Size,
+		NumChallenges:                      cfg.NumChallenges,
+		Witness2ClaimedEvaluations:         fSums2,
+		Witness2BlindingEvaluations:        gSums2,
+		Witness2LinearStatementEvaluations: witness2LinearStatementEvaluations,
+		Witness2Merkle:                     newMerkle(hints.witness2Hints.roundHints, false),
+		Witness2FirstRound:                 newMerkle(hints.witness2Hints.firstRoundMerklePaths.path, false),
+		WHIRParamsWitness2:                 whirParamsWitness2,
+
 		MatrixA: matrixA,
 		MatrixB: matrixB,
 		MatrixC: matrixC,
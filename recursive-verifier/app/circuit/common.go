@@ -26,6 +26,7 @@ func PrepareAndVerifyCircuit(config Config, r1cs R1CS, pk *groth16.ProvingKey, v
 	var stirAnswers [][][]Fp256
 	var deferred []Fp256
 	var claimedEvaluations ClaimedEvaluations
+	var claimedEvaluations2 ClaimedEvaluations
 
 	for _, op := range io.Ops {
 		switch op.Kind {
@@ -80,6 +81,15 @@ func PrepareAndVerifyCircuit(config Config, r1cs R1CS, pk *groth16.ProvingKey, v
 				if err != nil {
 					return fmt.Errorf("failed to deserialize claimed_evaluations: %w", err)
 				}
+			case "claimed_evaluations_2":
+				_, err = arkSerialize.CanonicalDeserializeWithMode(
+					bytes.NewReader(config.Transcript[start:end]),
+					&claimedEvaluations2,
+					false, false,
+				)
+				if err != nil {
+					return fmt.Errorf("failed to deserialize claimed_evaluations_2: %w", err)
+				}
 			}
 
 			if err != nil {
@@ -123,11 +133,17 @@ func PrepareAndVerifyCircuit(config Config, r1cs R1CS, pk *groth16.ProvingKey, v
 
 	var witnessData = consumeWhirData(config.WHIRConfigWitness, &merklePaths, &stirAnswers)
 
+	var witness2Data ZKHint
+	if config.WHIRConfigWitness2 != nil {
+		witness2Data = consumeWhirData(*config.WHIRConfigWitness2, &merklePaths, &stirAnswers)
+	}
+
 	hints := Hints{
 		witnessHints:      witnessData,
+		witness2Hints:     witness2Data,
 		spartanHidingHint: hidingSpartanData,
 	}
-	verifyCircuit(deferred, config, hints, pk, vk, outputCcsPath, claimedEvaluations, r1cs, interner)
+	verifyCircuit(deferred, config, hints, pk, vk, outputCcsPath, claimedEvaluations, claimedEvaluations2, r1cs, interner)
 	return nil
 }
 
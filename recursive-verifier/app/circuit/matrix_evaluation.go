@@ -38,25 +38,24 @@ type MatrixCell struct {
 	value  *big.Int
 }
 
-func evaluateR1CSMatrixExtension(api frontend.API, circuit *Circuit, rowRand []frontend.Variable, colRand []frontend.Variable) []frontend.Variable {
-	ansA := frontend.Variable(0)
-	ansB := frontend.Variable(0)
-	ansC := frontend.Variable(0)
-
-	rowEval := calculateEQOverBooleanHypercube(api, rowRand)
+// evaluateR1CSMatrixExtension evaluates the multilinear extensions of the
+// matrices restricted to the numColumns columns starting at columnOffset, with
+// the columns renumbered from zero.
+func evaluateR1CSMatrixExtension(api frontend.API, circuit *Circuit, rowEval []frontend.Variable, colRand []frontend.Variable, columnOffset int, numColumns int) []frontend.Variable {
 	colEval := calculateEQOverBooleanHypercube(api, colRand)
 
-	for i := range len(circuit.MatrixA) {
-		ansA = api.Add(ansA, api.Mul(circuit.MatrixA[i].value, api.Mul(rowEval[circuit.MatrixA[i].row], colEval[circuit.MatrixA[i].column])))
-	}
-	for i := range circuit.MatrixB {
-		ansB = api.Add(ansB, api.Mul(circuit.MatrixB[i].value, api.Mul(rowEval[circuit.MatrixB[i].row], colEval[circuit.MatrixB[i].column])))
-	}
-	for i := range circuit.MatrixC {
-		ansC = api.Add(ansC, api.Mul(circuit.MatrixC[i].value, api.Mul(rowEval[circuit.MatrixC[i].row], colEval[circuit.MatrixC[i].column])))
+	evaluate := func(matrix []MatrixCell) frontend.Variable {
+		ans := frontend.Variable(0)
+		for _, cell := range matrix {
+			if cell.column < columnOffset || cell.column >= columnOffset+numColumns {
+				continue
+			}
+			ans = api.Add(ans, api.Mul(cell.value, api.Mul(rowEval[cell.row], colEval[cell.column-columnOffset])))
+		}
+		return ans
 	}
 
-	return []frontend.Variable{ansA, ansB, ansC}
+	return []frontend.Variable{evaluate(circuit.MatrixA), evaluate(circuit.MatrixB), evaluate(circuit.MatrixC)}
 }
 
 func calculateEQOverBooleanHypercube(api frontend.API, r []frontend.Variable) []frontend.Variable {
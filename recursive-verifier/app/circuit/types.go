@@ -86,20 +86,26 @@ type ProofObject struct {
 }
 
 type Config struct {
-	WHIRConfigWitness            WHIRConfig `json:"whir_config_witness"`
-	WHIRConfigHidingSpartan      WHIRConfig `json:"whir_config_hiding_spartan"`
-	LogNumConstraints            int        `json:"log_num_constraints"`
-	LogNumVariables              int        `json:"log_num_variables"`
-	LogANumTerms                 int        `json:"log_a_num_terms"`
-	IOPattern                    string     `json:"io_pattern"`
-	Transcript                   []byte     `json:"transcript"`
-	TranscriptLen                int        `json:"transcript_len"`
-	WitnessStatementEvaluations  []string   `json:"witness_statement_evaluations"`
-	BlindingStatementEvaluations []string   `json:"blinding_statement_evaluations"`
+	WHIRConfigWitness            WHIRConfig  `json:"whir_config_witness"`
+	WHIRConfigWitness2           *WHIRConfig `json:"whir_config_witness_2"`
+	W1Size                       int         `json:"w1_size"`
+	NumChallenges                int         `json:"num_challenges"`
+	WHIRConfigHidingSpartan      WHIRConfig  `json:"whir_config_hiding_spartan"`
+	LogNumConstraints            int         `json:"log_num_constraints"`
+	LogNumVariables              int         `json:"log_num_variables"`
+	LogANumTerms                 int         `json:"log_a_num_terms"`
+	IOPattern                    string      `json:"io_pattern"`
+	CircuitDigest                string      `json:"circuit_digest"`
+	PublicValues                 []string    `json:"public_values"`
+	Transcript                   []byte      `json:"transcript"`
+	TranscriptLen                int         `json:"transcript_len"`
+	WitnessStatementEvaluations  []string    `json:"witness_statement_evaluations"`
+	BlindingStatementEvaluations []string    `json:"blinding_statement_evaluations"`
 }
 
 type Hints struct {
 	witnessHints      ZKHint
+	witness2Hints     ZKHint
 	spartanHidingHint ZKHint
 }
 
@@ -27,6 +27,22 @@ func calculateEQ(api frontend.API, alphas []frontend.Variable, r []frontend.Vari
 	return ans
 }
 
+// evaluatePublicInputWeight evaluates the multilinear extension of the weights
+// selecting the public values, tau^(i-1) at witness index i, at point.
+func evaluatePublicInputWeight(api frontend.API, tau frontend.Variable, numPublicValues int, point []frontend.Variable) frontend.Variable {
+	result := frontend.Variable(0)
+	power := frontend.Variable(1)
+	for index := 1; index <= numPublicValues; index++ {
+		bits := make([]frontend.Variable, len(point))
+		for k := range point {
+			bits[k] = (index >> (len(point) - 1 - k)) & 1
+		}
+		result = api.Add(result, api.Mul(power, calculateEQ(api, bits, point)))
+		power = api.Mul(power, tau)
+	}
+	return result
+}
+
 func initializeComponents(api frontend.API, circuit *Circuit) (*skyscraper.Skyscraper, gnarkNimue.Arthur, *uints.BinaryField[uints.U64], error) {
 	sc := skyscraper.NewSkyscraper(api, 2)
 	arthur, err := gnarkNimue.NewSkyscraperArthur(api, sc, circuit.IO, circuit.Transcript[:], true)
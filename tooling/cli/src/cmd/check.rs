@@ -0,0 +1,41 @@
+use {
+    super::Command,
+    anyhow::{ensure, Context, Result},
+    argh::FromArgs,
+    provekit_common::{file::read, ProvingKey},
+    provekit_prover::NoirProofSchemeProver,
+    std::path::PathBuf,
+    tracing::{info, instrument},
+};
+
+/// Check that the inputs of a prepared Noir program satisfy its constraints
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "check")]
+pub struct Args {
+    /// path to the proving key
+    #[argh(positional)]
+    proving_key_path: PathBuf,
+
+    /// path to the input values
+    #[argh(positional)]
+    input_path: PathBuf,
+}
+
+impl Command for Args {
+    #[instrument(skip_all)]
+    fn run(&self) -> Result<()> {
+        let proving_key: ProvingKey =
+            read(&self.proving_key_path).context("while reading proving key")?;
+        if proving_key.scheme.source_map.is_none() {
+            info!("The proving key has no source map, so origins are not reported");
+        }
+        let input_map = proving_key.scheme.read_witness(&self.input_path)?;
+
+        let check = proving_key
+            .check_witness(&input_map)
+            .context("while solving witness")?;
+        ensure!(check.is_satisfied(), "{check}");
+        info!("{check}");
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use {
     crate::Command,
     anyhow::{Context, Result},
     argh::FromArgs,
-    provekit_common::{file::read, NoirProof, NoirProofScheme},
+    provekit_common::{file::read, NoirProof, VerificationKey},
     provekit_gnark::write_gnark_parameters_to_file,
     std::{fs::File, io::Write, path::PathBuf},
     tracing::{info, instrument},
@@ -12,9 +12,9 @@ use {
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 #[argh(subcommand, name = "generate-gnark-inputs")]
 pub struct Args {
-    /// path to the compiled Noir program
+    /// path to the verification key
     #[argh(positional)]
-    scheme_path: PathBuf,
+    verification_key_path: PathBuf,
 
     /// path to the proof file
     #[argh(positional)]
@@ -40,27 +40,27 @@ pub struct Args {
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        // Read the scheme
-        let scheme: NoirProofScheme =
-            read(&self.scheme_path).context("while reading Noir proof scheme")?;
-        let (constraints, witnesses) = scheme.size();
-        info!(constraints, witnesses, "Read Noir proof scheme");
+        // Read the verification key
+        let verification_key: VerificationKey =
+            read(&self.verification_key_path).context("while reading verification key")?;
+        match verification_key.size() {
+            Some((constraints, witnesses)) => {
+                info!(constraints, witnesses, "Read verification key");
+            }
+            None => info!("Read verification key with committed matrices"),
+        }
 
         // Read the proof
         let proof: NoirProof = read(&self.proof_path).context("while reading proof")?;
 
         write_gnark_parameters_to_file(
-            &scheme.whir_for_witness.whir_witness,
-            &scheme.whir_for_witness.whir_for_hiding_spartan,
-            &proof.whir_r1cs_proof.transcript,
-            &scheme.whir_for_witness.create_io_pattern(),
-            scheme.whir_for_witness.m_0,
-            scheme.whir_for_witness.m,
-            scheme.whir_for_witness.a_num_terms,
+            &verification_key,
+            &proof,
             &self.params_for_recursive_verifier,
-        );
+        )
+        .context("while writing parameters for the recursive verifier")?;
 
-        let json = serde_json::to_string_pretty(&scheme.r1cs).unwrap(); // Or `to_string` for compact
+        let json = serde_json::to_string_pretty(&verification_key.r1cs).unwrap(); // Or `to_string` for compact
         let mut file = File::create("r1cs.json")?;
         file.write_all(json.as_bytes())?;
 
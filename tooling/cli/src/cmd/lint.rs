@@ -0,0 +1,43 @@
+use {
+    super::Command,
+    anyhow::{ensure, Context, Result},
+    argh::FromArgs,
+    provekit_common::{file::read, NoirProofScheme, ProvingKey},
+    provekit_r1cs_compiler::lint_scheme,
+    std::{ffi::OsStr, path::PathBuf},
+    tracing::{info, instrument, warn},
+};
+
+/// Find the underconstrained witnesses of a prepared Noir program
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "lint")]
+pub struct Args {
+    /// path to the proof scheme or proving key
+    #[argh(positional)]
+    scheme_path: PathBuf,
+}
+
+impl Command for Args {
+    #[instrument(skip_all)]
+    fn run(&self) -> Result<()> {
+        let scheme: NoirProofScheme = if self.scheme_path.extension() == Some(OsStr::new("npk")) {
+            read::<ProvingKey>(&self.scheme_path)
+                .context("while reading proving key")?
+                .scheme
+        } else {
+            read(&self.scheme_path).context("while reading proof scheme")?
+        };
+
+        let lints = lint_scheme(&scheme);
+        for lint in &lints {
+            warn!("{lint}");
+        }
+        ensure!(
+            lints.is_empty(),
+            "Found {} underconstrained witnesses",
+            lints.len()
+        );
+        info!("No underconstrained witnesses");
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
+mod check;
 mod circuit_stats;
 mod generate_gnark_inputs;
+mod lint;
 mod prepare;
 mod prove;
 mod verify;
@@ -23,6 +25,8 @@ enum Commands {
     Prepare(prepare::Args),
     Prove(prove::Args),
     CircuitStats(circuit_stats::Args),
+    Check(check::Args),
+    Lint(lint::Args),
     Verify(verify::Args),
     GenerateGnarkInputs(generate_gnark_inputs::Args),
 }
@@ -39,6 +43,8 @@ impl Command for Commands {
             Self::Prepare(args) => args.run(),
             Self::Prove(args) => args.run(),
             Self::CircuitStats(args) => args.run(),
+            Self::Check(args) => args.run(),
+            Self::Lint(args) => args.run(),
             Self::Verify(args) => args.run(),
             Self::GenerateGnarkInputs(args) => args.run(),
         }
@@ -2,10 +2,10 @@ use {
     super::Command,
     anyhow::{Context, Result},
     argh::FromArgs,
-    provekit_common::{file::write, NoirProofScheme},
+    provekit_common::{file::write, NoirProofScheme, ProvingKey, VerificationKey},
     provekit_r1cs_compiler::NoirProofSchemeBuilder,
     std::path::PathBuf,
-    tracing::instrument,
+    tracing::{info, instrument},
 };
 
 /// Prepare a Noir program for proving
@@ -16,22 +16,49 @@ pub struct Args {
     #[argh(positional)]
     program_path: PathBuf,
 
-    /// output path for the prepared proof scheme
+    /// output path for the proving key
     #[argh(
         option,
-        long = "out",
-        short = 'o',
-        default = "PathBuf::from(\"noir_proof_scheme.bin\")"
+        long = "pk",
+        default = "PathBuf::from(\"noir-proving-key.npk\")"
     )]
-    output_path: PathBuf,
+    proving_key_path: PathBuf,
+
+    /// output path for the verification key
+    #[argh(
+        option,
+        long = "vk",
+        default = "PathBuf::from(\"noir-verification-key.nvk\")"
+    )]
+    verification_key_path: PathBuf,
+
+    /// commit to the R1CS matrices, so the verifier does not need them
+    #[argh(switch)]
+    spark: bool,
+
+    /// omit the origins of the constraints and witnesses from the proving key
+    #[argh(switch)]
+    no_source_map: bool,
 }
 
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        let scheme = NoirProofScheme::from_file(&self.program_path)
+        let mut scheme = NoirProofScheme::from_file(&self.program_path)
             .context("while compiling Noir program")?;
-        write(&scheme, &self.output_path).context("while writing Noir proof scheme")?;
+        if self.spark {
+            scheme.commit_to_matrices();
+        }
+        if self.no_source_map {
+            scheme.source_map = None;
+        }
+        let proving_key = ProvingKey::new(scheme).context("while creating proving key")?;
+        let verification_key = VerificationKey::new(&proving_key);
+        info!(digest = %proving_key.digest, "Circuit digest");
+
+        write(&proving_key, &self.proving_key_path).context("while writing proving key")?;
+        write(&verification_key, &self.verification_key_path)
+            .context("while writing verification key")?;
         Ok(())
     }
 }
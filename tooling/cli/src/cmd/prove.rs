@@ -2,11 +2,12 @@
 use provekit_verifier::NoirProofSchemeVerifier;
 use {
     super::Command,
-    anyhow::{Context, Result},
+    anyhow::{ensure, Context, Result},
     argh::FromArgs,
     provekit_common::{
         file::{read, write},
-        NoirProofScheme,
+        utils::PrintInputValue,
+        ProvingKey,
     },
     provekit_prover::NoirProofSchemeProver,
     std::path::PathBuf,
@@ -17,9 +18,9 @@ use {
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 #[argh(subcommand, name = "prove")]
 pub struct Args {
-    /// path to the prepared proof scheme
+    /// path to the proving key
     #[argh(positional)]
-    scheme_path: PathBuf,
+    proving_key_path: PathBuf,
 
     /// path to the input values
     #[argh(positional)]
@@ -46,24 +47,49 @@ pub struct Args {
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        // Read the scheme
-        let scheme: NoirProofScheme =
-            read(&self.scheme_path).context("while reading Noir proof scheme")?;
-        let (constraints, witnesses) = scheme.size();
-        info!(constraints, witnesses, "Read Noir proof scheme");
+        // Read the proving key
+        let proving_key: ProvingKey =
+            read(&self.proving_key_path).context("while reading proving key")?;
+        let (constraints, witnesses) = proving_key.scheme.size();
+        info!(
+            constraints,
+            witnesses,
+            digest = %proving_key.digest,
+            "Read proving key"
+        );
 
         // Read the input toml
-        let input_map = scheme.read_witness(&self.input_path)?;
+        let input_map = proving_key.scheme.read_witness(&self.input_path)?;
 
         // Generate the proof
-        let proof = scheme
+        let proof = proving_key
             .prove(&input_map)
             .context("While proving Noir program statement")?;
 
+        // Report the return value, and check it against the input file if given there
+        if let Some(return_value) = proving_key
+            .scheme
+            .decode_public_outputs(&proof.public_outputs)
+            .context("while decoding return value")?
+        {
+            info!(return_value = %PrintInputValue(&return_value), "Program returned");
+        }
+        if let Some(expected_outputs) = proving_key
+            .scheme
+            .read_public_outputs(&self.input_path)
+            .context("while reading expected return value")?
+        {
+            ensure!(
+                proof.public_outputs == expected_outputs,
+                "Return value does not match the one in the input file"
+            );
+        }
+
         // Verify the proof (not in release build)
         #[cfg(test)]
-        scheme
-            .verify(&proof)
+        proving_key
+            .scheme
+            .verify(&proof, &proof.public_inputs, None)
             .context("While verifying Noir proof")?;
 
         // Store the proof to file
@@ -1,8 +1,12 @@
 use {
     super::Command,
-    anyhow::{Context, Result},
+    anyhow::{ensure, Context, Result},
     argh::FromArgs,
-    provekit_common::{file::read, NoirProofScheme},
+    provekit_common::{
+        file::{read, read_digest},
+        utils::PrintInputValue,
+        NoirProof, VerificationKey,
+    },
     provekit_verifier::NoirProofSchemeVerifier,
     std::path::PathBuf,
     tracing::{info, instrument},
@@ -12,32 +16,87 @@ use {
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 #[argh(subcommand, name = "verify")]
 pub struct Args {
-    /// path to the compiled Noir program
+    /// path to the verification key
     #[argh(positional)]
-    scheme_path: PathBuf,
+    verification_key_path: PathBuf,
 
     /// path to the proof file
     #[argh(positional)]
     proof_path: PathBuf,
+
+    /// path to the expected public input values (TOML or JSON)
+    #[argh(option, long = "input")]
+    input_path: Option<PathBuf>,
+
+    /// path to the expected return value (TOML or JSON, under `return`)
+    #[argh(option, long = "output")]
+    output_path: Option<PathBuf>,
 }
 
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        // Read the scheme
-        let scheme: NoirProofScheme =
-            read(&self.scheme_path).context("while reading Noir proof scheme")?;
-        let (constraints, witnesses) = scheme.size();
-        info!(constraints, witnesses, "Read Noir proof scheme");
+        // Read the verification key
+        let verification_key: VerificationKey =
+            read(&self.verification_key_path).context("while reading verification key")?;
+        match verification_key.size() {
+            Some((constraints, witnesses)) => info!(
+                constraints,
+                witnesses,
+                digest = %verification_key.digest,
+                "Read verification key"
+            ),
+            None => info!(
+                digest = %verification_key.digest,
+                "Read verification key with committed matrices"
+            ),
+        }
+
+        // Check that the proof is for this circuit before decoding it
+        let proof_digest =
+            read_digest::<NoirProof>(&self.proof_path).context("while reading proof header")?;
+        ensure!(
+            proof_digest == verification_key.digest,
+            "Proof is for a different circuit than the verification key"
+        );
 
         // Read the proof
-        let proof = read(&self.proof_path).context("while reading proof")?;
+        let proof: NoirProof = read(&self.proof_path).context("while reading proof")?;
+
+        // Read the expected public inputs, or else take the ones claimed by the proof
+        let public_inputs = match &self.input_path {
+            Some(input_path) => verification_key
+                .read_public_inputs(input_path)
+                .context("while reading public inputs")?,
+            None => {
+                info!(public_inputs = ?proof.public_inputs, "Using public inputs from proof");
+                proof.public_inputs.clone()
+            }
+        };
+
+        // Read the expected return value, if any
+        let public_outputs = match &self.output_path {
+            Some(output_path) => Some(
+                verification_key
+                    .read_public_outputs(output_path)
+                    .context("while reading expected return value")?
+                    .context("Output file has no return value")?,
+            ),
+            None => None,
+        };
 
         // Verify the proof
-        scheme
-            .verify(&proof)
+        verification_key
+            .verify(&proof, &public_inputs, public_outputs.as_deref())
             .context("While verifying Noir proof")?;
 
+        if let Some(return_value) = verification_key
+            .decode_public_outputs(&proof.public_outputs)
+            .context("while decoding return value")?
+        {
+            info!(return_value = %PrintInputValue(&return_value), "Verified return value");
+        }
+
         Ok(())
     }
 }
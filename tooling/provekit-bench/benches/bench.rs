@@ -69,7 +69,7 @@ fn prove_poseidon_1000_with_io(bencher: Bencher) {
 fn verify_poseidon_1000(bencher: Bencher) {
     let scheme: NoirProofScheme = read("benches/poseidon-1000.nps".as_ref()).unwrap();
     let proof: NoirProof = read("benches/poseidon-1000.np".as_ref()).unwrap();
-    bencher.bench(|| black_box(&scheme).verify(black_box(&proof)));
+    bencher.bench(|| black_box(&scheme).verify(black_box(&proof), &proof.public_inputs, None));
 }
 
 fn main() {
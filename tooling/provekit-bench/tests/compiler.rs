@@ -1,15 +1,34 @@
 use {
+    acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Opcode,
+        },
+        native_types::Witness,
+    },
+    acvm_blackbox_solver::{blake2s, blake3, BlackBoxResolutionError},
     anyhow::Result,
     nargo::workspace::Workspace,
     nargo_cli::cli::compile_cmd::compile_workspace_full,
     nargo_toml::{resolve_workspace_from_toml, PackageSelection},
+    noirc_artifacts::program::ProgramArtifact,
     noirc_driver::CompileOptions,
-    provekit_common::NoirProofScheme,
+    provekit_common::{
+        file::{read, read_digest, write},
+        FieldElement, NoirProof, NoirProofScheme, ProvingKey, VerificationKey,
+    },
+    provekit_gnark::gnark_parameters,
     provekit_prover::NoirProofSchemeProver,
-    provekit_r1cs_compiler::NoirProofSchemeBuilder,
+    provekit_r1cs_compiler::{
+        lint_scheme, noir_to_r1cs, noir_to_r1cs_without_product_reuse, NoirProofSchemeBuilder,
+        ProductStats,
+    },
     provekit_verifier::NoirProofSchemeVerifier,
     serde::Deserialize,
-    std::path::Path,
+    std::{
+        fs::File,
+        path::{Path, PathBuf},
+    },
     test_case::test_case,
 };
 
@@ -23,9 +42,8 @@ struct NargoTomlPackage {
     name: String,
 }
 
-fn test_compiler(test_case_path: impl AsRef<Path>) {
-    let test_case_path = test_case_path.as_ref();
-
+/// Compile a test case, returning the path of its program artifact.
+fn compile_test_case(test_case_path: &Path) -> PathBuf {
     compile_workspace(test_case_path).expect("Compiling workspace");
 
     let nargo_toml_path = test_case_path.join("Nargo.toml");
@@ -35,19 +53,58 @@ fn test_compiler(test_case_path: impl AsRef<Path>) {
 
     let package_name = nargo_toml.package.name;
 
-    let circuit_path = test_case_path.join(format!("target/{package_name}.json"));
+    test_case_path.join(format!("target/{package_name}.json"))
+}
+
+fn prove_test_case(
+    test_case_path: impl AsRef<Path>,
+    commit_to_matrices: bool,
+) -> (NoirProofScheme, NoirProof, Vec<FieldElement>) {
+    let test_case_path = test_case_path.as_ref();
+    let circuit_path = compile_test_case(test_case_path);
     let witness_file_path = test_case_path.join("Prover.toml");
 
-    let proof_schema = NoirProofScheme::from_file(&circuit_path).expect("Reading proof scheme");
+    let mut proof_schema = NoirProofScheme::from_file(&circuit_path).expect("Reading proof scheme");
+    if commit_to_matrices {
+        proof_schema.commit_to_matrices();
+    }
     let input_map = proof_schema
         .read_witness(&witness_file_path)
         .expect("Reading witness data");
+    let public_inputs = proof_schema
+        .public_inputs(&input_map)
+        .expect("Encoding public inputs");
 
     let proof = proof_schema
         .prove(&input_map)
         .expect("While proving Noir program statement");
 
-    proof_schema.verify(&proof).expect("Verifying proof");
+    (proof_schema, proof, public_inputs)
+}
+
+fn test_compiler(test_case_path: impl AsRef<Path>) {
+    let (proof_schema, proof, public_inputs) = prove_test_case(test_case_path, false);
+
+    proof_schema
+        .verify(&proof, &public_inputs, None)
+        .expect("Verifying proof");
+
+    let proving_key = ProvingKey::new(proof_schema).expect("Creating proving key");
+    VerificationKey::new(&proving_key)
+        .verify(&proof, &public_inputs, None)
+        .expect("Verifying proof with verification key");
+}
+
+fn test_spark(test_case_path: impl AsRef<Path>) {
+    let (proof_schema, proof, public_inputs) = prove_test_case(test_case_path, true);
+
+    // The verification key does not need the R1CS matrices
+    let proving_key = ProvingKey::new(proof_schema).expect("Creating proving key");
+    let verification_key = VerificationKey::new(&proving_key);
+    assert!(verification_key.r1cs.is_none());
+    verification_key
+        .verify(&proof, &public_inputs, None)
+        .expect("Verifying proof with committed matrices");
 }
 
 pub fn compile_workspace(workspace_path: impl AsRef<Path>) -> Result<Workspace> {
@@ -80,8 +137,361 @@ pub fn compile_workspace(workspace_path: impl AsRef<Path>) -> Result<Workspace>
 #[test_case("../../noir-examples/noir-r1cs-test-programs/read-write-memory")]
 #[test_case("../../noir-examples/noir-r1cs-test-programs/conditional-write")]
 #[test_case("../../noir-examples/noir-r1cs-test-programs/bin-opcode")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/bin-opcode-widths")]
 #[test_case("../../noir-examples/noir-r1cs-test-programs/small-sha")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/public-inputs")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/return-values")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/acir-calls")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/sha256-compression")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/poseidon2-permutation")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/keccakf1600")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/blake2s")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/blake3")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/ecdsa-secp256k1")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/ecdsa-secp256r1")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/embedded-curve-ops")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/bigint")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/aes128")]
 #[test_case("../../noir-examples/noir-passport-examples/complete_age_check"; "complete_age_check")]
 fn case(path: &str) {
     test_compiler(path);
 }
+
+/// AND and XOR on widths that are not multiples of the digits, which Noir has
+/// no types for, added to the ACIR of bin-opcode-widths: of its `x1`, `x8` and
+/// `x32` parameters with `y1`, `y8` and `y32`, on 3, 12 and 33 bits.
+#[test]
+fn bin_opcode_odd_widths() {
+    let test_case_path = Path::new("../../noir-examples/noir-r1cs-test-programs/bin-opcode-widths");
+    let circuit_path = compile_test_case(test_case_path);
+    let file = File::open(&circuit_path).expect("Opening program artifact");
+    let mut program: ProgramArtifact =
+        serde_json::from_reader(file).expect("Reading program artifact");
+
+    // Each parameter is a single witness, in order
+    let parameter = |name: &str| {
+        let index = program
+            .abi
+            .parameters
+            .iter()
+            .position(|parameter| parameter.name == name)
+            .expect("Parameter of the program");
+        Witness(index as u32)
+    };
+    let operands = [("x1", "y1", 3), ("x8", "y8", 12), ("x32", "y32", 33)]
+        .map(|(lhs, rhs, num_bits)| (parameter(lhs), parameter(rhs), num_bits));
+    let main = &mut program.bytecode.functions[0];
+    for (lhs, rhs, num_bits) in operands {
+        let [and_output, xor_output] = [1, 2].map(|i| Witness(main.current_witness_index + i));
+        main.current_witness_index += 2;
+        main.opcodes.extend([
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::AND {
+                lhs:    FunctionInput::witness(lhs, num_bits),
+                rhs:    FunctionInput::witness(rhs, num_bits),
+                output: and_output,
+            }),
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::XOR {
+                lhs:    FunctionInput::witness(lhs, num_bits),
+                rhs:    FunctionInput::witness(rhs, num_bits),
+                output: xor_output,
+            }),
+        ]);
+    }
+
+    let proof_schema = NoirProofScheme::from_program(program).expect("Compiling program");
+    let input_map = proof_schema
+        .read_witness(test_case_path.join("Prover.toml"))
+        .expect("Reading witness data");
+    let public_inputs = proof_schema
+        .public_inputs(&input_map)
+        .expect("Encoding public inputs");
+    let proof = proof_schema
+        .prove(&input_map)
+        .expect("While proving Noir program statement");
+    proof_schema
+        .verify(&proof, &public_inputs, None)
+        .expect("Verifying proof");
+}
+
+#[test_case("../../noir-examples/noir-r1cs-test-programs/acir_assert_zero")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/range-check-u8")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/read-write-memory")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/public-inputs")]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/return-values")]
+fn spark_case(path: &str) {
+    test_spark(path);
+}
+
+#[test]
+fn proof_is_bound_to_r1cs_matrices() {
+    let (mut proof_schema, proof, public_inputs) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/range-check-u8",
+        false,
+    );
+
+    // Same shape, different circuit.
+    std::mem::swap(&mut proof_schema.r1cs.a, &mut proof_schema.r1cs.c);
+
+    assert!(proof_schema.verify(&proof, &public_inputs, None).is_err());
+}
+
+#[test]
+fn spark_proof_is_bound_to_matrix_commitment() {
+    let (proof_schema, proof, public_inputs) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/range-check-u8",
+        true,
+    );
+    let proving_key = ProvingKey::new(proof_schema).expect("Creating proving key");
+    let mut verification_key = VerificationKey::new(&proving_key);
+
+    // Same digest, different matrices.
+    let spark = verification_key.whir_for_witness.spark.as_mut().unwrap();
+    spark.matrix_root += FieldElement::from(1);
+
+    assert!(verification_key
+        .verify(&proof, &public_inputs, None)
+        .is_err());
+}
+
+#[test]
+fn spark_proof_is_not_exported_to_gnark() {
+    let (proof_schema, proof, _) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/range-check-u8",
+        true,
+    );
+    let proving_key = ProvingKey::new(proof_schema).expect("Creating proving key");
+    let verification_key = VerificationKey::new(&proving_key);
+
+    // The recursive verifier evaluates the matrices itself.
+    assert!(gnark_parameters(&verification_key, &proof).is_err());
+}
+
+#[test]
+fn proof_file_header_has_circuit_digest() {
+    let (proof_schema, proof, public_inputs) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/range-check-u8",
+        false,
+    );
+    let proving_key = ProvingKey::new(proof_schema).expect("Creating proving key");
+    let verification_key = VerificationKey::new(&proving_key);
+
+    let proof_path = std::env::temp_dir().join("provekit_proof_file_header_has_circuit_digest.np");
+    write(&proof, &proof_path).expect("Writing proof");
+    let proof_digest = read_digest::<NoirProof>(&proof_path).expect("Reading proof header");
+    assert_eq!(proof_digest, verification_key.digest);
+    let read_proof: NoirProof = read(&proof_path).expect("Reading proof");
+    assert_eq!(read_proof, proof);
+
+    // Verification key of another circuit
+    let (other_schema, ..) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/public-inputs",
+        false,
+    );
+    let other_key = ProvingKey::new(other_schema).expect("Creating proving key");
+    let other_verification_key = VerificationKey::new(&other_key);
+    assert_ne!(proof_digest, other_verification_key.digest);
+    assert!(other_verification_key
+        .verify(&proof, &public_inputs, None)
+        .is_err());
+}
+
+#[test]
+fn proof_is_bound_to_public_inputs() {
+    let (proof_schema, mut proof, mut public_inputs) = prove_test_case(
+        "../../noir-examples/noir-r1cs-test-programs/public-inputs",
+        false,
+    );
+    assert_eq!(public_inputs, [
+        FieldElement::from(9),
+        FieldElement::from(1)
+    ]);
+
+    // Wrong expected values
+    public_inputs[1] = FieldElement::from(2);
+    assert!(proof_schema.verify(&proof, &public_inputs, None).is_err());
+
+    // Proof claiming different values than the committed witness
+    proof.public_inputs = public_inputs.clone();
+    assert!(proof_schema.verify(&proof, &public_inputs, None).is_err());
+}
+
+#[test]
+fn proof_is_bound_to_public_outputs() {
+    let test_case_path = Path::new("../../noir-examples/noir-r1cs-test-programs/return-values");
+    let (proof_schema, mut proof, public_inputs) = prove_test_case(test_case_path, false);
+    let public_outputs = proof_schema
+        .read_public_outputs(test_case_path.join("Prover.toml"))
+        .expect("Reading expected return value")
+        .expect("Prover.toml has a return value");
+    assert_eq!(public_outputs, [
+        FieldElement::from(27),
+        FieldElement::from(12)
+    ]);
+    assert_eq!(proof.public_outputs, public_outputs);
+
+    proof_schema
+        .verify(&proof, &public_inputs, Some(&public_outputs))
+        .expect("Verifying proof with expected return value");
+
+    // Wrong expected values
+    let mut wrong_outputs = public_outputs.clone();
+    wrong_outputs[1] = FieldElement::from(13);
+    assert!(proof_schema
+        .verify(&proof, &public_inputs, Some(&wrong_outputs))
+        .is_err());
+
+    // Proof claiming different values than the committed witness
+    proof.public_outputs = wrong_outputs.clone();
+    assert!(proof_schema
+        .verify(&proof, &public_inputs, Some(&wrong_outputs))
+        .is_err());
+    assert!(proof_schema.verify(&proof, &public_inputs, None).is_err());
+}
+
+#[test]
+fn check_reports_violated_constraints() {
+    let test_case_path = Path::new("../../noir-examples/noir-r1cs-test-programs/range-check-u8");
+    let circuit_path = compile_test_case(test_case_path);
+    let mut proof_schema = NoirProofScheme::from_file(&circuit_path).expect("Reading proof scheme");
+    let input_map = proof_schema
+        .read_witness(test_case_path.join("Prover.toml"))
+        .expect("Reading witness data");
+
+    let check = proof_schema
+        .check_witness(&input_map)
+        .expect("Checking witness");
+    assert!(check.is_satisfied(), "{check}");
+
+    // Same shape, different circuit.
+    std::mem::swap(&mut proof_schema.r1cs.a, &mut proof_schema.r1cs.c);
+
+    let check = proof_schema
+        .check_witness(&input_map)
+        .expect("Checking witness");
+    assert!(check.unsolved.is_empty());
+    assert!(!check.violated.is_empty());
+    for violated in &check.violated {
+        assert_ne!(violated.a * violated.b, violated.c);
+        assert!(violated.origin.is_some());
+    }
+}
+
+#[test]
+fn check_reports_failed_execution() {
+    let test_case_path = Path::new("../../noir-examples/noir-r1cs-test-programs/acir_assert_zero");
+    let circuit_path = compile_test_case(test_case_path);
+    let proof_schema = NoirProofScheme::from_file(&circuit_path).expect("Reading proof scheme");
+
+    // Inputs failing `assert(x - y == 0)`
+    let input_path = std::env::temp_dir().join("provekit_check_reports_failed_execution.toml");
+    std::fs::write(&input_path, "x = 1\ny = 2\n").expect("Writing inputs");
+    let input_map = proof_schema
+        .read_witness(&input_path)
+        .expect("Reading witness data");
+
+    let check = proof_schema
+        .check_witness(&input_map)
+        .expect("Checking witness");
+    assert!(!check.is_satisfied());
+    assert!(check.execution_error.is_some(), "{check}");
+}
+
+/// The digests computed by the ACVM black box solver must satisfy the
+/// constraints of the hash gadgets. The input of the programs in their
+/// Prover.toml is the bytes 0..100.
+#[test_case("../../noir-examples/noir-r1cs-test-programs/blake2s", blake2s)]
+#[test_case("../../noir-examples/noir-r1cs-test-programs/blake3", blake3)]
+fn hash_matches_blackbox_solver(
+    path: &str,
+    hash: fn(&[u8]) -> Result<[u8; 32], BlackBoxResolutionError>,
+) {
+    let test_case_path = Path::new(path);
+    let (proof_schema, proof, public_inputs) = prove_test_case(test_case_path, false);
+    let input = (0..100).collect::<Vec<u8>>();
+    let expected_digest = hash(&input)
+        .expect("Hashing with the black box solver")
+        .map(FieldElement::from)
+        .to_vec();
+    assert_eq!(proof.public_outputs, expected_digest);
+
+    proof_schema
+        .verify(&proof, &public_inputs, Some(&expected_digest))
+        .expect("Verifying proof with expected digest");
+}
+
+/// The Noir projects in a directory, in order.
+fn noir_projects(dir: impl AsRef<Path>) -> Vec<PathBuf> {
+    let mut paths = std::fs::read_dir(dir)
+        .expect("Reading Noir projects")
+        .map(|entry| entry.expect("Reading Noir project").path())
+        .filter(|path| path.join("Nargo.toml").exists())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// The test programs must not have witnesses the prover can choose freely.
+#[test]
+fn test_programs_are_fully_constrained() {
+    for path in noir_projects("../../noir-examples/noir-r1cs-test-programs") {
+        let circuit_path = compile_test_case(&path);
+        let proof_schema = NoirProofScheme::from_file(&circuit_path).expect("Reading proof scheme");
+        let lints = lint_scheme(&proof_schema)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert!(
+            lints.is_empty(),
+            "{}:\n{}",
+            path.display(),
+            lints.join("\n")
+        );
+    }
+}
+
+/// Report the constraints saved by reusing and factoring products on the test
+/// programs and examples, which must not have more constraints than without.
+/// Run with `--nocapture` to see the report.
+#[test]
+fn product_savings_report() {
+    let paths = noir_projects("../../noir-examples/noir-r1cs-test-programs")
+        .into_iter()
+        .chain([
+            PathBuf::from("../../noir-examples/noir-passport-examples/complete_age_check"),
+            PathBuf::from("../../noir-examples/sha256"),
+            PathBuf::from("../../noir-examples/p256_std"),
+        ]);
+
+    let mut total = ProductStats::default();
+    for path in paths {
+        let circuit_path = compile_test_case(&path);
+        let file = File::open(&circuit_path).expect("Opening program artifact");
+        let program: ProgramArtifact =
+            serde_json::from_reader(file).expect("Reading program artifact");
+        let (r1cs, .., stats) = noir_to_r1cs(&program.bytecode).expect("Compiling to R1CS");
+        let (uncached, ..) = noir_to_r1cs_without_product_reuse(&program.bytecode)
+            .expect("Compiling to R1CS without product reuse");
+        let name = path.file_name().unwrap().to_string_lossy();
+        println!(
+            "{name}: {} constraints ({} without reuse), {} saved on products ({} reused, {} \
+             factored)",
+            r1cs.num_constraints(),
+            uncached.num_constraints(),
+            stats.saved(),
+            stats.reused,
+            stats.factored
+        );
+        assert!(
+            r1cs.num_constraints() <= uncached.num_constraints(),
+            "{name}: reusing products added constraints"
+        );
+        total.reused += stats.reused;
+        total.factored += stats.factored;
+    }
+    println!(
+        "Total: {} saved on products ({} reused, {} factored)",
+        total.saved(),
+        total.reused,
+        total.factored
+    );
+}
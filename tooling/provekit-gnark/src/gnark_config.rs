@@ -1,6 +1,7 @@
 use {
+    anyhow::{ensure, Context, Result},
     ark_poly::EvaluationDomain,
-    provekit_common::{IOPattern, WhirConfig},
+    provekit_common::{NoirProof, VerificationKey, WhirConfig},
     serde::{Deserialize, Serialize},
     std::{fs::File, io::Write},
     tracing::instrument,
@@ -11,6 +12,12 @@ use {
 pub struct GnarkConfig {
     /// WHIR parameters for witness
     pub whir_config_witness:        WHIRConfigGnark,
+    /// WHIR parameters for the witnesses committed after the challenges, if any
+    pub whir_config_witness_2:      Option<WHIRConfigGnark>,
+    /// number of witnesses committed before the challenges
+    pub w1_size:                    usize,
+    /// number of challenges derived from the first witness commitment
+    pub num_challenges:             usize,
     /// WHIR parameters for hiding spartan
     pub whir_config_hiding_spartan: WHIRConfigGnark,
     /// log of number of constraints in R1CS
@@ -21,6 +28,10 @@ pub struct GnarkConfig {
     pub log_a_num_terms:            usize,
     /// nimue input output pattern
     pub io_pattern:                 String,
+    /// digest of the circuit, absorbed first in the transcript
+    pub circuit_digest:             String,
+    /// public inputs followed by the public outputs, absorbed after the digest
+    pub public_values:              Vec<String>,
     /// transcript in byte form
     pub transcript:                 Vec<u8>,
     /// length of the transcript
@@ -98,52 +109,63 @@ impl WHIRConfigGnark {
     }
 }
 
-/// Writes config used for Gnark circuit to a file
+/// Config used for Gnark circuit
 #[instrument(skip_all)]
 pub fn gnark_parameters(
-    whir_params_witness: &WhirConfig,
-    whir_params_hiding_spartan: &WhirConfig,
-    transcript: &[u8],
-    io: &IOPattern,
-    m_0: usize,
-    m: usize,
-    a_num_terms: usize,
-) -> GnarkConfig {
-    GnarkConfig {
-        whir_config_witness:        WHIRConfigGnark::new(whir_params_witness),
-        whir_config_hiding_spartan: WHIRConfigGnark::new(whir_params_hiding_spartan),
-        log_num_constraints:        m_0,
-        log_num_variables:          m,
-        log_a_num_terms:            a_num_terms,
-        io_pattern:                 String::from_utf8(io.as_bytes().to_vec()).unwrap(),
-        transcript:                 transcript.to_vec(),
-        transcript_len:             transcript.to_vec().len(),
-    }
+    verification_key: &VerificationKey,
+    proof: &NoirProof,
+) -> Result<GnarkConfig> {
+    let scheme = &verification_key.whir_for_witness;
+    ensure!(
+        scheme.spark.is_none(),
+        "The Gnark recursive verifier does not support schemes committing to the R1CS matrices"
+    );
+    ensure!(
+        proof.digest == verification_key.digest,
+        "Proof is for a different circuit"
+    );
+    ensure!(
+        proof.public_inputs.len() == scheme.num_public_inputs
+            && proof.public_outputs.len() == scheme.num_public_outputs,
+        "Unexpected number of public values"
+    );
+
+    let transcript = &proof.whir_r1cs_proof.transcript;
+    Ok(GnarkConfig {
+        whir_config_witness:        WHIRConfigGnark::new(&scheme.whir_witness),
+        whir_config_witness_2:      scheme.whir_witness_2.as_ref().map(WHIRConfigGnark::new),
+        w1_size:                    scheme.w1_size,
+        num_challenges:             scheme.num_challenges,
+        whir_config_hiding_spartan: WHIRConfigGnark::new(&scheme.whir_for_hiding_spartan),
+        log_num_constraints:        scheme.m_0,
+        log_num_variables:          scheme.m,
+        log_a_num_terms:            scheme.a_num_terms,
+        io_pattern:                 String::from_utf8(
+            scheme.create_io_pattern().as_bytes().to_vec(),
+        )?,
+        circuit_digest:             format!("{}", verification_key.digest),
+        public_values:              proof
+            .public_inputs
+            .iter()
+            .chain(&proof.public_outputs)
+            .map(|value| format!("{value}"))
+            .collect(),
+        transcript:                 transcript.clone(),
+        transcript_len:             transcript.len(),
+    })
 }
 
 /// Writes config used for Gnark circuit to a file
 #[instrument(skip_all)]
 pub fn write_gnark_parameters_to_file(
-    whir_params_witness: &WhirConfig,
-    whir_params_hiding_spartan: &WhirConfig,
-    transcript: &[u8],
-    io: &IOPattern,
-    m_0: usize,
-    m: usize,
-    a_num_terms: usize,
+    verification_key: &VerificationKey,
+    proof: &NoirProof,
     file_path: &str,
-) {
-    let gnark_config = gnark_parameters(
-        whir_params_witness,
-        whir_params_hiding_spartan,
-        transcript,
-        io,
-        m_0,
-        m,
-        a_num_terms,
-    );
-    let mut file_params = File::create(file_path).unwrap();
+) -> Result<()> {
+    let gnark_config = gnark_parameters(verification_key, proof)?;
+    let mut file_params = File::create(file_path)?;
     file_params
-        .write_all(serde_json::to_string(&gnark_config).unwrap().as_bytes())
-        .expect("Writing gnark parameters to a file failed");
+        .write_all(serde_json::to_string(&gnark_config)?.as_bytes())
+        .context("Writing gnark parameters to a file failed")?;
+    Ok(())
 }
@@ -1,6 +1,6 @@
 mod gnark_config;
 
-pub use gnark_config::write_gnark_parameters_to_file;
+pub use gnark_config::{gnark_parameters, write_gnark_parameters_to_file, GnarkConfig};
 
 #[cfg(test)]
 mod tests {}